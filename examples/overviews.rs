@@ -112,7 +112,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     match cmd.clone() {
         Cmd::Xover { low, high, out } => {
             run_cmd(&mut conn, Group(group.clone()), true);
-            let _overview = run_cmd(&mut conn, XOver::Range { low, high }, false)?;
+            let _overview = run_cmd(&mut conn, XOver::range((low, high))?, false)?;
             info!("XOVER COMPLETE");
             if let Some(path) = out {
                 info!("Writing overviews to file `{}`", path.display());
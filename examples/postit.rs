@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use log::*;
+
+use brokaw::types::article::ArticleBuilder;
+use brokaw::{ClientConfig, ConnectionConfig};
+use structopt::StructOpt;
+
+/// Post an article to a group
+///
+/// The body of the article is read from stdin
+#[derive(Debug, StructOpt)]
+struct Opt {
+    #[structopt(long, short)]
+    address: String,
+    #[structopt(long, short, default_value = "119")]
+    port: u16,
+    #[structopt(long, short)]
+    group: Vec<String>,
+    #[structopt(long, short)]
+    from: String,
+    #[structopt(long, short)]
+    subject: String,
+    #[structopt(long)]
+    dry_run: bool,
+}
+
+fn main() -> anyhow::Result<()> {
+    env_logger::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let Opt {
+        address,
+        port,
+        group,
+        from,
+        subject,
+        dry_run,
+    } = Opt::from_args();
+
+    let mut body = String::new();
+    std::io::Read::read_to_string(&mut std::io::stdin(), &mut body)?;
+
+    let groups = group.iter().map(String::as_str).collect::<Vec<_>>();
+    let mut builder = ArticleBuilder::default();
+    builder
+        .from(from)
+        .newsgroups(&groups)
+        .subject(subject)
+        .header("User-Agent", "brokaw-postit")
+        .body_text(&body);
+
+    let article = builder.build()?;
+    println!("{}", builder);
+
+    if dry_run {
+        return Ok(());
+    }
+
+    info!("Connecting...");
+    let mut client = ClientConfig::default()
+        .connection_config(
+            ConnectionConfig::default()
+                .read_timeout(Some(Duration::from_secs(10)))
+                .to_owned(),
+        )
+        .connect((address.as_str(), port))?;
+
+    if !client.posting_allowed() {
+        warn!("The server reports that posting is not allowed");
+    }
+
+    client.post(article)?;
+    info!("Posted!");
+
+    client.close()?;
+    Ok(())
+}
@@ -71,7 +71,7 @@ fn main() -> anyhow::Result<()> {
     let high = group.high;
     let low = high - num_headers;
     info!("Retrieving headers {} through {}", low, high);
-    let resp = client.conn().command(&XOver::Range { low, high })?;
+    let resp = client.conn().command(&XOver::range((low, high))?)?;
     resp.data_blocks().unwrap().lines().for_each(|header| {
         let s = String::from_utf8_lossy(header).to_string();
         println!("{}", s);
@@ -0,0 +1,195 @@
+use std::io::{Read, Write};
+use std::ops::RangeInclusive;
+
+use crate::client::NntpClient;
+use crate::error::{Error, Result};
+use crate::types::prelude::*;
+
+/// What to retrieve for each article walked by [`Articles`]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FetchMode {
+    /// The headers and body (`ARTICLE`)
+    Article,
+    /// Only the headers (`HEAD`)
+    Head,
+    /// Only the body (`BODY`)
+    Body,
+}
+
+/// An item yielded by [`Articles`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ArticleItem {
+    /// An article retrieved in [`FetchMode::Article`]
+    Article(BinaryArticle),
+    /// Headers retrieved in [`FetchMode::Head`]
+    Head(Head),
+    /// A body retrieved in [`FetchMode::Body`]
+    Body(Body),
+    /// The server has no article with the number
+    ///
+    /// This is only yielded if [`report_skipped`](Articles::report_skipped) is enabled
+    Skipped(ArticleNumber),
+}
+
+/// An iterator over the articles in a range of the selected group
+///
+/// Created by [`NntpClient::articles`], a command is sent for each article number.
+///
+/// * Numbers without an article (`423`/`430`) are skipped, see [`report_skipped`](Self::report_skipped)
+/// * Connection errors and [`Error::DeadlineExceeded`] end the iteration after being yielded
+/// * Other errors (e.g. an article that fails to parse) are yielded and iteration continues
+#[derive(Debug)]
+pub struct Articles<'a, S> {
+    client: &'a mut NntpClient<S>,
+    numbers: RangeInclusive<ArticleNumber>,
+    mode: FetchMode,
+    report_skipped: bool,
+    done: bool,
+}
+
+impl<'a, S: Read + Write> Articles<'a, S> {
+    /// Set what to retrieve for each article, defaults to [`FetchMode::Article`]
+    pub fn mode(mut self, mode: FetchMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Yield [`ArticleItem::Skipped`] for numbers without an article rather than dropping them
+    pub fn report_skipped(mut self, report_skipped: bool) -> Self {
+        self.report_skipped = report_skipped;
+        self
+    }
+
+    fn fetch(&mut self, number: ArticleNumber) -> Result<Option<ArticleItem>> {
+        self.client.check_interrupted()?;
+        let item = match self.mode {
+            FetchMode::Article => self
+                .client
+                .article_by_number(number)?
+                .map(ArticleItem::Article),
+            FetchMode::Head => self.client.head_by_number(number)?.map(ArticleItem::Head),
+            FetchMode::Body => self.client.body_by_number(number)?.map(ArticleItem::Body),
+        };
+        Ok(item)
+    }
+}
+
+impl<'a, S: Read + Write> Iterator for Articles<'a, S> {
+    type Item = Result<ArticleItem>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        while let Some(number) = self.numbers.next() {
+            match self.fetch(number) {
+                Ok(Some(item)) => return Some(Ok(item)),
+                Ok(None) if self.report_skipped => return Some(Ok(ArticleItem::Skipped(number))),
+                Ok(None) => continue,
+                Err(e) => {
+                    if let Error::Connection(_) | Error::DeadlineExceeded = e.inner() {
+                        self.done = true;
+                    }
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl<S: Read + Write> NntpClient<S> {
+    /// Iterate over the articles in a range of the selected group
+    ///
+    /// ```no_run
+    /// use brokaw::client::FetchMode;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = brokaw::ClientConfig::default()
+    ///     .group(Some("misc.test"))
+    ///     .connect(("news.example.com", 119))?;
+    /// let (low, high) = client.group().map(|g| (g.low, g.high)).unwrap();
+    ///
+    /// for item in client.articles(low..=high).mode(FetchMode::Head) {
+    ///     println!("{:?}", item?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn articles(&mut self, range: RangeInclusive<ArticleNumber>) -> Articles<'_, S> {
+        Articles {
+            client: self,
+            numbers: range,
+            mode: FetchMode::Article,
+            report_skipped: false,
+            done: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{fixtures, MockStream};
+    use crate::ClientConfig;
+
+    fn head(number: ArticleNumber) -> String {
+        format!(
+            "221 {} <{}@example.com>\r\nSubject: {}\r\n\r\n.\r\n",
+            number, number, number
+        )
+    }
+
+    fn mock_client(stream: MockStream) -> NntpClient<MockStream> {
+        ClientConfig::default().connect_stream(stream).unwrap()
+    }
+
+    fn stream() -> MockStream {
+        MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("HEAD 1", head(1))
+            .expect("HEAD 2", "423 no such article\r\n")
+            .expect("HEAD 3", head(3))
+    }
+
+    #[test]
+    fn test_articles() {
+        let mut client = mock_client(stream());
+        let numbers = client
+            .articles(1..=3)
+            .mode(FetchMode::Head)
+            .map(|item| match item.unwrap() {
+                ArticleItem::Head(head) => head.number,
+                other => panic!("Unexpected item {:?}", other),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(numbers, vec![1, 3]);
+
+        let mut client = mock_client(stream());
+        let items = client
+            .articles(1..=3)
+            .mode(FetchMode::Head)
+            .report_skipped(true)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(items[1], ArticleItem::Skipped(2));
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_articles_connection_error() {
+        // The stream ends after the first article
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("ARTICLE 1", fixtures::ARTICLE);
+        let mut client = mock_client(stream);
+
+        let items = client.articles(1..=5).collect::<Vec<_>>();
+        assert_eq!(items.len(), 2);
+        assert!(matches!(items[0], Ok(ArticleItem::Article(_))));
+        assert!(matches!(items[1], Err(Error::Connection(_))));
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+}
@@ -0,0 +1,287 @@
+use std::io::{Read, Write};
+
+use log::*;
+
+use crate::client::{deserialize, NntpClient};
+use crate::error::{Error, Result};
+use crate::types::command as cmd;
+use crate::types::command::{IntoArticleRange, RangeChunker};
+use crate::types::prelude::*;
+use crate::types::response::{OverviewEntry, OverviewResponse};
+
+/// Failure codes that cause a chunk to be retried as two smaller chunks
+///
+/// Servers that cap the size of ranges respond to large ranges with one of these.
+const SPLIT_CODES: &[u16] = &[420, 502, 503];
+
+impl<S: Read + Write> NntpClient<S> {
+    /// Retrieve the overview entries for a large range of articles in chunks
+    ///
+    /// The range is split into chunks of at most `chunk_size` articles which are retrieved
+    /// sequentially. An open range (e.g. `1000..`) ends at the cached high watermark of the
+    /// selected group.
+    ///
+    /// If the server rejects a chunk with `420`, `502`, or `503` the chunk is retried once
+    /// as two halves, since some servers reject ranges above a certain size. Chunks without
+    /// any articles (`423`) are skipped.
+    ///
+    /// `progress` is called with the number of articles covered so far and the total after
+    /// each chunk, e.g. to render a progress bar.
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = brokaw::ClientConfig::default()
+    ///     .group(Some("misc.test"))
+    ///     .connect(("news.example.com", 119))?;
+    ///
+    /// let entries = client.overview_chunked(1.., 10_000, |done, total| {
+    ///     println!("{}/{}", done, total);
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn overview_chunked(
+        &mut self,
+        range: impl IntoArticleRange,
+        chunk_size: ArticleNumber,
+        progress: impl FnMut(ArticleNumber, ArticleNumber),
+    ) -> Result<Vec<OverviewEntry>> {
+        self.chunked(
+            range.into_article_range()?,
+            chunk_size,
+            progress,
+            |client, low, high| {
+                let resp = client.over_range((low, high))?;
+                deserialize(&client.conn, &resp).map(|o: OverviewResponse| o.entries)
+            },
+        )
+    }
+
+    /// Retrieve a header field for a large range of articles in chunks
+    ///
+    /// The field is retrieved via [`hdr_or_xhdr`](Self::hdr_or_xhdr), please see
+    /// [`overview_chunked`](Self::overview_chunked) for details on the chunking.
+    pub fn hdr_chunked(
+        &mut self,
+        field: impl AsRef<str>,
+        range: impl IntoArticleRange,
+        chunk_size: ArticleNumber,
+        progress: impl FnMut(ArticleNumber, ArticleNumber),
+    ) -> Result<HdrResponse> {
+        let field = field.as_ref();
+        let entries = self.chunked(
+            range.into_article_range()?,
+            chunk_size,
+            progress,
+            |client, low, high| {
+                client
+                    .hdr_or_xhdr(cmd::Hdr::range(field, (low, high))?)
+                    .map(|hdr| hdr.entries)
+            },
+        )?;
+
+        Ok(HdrResponse { entries })
+    }
+
+    /// Resolve a range into its bounds, ending an open range at the selected group's high
+    /// watermark
+    pub(crate) fn range_bounds(
+        &self,
+        range: cmd::ArticleRange,
+    ) -> Result<(ArticleNumber, ArticleNumber)> {
+        let high = match range.high() {
+            Some(high) => high,
+            None => self.group.as_ref().ok_or(Error::NoGroupSelected)?.high,
+        };
+        Ok((range.low(), high))
+    }
+
+    fn chunked<T>(
+        &mut self,
+        range: cmd::ArticleRange,
+        chunk_size: ArticleNumber,
+        mut progress: impl FnMut(ArticleNumber, ArticleNumber),
+        mut fetch: impl FnMut(&mut Self, ArticleNumber, ArticleNumber) -> Result<Vec<T>>,
+    ) -> Result<Vec<T>> {
+        let (low, high) = self.range_bounds(range)?;
+        let total = if low > high { 0 } else { high - low + 1 };
+
+        let mut items = Vec::new();
+        let mut done = 0;
+
+        for (chunk_low, chunk_high) in RangeChunker::new(low, high, chunk_size) {
+            match fetch_chunk(self, &mut fetch, chunk_low, chunk_high) {
+                Ok(chunk) => items.extend(chunk),
+                Err(e) if should_split(&e) && chunk_low < chunk_high => {
+                    let mid = chunk_low + (chunk_high - chunk_low) / 2;
+                    debug!(
+                        "Server rejected {}-{}, retrying as {}-{} and {}-{} -- {}",
+                        chunk_low,
+                        chunk_high,
+                        chunk_low,
+                        mid,
+                        mid + 1,
+                        chunk_high,
+                        e
+                    );
+                    items.extend(fetch_chunk(self, &mut fetch, chunk_low, mid)?);
+                    items.extend(fetch_chunk(self, &mut fetch, mid + 1, chunk_high)?);
+                }
+                Err(e) => return Err(e),
+            }
+
+            done += chunk_high - chunk_low + 1;
+            progress(done, total);
+        }
+
+        Ok(items)
+    }
+}
+
+/// Fetch a single chunk, treating a chunk without articles as empty
+fn fetch_chunk<S: Read + Write, T>(
+    client: &mut NntpClient<S>,
+    fetch: &mut impl FnMut(&mut NntpClient<S>, ArticleNumber, ArticleNumber) -> Result<Vec<T>>,
+    low: ArticleNumber,
+    high: ArticleNumber,
+) -> Result<Vec<T>> {
+    client.check_interrupted()?;
+    match fetch(client, low, high) {
+        Err(Error::Failure { code, .. }) if code == Kind::NoArticleWithNumber.into() => {
+            trace!("No articles in {}-{}", low, high);
+            Ok(Vec::new())
+        }
+        res => res,
+    }
+}
+
+fn should_split(err: &Error) -> bool {
+    match err.inner() {
+        Error::Failure { code, .. } => SPLIT_CODES.contains(&u16::from(*code)),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{fixtures, MockStream};
+    use crate::ClientConfig;
+
+    fn numbers(entries: &[OverviewEntry]) -> Vec<ArticleNumber> {
+        entries.iter().map(|e| e.number).collect()
+    }
+
+    #[test]
+    fn test_overview_chunked() {
+        // The server rejects ranges of more than 2 articles
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("GROUP misc.test", "211 8 1 8 misc.test\r\n")
+            .expect("OVER 1-4", "502 range too large\r\n")
+            .expect("OVER 1-2", fixtures::overview(&[1, 2]))
+            .expect("OVER 3-4", fixtures::overview(&[3]))
+            .expect("OVER 5-8", "503 range too large\r\n")
+            .expect("OVER 5-6", "423 no articles\r\n")
+            .expect("OVER 7-8", fixtures::overview(&[7, 8]));
+        let mut client = ClientConfig::default()
+            .group(Some("misc.test"))
+            .connect_stream(stream)
+            .unwrap();
+
+        let mut progress = vec![];
+        let entries = client
+            .overview_chunked(1.., 4, |done, total| progress.push((done, total)))
+            .unwrap();
+        assert_eq!(numbers(&entries), vec![1, 2, 3, 7, 8]);
+        assert_eq!(progress, vec![(4, 8), (8, 8)]);
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_overview_chunked_deadline() {
+        use std::time::{Duration, Instant};
+
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("OVER 1-2", fixtures::overview(&[1, 2]));
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+
+        let later = Instant::now() + Duration::from_secs(3600);
+        let entries = client
+            .with_deadline(later, |client| client.overview_chunked(1..=2, 2, |_, _| {}))
+            .unwrap();
+        assert_eq!(numbers(&entries), vec![1, 2]);
+
+        // No command is sent once the deadline has passed
+        let res = client.with_deadline(Instant::now(), |client| {
+            client.overview_chunked(3..=4, 2, |_, _| {})
+        });
+        assert!(matches!(res, Err(Error::DeadlineExceeded)));
+        assert!(client.deadline.is_none());
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_overview_chunked_cancelled() {
+        use crate::raw::{CancellationToken, ConnectionConfig};
+
+        let token = CancellationToken::new();
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("OVER 1-2", fixtures::overview(&[1, 2]));
+        let mut client = ClientConfig::default()
+            .connection_config(
+                ConnectionConfig::new()
+                    .cancellation_token(Some(token.clone()))
+                    .to_owned(),
+            )
+            .connect_stream(stream)
+            .unwrap();
+
+        // The chunk in flight completes but no further commands are sent
+        let err = client
+            .overview_chunked(1..=4, 2, |_, _| token.cancel())
+            .unwrap_err();
+        assert!(err.is_cancelled());
+        assert!(!client.conn().is_poisoned());
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_overview_chunked_retry_once() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("OVER 1-4", "502 range too large\r\n")
+            .expect("OVER 1-2", "502 range too large\r\n");
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+
+        let res = client.overview_chunked(1..=4, 10, |_, _| {});
+        assert!(matches!(res, Err(Error::Failure { .. })));
+
+        // Open ranges require a selected group
+        let res = client.overview_chunked(1.., 10, |_, _| {});
+        assert!(matches!(res, Err(Error::NoGroupSelected)));
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_hdr_chunked() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("HDR Subject 1-2", "225 follows\r\n1 one\r\n2 two\r\n.\r\n")
+            .expect("HDR Subject 3-3", "225 follows\r\n3 three\r\n.\r\n");
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+
+        let hdr = client.hdr_chunked("Subject", 1..=3, 2, |_, _| {}).unwrap();
+        assert_eq!(
+            hdr.entries,
+            vec![
+                (1, "one".to_string()),
+                (2, "two".to_string()),
+                (3, "three".to_string())
+            ]
+        );
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+}
@@ -0,0 +1,306 @@
+use std::io::{Read, Write};
+
+use log::*;
+
+use crate::client::NntpClient;
+use crate::error::{Error, Result};
+use crate::types::prelude::*;
+use crate::types::response::OverviewEntry;
+
+/// The default number of articles retrieved per request by [`NntpClient::fetch_new`]
+pub const DEFAULT_CHUNK_SIZE: ArticleNumber = 1000;
+
+/// The position of a reader within a group
+///
+/// A cursor records the highest article number seen so that the next session only retrieves
+/// newer articles via [`NntpClient::fetch_new`]. Cursors are persisted by the caller, e.g. by
+/// storing [`group`](Self::group) and [`last_seen`](Self::last_seen).
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub struct GroupCursor {
+    /// The name of the group
+    pub group: String,
+    /// The highest article number seen, `0` if no articles have been seen
+    pub last_seen: ArticleNumber,
+    #[cfg_attr(feature = "serde", serde(skip, default = "default_chunk_size"))]
+    chunk_size: ArticleNumber,
+}
+
+#[cfg(feature = "serde")]
+fn default_chunk_size() -> ArticleNumber {
+    DEFAULT_CHUNK_SIZE
+}
+
+impl GroupCursor {
+    /// Create a cursor for a group, `last_seen` is `0` for a group that has not been read
+    pub fn new(group: impl Into<String>, last_seen: ArticleNumber) -> Self {
+        Self {
+            group: group.into(),
+            last_seen,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+
+    /// Set the maximum number of articles retrieved per request, defaults to [`DEFAULT_CHUNK_SIZE`]
+    ///
+    /// Some servers limit the size of the range accepted by `OVER`.
+    pub fn chunk_size(&mut self, chunk_size: ArticleNumber) -> &mut Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+}
+
+impl<S: Read + Write> NntpClient<S> {
+    /// Retrieve the overview entries for articles newer than the cursor
+    ///
+    /// The cursor's group is selected and articles from `last_seen + 1` to the server's high
+    /// watermark are retrieved in chunks via [`overview_or_fallback`](Self::overview_or_fallback).
+    /// The cursor is advanced past each chunk that is retrieved successfully.
+    ///
+    /// If a chunk fails after earlier chunks succeeded, the entries retrieved so far are
+    /// returned and the cursor is left after the last successful chunk, so the next call
+    /// resumes from the failed chunk. If the first chunk fails the error is returned.
+    ///
+    /// Returns [`Error::Renumbered`] without changing the cursor if the server's high watermark
+    /// is below `last_seen`, or if articles after `last_seen` have already expired. A cursor
+    /// with `last_seen` of `0` starts from the lowest article in the group.
+    ///
+    /// ```no_run
+    /// use brokaw::client::GroupCursor;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = brokaw::ClientConfig::default().connect(("news.example.com", 119))?;
+    /// let mut cursor = GroupCursor::new("misc.test", 0);
+    ///
+    /// for entry in client.fetch_new(&mut cursor)? {
+    ///     println!("{:?}", entry.subject);
+    /// }
+    /// println!("Read up to {}", cursor.last_seen);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fetch_new(&mut self, cursor: &mut GroupCursor) -> Result<Vec<OverviewEntry>> {
+        let group = self.select_group(&cursor.group)?;
+
+        let renumbered = group.high < cursor.last_seen
+            || (cursor.last_seen > 0 && group.low > cursor.last_seen + 1 && group.number > 0);
+        if renumbered {
+            return Err(Error::Renumbered {
+                group: group.name,
+                last_seen: cursor.last_seen,
+                low: group.low,
+                high: group.high,
+            });
+        }
+
+        let mut low = if cursor.last_seen == 0 {
+            group.low
+        } else {
+            cursor.last_seen + 1
+        };
+        let mut entries = Vec::new();
+
+        while low <= group.high && group.number > 0 {
+            let high = low.saturating_add(cursor.chunk_size - 1).min(group.high);
+
+            match self
+                .check_interrupted()
+                .and_then(|_| self.overview_or_fallback(low..=high))
+            {
+                Ok(chunk) => entries.extend(chunk),
+                Err(e) if entries.is_empty() => return Err(e),
+                Err(e) => {
+                    warn!(
+                        "Failed to retrieve {}-{} in {}, resuming later -- {}",
+                        low, high, cursor.group, e
+                    );
+                    break;
+                }
+            }
+
+            cursor.last_seen = high;
+            low = high + 1;
+        }
+
+        Ok(entries)
+    }
+
+    /// Retrieve the overview entries for articles in the current group newer than `last`
+    ///
+    /// The current group is [refreshed](Self::refresh_group) and the entries for `last + 1` to
+    /// the server's high watermark are retrieved in a single request via
+    /// [`overview_or_fallback`](Self::overview_or_fallback). The high watermark is returned
+    /// alongside the entries so that it can be passed as `last` on the next poll.
+    ///
+    /// If articles after `last` have expired (the server's low watermark is above `last + 1`)
+    /// retrieval starts from the low watermark instead. No entries are returned if nothing is
+    /// new, including when the group was renumbered below `last`.
+    ///
+    /// Returns [`Error::NoGroupSelected`] if no group is selected. Use
+    /// [`fetch_new`](Self::fetch_new) to retrieve large backlogs in chunks.
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = brokaw::ClientConfig::default()
+    ///     .group(Some("misc.test"))
+    ///     .connect(("news.example.com", 119))?;
+    /// let mut last = 0;
+    ///
+    /// loop {
+    ///     let (high, entries) = client.poll_new_since(last)?;
+    ///     for entry in entries {
+    ///         println!("{:?}", entry.subject);
+    ///     }
+    ///     last = high;
+    ///     std::thread::sleep(std::time::Duration::from_secs(60));
+    /// }
+    /// # }
+    /// ```
+    pub fn poll_new_since(
+        &mut self,
+        last: ArticleNumber,
+    ) -> Result<(ArticleNumber, Vec<OverviewEntry>)> {
+        let group = self.refresh_group()?;
+
+        if group.is_empty() || group.high <= last {
+            return Ok((group.high, Vec::new()));
+        }
+
+        let low = last.saturating_add(1).max(group.low);
+        if low > last.saturating_add(1) {
+            debug!(
+                "Articles {}-{} in {} have expired, polling from {}",
+                last.saturating_add(1),
+                low - 1,
+                group.name,
+                low
+            );
+        }
+
+        let entries = self.overview_or_fallback(low..=group.high)?;
+        Ok((group.high, entries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{fixtures, MockStream};
+    use crate::ClientConfig;
+
+    fn mock_stream(group: &str) -> MockStream {
+        MockStream::new(fixtures::GREETING)
+            .expect(
+                "CAPABILITIES",
+                "101 Capability list:\r\nVERSION 2\r\nREADER\r\nOVER\r\n.\r\n",
+            )
+            .expect("GROUP misc.test", group)
+    }
+
+    #[test]
+    fn test_fetch_new() {
+        let stream = mock_stream("211 5 3 9 misc.test\r\n")
+            .expect("OVER 6-7", fixtures::overview(&[6, 7]))
+            .expect("OVER 8-9", fixtures::overview(&[9]));
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+
+        let mut cursor = GroupCursor::new("misc.test", 5);
+        cursor.chunk_size(2);
+        let entries = client.fetch_new(&mut cursor).unwrap();
+        assert_eq!(
+            entries.iter().map(|e| e.number).collect::<Vec<_>>(),
+            vec![6, 7, 9]
+        );
+        assert_eq!(cursor.last_seen, 9);
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_fetch_new_partial() {
+        let stream = mock_stream("211 5 1 5 misc.test\r\n")
+            .expect("OVER 1-3", fixtures::overview(&[1, 2, 3]))
+            .expect("OVER 4-5", "503 program fault\r\n");
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+
+        let mut cursor = GroupCursor::new("misc.test", 0);
+        cursor.chunk_size(3);
+        let entries = client.fetch_new(&mut cursor).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(cursor.last_seen, 3);
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_fetch_new_up_to_date() {
+        let stream = mock_stream("211 5 1 5 misc.test\r\n");
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+
+        let mut cursor = GroupCursor::new("misc.test", 5);
+        assert!(client.fetch_new(&mut cursor).unwrap().is_empty());
+        assert_eq!(cursor.last_seen, 5);
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_poll_new_since() {
+        let stream = mock_stream("211 5 1 5 misc.test\r\n")
+            .expect("GROUP misc.test", "211 7 1 7 misc.test\r\n")
+            .expect("OVER 6-7", fixtures::overview(&[6, 7]))
+            .expect("GROUP misc.test", "211 7 1 7 misc.test\r\n")
+            .expect("GROUP misc.test", "211 3 20 22 misc.test\r\n")
+            .expect("OVER 20-22", fixtures::overview(&[20, 22]));
+        let mut client = ClientConfig::default()
+            .group(Some("misc.test"))
+            .connect_stream(stream)
+            .unwrap();
+
+        let (high, entries) = client.poll_new_since(5).unwrap();
+        assert_eq!(high, 7);
+        assert_eq!(
+            entries.iter().map(|e| e.number).collect::<Vec<_>>(),
+            vec![6, 7]
+        );
+
+        let (high, entries) = client.poll_new_since(high).unwrap();
+        assert_eq!(high, 7);
+        assert!(entries.is_empty());
+
+        // Articles 8-19 expired before they were seen
+        let (high, entries) = client.poll_new_since(high).unwrap();
+        assert_eq!(high, 22);
+        assert_eq!(entries.len(), 2);
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_poll_without_group() {
+        let stream =
+            MockStream::new(fixtures::GREETING).expect("CAPABILITIES", fixtures::CAPABILITIES);
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+        assert!(matches!(
+            client.poll_new_since(0),
+            Err(Error::NoGroupSelected)
+        ));
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_renumbered() {
+        for &group in &["211 5 1 5 misc.test\r\n", "211 5 20 25 misc.test\r\n"] {
+            let mut client = ClientConfig::default()
+                .connect_stream(mock_stream(group))
+                .unwrap();
+
+            let mut cursor = GroupCursor::new("misc.test", 10);
+            assert!(matches!(
+                client.fetch_new(&mut cursor),
+                Err(Error::Renumbered { last_seen: 10, .. })
+            ));
+            assert_eq!(cursor.last_seen, 10);
+        }
+    }
+}
@@ -0,0 +1,409 @@
+//! Offering articles to a peer (e.g. site-to-site feeds)
+//!
+//! A [`Feeder`] negotiates streaming mode with [`MODE STREAM`](cmd::ModeStream). When streaming is
+//! permitted, articles are offered with `CHECK`s that are pipelined up to a configurable
+//! window and written together once it fills, and wanted articles are sent with `TAKETHIS`
+//! ([RFC 4644](https://tools.ietf.org/html/rfc4644)). Otherwise each article is offered with
+//! `IHAVE`, waiting for every response.
+//!
+//! ```no_run
+//! use brokaw::client::feeder::Feeder;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = brokaw::ClientConfig::default().connect(("news.example.com", 119))?;
+//! let mut feeder = Feeder::new(client)?;
+//!
+//! let articles: Vec<(String, Vec<u8>)> = vec![];
+//! for (message_id, article) in articles {
+//!     for offered in feeder.offer(message_id, article)? {
+//!         println!("{} -> {:?}", offered.message_id, offered.outcome);
+//!     }
+//! }
+//! for offered in feeder.flush()? {
+//!     println!("{} -> {:?}", offered.message_id, offered.outcome);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+
+use log::*;
+
+use crate::client::NntpClient;
+use crate::error::{Error, Result};
+use crate::raw::stream::NntpStream;
+use crate::types::command as cmd;
+use crate::types::prelude::*;
+
+/// The default number of `CHECK`s that may await a response
+pub const DEFAULT_WINDOW: usize = 16;
+
+/// What happened to an offered article
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    /// The article was transferred (`239` or `235`)
+    Accepted,
+    /// The server does not want the article (`438` or `435`)
+    NotWanted,
+    /// The article should be offered again later (`431` or `436`)
+    Deferred,
+    /// The server rejected the transferred article (`439` or `437`)
+    Rejected,
+}
+
+/// The outcome of an offered article
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Offered {
+    /// The message ID of the article
+    pub message_id: String,
+    /// What happened to the article
+    pub outcome: Outcome,
+}
+
+/// A command awaiting a response
+#[derive(Debug)]
+enum InFlight {
+    Check {
+        message_id: String,
+        article: Vec<u8>,
+    },
+    TakeThis {
+        message_id: String,
+    },
+}
+
+/// Offers articles to a server, streaming them if possible
+///
+/// Please see the [module documentation](self) for an overview.
+///
+/// Responses are read in the order the commands were sent, so the outcome of an article may
+/// be returned by a later call to [`offer`](Self::offer) or by [`flush`](Self::flush).
+/// If an error is returned, outcomes for pending articles are lost and the connection
+/// should be discarded.
+#[derive(Debug)]
+pub struct Feeder<S = NntpStream> {
+    client: NntpClient<S>,
+    streaming: bool,
+    window: usize,
+    in_flight: VecDeque<InFlight>,
+}
+
+impl<S: Read + Write> Feeder<S> {
+    /// Create a feeder, switching the connection to streaming mode if the server permits it
+    pub fn new(mut client: NntpClient<S>) -> Result<Self> {
+        let resp = client.command(cmd::ModeStream)?;
+        let streaming = resp.code() == ResponseCode::Known(Kind::StreamingPermitted);
+        if !streaming {
+            debug!(
+                "Streaming is not permitted, falling back to IHAVE -- {}",
+                resp.first_line_to_utf8_lossy().trim_end()
+            );
+        }
+
+        Ok(Self {
+            client,
+            streaming,
+            window: DEFAULT_WINDOW,
+            in_flight: VecDeque::new(),
+        })
+    }
+
+    /// Set the number of `CHECK`s that may await a response, defaults to [`DEFAULT_WINDOW`]
+    pub fn window(&mut self, window: usize) -> &mut Self {
+        self.window = window.max(1);
+        self
+    }
+
+    /// Returns true if the server permitted streaming mode
+    pub fn is_streaming(&self) -> bool {
+        self.streaming
+    }
+
+    /// Offer an article, returning the outcomes of any articles that completed
+    ///
+    /// The article should include its headers and MUST NOT be dot-stuffed or terminated.
+    pub fn offer(
+        &mut self,
+        message_id: impl AsRef<str>,
+        article: impl Into<Vec<u8>>,
+    ) -> Result<Vec<Offered>> {
+        let message_id = cmd::MessageId::new(message_id).into();
+        let article = article.into();
+
+        if !self.streaming {
+            return self.ihave(message_id, article).map(|offered| vec![offered]);
+        }
+
+        self.client.conn().queue(&cmd::Check(message_id.clone()))?;
+        self.in_flight.push_back(InFlight::Check {
+            message_id,
+            article,
+        });
+
+        let mut completed = Vec::new();
+        if self.in_flight.len() > self.window {
+            self.client.conn().flush()?;
+        }
+        while self.in_flight.len() > self.window {
+            completed.extend(self.read_one()?);
+        }
+        Ok(completed)
+    }
+
+    /// Write any queued commands and wait for the responses to all of them, returning the
+    /// outcomes
+    pub fn flush(&mut self) -> Result<Vec<Offered>> {
+        self.client.conn().flush()?;
+        let mut completed = Vec::new();
+        while !self.in_flight.is_empty() {
+            completed.extend(self.read_one()?);
+        }
+        Ok(completed)
+    }
+
+    /// Flush any pending commands and return the client
+    pub fn into_client(mut self) -> Result<(NntpClient<S>, Vec<Offered>)> {
+        let completed = self.flush()?;
+        Ok((self.client, completed))
+    }
+
+    /// Read the response to the oldest in-flight command
+    fn read_one(&mut self) -> Result<Option<Offered>> {
+        let in_flight = match self.in_flight.pop_front() {
+            Some(in_flight) => in_flight,
+            None => return Ok(None),
+        };
+        let resp = self.client.conn().read_response_auto()?;
+        let code = resp.code();
+
+        let (message_id, outcome) = match in_flight {
+            InFlight::Check {
+                message_id,
+                article,
+            } => match code {
+                ResponseCode::Known(Kind::CheckSendArticle) => {
+                    let take_this = cmd::TakeThis {
+                        message_id,
+                        article,
+                    };
+                    self.client.conn().queue(&take_this)?;
+                    self.in_flight.push_back(InFlight::TakeThis {
+                        message_id: take_this.message_id,
+                    });
+                    return Ok(None);
+                }
+                ResponseCode::Known(Kind::CheckTryLater) => (message_id, Outcome::Deferred),
+                ResponseCode::Known(Kind::CheckNotWanted) => (message_id, Outcome::NotWanted),
+                _ => {
+                    return Err(Error::unexpected_code(
+                        resp,
+                        &[
+                            Kind::CheckSendArticle.into(),
+                            Kind::CheckTryLater.into(),
+                            Kind::CheckNotWanted.into(),
+                        ],
+                    ))
+                }
+            },
+            InFlight::TakeThis { message_id } => match code {
+                ResponseCode::Known(Kind::TakeThisAccepted) => (message_id, Outcome::Accepted),
+                ResponseCode::Known(Kind::TakeThisRejected) => (message_id, Outcome::Rejected),
+                _ => {
+                    return Err(Error::unexpected_code(
+                        resp,
+                        &[Kind::TakeThisAccepted.into(), Kind::TakeThisRejected.into()],
+                    ))
+                }
+            },
+        };
+
+        Ok(Some(Offered {
+            message_id,
+            outcome,
+        }))
+    }
+
+    /// Offer an article with `IHAVE`
+    fn ihave(&mut self, message_id: String, article: Vec<u8>) -> Result<Offered> {
+        let ihave = cmd::IHaveArticle {
+            message_id,
+            article,
+        };
+        let resp = self.client.conn().command_stages(&ihave)?;
+
+        let outcome = match resp.code() {
+            ResponseCode::Known(Kind::ArticleTransferredOk) => Outcome::Accepted,
+            ResponseCode::Known(Kind::ArticleNotWanted) => Outcome::NotWanted,
+            ResponseCode::Known(Kind::TransferFailed) => Outcome::Deferred,
+            ResponseCode::Known(Kind::TransferRejected) => Outcome::Rejected,
+            _ => {
+                return Err(Error::unexpected_code(
+                    resp,
+                    &[
+                        Kind::ArticleTransferredOk.into(),
+                        Kind::ArticleNotWanted.into(),
+                        Kind::TransferFailed.into(),
+                        Kind::TransferRejected.into(),
+                    ],
+                ))
+            }
+        };
+
+        Ok(Offered {
+            message_id: ihave.message_id,
+            outcome,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::io;
+    use std::rc::Rc;
+
+    use crate::testing::{fixtures, MockStream};
+    use crate::ClientConfig;
+
+    fn feeder(stream: MockStream) -> Feeder<MockStream> {
+        let client = ClientConfig::default().connect_stream(stream).unwrap();
+        Feeder::new(client).unwrap()
+    }
+
+    fn offered(message_id: &str, outcome: Outcome) -> Offered {
+        Offered {
+            message_id: message_id.to_string(),
+            outcome,
+        }
+    }
+
+    #[test]
+    fn test_streaming() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("MODE STREAM", "203 streaming permitted\r\n")
+            .expect("CHECK <a@example.com>", "238 <a@example.com>\r\n")
+            .expect("CHECK <b@example.com>", "438 <b@example.com>\r\n")
+            .expect("CHECK <c@example.com>", "431 <c@example.com>\r\n")
+            .expect("TAKETHIS <a@example.com>", "")
+            .expect("Subject: a", "")
+            .expect(".", "239 <a@example.com>\r\n");
+
+        let mut feeder = feeder(stream);
+        feeder.window(2);
+        assert!(feeder.is_streaming());
+
+        assert!(feeder
+            .offer("a@example.com", "Subject: a")
+            .unwrap()
+            .is_empty());
+        assert!(feeder
+            .offer("b@example.com", "Subject: b")
+            .unwrap()
+            .is_empty());
+        // The third CHECK exceeds the window, so responses are read until it is not exceeded
+        assert_eq!(
+            feeder.offer("c@example.com", "Subject: c").unwrap(),
+            vec![offered("<b@example.com>", Outcome::NotWanted)]
+        );
+        assert_eq!(
+            feeder.flush().unwrap(),
+            vec![
+                offered("<c@example.com>", Outcome::Deferred),
+                offered("<a@example.com>", Outcome::Accepted),
+            ]
+        );
+        let (mut client, _) = feeder.into_client().unwrap();
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    /// Records each write to the wrapped stream
+    struct Writes {
+        inner: MockStream,
+        writes: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl Read for Writes {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.inner.read(buf)
+        }
+    }
+
+    impl Write for Writes {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let n = self.inner.write(buf)?;
+            let write = String::from_utf8_lossy(&buf[..n]).into_owned();
+            self.writes.borrow_mut().push(write);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.inner.flush()
+        }
+    }
+
+    #[test]
+    fn test_checks_pipelined() {
+        let inner = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("MODE STREAM", "203 streaming permitted\r\n")
+            .expect("CHECK <a@example.com>", "438 <a@example.com>\r\n")
+            .expect("CHECK <b@example.com>", "438 <b@example.com>\r\n")
+            .expect("CHECK <c@example.com>", "438 <c@example.com>\r\n");
+        let writes = Rc::new(RefCell::new(Vec::new()));
+        let stream = Writes {
+            inner,
+            writes: writes.clone(),
+        };
+        let client = ClientConfig::default().connect_stream(stream).unwrap();
+        let mut feeder = Feeder::new(client).unwrap();
+        feeder.window(2);
+        writes.borrow_mut().clear();
+
+        feeder.offer("a@example.com", "Subject: a").unwrap();
+        feeder.offer("b@example.com", "Subject: b").unwrap();
+        // Nothing is written until the window fills
+        assert!(writes.borrow().is_empty());
+
+        // Filling the window writes every CHECK at once, before reading any response
+        assert_eq!(
+            feeder.offer("c@example.com", "Subject: c").unwrap(),
+            vec![offered("<a@example.com>", Outcome::NotWanted)]
+        );
+        assert_eq!(
+            *writes.borrow(),
+            vec!["CHECK <a@example.com>\r\nCHECK <b@example.com>\r\nCHECK <c@example.com>\r\n"]
+        );
+
+        assert_eq!(feeder.flush().unwrap().len(), 2);
+        let (mut client, _) = feeder.into_client().unwrap();
+        assert!(client.conn().stream().get_ref().inner.is_done());
+    }
+
+    #[test]
+    fn test_ihave_fallback() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("MODE STREAM", "500 unknown command\r\n")
+            .expect("IHAVE <a@example.com>", "335 send it\r\n")
+            .expect("Subject: a", "")
+            .expect(".", "235 transferred\r\n")
+            .expect("IHAVE <b@example.com>", "435 not wanted\r\n");
+
+        let mut feeder = feeder(stream);
+        assert!(!feeder.is_streaming());
+        assert_eq!(
+            feeder.offer("<a@example.com>", "Subject: a").unwrap(),
+            vec![offered("<a@example.com>", Outcome::Accepted)]
+        );
+        assert_eq!(
+            feeder.offer("<b@example.com>", "Subject: b").unwrap(),
+            vec![offered("<b@example.com>", Outcome::NotWanted)]
+        );
+        assert!(feeder.flush().unwrap().is_empty());
+        let (mut client, _) = feeder.into_client().unwrap();
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+}
@@ -0,0 +1,1916 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use log::*;
+
+use crate::error::{Error, Result};
+
+use crate::client::retry::RetryPolicy;
+use crate::raw::compression::Compression;
+use crate::raw::connection::{ConnectionConfig, NntpConnection};
+use crate::raw::response::RawResponse;
+use crate::raw::stats::{ConnectionStats, ResponseStats};
+use crate::raw::stream::NntpStream;
+use crate::types::command as cmd;
+use crate::types::command::IntoArticleRange;
+use crate::types::prelude::*;
+
+mod articles;
+mod chunked;
+mod cursor;
+pub mod feeder;
+mod overview;
+mod paged;
+pub mod retry;
+
+pub use articles::{ArticleItem, Articles, FetchMode};
+pub use cursor::{GroupCursor, DEFAULT_CHUNK_SIZE};
+pub use overview::ArticleWithOverview;
+pub use paged::OverviewPages;
+
+/// A client that returns typed responses and provides state management
+///
+/// `NntpClient` is built on top of [`NntpConnection`] and offers several niceties:
+///
+/// 1. Responses from the server are typed and semantically validated
+/// 2. Management of the connection state (e.g. current group, known capabilities)
+///
+/// In exchange for these niceties, `NntpClient` does not provide the low-allocation guarantees
+/// that `NntpConnection` does. If you are really concerned about memory management,
+/// you may want to use the [`NntpConnection`].
+///
+/// Like the `NntpConnection`, the client is generic over the underlying stream and
+/// defaults to an [`NntpStream`].
+#[derive(Debug)]
+pub struct NntpClient<S = NntpStream> {
+    conn: NntpConnection<S>,
+    config: ClientConfig,
+    capabilities: Capabilities,
+    group: Option<Group>,
+    group_selected_at: Option<Instant>,
+    greeting: RawResponse,
+    banner: String,
+    posting_allowed: bool,
+    endpoint: Option<Endpoint>,
+    deadline: Option<Instant>,
+    reconnect: retry::Reconnect<S>,
+    quit_on_drop: QuitOnDrop<S>,
+}
+
+/// How the connection is signed off when a client is dropped
+///
+/// Only [`NntpStream`] supports bounding this with a timeout, so it is chosen when the
+/// client is created rather than requiring `Drop` to know the stream type.
+type QuitOnDrop<S> = fn(&mut NntpConnection<S>, Duration);
+
+/// Where a client connected to, used for reconnecting
+#[derive(Clone, Debug)]
+enum Endpoint {
+    Addrs(Vec<SocketAddr>),
+    Host(String, u16),
+}
+
+impl<S: Read + Write> NntpClient<S> {
+    /// Get the raw [`NntpConnection`] for the client
+    ///
+    /// # Usage
+    ///
+    /// NNTP is a **STATEFUL PROTOCOL** and misusing the underlying connection may mess up the
+    /// state in the client that owns the connection.
+    ///
+    /// For example, manually sending a `GROUP`  command would leave change the group of
+    /// the connection but will not update the NntpClient's internal record.
+    ///
+    /// Caveat emptor!
+    pub fn conn(&mut self) -> &mut NntpConnection<S> {
+        &mut self.conn
+    }
+
+    /// A snapshot of the traffic on the connection, see [`NntpConnection::stats`]
+    ///
+    /// The counters start over when the client [reconnects](NntpClient::reconnect).
+    pub fn stats(&self) -> ConnectionStats {
+        self.conn.stats()
+    }
+
+    /// Reset the counters returned by [`stats`](Self::stats) to zero
+    pub fn reset_stats(&mut self) {
+        self.conn.reset_stats()
+    }
+
+    /// The size of the most recent response, see [`NntpConnection::last_response_stats`]
+    pub fn last_response_stats(&self) -> ResponseStats {
+        self.conn.last_response_stats()
+    }
+
+    /// Send a command
+    ///
+    /// This is useful if you want to use a command you have implemented or one that is not
+    /// provided by a client method
+    ///
+    /// # Example
+    ///
+    /// Say we have a server that uses mode switching for whatever reason. Brokaw implements
+    /// a [`ModeReader`](cmd::ModeReader) command but it does not provide a return type.
+    /// We implement one in the following example
+    /// <details><summary>MOTD</summary>
+    ///
+    /// ```no_run
+    /// use std::convert::TryFrom;
+    /// use brokaw::types::prelude::*;
+    /// use brokaw::types::command as cmd;
+    ///
+    /// struct Motd {
+    ///     posting_allowed: bool,
+    ///     motd: String,
+    /// }
+    ///
+    /// impl TryFrom<&RawResponse> for Motd {
+    ///     type Error = String;
+    ///
+    ///     fn try_from(resp: &RawResponse) -> Result<Self, Self::Error> {
+    ///         let posting_allowed = match resp.code() {
+    ///             ResponseCode::Known(Kind::PostingAllowed) => true,
+    ///             ResponseCode::Known(Kind::PostingNotPermitted) => false,
+    ///             ResponseCode::Known(Kind::PermanentlyUnavailable) => {
+    ///                 return Err("Server is gone forever".to_string());
+    ///             }
+    ///             ResponseCode::Known(Kind::TemporarilyUnavailable) => {
+    ///                 return Err("Server is down?".to_string());
+    ///             }
+    ///             code => return Err(format!("Unexpected {:?}", code))
+    ///         };
+    ///         let mut motd = String::from_utf8_lossy(resp.first_line_without_code())
+    ///             .to_string();
+    ///
+    ///         Ok(Motd { posting_allowed, motd })
+    ///     }
+    /// }
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     use brokaw::client::{NntpClient, ClientConfig};
+    ///     let mut client = ClientConfig::default()
+    ///         .connect(("news.modeswitching.notreal", 119))?;
+    ///
+    ///     let resp: Motd = client.command_as(cmd::ModeReader, false)?;
+    ///     println!("Motd: {}", resp.motd);
+    ///     Ok(())
+    /// }
+    /// ```
+    /// </details>
+    pub fn command(&mut self, c: impl NntpCommand) -> Result<RawResponse> {
+        let resp = self.conn.command(&c)?;
+        Ok(resp)
+    }
+
+    /// Send a command and convert the response into `T`
+    ///
+    /// If `force_multiline` is set the response is always read as multiline, which is required
+    /// for commands whose response codes are not known to the library. Otherwise this is
+    /// determined from the response code. Conversion failures are returned as an
+    /// [`Error::Deserialization`].
+    ///
+    /// See [`command`](Self::command) for an example.
+    pub fn command_as<T>(&mut self, c: impl NntpCommand, force_multiline: bool) -> Result<T>
+    where
+        T: for<'a> TryFrom<&'a RawResponse>,
+        for<'a> <T as TryFrom<&'a RawResponse>>::Error: fmt::Display,
+    {
+        // Leave it to the response code unless the caller knows better
+        let is_multiline = Some(true).filter(|_| force_multiline);
+        self.conn.send(&c)?;
+        let resp = self.conn.read_response(is_multiline)?;
+        T::try_from(&resp).map_err(|e| Error::de(e.to_string()))
+    }
+
+    /// Send a command and deserialize the response, keeping the response code
+    ///
+    /// Any response that can be deserialized into `B` is accepted, so this is useful when
+    /// the code matters (e.g. to tell `200` and `201` apart). State such as the selected
+    /// group is **not** updated.
+    ///
+    /// ```no_run
+    /// use brokaw::types::command as cmd;
+    /// use brokaw::types::response::Stat;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = brokaw::ClientConfig::default().connect(("news.example.com", 119))?;
+    /// let stat = client.command_typed::<Stat>(cmd::Stat::Current)?;
+    /// println!("{} -> {}", stat.code(), stat.body().message_id);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn command_typed<B>(&mut self, c: impl NntpCommand) -> Result<Response<B>>
+    where
+        B: for<'a> TryFrom<&'a RawResponse, Error = Error>,
+    {
+        let resp = self.conn.command(&c)?;
+        let body = deserialize(&self.conn, &resp)?;
+        Ok(Response::new(resp.code(), body))
+    }
+
+    /// Get the currently selected group
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    /// Get the last selected group
+    pub fn group(&self) -> Option<&Group> {
+        self.group.as_ref()
+    }
+
+    /// The time elapsed since the [`group`](Self::group) was selected or refreshed
+    ///
+    /// Returns `None` if no group is selected.
+    pub fn group_age(&self) -> Option<Duration> {
+        self.group_selected_at.map(|t| t.elapsed())
+    }
+
+    /// Select a newsgroup
+    ///
+    /// Returns [`Error::InvalidGroupName`] without contacting the server if the name is not
+    /// valid, see [`cmd::Group::new`].
+    pub fn select_group(&mut self, name: impl AsRef<str>) -> Result<Group> {
+        let command = cmd::Group::new(name)?;
+        let resp = self.command_retrying(&command, &[Kind::GroupSelected])?;
+        let group: Group = deserialize(&self.conn, &resp)?;
+        self.group = Some(group.clone());
+        self.group_selected_at = Some(Instant::now());
+        Ok(group)
+    }
+
+    /// Run operations that must finish by `deadline`
+    ///
+    /// Operations that send several commands (e.g. [`overview_chunked`](Self::overview_chunked),
+    /// [`fetch_new`](Self::fetch_new), or iterating [`articles`](Self::articles)) check the
+    /// deadline before each command and return [`Error::DeadlineExceeded`] once it has passed.
+    /// Reads that are in progress also stop at the deadline: for TCP streams the socket's
+    /// [`read_timeout`](ConnectionConfig::read_timeout) is clamped to the time remaining.
+    /// A command interrupted this way poisons the connection.
+    ///
+    /// The previous deadline is restored afterwards, nested deadlines cannot extend an outer one.
+    ///
+    /// ```no_run
+    /// use std::time::{Duration, Instant};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = brokaw::ClientConfig::default()
+    ///     .group(Some("misc.test"))
+    ///     .connect(("news.example.com", 119))?;
+    ///
+    /// let deadline = Instant::now() + Duration::from_secs(30);
+    /// let entries = client.with_deadline(deadline, |client| {
+    ///     client.overview_chunked(1.., 1000, |_, _| {})
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_deadline<T>(
+        &mut self,
+        deadline: Instant,
+        f: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        let previous = self.deadline;
+        self.deadline = Some(previous.map_or(deadline, |p| p.min(deadline)));
+        self.conn.set_deadline(self.deadline);
+        let res = f(self);
+        self.deadline = previous;
+        self.conn.set_deadline(previous);
+        res
+    }
+
+    /// Returns [`Error::DeadlineExceeded`] if the deadline set by
+    /// [`with_deadline`](Self::with_deadline) has passed, or a cancelled error if the
+    /// connection's [`CancellationToken`](crate::raw::CancellationToken) has been tripped
+    pub(crate) fn check_interrupted(&self) -> Result<()> {
+        let cancellation = self.conn.config().cancellation.as_ref();
+        if matches!(cancellation, Some(token) if token.is_cancelled()) {
+            return Err(crate::raw::error::Error::Cancelled.into());
+        }
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Err(Error::DeadlineExceeded),
+            _ => Ok(()),
+        }
+    }
+
+    /// The time left until the deadline set by [`with_deadline`](Self::with_deadline)
+    pub(crate) fn time_remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Re-select the current group to update its article counts and bounds
+    ///
+    /// The counts returned by `GROUP` drift as articles are posted, so callers that need an
+    /// accurate `high` watermark (e.g. before polling for new articles) should refresh the
+    /// group periodically, see [`group_age`](Self::group_age).
+    ///
+    /// Returns [`Error::NoGroupSelected`] if no group is selected.
+    pub fn refresh_group(&mut self) -> Result<Group> {
+        let name = match &self.group {
+            Some(group) => group.name.clone(),
+            None => return Err(Error::NoGroupSelected),
+        };
+        self.select_group(name)
+    }
+
+    /// Select a newsgroup only if it is not already the selected group
+    ///
+    /// If the client's [`group`](Self::group) has the same name, the cached [`Group`] is returned
+    /// and no command is sent to the server.
+    ///
+    /// Note that the article counts and bounds of the cached group may be stale, callers that
+    /// need fresh counts should use [`select_group`](Self::select_group) instead.
+    pub fn select_group_if_needed(&mut self, name: impl AsRef<str>) -> Result<Group> {
+        match &self.group {
+            Some(group) if group.name == name.as_ref() => {
+                trace!("Group {} is already selected", group.name);
+                Ok(group.clone())
+            }
+            _ => self.select_group(name),
+        }
+    }
+
+    /// The capabilities cached in the client
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    /// Returns true if the server advertises the `POST` capability
+    ///
+    /// This and the other `supports_*` methods are derived from the cached
+    /// [`capabilities`](Self::capabilities) and do not contact the server.
+    /// They reflect the latest call to [`update_capabilities`](Self::update_capabilities).
+    pub fn supports_post(&self) -> bool {
+        self.capabilities.post()
+    }
+
+    /// Returns true if the server advertises the `OVER` capability
+    pub fn supports_over(&self) -> bool {
+        self.capabilities.supports("OVER")
+    }
+
+    /// Returns true if the server advertises the `COMPRESS` capability
+    ///
+    /// [RFC 8054](https://tools.ietf.org/html/rfc8054) `COMPRESS` and Giganews style
+    /// `XFEATURE-COMPRESS` are both considered
+    pub fn supports_compress(&self) -> bool {
+        self.capabilities.supports("COMPRESS") || self.capabilities.supports("XFEATURE-COMPRESS")
+    }
+
+    /// Enable compression using a method advertised by the server
+    ///
+    /// [RFC 8054](https://tools.ietf.org/html/rfc8054) `COMPRESS DEFLATE` is preferred, falling
+    /// back to Giganews style `XFEATURE COMPRESS GZIP TERMINATOR`, which only compresses
+    /// overview responses. The method that was enabled is returned, if `COMPRESS DEFLATE` is
+    /// already active no command is sent.
+    ///
+    /// Returns [`Error::Unsupported`] if the server advertises neither, or an [`Error::Failure`]
+    /// if it refuses to enable compression. The connection configuration is updated so that
+    /// `COMPRESS DEFLATE` is negotiated again if the client [reconnects](NntpClient::reconnect).
+    pub fn enable_compression(&mut self) -> Result<Compression> {
+        if self.conn.is_deflate_active() {
+            return Ok(Compression::Deflate);
+        }
+
+        let compression = if self.capabilities.supports_arg("COMPRESS", "DEFLATE") {
+            debug!("Enabling COMPRESS DEFLATE");
+            self.conn
+                .command_expect(&cmd::Compress::Deflate, Kind::CompressionActive)?;
+            Compression::Deflate
+        } else if self.capabilities.supports_arg("XFEATURE-COMPRESS", "GZIP") {
+            debug!("Enabling XFEATURE COMPRESS GZIP");
+            self.conn
+                .command_expect(&cmd::XFeatureCompress, XFEATURE_ENABLED)?;
+            self.conn.set_compression(Some(Compression::XFeature));
+            Compression::XFeature
+        } else {
+            return Err(Error::Unsupported {
+                capability: "COMPRESS",
+            });
+        };
+
+        self.config.conn_config.compression(Some(compression));
+        Ok(compression)
+    }
+
+    /// Returns true if the server advertises the `STARTTLS` capability
+    pub fn supports_starttls(&self) -> bool {
+        self.capabilities.supports("STARTTLS")
+    }
+
+    /// Returns true if the server must be switched to reader mode via `MODE READER`
+    ///
+    /// Per [RFC 3977](https://tools.ietf.org/html/rfc3977#section-3.4.2) this is the case
+    /// when the server advertises `MODE-READER` but not `READER`.
+    pub fn reader_mode_required(&self) -> bool {
+        self.capabilities.supports("MODE-READER") && !self.capabilities.reader()
+    }
+
+    /// Switch the server to reader mode via `MODE READER`
+    ///
+    /// The server may change whether posting is allowed when switching modes, so
+    /// [`posting_allowed`](Self::posting_allowed) is updated from the response. Capabilities
+    /// are retrieved again since they may differ in reader mode.
+    pub fn mode_reader(&mut self) -> Result<RawResponse> {
+        let resp = self.conn.command_expect_any(
+            &cmd::ModeReader,
+            &[Kind::PostingAllowed, Kind::PostingProhibited],
+        )?;
+        self.posting_allowed = is_posting_allowed(&resp);
+        self.update_capabilities()?;
+
+        Ok(resp)
+    }
+
+    /// Returns true if the server allows posting
+    ///
+    /// This is determined by the greeting (`200` vs `201`) and updated by
+    /// [`mode_reader`](Self::mode_reader). The server may still reject an individual post.
+    pub fn posting_allowed(&self) -> bool {
+        self.posting_allowed
+    }
+
+    /// Retrieve updated capabilities from the server
+    pub fn update_capabilities(&mut self) -> Result<&Capabilities> {
+        let resp = self
+            .conn
+            .command_expect(&cmd::Capabilities, Kind::Capabilities)?;
+
+        let capabilities = deserialize(&self.conn, &resp)?;
+
+        self.capabilities = capabilities;
+
+        Ok(&self.capabilities)
+    }
+
+    /// Retrieve a specific header field from one or more articles
+    ///
+    /// The fields supported by the server can be retrieved via [`list_headers`](Self::list_headers)
+    pub fn hdr(&mut self, hdr: cmd::Hdr) -> Result<HdrResponse> {
+        let resp = self.command_retrying(&hdr, &[Kind::Hdr])?;
+        deserialize(&self.conn, &resp)
+    }
+
+    /// Retrieve a specific header from one or more articles using the RFC 2980 `XHDR` command
+    pub fn xhdr(&mut self, xhdr: cmd::XHdr) -> Result<XHdrResponse> {
+        let resp = self.command_retrying(&xhdr, &[Kind::Head])?;
+        deserialize(&self.conn, &resp)
+    }
+
+    /// Retrieve a specific header field using `HDR` if available, falling back to `XHDR`
+    ///
+    /// The choice is based on whether the server advertises the `HDR` capability, many older
+    /// servers only implement `XHDR`.
+    pub fn hdr_or_xhdr(&mut self, hdr: cmd::Hdr) -> Result<HdrResponse> {
+        if self.capabilities.hdr() {
+            self.hdr(hdr)
+        } else {
+            debug!("Server does not advertise HDR, falling back to XHDR");
+            self.xhdr(hdr.into()).map(Into::into)
+        }
+    }
+
+    /// Retrieve a header field for a range of articles, see [`hdr_or_xhdr`](Self::hdr_or_xhdr)
+    pub fn hdr_range(
+        &mut self,
+        field: impl Into<String>,
+        range: impl IntoArticleRange,
+    ) -> Result<HdrResponse> {
+        self.hdr_or_xhdr(cmd::Hdr::range(field, range)?)
+    }
+
+    /// Retrieve the overview fields for one or more articles
+    ///
+    /// Message-ids are only accepted if the server advertises `OVER MSGID`, see
+    /// [`over_msgid`](Self::over_msgid).
+    ///
+    /// The overview lines are returned in the data blocks of the response.
+    pub fn over(&mut self, over: cmd::Over) -> Result<RawResponse> {
+        if let cmd::Over::MessageId(_) = &over {
+            if !self.capabilities.over_msgid() {
+                return Err(Error::Unsupported {
+                    capability: "OVER MSGID",
+                });
+            }
+        }
+
+        let resp = self.command_retrying(&over, &[Kind::Overview])?;
+        Ok(resp)
+    }
+
+    /// Retrieve the overview fields for a range of articles
+    ///
+    /// e.g. `client.over_range(1000..)` retrieves every article from 1000 onwards. A reversed
+    /// range (`low > high`) returns an [`Error::InvalidRange`] without contacting the server.
+    pub fn over_range(&mut self, range: impl IntoArticleRange) -> Result<RawResponse> {
+        self.over(cmd::Over::range(range)?)
+    }
+
+    /// Retrieve the overview fields for an article by message-id
+    ///
+    /// Returns [`Error::Unsupported`] without contacting the server if it does not advertise
+    /// `OVER MSGID`.
+    pub fn over_msgid(&mut self, message_id: impl AsRef<str>) -> Result<RawResponse> {
+        self.over(cmd::Over::MessageId(message_id.as_ref().to_string()))
+    }
+
+    /// Retrieve the fields that may be retrieved via the `HDR` command
+    pub fn list_headers(&mut self) -> Result<ListHeaders> {
+        let resp = self.command_retrying(&cmd::List::Headers, &[Kind::List])?;
+
+        deserialize(&self.conn, &resp)
+    }
+
+    /// Send an arbitrary `LIST` command and return the lines of the response
+    ///
+    /// This can be used for `LIST` keywords that do not have a dedicated type
+    /// (e.g. `LIST MOTD` or `LIST SUBSCRIPTIONS`). A `215` response is always read as multi-line,
+    /// any other response code is returned as an error.
+    ///
+    /// Lines are returned without their CRLF terminators, are dot-unstuffed, and are
+    /// converted to UTF-8 lossily.
+    pub fn list_raw(&mut self, keyword: &str, args: &[&str]) -> Result<Vec<String>> {
+        let list = cmd::List::Other {
+            keyword: keyword.to_string(),
+            args: args.iter().map(ToString::to_string).collect(),
+        };
+        let resp = self.command_retrying(&list, &[Kind::List])?;
+
+        let lines = resp
+            .data_blocks()
+            .ok_or_else(Error::missing_data_blocks)?
+            .unterminated()
+            .map(|line| {
+                // https://tools.ietf.org/html/rfc3977#section-3.1.1
+                let line = if line.starts_with(b"..") {
+                    &line[1..]
+                } else {
+                    line
+                };
+                String::from_utf8_lossy(line).into_owned()
+            })
+            .collect();
+
+        Ok(lines)
+    }
+
+    /// Retrieve an article from the server
+    ///
+    ///
+    /// # Text Articles
+    ///
+    /// Binary articles can be converted to text using the [`to_text`](BinaryArticle::to_text)
+    /// and [`to_text_lossy`](BinaryArticle::to_text_lossy) methods. Note that the former is fallible
+    /// as it will validate that the body of the article is UTF-8.
+    ///
+    /// The [`text_article`](Self::text_article) and [`text_article_lossy`](Self::text_article_lossy)
+    /// methods perform the conversion for you.
+    ///
+    /// ```
+    /// use brokaw::client::NntpClient;
+    /// use brokaw::error::Result;
+    /// use brokaw::types::prelude::*;
+    /// use brokaw::types::command::Article;
+    ///
+    /// fn checked_conversion(client: &mut NntpClient) -> Result<TextArticle> {
+    ///     client.article(Article::Number(42))
+    ///         .and_then(|b| b.to_text())
+    /// }
+    ///
+    /// fn lossy_conversion(client: &mut NntpClient) -> Result<TextArticle> {
+    ///     client.article(Article::Number(42))
+    ///         .map(|b| b.to_text_lossy())
+    /// }
+    ///
+    /// ```
+    pub fn article(&mut self, article: cmd::Article) -> Result<BinaryArticle> {
+        let resp = self.command_retrying(&article, &[Kind::Article])?;
+
+        deserialize_owned(&self.conn, resp)
+    }
+
+    /// Retrieve a text article from the server
+    ///
+    /// This is equivalent to calling [`article`](Self::article) followed by
+    /// [`to_text`](BinaryArticle::to_text). Prefer this method for text newsgroups where
+    /// the body is expected to be UTF-8.
+    ///
+    /// If the body is not valid UTF-8 an [`Error::Utf8`] will be returned. For binary
+    /// newsgroups (e.g. attachments) use [`article`](Self::article) instead.
+    pub fn text_article(&mut self, article: cmd::Article) -> Result<TextArticle> {
+        self.article(article)?.to_text()
+    }
+
+    /// Retrieve a text article from the server, replacing invalid UTF-8 with placeholders
+    ///
+    /// This is equivalent to calling [`article`](Self::article) followed by
+    /// [`to_text_lossy`](BinaryArticle::to_text_lossy). Prefer this method over
+    /// [`text_article`](Self::text_article) if you would rather display a mangled article
+    /// than fail outright.
+    pub fn text_article_lossy(&mut self, article: cmd::Article) -> Result<TextArticle> {
+        self.article(article).map(|b| b.to_text_lossy())
+    }
+
+    /// Retrieve the body for an article
+    pub fn body(&mut self, body: cmd::Body) -> Result<Body> {
+        let resp = self.command_retrying(&body, &[Kind::Body])?;
+        deserialize_owned(&self.conn, resp)
+    }
+
+    /// Retrieve the body for an article, reporting the number of bytes read so far
+    ///
+    /// `progress` is called each time at least `interval` more bytes of the body have been
+    /// read, and once more with the total when the body is complete. Use
+    /// [`article_size`](Self::article_size) beforehand to turn this into a percentage.
+    ///
+    /// Unlike [`body`](Self::body) the command is not retried, as the callback would observe
+    /// the download restarting. See
+    /// [`NntpConnection::command_with_progress`] for how panics in `progress` are handled.
+    ///
+    /// ```no_run
+    /// use brokaw::client::NntpClient;
+    /// use brokaw::error::Result;
+    /// use brokaw::types::command::{Article, Body};
+    ///
+    /// fn download(client: &mut NntpClient) -> Result<()> {
+    ///     let size = client.article_size(Article::Number(42))?;
+    ///     let body = client.body_with_progress(Body::Number(42), 64 * 1024, |bytes| {
+    ///         match size {
+    ///             Some(size) if size > 0 => println!("{}%", (bytes * 100 / size).min(100)),
+    ///             _ => println!("{} bytes", bytes),
+    ///         }
+    ///     })?;
+    ///     println!("Read {} lines", body.lines_len());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn body_with_progress(
+        &mut self,
+        body: cmd::Body,
+        interval: u64,
+        progress: impl FnMut(u64),
+    ) -> Result<Body> {
+        let resp = self
+            .conn
+            .command_with_progress(&body, interval, progress)?
+            .fail_unless(Kind::Body)?;
+        deserialize_owned(&self.conn, resp)
+    }
+
+    /// Retrieve the headers for an article
+    pub fn head(&mut self, head: cmd::Head) -> Result<Head> {
+        let resp = self.command_retrying(&head, &[Kind::Head])?;
+        deserialize(&self.conn, &resp)
+    }
+
+    /// Retrieve an article by message-id, returning `None` if the server does not have it
+    ///
+    /// The id may be given with or without angle brackets, see [`cmd::MessageId`].
+    pub fn article_by_id(&mut self, id: impl AsRef<str>) -> Result<Option<BinaryArticle>> {
+        let id = cmd::MessageId::new(id).into();
+        self.fetch_optional(cmd::Article::MessageId(id), Kind::Article)
+    }
+
+    /// Retrieve the headers of an article by message-id, returning `None` if the server
+    /// does not have it
+    pub fn head_by_id(&mut self, id: impl AsRef<str>) -> Result<Option<Head>> {
+        let id = cmd::MessageId::new(id).into();
+        self.fetch_optional(cmd::Head::MessageId(id), Kind::Head)
+    }
+
+    /// Retrieve the body of an article by message-id, returning `None` if the server
+    /// does not have it
+    pub fn body_by_id(&mut self, id: impl AsRef<str>) -> Result<Option<Body>> {
+        let id = cmd::MessageId::new(id).into();
+        self.fetch_optional(cmd::Body::MessageId(id), Kind::Body)
+    }
+
+    /// Retrieve the status of an article by message-id, returning `None` if the server
+    /// does not have it
+    pub fn stat_by_id(&mut self, id: impl AsRef<str>) -> Result<Option<Stat>> {
+        let id = cmd::MessageId::new(id).into();
+        self.fetch_optional(cmd::Stat::MessageId(id), Kind::ArticleExists)
+    }
+
+    /// Retrieve an article from the current group, returning `None` if there is no article
+    /// with the number
+    pub fn article_by_number(&mut self, number: ArticleNumber) -> Result<Option<BinaryArticle>> {
+        self.fetch_optional(cmd::Article::Number(number), Kind::Article)
+    }
+
+    /// Retrieve the headers of an article from the current group, returning `None` if there
+    /// is no article with the number
+    pub fn head_by_number(&mut self, number: ArticleNumber) -> Result<Option<Head>> {
+        self.fetch_optional(cmd::Head::Number(number), Kind::Head)
+    }
+
+    /// Retrieve the body of an article from the current group, returning `None` if there
+    /// is no article with the number
+    pub fn body_by_number(&mut self, number: ArticleNumber) -> Result<Option<Body>> {
+        self.fetch_optional(cmd::Body::Number(number), Kind::Body)
+    }
+
+    /// Retrieve the status of an article from the current group, returning `None` if there
+    /// is no article with the number
+    pub fn stat_by_number(&mut self, number: ArticleNumber) -> Result<Option<Stat>> {
+        self.fetch_optional(cmd::Stat::Number(number), Kind::ArticleExists)
+    }
+
+    /// Send a command that retrieves an article, mapping `430` and `423` to `None`
+    fn fetch_optional<C, T>(&mut self, command: C, kind: Kind) -> Result<Option<T>>
+    where
+        C: NntpCommand,
+        T: TryFrom<RawResponse, Error = Error> + for<'a> TryFrom<&'a RawResponse, Error = Error>,
+    {
+        let resp = self.command_retrying(
+            &command,
+            &[
+                kind,
+                Kind::NoArticleWithMessageId,
+                Kind::NoArticleWithNumber,
+            ],
+        )?;
+
+        if resp.code() == ResponseCode::Known(kind) {
+            deserialize_owned(&self.conn, resp).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Send an idempotent command, retrying it according to the configured [`RetryPolicy`]
+    ///
+    /// Any response code other than `expected` is returned as an [`Error::Failure`].
+    fn command_retrying<C: NntpCommand>(
+        &mut self,
+        command: &C,
+        expected: &[Kind],
+    ) -> Result<RawResponse> {
+        if !self.config.retry_policy.is_enabled() {
+            return self.conn.command_expect_any(command, expected);
+        }
+
+        let policy = self.config.retry_policy.clone();
+        // A new connection has no current article, so the command can't be retried on it
+        let reconnect = Some(self.reconnect).filter(|_| !command.uses_current_article());
+        retry::retry(self, &policy, reconnect, |client| {
+            client.conn.command_expect_any(command, expected)
+        })
+    }
+
+    /// Retrieve the status of an article
+    pub fn stat(&mut self, stat: cmd::Stat) -> Result<Option<Stat>> {
+        let resp = self.command_retrying(
+            &stat,
+            &[
+                Kind::ArticleExists,
+                Kind::NoArticleWithMessageId,
+                Kind::InvalidCurrentArticleNumber,
+                Kind::NoArticleWithNumber,
+            ],
+        )?;
+
+        if resp.code() == ResponseCode::Known(Kind::ArticleExists) {
+            deserialize(&self.conn, &resp).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Retrieve the storage path(s) of an article by message-id
+    ///
+    /// `XPATH` is a non-standard extension from [RFC 2980](https://tools.ietf.org/html/rfc2980#section-2.11)
+    /// and the format of the returned paths is server-dependent.
+    ///
+    /// Returns `None` if the server does not know the article (`430`) or rejects the
+    /// message-id (`501`).
+    pub fn xpath(&mut self, message_id: impl AsRef<str>) -> Result<Option<XPathResponse>> {
+        let resp = self.conn.command_expect_any(
+            &cmd::XPath(message_id.as_ref().to_string()),
+            &[
+                Kind::ArticleExists,
+                Kind::NoArticleWithMessageId,
+                Kind::SyntaxError,
+            ],
+        )?;
+
+        if resp.code() == ResponseCode::Known(Kind::ArticleExists) {
+            deserialize(&self.conn, &resp).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Post an article
+    ///
+    /// The article should include its headers and MUST NOT be dot-stuffed or terminated.
+    /// If the server refuses the article (e.g. `440` or `441`) an [`Error::Failure`] is returned.
+    pub fn post(&mut self, article: impl Into<Vec<u8>>) -> Result<()> {
+        self.conn
+            .command_stages(&cmd::Post(article.into()))?
+            .fail_unless(Kind::ArticleReceivedOk)?;
+        Ok(())
+    }
+
+    /// Offer an article to the server via `IHAVE`, transferring it if it is wanted
+    ///
+    /// If the server does not want the article (`435`) or rejects it (e.g. `436` or `437`)
+    /// an [`Error::Failure`] is returned.
+    pub fn ihave(
+        &mut self,
+        message_id: impl AsRef<str>,
+        article: impl Into<Vec<u8>>,
+    ) -> Result<()> {
+        let ihave = cmd::IHaveArticle {
+            message_id: message_id.as_ref().to_string(),
+            article: article.into(),
+        };
+        self.conn
+            .command_stages(&ihave)?
+            .fail_unless(Kind::ArticleTransferredOk)?;
+        Ok(())
+    }
+
+    /// The greeting sent by the server when the connection was opened
+    pub fn greeting(&self) -> &RawResponse {
+        &self.greeting
+    }
+
+    /// The text of the greeting following the response code
+    ///
+    /// Some providers include account or quota information in the banner.
+    pub fn server_banner(&self) -> &str {
+        &self.banner
+    }
+
+    /// Whether the greeting allowed posting, `true` for `200` and `false` for `201`
+    ///
+    /// Returns `None` for any other greeting. Unlike [`posting_allowed`](Self::posting_allowed)
+    /// this is not updated by [`mode_reader`](Self::mode_reader).
+    pub fn posting_allowed_on_connect(&self) -> Option<bool> {
+        match self.greeting.code() {
+            ResponseCode::Known(Kind::PostingAllowed) => Some(true),
+            ResponseCode::Known(Kind::PostingProhibited) => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Send `QUIT` and return the server's sign-off message
+    ///
+    /// The client is consumed so that it cannot be used after the server closes the session.
+    pub fn quit(mut self) -> Result<String> {
+        let resp = self.close()?;
+        let msg = String::from_utf8_lossy(resp.first_line_without_code());
+
+        Ok(msg.trim().to_string())
+    }
+
+    /// Close the connection to the server
+    ///
+    /// Prefer [`quit`](Self::quit) which prevents the client from being reused
+    pub fn close(&mut self) -> Result<RawResponse> {
+        let resp = self
+            .conn
+            .command_expect(&cmd::Quit, Kind::ConnectionClosing)?;
+
+        Ok(resp)
+    }
+}
+
+impl NntpClient<NntpStream> {
+    /// Replace the connection with a new one to the same server
+    ///
+    /// The new connection is initialized like the original (authentication, capabilities,
+    /// compression, and the configured group). If a different group was selected it is
+    /// selected again.
+    ///
+    /// Clients created with [`ClientConfig::connect_stream`] cannot reconnect and return an
+    /// I/O error with [`ErrorKind::NotConnected`](std::io::ErrorKind::NotConnected).
+    pub fn reconnect(&mut self) -> Result<()> {
+        let mut client = match &self.endpoint {
+            Some(Endpoint::Addrs(addrs)) => self.config.connect(&addrs[..])?,
+            Some(Endpoint::Host(host, port)) => self.config.connect_host(host, *port)?,
+            None => return Err(not_reconnectable()),
+        };
+        client.deadline = self.deadline;
+        client.conn.set_deadline(self.deadline);
+
+        if let Some(group) = &self.group {
+            if client.group.as_ref().map(|g| &g.name) != Some(&group.name) {
+                client.select_group(&group.name)?;
+            }
+        }
+
+        *self = client;
+        Ok(())
+    }
+}
+
+impl<S> Drop for NntpClient<S> {
+    /// Sign off with `QUIT` unless the client was already closed
+    ///
+    /// See [`ClientConfig::quit_on_drop`].
+    fn drop(&mut self) {
+        if let Some(grace) = self.config.quit_on_drop {
+            (self.quit_on_drop)(&mut self.conn, grace)
+        }
+    }
+}
+
+/// Configuration for an [`NntpClient`]
+#[derive(Clone, Debug)]
+pub struct ClientConfig {
+    authinfo: Option<(String, String)>,
+    group: Option<String>,
+    conn_config: ConnectionConfig,
+    require_capabilities: bool,
+    quit_on_drop: Option<Duration>,
+    retry_policy: RetryPolicy,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            authinfo: None,
+            group: None,
+            conn_config: ConnectionConfig::default(),
+            require_capabilities: false,
+            quit_on_drop: Some(Duration::from_millis(250)),
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+}
+
+impl ClientConfig {
+    /// Create a new client builder
+    ///
+    /// Connection level settings (e.g. TLS, compression, and timeouts) are set via
+    /// [`connection_config`](Self::connection_config).
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use brokaw::ClientConfig;
+    /// use brokaw::raw::connection::{ConnectionConfig, TlsConfig};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = ClientConfig::new()
+    ///     .authinfo_user_pass("user", "pass")
+    ///     .group(Some("misc.test"))
+    ///     .connection_config(
+    ///         ConnectionConfig::new()
+    ///             .tls_config(Some(TlsConfig::default_connector("news.example.com")?))
+    ///             .read_timeout(Some(Duration::from_secs(30)))
+    ///             .to_owned(),
+    ///     )
+    ///     .connect(("news.example.com", 563))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new() -> ClientConfig {
+        Default::default()
+    }
+
+    /// Perform an AUTHINFO USER/PASS authentication after connecting to the server
+    ///
+    /// https://tools.ietf.org/html/rfc4643#section-2.3
+    pub fn authinfo_user_pass(
+        &mut self,
+        username: impl AsRef<str>,
+        password: impl AsRef<str>,
+    ) -> &mut Self {
+        self.authinfo = Some((username.as_ref().to_string(), password.as_ref().to_string()));
+        self
+    }
+
+    /// Join a group upon connection
+    ///
+    /// If this is set to None then no `GROUP` command will be sent when the client is initialized.
+    /// The name is validated when connecting.
+    pub fn group(&mut self, name: Option<impl AsRef<str>>) -> &mut Self {
+        self.group = name.map(|s| s.as_ref().to_string());
+        self
+    }
+
+    /// Fail to connect if the server does not respond to `CAPABILITIES`
+    ///
+    /// By default a server that rejects `CAPABILITIES` (e.g. legacy servers that predate
+    /// RFC 3977, or servers that require `MODE READER` first) is treated as advertising no
+    /// capabilities and a warning is logged.
+    pub fn require_capabilities(&mut self, require: bool) -> &mut Self {
+        self.require_capabilities = require;
+        self
+    }
+
+    /// Send `QUIT` when a client that was not closed is dropped
+    ///
+    /// Sending `QUIT` and waiting for the server's reply is bounded by the grace period, after
+    /// which the socket is shut down. Errors are ignored, use [`NntpClient::close`] to check them.
+    /// Clients over a custom stream cannot bound the wait, so they send `QUIT` without reading
+    /// the reply.
+    ///
+    /// Defaults to 250ms, `None` drops the connection without signing off.
+    pub fn quit_on_drop(&mut self, grace: Option<Duration>) -> &mut Self {
+        self.quit_on_drop = grace;
+        self
+    }
+
+    /// Retry idempotent commands that fail with transient errors
+    ///
+    /// Commands that only retrieve data (`ARTICLE`, `HEAD`, `BODY`, `STAT`, `OVER`, `HDR`,
+    /// `XHDR`, `GROUP`, and `LIST`) are retried according to the policy, reconnecting first if
+    /// the connection failed. The group that was selected is selected again after reconnecting.
+    /// `POST` and `IHAVE` are never retried since the article may have been accepted.
+    /// Commands on the current article (e.g. `STAT` without an argument) are not retried after
+    /// reconnecting since the new connection has no current article.
+    ///
+    /// Clients created via [`connect_stream`](Self::connect_stream) cannot reconnect, so only
+    /// failures with a transient response code can be retried.
+    ///
+    /// The default policy does not retry, see [`RetryPolicy`].
+    pub fn retry_policy(&mut self, policy: RetryPolicy) -> &mut Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Set the configuration of the underlying [`NntpConnection`]
+    pub fn connection_config(&mut self, config: ConnectionConfig) -> &mut Self {
+        self.conn_config = config;
+        self
+    }
+
+    /// Resolves the configuration into a client
+    ///
+    /// The address is only used to open the socket. If TLS is enabled the server's certificate
+    /// is validated against the domain of the [`TlsConfig`](crate::raw::TlsConfig), or
+    /// [`ConnectionConfig::tls_domain`] if it is set, rather than `addr`.
+    pub fn connect(&self, addr: impl ToSocketAddrs) -> Result<NntpClient> {
+        let addrs = addr
+            .to_socket_addrs()
+            .map_err(crate::raw::error::Error::from)?
+            .collect::<Vec<_>>();
+        let (conn, conn_response) = NntpConnection::connect(&addrs[..], self.conn_config.clone())?;
+        let mut client = self.init(conn, conn_response)?;
+        client.endpoint = Some(Endpoint::Addrs(addrs));
+        client.reconnect = NntpClient::reconnect;
+        client.quit_on_drop = NntpConnection::quit_with_grace;
+        Ok(client)
+    }
+
+    /// Resolves the configuration into a client, connecting by hostname
+    ///
+    /// See [`NntpConnection::connect_host`] for more info.
+    pub fn connect_host(&self, host: impl AsRef<str>, port: u16) -> Result<NntpClient> {
+        let host = host.as_ref();
+        let (conn, conn_response) =
+            NntpConnection::connect_host(host, port, self.conn_config.clone())?;
+        let mut client = self.init(conn, conn_response)?;
+        client.endpoint = Some(Endpoint::Host(host.to_string(), port));
+        client.reconnect = NntpClient::reconnect;
+        client.quit_on_drop = NntpConnection::quit_with_grace;
+        Ok(client)
+    }
+
+    /// Resolves the configuration into a client using an existing stream
+    ///
+    /// See [`NntpConnection::from_stream`] for caveats.
+    /// This is primarily useful for testing with a `MockStream`
+    /// (see the `testing` module provided by the `test-util` feature).
+    pub fn connect_stream<S: Read + Write>(&self, stream: S) -> Result<NntpClient<S>> {
+        let (conn, conn_response) = NntpConnection::from_stream(stream, self.conn_config.clone())?;
+        self.init(conn, conn_response)
+    }
+
+    /// Initialize a client on a freshly opened connection
+    fn init<S: Read + Write>(
+        &self,
+        mut conn: NntpConnection<S>,
+        conn_response: RawResponse,
+    ) -> Result<NntpClient<S>> {
+        debug!(
+            "Connected. Server returned `{}`",
+            conn_response.first_line_to_utf8_lossy()
+        );
+
+        if let Some((username, password)) = &self.authinfo {
+            if self.conn_config.tls_config.is_none() {
+                warn!("TLS is not enabled, credentials will be sent in the clear!");
+            }
+            // Some servers only advertise capabilities after authenticating, so this is advisory
+            // and a malformed list must not abort the connection
+            match try_get_capabilities(&mut conn) {
+                Ok(Some(caps)) if !caps.authinfo_user() => {
+                    warn!("Server does not advertise AUTHINFO USER, attempting anyway")
+                }
+                Ok(_) => {}
+                Err(e) if !conn.is_poisoned() => {
+                    debug!("Could not check for AUTHINFO USER -- {}", e)
+                }
+                Err(e) => return Err(e),
+            }
+            debug!("Authenticating with AUTHINFO USER/PASS");
+            authenticate(&mut conn, username, password)?;
+        }
+
+        debug!("Retrieving capabilities...");
+        let capabilities = if self.require_capabilities {
+            get_capabilities(&mut conn)?
+        } else {
+            try_get_capabilities(&mut conn)?.unwrap_or_else(|| {
+                warn!("Server did not return capabilities, assuming none are supported");
+                Capabilities::default()
+            })
+        };
+
+        if self.conn_config.compression == Some(Compression::Deflate) {
+            compress_deflate(&mut conn, &capabilities)?;
+        }
+
+        let group = if let Some(name) = &self.group {
+            debug!("Connecting to group {}...", name);
+            select_group(&mut conn, name)?.into()
+        } else {
+            debug!("No initial group specified");
+            None
+        };
+
+        let group_selected_at = group.as_ref().map(|_| Instant::now());
+
+        Ok(NntpClient {
+            conn,
+            config: self.clone(),
+            capabilities,
+            group,
+            group_selected_at,
+            posting_allowed: is_posting_allowed(&conn_response),
+            banner: String::from_utf8_lossy(conn_response.first_line_without_code())
+                .trim_end()
+                .to_string(),
+            greeting: conn_response,
+            endpoint: None,
+            deadline: None,
+            reconnect: |_| Err(not_reconnectable()),
+            quit_on_drop: |conn, _grace| conn.quit_without_reply(),
+        })
+    }
+}
+
+impl RawResponse {}
+
+/// The error returned when reconnecting a client that was created from a stream
+fn not_reconnectable() -> Error {
+    crate::raw::error::Error::Io(std::io::Error::new(
+        std::io::ErrorKind::NotConnected,
+        "Client was created from a stream and cannot reconnect",
+    ))
+    .into()
+}
+
+/// Returns true if a greeting or `MODE READER` response indicates that posting is allowed
+fn is_posting_allowed(resp: &RawResponse) -> bool {
+    resp.code() == ResponseCode::Known(Kind::PostingAllowed)
+}
+
+/// Perform an AUTHINFO USER/PASS exchange
+fn authenticate<S: Read + Write>(
+    conn: &mut NntpConnection<S>,
+    username: impl AsRef<str>,
+    password: impl AsRef<str>,
+) -> Result<()> {
+    debug!("Sending AUTHINFO USER");
+    let user_resp = conn.command_expect_any(
+        &cmd::AuthInfo::User(username.as_ref().to_string()),
+        &[Kind::PasswordRequired, Kind::AuthenticationAccepted],
+    )?;
+
+    // Per RFC 4643 the server MAY accept the username without a password
+    if user_resp.code() == ResponseCode::Known(Kind::AuthenticationAccepted) {
+        debug!("Successfully authenticated without a password");
+        return Ok(());
+    }
+
+    debug!("Sending AUTHINFO PASS");
+    let pass_resp = conn.command(&cmd::AuthInfo::Pass(password.as_ref().to_string()))?;
+
+    if pass_resp.code() != ResponseCode::Known(Kind::AuthenticationAccepted) {
+        return Err(Error::Failure {
+            code: pass_resp.code,
+            msg: Some(format!(
+                "AUTHINFO PASS failed -- {}",
+                pass_resp.code.describe()
+            )),
+            resp: pass_resp,
+        });
+    }
+    debug!("Successfully authenticated");
+
+    Ok(())
+}
+
+/// The response to `XFEATURE COMPRESS`, which is not part of any RFC
+const XFEATURE_ENABLED: u16 = 290;
+
+/// Enable `COMPRESS DEFLATE` if the server supports it
+///
+/// Failing to enable compression is not fatal as the session remains usable
+fn compress_deflate<S: Read + Write>(
+    conn: &mut NntpConnection<S>,
+    capabilities: &Capabilities,
+) -> Result<()> {
+    if !capabilities.supports_arg("COMPRESS", "DEFLATE") {
+        warn!("COMPRESS DEFLATE is configured but not advertised by the server");
+        return Ok(());
+    }
+
+    debug!("Enabling COMPRESS DEFLATE");
+    let resp = conn.command(&cmd::Compress::Deflate)?;
+    if !conn.is_deflate_active() {
+        warn!(
+            "Server refused COMPRESS DEFLATE -- {}",
+            resp.first_line_to_utf8_lossy().trim_end()
+        );
+    }
+
+    Ok(())
+}
+
+fn get_capabilities<S: Read + Write>(conn: &mut NntpConnection<S>) -> Result<Capabilities> {
+    let resp = conn.command_expect(&cmd::Capabilities, Kind::Capabilities)?;
+
+    deserialize(conn, &resp)
+}
+
+/// Retrieve capabilities, returning `None` if the server refuses the command
+fn try_get_capabilities<S: Read + Write>(
+    conn: &mut NntpConnection<S>,
+) -> Result<Option<Capabilities>> {
+    let resp = conn.command(&cmd::Capabilities)?;
+    if resp.code() != ResponseCode::Known(Kind::Capabilities) {
+        debug!(
+            "Server refused CAPABILITIES -- {}",
+            resp.first_line_to_utf8_lossy().trim_end()
+        );
+        return Ok(None);
+    }
+
+    deserialize(conn, &resp).map(Some)
+}
+
+fn select_group<S: Read + Write>(
+    conn: &mut NntpConnection<S>,
+    group: impl AsRef<str>,
+) -> Result<Group> {
+    let resp = conn.command_expect(&cmd::Group::new(group)?, Kind::GroupSelected)?;
+
+    deserialize(conn, &resp)
+}
+
+/// Deserialize a response into a concrete type
+///
+/// If the connection is configured to keep offending bytes, they will be attached to any errors
+fn deserialize<'a, S, T>(conn: &NntpConnection<S>, resp: &'a RawResponse) -> Result<T>
+where
+    S: Read + Write,
+    T: TryFrom<&'a RawResponse, Error = Error>,
+{
+    T::try_from(resp).map_err(|e| {
+        if conn.config().keep_offending_bytes && e.is_deserialization() {
+            e.with_offending_bytes(resp)
+        } else {
+            e
+        }
+    })
+}
+
+/// Deserialize a response into a concrete type, moving the response data if possible
+///
+/// If the connection is configured to keep offending bytes the response is borrowed instead,
+/// so that it can be attached to any errors
+fn deserialize_owned<S, T>(conn: &NntpConnection<S>, resp: RawResponse) -> Result<T>
+where
+    S: Read + Write,
+    T: TryFrom<RawResponse, Error = Error> + for<'a> TryFrom<&'a RawResponse, Error = Error>,
+{
+    if conn.config().keep_offending_bytes {
+        deserialize(conn, &resp)
+    } else {
+        T::try_from(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{fixtures, MockStream};
+
+    /// A stream that keeps a copy of everything written after the client is dropped
+    struct Recorder(MockStream, std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+    impl Read for Recorder {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl Write for Recorder {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.1.borrow_mut().extend_from_slice(buf);
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.0.flush()
+        }
+    }
+
+    fn recorded_client(
+        config: &ClientConfig,
+    ) -> (
+        NntpClient<Recorder>,
+        std::rc::Rc<std::cell::RefCell<Vec<u8>>>,
+    ) {
+        let written = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("QUIT", "205 Bye\r\n");
+        let client = config
+            .connect_stream(Recorder(stream, written.clone()))
+            .unwrap();
+        (client, written)
+    }
+
+    fn quit_count(written: &[u8]) -> usize {
+        String::from_utf8_lossy(written).matches("QUIT\r\n").count()
+    }
+
+    #[test]
+    fn test_quit_on_drop() {
+        let (client, written) = recorded_client(&ClientConfig::default());
+        drop(client);
+        assert_eq!(quit_count(&written.borrow()), 1);
+
+        let (mut client, written) = recorded_client(&ClientConfig::default());
+        client.close().unwrap();
+        drop(client);
+        assert_eq!(quit_count(&written.borrow()), 1);
+
+        let (client, written) = recorded_client(ClientConfig::default().quit_on_drop(None));
+        drop(client);
+        assert_eq!(quit_count(&written.borrow()), 0);
+    }
+
+    #[test]
+    fn test_capability_accessors() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect(
+                "CAPABILITIES",
+                b"101 Capability list:\r\nVERSION 2\r\nMODE-READER\r\nPOST\r\n\
+                STARTTLS\r\nCOMPRESS DEFLATE\r\n.\r\n",
+            );
+
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+        assert!(client.supports_over());
+        assert!(!client.supports_post());
+        assert!(!client.supports_compress());
+        assert!(!client.supports_starttls());
+        assert!(!client.reader_mode_required());
+
+        client.update_capabilities().unwrap();
+        assert!(!client.supports_over());
+        assert!(client.supports_post());
+        assert!(client.supports_compress());
+        assert!(client.supports_starttls());
+        assert!(client.reader_mode_required());
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_capabilities_refused() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", "500 What?\r\n")
+            .expect("GROUP misc.test", "211 2 1 2 misc.test\r\n");
+        let client = ClientConfig::default()
+            .group(Some("misc.test"))
+            .connect_stream(stream)
+            .unwrap();
+        assert_eq!(client.capabilities(), &Capabilities::default());
+        assert!(!client.supports_over());
+        assert_eq!(client.group().unwrap().name, "misc.test");
+
+        let stream = MockStream::new(fixtures::GREETING).expect("CAPABILITIES", "500 What?\r\n");
+        let res = ClientConfig::default()
+            .require_capabilities(true)
+            .connect_stream(stream);
+        assert!(matches!(res, Err(Error::Failure { .. })));
+    }
+
+    #[test]
+    fn test_posting_allowed() {
+        let client = ClientConfig::default()
+            .connect_stream(
+                MockStream::new(fixtures::GREETING).expect("CAPABILITIES", fixtures::CAPABILITIES),
+            )
+            .unwrap();
+        assert!(client.posting_allowed());
+
+        let stream = MockStream::new(b"201 Posting prohibited\r\n")
+            .expect(
+                "CAPABILITIES",
+                b"101 Capability list:\r\nVERSION 2\r\nMODE-READER\r\n.\r\n",
+            )
+            .expect("MODE READER", b"200 Posting allowed\r\n")
+            .expect("CAPABILITIES", fixtures::CAPABILITIES);
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+        assert!(!client.posting_allowed());
+        assert!(client.reader_mode_required());
+        assert_eq!(client.server_banner(), "Posting prohibited");
+        assert_eq!(client.posting_allowed_on_connect(), Some(false));
+
+        client.mode_reader().unwrap();
+        assert!(client.posting_allowed());
+        assert!(!client.reader_mode_required());
+        assert_eq!(client.posting_allowed_on_connect(), Some(false));
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_greeting_refused() {
+        for greeting in &["400 Try again later\r\n", "502 Account suspended\r\n"] {
+            let res = ClientConfig::default().connect_stream(MockStream::new(*greeting));
+            assert!(matches!(
+                res,
+                Err(Error::Connection(
+                    crate::raw::error::Error::ServerClosed { .. }
+                ))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_compress_deflate() {
+        use crate::raw::compression::{Deflate, Inflater};
+
+        let compressed = include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/compress_deflate_resp"
+        ));
+        let mut input = fixtures::GREETING.to_vec();
+        input.extend_from_slice(
+            b"101 Capability list:\r\nVERSION 2\r\nCOMPRESS DEFLATE\r\n.\r\n\
+            206 Compression active\r\n",
+        );
+        input.extend_from_slice(compressed);
+
+        let conn_config = ConnectionConfig::new()
+            .compression(Some(Compression::Deflate))
+            .to_owned();
+        let mut client = ClientConfig::default()
+            .connection_config(conn_config)
+            .connect_stream(MockStream::permissive(input))
+            .unwrap();
+        assert!(client.conn().is_deflate_active());
+
+        client.update_capabilities().unwrap();
+        assert!(client.capabilities().get("READER").is_some());
+        let group = client.select_group("misc.test").unwrap();
+        assert_eq!(group.number, 3);
+
+        let written = client.conn().stream().get_ref().written().to_vec();
+        let plain = b"CAPABILITIES\r\nCOMPRESS DEFLATE\r\n";
+        assert_eq!(&written[..plain.len()], &plain[..]);
+
+        let mut deflate = Deflate::new(8 * 1024);
+        let mut commands = String::new();
+        Inflater::new(&written[plain.len()..], Some(&mut deflate))
+            .read_to_string(&mut commands)
+            .unwrap();
+        assert_eq!(commands, "CAPABILITIES\r\nGROUP misc.test\r\n");
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_body_with_progress() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("BODY 1", "222 1 <a@b> body\r\nline one\r\n.\r\n")
+            .expect("BODY 2", "423 No article with that number\r\n");
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+
+        let mut calls = Vec::new();
+        let body = client
+            .body_with_progress(cmd::Body::Number(1), 1024, |bytes| calls.push(bytes))
+            .unwrap();
+        assert_eq!(body.body(), b"line one\r\n");
+        assert_eq!(calls, vec![13]);
+
+        let err = client
+            .body_with_progress(cmd::Body::Number(2), 1024, |_| {})
+            .unwrap_err();
+        assert!(matches!(err, Error::Failure { .. }));
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_enable_compression() {
+        let xover = include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/xover_resp_xfeature_compress"
+        ));
+        // The fixture contains two responses
+        let xover = &xover[..573];
+
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect(
+                "CAPABILITIES",
+                "101 Capability list:\r\nVERSION 2\r\nREADER\r\n\
+                XFEATURE-COMPRESS GZIP TERMINATOR\r\n.\r\n",
+            )
+            .expect(
+                "XFEATURE COMPRESS GZIP TERMINATOR",
+                "290 feature enabled\r\n",
+            )
+            .expect("XOVER 1-10", xover);
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+
+        assert_eq!(client.enable_compression().unwrap(), Compression::XFeature);
+        let resp = client.command(cmd::XOver::range((1, 10)).unwrap()).unwrap();
+        assert!(resp
+            .data_blocks()
+            .unwrap()
+            .payload()
+            .ends_with(b"us.config:29809\r\n.\r\n"));
+
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect(
+                "CAPABILITIES",
+                "101 Capability list:\r\nVERSION 2\r\nCOMPRESS DEFLATE\r\n\
+                XFEATURE-COMPRESS GZIP\r\n.\r\n",
+            )
+            .expect("COMPRESS DEFLATE", "206 Compression active\r\n");
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+        assert_eq!(client.enable_compression().unwrap(), Compression::Deflate);
+        assert!(client.conn().is_deflate_active());
+        // Already active, nothing is sent
+        assert_eq!(client.enable_compression().unwrap(), Compression::Deflate);
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_enable_compression_unsupported() {
+        let stream =
+            MockStream::new(fixtures::GREETING).expect("CAPABILITIES", fixtures::CAPABILITIES);
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+        assert!(matches!(
+            client.enable_compression(),
+            Err(Error::Unsupported {
+                capability: "COMPRESS"
+            })
+        ));
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_list_raw() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect(
+                "LIST MOTD",
+                b"215 motd follows\r\nWelcome!\r\n..hidden\r\n.\r\n",
+            )
+            .expect("LIST SUBSCRIPTIONS misc.*", b"503 not supported\r\n");
+
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+
+        let lines = client.list_raw("MOTD", &[]).unwrap();
+        assert_eq!(lines, vec!["Welcome!", ".hidden"]);
+
+        assert!(client.list_raw("SUBSCRIPTIONS", &["misc.*"]).is_err());
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_select_group_if_needed() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("GROUP misc.test", fixtures::GROUP)
+            .expect("GROUP misc.other", b"211 10 1 10 misc.other\r\n")
+            .expect("QUIT", fixtures::QUIT);
+
+        let mut client = ClientConfig::default()
+            .group(Some("misc.test"))
+            .connect_stream(stream)
+            .unwrap();
+
+        // No command is sent since the group is already selected
+        let group = client.select_group_if_needed("misc.test").unwrap();
+        assert_eq!(group.name, "misc.test");
+
+        let group = client.select_group_if_needed("misc.other").unwrap();
+        assert_eq!(group.name, "misc.other");
+        assert_eq!(client.group().unwrap().name, "misc.other");
+
+        client.close().unwrap();
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_authinfo() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", b"480 authentication required\r\n")
+            .expect("AUTHINFO USER user", b"381 password required\r\n")
+            .expect("AUTHINFO PASS pass", b"281 authentication accepted\r\n")
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("QUIT", fixtures::QUIT);
+
+        let mut client = ClientConfig::default()
+            .authinfo_user_pass("user", "pass")
+            .connect_stream(stream)
+            .unwrap();
+        assert_eq!(client.capabilities().version(), Some(2));
+
+        client.close().unwrap();
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_authinfo_malformed_capabilities() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", b"101 Capability list:\r\n\r\n.\r\n")
+            .expect("AUTHINFO USER user", b"381 password required\r\n")
+            .expect("AUTHINFO PASS pass", b"281 authentication accepted\r\n")
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("QUIT", fixtures::QUIT);
+
+        let mut client = ClientConfig::default()
+            .authinfo_user_pass("user", "pass")
+            .connect_stream(stream)
+            .unwrap();
+        assert_eq!(client.capabilities().version(), Some(2));
+
+        client.close().unwrap();
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_over() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect(
+                "OVER <1@brokaw.example.com>",
+                b"224 overview follows\r\n0\tsubject\r\n.\r\n",
+            )
+            .expect("QUIT", fixtures::QUIT);
+
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+        let resp = client.over_msgid("<1@brokaw.example.com>").unwrap();
+        assert_eq!(resp.data_blocks().unwrap().lines_len(), 2);
+
+        // Reversed ranges are rejected without contacting the server
+        assert!(matches!(
+            client.over_range((10, 1)),
+            Err(Error::InvalidRange { low: 10, high: 1 })
+        ));
+
+        client.close().unwrap();
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_post_and_ihave() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("POST", "340 send article\r\n")
+            .expect("Subject: hi", "")
+            .expect(".", "240 article received\r\n")
+            .expect("IHAVE <1@brokaw.example.com>", "435 not wanted\r\n");
+
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+        client.post(&b"Subject: hi"[..]).unwrap();
+
+        match client.ihave("<1@brokaw.example.com>", &b"Subject: hi"[..]) {
+            Err(Error::Failure { code, .. }) => {
+                assert_eq!(code, ResponseCode::Known(Kind::ArticleNotWanted))
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_over_msgid_unsupported() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect(
+                "CAPABILITIES",
+                b"101 Capability list:\r\nVERSION 2\r\nREADER\r\nOVER\r\n.\r\n",
+            )
+            .expect("QUIT", fixtures::QUIT);
+
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+        assert!(matches!(
+            client.over_msgid("<1@brokaw.example.com>"),
+            Err(Error::Unsupported {
+                capability: "OVER MSGID"
+            })
+        ));
+
+        client.close().unwrap();
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_select_invalid_group() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("QUIT", fixtures::QUIT);
+
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+        // Nothing is sent, otherwise the script would fail with an I/O error
+        assert!(matches!(
+            client.select_group("misc.test QUIT"),
+            Err(Error::InvalidGroupName { .. })
+        ));
+        assert!(client.group().is_none());
+
+        client.close().unwrap();
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_no_such_group_message() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("GROUP misc.missing", "411 No such newsgroup\r\n");
+
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+        let err = client.select_group("misc.missing").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Server returned 411 No such newsgroup -- \
+             Expected one of [211], got transient failure (navigation)"
+        );
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_command_typed() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("STAT", "223 1 <1@brokaw.example.com>\r\n");
+
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+        let stat = client.command_typed::<Stat>(cmd::Stat::Current).unwrap();
+        assert_eq!(stat.code(), ResponseCode::Known(Kind::ArticleExists));
+        assert_eq!(stat.map_body(|s| s.number).into_body(), 1);
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_fetch_by_id_and_number() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("ARTICLE <1@brokaw.example.com>", fixtures::ARTICLE)
+            .expect("HEAD <2@brokaw.example.com>", "430 no such article\r\n")
+            .expect("STAT 1", "223 1 <1@brokaw.example.com>\r\n")
+            .expect("BODY 4", "423 no article with that number\r\n")
+            .expect("HEAD 1", "412 no newsgroup selected\r\n");
+
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+        let article = client
+            .article_by_id("1@brokaw.example.com")
+            .unwrap()
+            .unwrap();
+        assert_eq!(article.message_id(), "<1@brokaw.example.com>");
+        assert_eq!(client.head_by_id("<2@brokaw.example.com>").unwrap(), None);
+        assert_eq!(client.stat_by_number(1).unwrap().unwrap().number, 1);
+        assert_eq!(client.body_by_number(4).unwrap(), None);
+        assert!(matches!(
+            client.head_by_number(1),
+            Err(Error::Failure { .. })
+        ));
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_command_as() {
+        struct Date(String);
+
+        impl TryFrom<&RawResponse> for Date {
+            type Error = &'static str;
+
+            fn try_from(resp: &RawResponse) -> std::result::Result<Self, Self::Error> {
+                match resp.code() {
+                    ResponseCode::Known(Kind::Date) => Ok(Date(
+                        String::from_utf8_lossy(resp.first_line_without_code())
+                            .trim()
+                            .to_string(),
+                    )),
+                    _ => Err("not a date"),
+                }
+            }
+        }
+
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("DATE", "111 20200101000000\r\n")
+            .expect("DATE", "500 what?\r\n");
+
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+        let date: Date = client.command_as(cmd::Date, false).unwrap();
+        assert_eq!(date.0, "20200101000000");
+
+        match client.command_as::<Date>(cmd::Date, false) {
+            Err(Error::Deserialization(msg)) => assert_eq!(msg, "not a date"),
+            Err(e) => panic!("Unexpected error {}", e),
+            Ok(_) => panic!("Expected an error"),
+        }
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_command_as_multiline_reply() {
+        struct Lines(usize);
+
+        impl TryFrom<&RawResponse> for Lines {
+            type Error = &'static str;
+
+            fn try_from(resp: &RawResponse) -> std::result::Result<Self, Self::Error> {
+                resp.data_blocks()
+                    .map(|db| Lines(db.lines_len()))
+                    .ok_or("no data blocks")
+            }
+        }
+
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("HELP", "100 help follows\r\nDATE\r\nHELP\r\n.\r\n")
+            .expect("DATE", "111 20200101000000\r\n");
+
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+        // The data blocks are read based on the response code and don't leak into the next reply
+        let help: Lines = client.command_as(cmd::Help, false).unwrap();
+        assert_eq!(help.0, 3);
+
+        let resp = client.command(cmd::Date).unwrap();
+        assert_eq!(resp.code(), ResponseCode::Known(Kind::Date));
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_quit() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("QUIT", b"205 Goodbye, thanks for all the fish\r\n");
+
+        let client = ClientConfig::default().connect_stream(stream).unwrap();
+        assert_eq!(
+            client.greeting().code(),
+            ResponseCode::Known(Kind::PostingAllowed)
+        );
+        assert_eq!(client.quit().unwrap(), "Goodbye, thanks for all the fish");
+    }
+
+    #[test]
+    fn test_refresh_group() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("GROUP misc.test", fixtures::GROUP)
+            .expect("GROUP misc.test", b"211 4 1 4 misc.test\r\n")
+            .expect("QUIT", fixtures::QUIT);
+
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+        assert!(client.group_age().is_none());
+        assert!(matches!(
+            client.refresh_group(),
+            Err(Error::NoGroupSelected)
+        ));
+
+        let group = client.select_group("misc.test").unwrap();
+        assert_eq!(group.high, 3);
+        assert!(client.group_age().is_some());
+
+        let group = client.refresh_group().unwrap();
+        assert_eq!(group.high, 4);
+        assert_eq!(client.group().unwrap().high, 4);
+
+        client.close().unwrap();
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_text_article() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("ARTICLE 1", fixtures::ARTICLE);
+
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+        let article = client.text_article(cmd::Article::Number(1)).unwrap();
+
+        assert_eq!(article.message_id(), "<1@brokaw.example.com>");
+        assert_eq!(article.body(), &["This is just a test article."]);
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+}
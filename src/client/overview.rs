@@ -0,0 +1,529 @@
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::ops::RangeInclusive;
+
+use log::*;
+
+use crate::client::{deserialize, NntpClient};
+use crate::error::{Error, Result};
+use crate::types::command as cmd;
+use crate::types::command::RangeChunker;
+use crate::types::prelude::*;
+use crate::types::response::{OverviewEntry, OverviewResponse};
+
+/// The maximum number of articles requested by a single `HDR` when synthesizing overviews
+const HDR_BATCH_SIZE: ArticleNumber = 1000;
+
+/// The average line length assumed by [`NntpClient::article_size`] when estimating from `:lines`
+const ESTIMATED_BYTES_PER_LINE: u64 = 80;
+
+/// The fields requested via `HDR` when the server does not support `OVER`
+const FALLBACK_FIELDS: &[&str] = &["Subject", "From", "Date", "Message-ID", "References"];
+
+/// An article along with its overview entry, see [`NntpClient::article_with_overview`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ArticleWithOverview {
+    /// The overview entry for the article
+    pub overview: OverviewEntry,
+    /// The article
+    pub article: BinaryArticle,
+}
+
+impl<S: Read + Write> NntpClient<S> {
+    /// Retrieve an article from the current group along with its overview entry
+    ///
+    /// This sends `OVER` followed by `ARTICLE`. If the server does not advertise `OVER`, the
+    /// overview entry is derived from the article headers instead, with
+    /// [`lines`](OverviewEntry::lines) taken from the body and no [`bytes`](OverviewEntry::bytes).
+    ///
+    /// Returns [`Error::NoGroupSelected`] if no group is selected. Returns `None` without
+    /// contacting the server if the number is outside of the cached bounds of the group
+    /// (see [`refresh_group`](Self::refresh_group)), or if the server has no such article.
+    pub fn article_with_overview(
+        &mut self,
+        number: ArticleNumber,
+    ) -> Result<Option<ArticleWithOverview>> {
+        let group = self.group.as_ref().ok_or(Error::NoGroupSelected)?;
+        if number < group.low || number > group.high {
+            debug!(
+                "Article {} is outside of {} ({}-{})",
+                number, group.name, group.low, group.high
+            );
+            return Ok(None);
+        }
+
+        if !self.supports_over() {
+            let article = match self.article_by_number(number)? {
+                Some(article) => article,
+                None => return Ok(None),
+            };
+            let mut overview = entry_from_headers(number, article.message_id(), article.headers());
+            overview.lines = Some(article.lines_len() as u64);
+            return Ok(Some(ArticleWithOverview { overview, article }));
+        }
+
+        let resp = self.conn.command_expect_any(
+            &cmd::Over::Range(cmd::ArticleRange::single(number)),
+            &[Kind::Overview, Kind::NoArticleWithNumber],
+        )?;
+        if resp.code() != ResponseCode::Known(Kind::Overview) {
+            return Ok(None);
+        }
+        let overview = deserialize(&self.conn, &resp)
+            .map(|o: OverviewResponse| o.entries.into_iter().next())?;
+        let overview = match overview {
+            Some(overview) => overview,
+            None => return Ok(None),
+        };
+
+        match self.article_by_number(number)? {
+            Some(article) => Ok(Some(ArticleWithOverview { overview, article })),
+            None => Ok(None),
+        }
+    }
+
+    /// Retrieve the overview entries for a range of articles, even if the server lacks `OVER`
+    ///
+    /// If the server advertises `OVER` this is equivalent to [`over_range`](Self::over_range).
+    /// Otherwise the entries are synthesized from the `Subject`, `From`, `Date`, `Message-ID`,
+    /// and `References` headers:
+    ///
+    /// * If the server advertises `HDR`, each field is retrieved for batches of up to 1000
+    ///   articles
+    /// * Otherwise `HEAD` is sent for every article in the range
+    ///
+    /// Synthesized entries never have [`bytes`](OverviewEntry::bytes) or
+    /// [`lines`](OverviewEntry::lines). An article missing from some of the `HDR` responses
+    /// still gets an entry, with `None` for the missing fields. Numbers without an article are
+    /// skipped, any other error is returned.
+    pub fn overview_or_fallback(
+        &mut self,
+        range: RangeInclusive<ArticleNumber>,
+    ) -> Result<Vec<OverviewEntry>> {
+        let (low, high) = range.into_inner();
+        if low > high {
+            return Err(Error::InvalidRange { low, high });
+        }
+
+        if self.supports_over() {
+            let resp = self.over_range((low, high))?;
+            return deserialize(&self.conn, &resp).map(|o: OverviewResponse| o.entries);
+        }
+
+        if self.capabilities.hdr() {
+            debug!("Server does not advertise OVER, synthesizing overview from HDR");
+            self.overview_from_hdr(low, high)
+        } else {
+            debug!("Server does not advertise OVER or HDR, synthesizing overview from HEAD");
+            self.overview_from_head(low, high)
+        }
+    }
+
+    /// Determine the size of an article in bytes without retrieving it
+    ///
+    /// The size is determined from the first of the following that is available:
+    ///
+    /// 1. The `:bytes` field of `OVER` (only for message-ids if `OVER MSGID` is advertised)
+    /// 2. The `:bytes` metadata via `HDR`
+    /// 3. An estimate from the `:lines` field of `OVER` or `HDR`, assuming 80 bytes per line
+    ///
+    /// A size of `0` is treated as unknown. Returns `None` if the size is unknown, if the server
+    /// supports neither `OVER` nor `HDR`, or if the article does not exist.
+    pub fn article_size(&mut self, article: cmd::Article) -> Result<Option<u64>> {
+        let over = match &article {
+            cmd::Article::Number(n) => Some(cmd::Over::Range(cmd::ArticleRange::single(*n))),
+            cmd::Article::MessageId(id) if self.capabilities.over_msgid() => {
+                Some(cmd::Over::MessageId(id.clone()))
+            }
+            cmd::Article::MessageId(_) => None,
+            cmd::Article::Current => Some(cmd::Over::Current),
+        };
+
+        let mut lines = None;
+        if let Some(over) = over.filter(|_| self.supports_over()) {
+            if let Some(entry) = self.overview_entry(&over)? {
+                if let Some(bytes) = entry.bytes() {
+                    return Ok(Some(bytes));
+                }
+                lines = entry.lines();
+            }
+        }
+
+        if self.capabilities.hdr() {
+            if let Some(bytes) = self.hdr_number(":bytes", &article)? {
+                return Ok(Some(bytes));
+            }
+            if lines.is_none() {
+                lines = self.hdr_number(":lines", &article)?;
+            }
+        }
+
+        Ok(lines.map(|lines| lines.saturating_mul(ESTIMATED_BYTES_PER_LINE)))
+    }
+
+    /// Retrieve the overview entry of a single article, `None` if there is no such article
+    fn overview_entry(&mut self, over: &cmd::Over) -> Result<Option<OverviewEntry>> {
+        let resp = self.conn.command_expect_any(over, ARTICLE_LOOKUP_KINDS)?;
+        if resp.code() != ResponseCode::Known(Kind::Overview) {
+            return Ok(None);
+        }
+        deserialize(&self.conn, &resp).map(|o: OverviewResponse| o.entries.into_iter().next())
+    }
+
+    /// Retrieve a numeric field of a single article via `HDR`, treating `0` as unknown
+    fn hdr_number(&mut self, field: &str, article: &cmd::Article) -> Result<Option<u64>> {
+        let hdr = match article {
+            cmd::Article::Number(n) => cmd::Hdr::Range {
+                field: field.to_string(),
+                range: cmd::ArticleRange::single(*n),
+            },
+            cmd::Article::MessageId(id) => cmd::Hdr::MessageId {
+                field: field.to_string(),
+                id: id.clone(),
+            },
+            cmd::Article::Current => cmd::Hdr::Current {
+                field: field.to_string(),
+            },
+        };
+
+        let resp = self.conn.command_expect_any(&hdr, ARTICLE_LOOKUP_KINDS)?;
+        if resp.code() != ResponseCode::Known(Kind::Hdr) {
+            return Ok(None);
+        }
+        let hdr: HdrResponse = deserialize(&self.conn, &resp)?;
+        let value = hdr
+            .entries
+            .first()
+            .and_then(|(_, value)| value.trim().parse::<u64>().ok())
+            .filter(|&n| n > 0);
+        Ok(value)
+    }
+
+    fn overview_from_hdr(
+        &mut self,
+        low: ArticleNumber,
+        high: ArticleNumber,
+    ) -> Result<Vec<OverviewEntry>> {
+        let mut entries = BTreeMap::new();
+
+        for (batch_low, batch_high) in RangeChunker::new(low, high, HDR_BATCH_SIZE) {
+            for &field in FALLBACK_FIELDS {
+                self.check_interrupted()?;
+                let hdr = cmd::Hdr::range(field, (batch_low, batch_high))?;
+                let resp = self
+                    .conn
+                    .command_expect_any(&hdr, &[Kind::Hdr, Kind::NoArticleWithNumber])?;
+                if resp.code() != ResponseCode::Known(Kind::Hdr) {
+                    trace!("No articles in {}-{}", batch_low, batch_high);
+                    break;
+                }
+
+                let hdr: HdrResponse = deserialize(&self.conn, &resp)?;
+                for (number, value) in hdr.entries {
+                    let entry = entries.entry(number).or_insert_with(|| empty_entry(number));
+                    set_field(entry, field, value);
+                }
+            }
+        }
+
+        let (_, entries): (Vec<_>, _) = entries.into_iter().unzip();
+        Ok(entries)
+    }
+
+    fn overview_from_head(
+        &mut self,
+        low: ArticleNumber,
+        high: ArticleNumber,
+    ) -> Result<Vec<OverviewEntry>> {
+        let mut entries = Vec::new();
+
+        for number in low..=high {
+            self.check_interrupted()?;
+            let head = match self.head_by_number(number)? {
+                Some(head) => head,
+                None => continue,
+            };
+
+            entries.push(entry_from_headers(number, &head.message_id, &head.headers));
+        }
+
+        Ok(entries)
+    }
+}
+
+/// Synthesize an overview entry from the headers of an article
+fn entry_from_headers(number: ArticleNumber, message_id: &str, headers: &Headers) -> OverviewEntry {
+    let mut entry = empty_entry(number);
+    for &field in FALLBACK_FIELDS {
+        let value = headers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case(field))
+            .and_then(|h| h.content.first());
+        if let Some(value) = value {
+            set_field(&mut entry, field, value.clone());
+        }
+    }
+    if entry.message_id.is_none() && !message_id.is_empty() {
+        entry.message_id = Some(message_id.to_string());
+    }
+    entry
+}
+
+fn empty_entry(number: ArticleNumber) -> OverviewEntry {
+    OverviewEntry {
+        number,
+        subject: None,
+        from: None,
+        date: None,
+        message_id: None,
+        references: None,
+        bytes: None,
+        lines: None,
+        extra: Vec::new(),
+    }
+}
+
+/// The responses to a lookup of a single article by `OVER` or `HDR`
+const ARTICLE_LOOKUP_KINDS: &[Kind] = &[
+    Kind::Overview,
+    Kind::Hdr,
+    Kind::NoArticleWithNumber,
+    Kind::NoArticleWithMessageId,
+    Kind::InvalidCurrentArticleNumber,
+];
+
+/// Set one of the [`FALLBACK_FIELDS`], empty values are left as `None`
+fn set_field(entry: &mut OverviewEntry, field: &str, value: String) {
+    let value = Some(value).filter(|v| !v.is_empty());
+    match field {
+        "Subject" => entry.subject = value,
+        "From" => entry.from = value,
+        "Date" => entry.date = value,
+        "Message-ID" => entry.message_id = value,
+        "References" => entry.references = value,
+        _ => unreachable!("Unexpected overview field {}", field),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{fixtures, MockStream};
+    use crate::ClientConfig;
+
+    fn mock_stream(capabilities: &str) -> MockStream {
+        MockStream::new(fixtures::GREETING).expect(
+            "CAPABILITIES",
+            format!(
+                "101 Capability list:\r\nVERSION 2\r\nREADER\r\n{}.\r\n",
+                capabilities
+            ),
+        )
+    }
+
+    #[test]
+    fn test_over() {
+        let stream = mock_stream("OVER\r\n").expect(
+            "OVER 1-2",
+            "224 overview\r\n1\tone\t\t\t<1@example.com>\t\t\t\r\n.\r\n",
+        );
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+
+        let entries = client.overview_or_fallback(1..=2).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].subject.as_deref(), Some("one"));
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_hdr_fallback() {
+        // Article 2 lacks a From header and article 3 only appears in some responses
+        let stream = mock_stream("HDR\r\n")
+            .expect("HDR Subject 1-3", "225 follows\r\n1 one\r\n2 two\r\n.\r\n")
+            .expect("HDR From 1-3", "225 follows\r\n1 a@example.com\r\n2 \r\n.\r\n")
+            .expect("HDR Date 1-3", "225 follows\r\n.\r\n")
+            .expect(
+                "HDR Message-ID 1-3",
+                "225 follows\r\n1 <1@example.com>\r\n2 <2@example.com>\r\n3 <3@example.com>\r\n.\r\n",
+            )
+            .expect("HDR References 1-3", "225 follows\r\n2 <1@example.com>\r\n.\r\n");
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+
+        let entries = client.overview_or_fallback(1..=3).unwrap();
+        assert_eq!(
+            entries.iter().map(|e| e.number).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(entries[0].from.as_deref(), Some("a@example.com"));
+        assert_eq!(entries[1].from, None);
+        assert_eq!(
+            entries[1].references().collect::<Vec<_>>(),
+            vec!["<1@example.com>"]
+        );
+        assert_eq!(entries[2].subject, None);
+        assert_eq!(entries[2].message_id.as_deref(), Some("<3@example.com>"));
+        assert_eq!(entries[2].date, None);
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_hdr_fallback_empty_range() {
+        let stream = mock_stream("HDR\r\n").expect("HDR Subject 5-6", "423 no articles\r\n");
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+
+        assert!(client.overview_or_fallback(5..=6).unwrap().is_empty());
+        assert!(matches!(
+            client.overview_or_fallback(RangeInclusive::new(6, 5)),
+            Err(Error::InvalidRange { low: 6, high: 5 })
+        ));
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    fn article(number: ArticleNumber) -> String {
+        format!(
+            "220 {} <{}@example.com>\r\nSubject: article\r\n\r\nfirst\r\nsecond\r\n.\r\n",
+            number, number
+        )
+    }
+
+    #[test]
+    fn test_article_with_overview() {
+        let stream = mock_stream("OVER\r\n")
+            .expect("GROUP misc.test", "211 3 1 3 misc.test\r\n")
+            .expect(
+                "OVER 1",
+                "224 overview\r\n1\tone\t\t\t<1@example.com>\t\t100\t2\r\n.\r\n",
+            )
+            .expect("ARTICLE 1", article(1))
+            .expect("OVER 2", "423 no such article\r\n");
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+
+        assert!(matches!(
+            client.article_with_overview(1),
+            Err(Error::NoGroupSelected)
+        ));
+        client.select_group("misc.test").unwrap();
+
+        let combined = client.article_with_overview(1).unwrap().unwrap();
+        assert_eq!(combined.overview.subject.as_deref(), Some("one"));
+        assert_eq!(combined.overview.bytes, Some(100));
+        assert_eq!(combined.article.number(), 1);
+
+        assert_eq!(client.article_with_overview(2).unwrap(), None);
+        // Outside of the group bounds, the server is not contacted
+        assert_eq!(client.article_with_overview(4).unwrap(), None);
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_article_with_overview_fallback() {
+        let stream = mock_stream("")
+            .expect("GROUP misc.test", "211 3 1 3 misc.test\r\n")
+            .expect("ARTICLE 3", article(3));
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+        client.select_group("misc.test").unwrap();
+
+        let combined = client.article_with_overview(3).unwrap().unwrap();
+        assert_eq!(combined.overview.number, 3);
+        assert_eq!(combined.overview.subject.as_deref(), Some("article"));
+        assert_eq!(
+            combined.overview.message_id.as_deref(),
+            Some("<3@example.com>")
+        );
+        assert_eq!(combined.overview.lines, Some(2));
+        assert_eq!(combined.overview.bytes, None);
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_article_size() {
+        let stream = mock_stream("OVER\r\nHDR\r\n")
+            .expect(
+                "OVER 1",
+                "224 overview\r\n1\ts\t\t\t<1@example.com>\t\t1234\t10\r\n.\r\n",
+            )
+            // Unknown bytes in the overview, the size is retrieved via HDR
+            .expect(
+                "OVER 2",
+                "224 overview\r\n2\ts\t\t\t<2@example.com>\t\t0\t10\r\n.\r\n",
+            )
+            .expect("HDR :bytes 2", "225 follows\r\n2 2048\r\n.\r\n")
+            // Unknown bytes everywhere, the size is estimated from the lines in the overview
+            .expect(
+                "OVER 3",
+                "224 overview\r\n3\ts\t\t\t<3@example.com>\t\t\t10\r\n.\r\n",
+            )
+            .expect("HDR :bytes 3", "225 follows\r\n3 \r\n.\r\n")
+            .expect("OVER 4", "423 no such article\r\n")
+            .expect("HDR :bytes 4", "423 no such article\r\n")
+            .expect("HDR :lines 4", "423 no such article\r\n");
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+
+        let size = |client: &mut NntpClient<MockStream>, n| {
+            client.article_size(cmd::Article::Number(n)).unwrap()
+        };
+        assert_eq!(size(&mut client, 1), Some(1234));
+        assert_eq!(size(&mut client, 2), Some(2048));
+        assert_eq!(size(&mut client, 3), Some(800));
+        assert_eq!(size(&mut client, 4), None);
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_article_size_hdr_only() {
+        let id = "<1@example.com>";
+        let stream = mock_stream("HDR\r\n")
+            .expect("HDR :bytes <1@example.com>", "225 follows\r\n0 0\r\n.\r\n")
+            .expect("HDR :lines <1@example.com>", "225 follows\r\n0 3\r\n.\r\n")
+            // A bogus line count saturates instead of overflowing
+            .expect("HDR :bytes <1@example.com>", "225 follows\r\n0 0\r\n.\r\n")
+            .expect(
+                "HDR :lines <1@example.com>",
+                format!("225 follows\r\n0 {}\r\n.\r\n", u64::MAX),
+            );
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+        assert_eq!(
+            client
+                .article_size(cmd::Article::MessageId(id.to_string()))
+                .unwrap(),
+            Some(240)
+        );
+        assert_eq!(
+            client
+                .article_size(cmd::Article::MessageId(id.to_string()))
+                .unwrap(),
+            Some(u64::MAX)
+        );
+
+        // Without OVER or HDR the server is not contacted
+        let mut client = ClientConfig::default()
+            .connect_stream(mock_stream(""))
+            .unwrap();
+        assert_eq!(
+            client
+                .article_size(cmd::Article::MessageId(id.to_string()))
+                .unwrap(),
+            None
+        );
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_head_fallback() {
+        let stream = mock_stream("")
+            .expect(
+                "HEAD 1",
+                "221 1 <1@example.com>\r\nsubject: one\r\nFrom: a@example.com\r\n\r\n.\r\n",
+            )
+            .expect("HEAD 2", "423 no such article\r\n");
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+
+        let entries = client.overview_or_fallback(1..=2).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].subject.as_deref(), Some("one"));
+        assert_eq!(entries[0].from.as_deref(), Some("a@example.com"));
+        assert_eq!(entries[0].message_id.as_deref(), Some("<1@example.com>"));
+        assert_eq!(entries[0].references, None);
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+}
@@ -0,0 +1,161 @@
+use std::io::{Read, Write};
+use std::vec;
+
+use crate::client::NntpClient;
+use crate::error::{Error, Result};
+use crate::types::command::{IntoArticleRange, RangeChunker};
+use crate::types::prelude::*;
+use crate::types::response::OverviewEntry;
+
+/// An iterator over the overview entries of a range of articles, retrieved a page at a time
+///
+/// Created by [`NntpClient::over_paged`]. Only one page of entries is held in memory, so
+/// arbitrarily large groups can be walked.
+///
+/// * Articles missing from a page are skipped, as are pages without any articles (`423`)
+/// * Pages rejected with `420`, `502`, or `503` are retried once as two halves, see
+///   [`overview_chunked`](NntpClient::overview_chunked)
+/// * Connection errors and [`Error::DeadlineExceeded`] end the iteration after being yielded
+/// * Other errors (e.g. a page that fails to parse) are yielded and iteration continues with
+///   the next page
+#[derive(Debug)]
+pub struct OverviewPages<'a, S> {
+    client: &'a mut NntpClient<S>,
+    pages: RangeChunker,
+    page_size: ArticleNumber,
+    page: vec::IntoIter<OverviewEntry>,
+    done: bool,
+}
+
+impl<'a, S: Read + Write> Iterator for OverviewPages<'a, S> {
+    type Item = Result<OverviewEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.page.next() {
+                return Some(Ok(entry));
+            }
+            if self.done {
+                return None;
+            }
+
+            let (low, high) = self.pages.next()?;
+            match self
+                .client
+                .overview_chunked(low..=high, self.page_size, |_, _| {})
+            {
+                Ok(entries) => self.page = entries.into_iter(),
+                Err(e) => {
+                    if let Error::Connection(_) | Error::DeadlineExceeded = e.inner() {
+                        self.done = true;
+                    }
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+impl<S: Read + Write> NntpClient<S> {
+    /// Lazily iterate over the overview entries for a range of articles
+    ///
+    /// Unlike [`over`](Self::over), which retrieves a range with a single command, and
+    /// [`overview_chunked`](Self::overview_chunked), which collects every entry, the range is
+    /// retrieved with successive `OVER` commands of at most `page_size` articles as the
+    /// iterator is consumed. An open range (e.g. `1..`) ends at the cached high watermark of
+    /// the selected group. A `page_size` of `0` is treated as `1`.
+    ///
+    /// Returns an error if the range is invalid, or if it is open and no group is selected.
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = brokaw::ClientConfig::default()
+    ///     .group(Some("misc.test"))
+    ///     .connect(("news.example.com", 119))?;
+    ///
+    /// for entry in client.over_paged(1.., 10_000)? {
+    ///     let entry = entry?;
+    ///     println!("{} {:?}", entry.number, entry.subject);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn over_paged(
+        &mut self,
+        range: impl IntoArticleRange,
+        page_size: ArticleNumber,
+    ) -> Result<OverviewPages<'_, S>> {
+        let (low, high) = self.range_bounds(range.into_article_range()?)?;
+        let page_size = page_size.max(1);
+        Ok(OverviewPages {
+            client: self,
+            pages: RangeChunker::new(low, high, page_size),
+            page_size,
+            page: Vec::new().into_iter(),
+            done: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{fixtures, MockStream};
+    use crate::ClientConfig;
+
+    #[test]
+    fn test_over_paged() {
+        // Article 3 is missing, 5-6 is an empty page, and 9 is a partial page
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("GROUP misc.test", "211 7 1 9 misc.test\r\n")
+            .expect("OVER 1-2", fixtures::overview(&[1, 2]))
+            .expect("OVER 3-4", fixtures::overview(&[4]))
+            .expect("OVER 5-6", "423 no articles in that range\r\n")
+            .expect("OVER 7-8", fixtures::overview(&[7, 8]))
+            .expect("OVER 9-9", fixtures::overview(&[9]));
+        let mut client = ClientConfig::default()
+            .group(Some("misc.test"))
+            .connect_stream(stream)
+            .unwrap();
+
+        let numbers = client
+            .over_paged(1.., 2)
+            .unwrap()
+            .map(|entry| entry.unwrap().number)
+            .collect::<Vec<_>>();
+        assert_eq!(numbers, vec![1, 2, 4, 7, 8, 9]);
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_over_paged_is_lazy() {
+        // Only the first page is requested until it has been consumed
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("OVER 1-2", fixtures::overview(&[1, 2]));
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+
+        let mut pages = client.over_paged(1..=4, 2).unwrap();
+        assert_eq!(pages.next().unwrap().unwrap().number, 1);
+        assert_eq!(pages.next().unwrap().unwrap().number, 2);
+        // The stream has no response for the next page
+        assert!(matches!(pages.next(), Some(Err(Error::Connection(_)))));
+        assert!(pages.next().is_none());
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_over_paged_invalid_range() {
+        let stream =
+            MockStream::new(fixtures::GREETING).expect("CAPABILITIES", fixtures::CAPABILITIES);
+        let mut client = ClientConfig::default().connect_stream(stream).unwrap();
+
+        assert!(matches!(
+            client.over_paged(1.., 2),
+            Err(Error::NoGroupSelected)
+        ));
+        assert!(client.over_paged((2, 1), 2).is_err());
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+}
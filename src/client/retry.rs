@@ -0,0 +1,566 @@
+//! Retrying operations that fail with transient errors
+//!
+//! Some providers return errors under load (e.g. `502` or `403`) that succeed when retried.
+//! [`with_backoff`] retries an operation according to a [`RetryPolicy`], reconnecting if the
+//! connection fails.
+//!
+//! A policy can also be set via [`ClientConfig::retry_policy`](crate::ClientConfig::retry_policy),
+//! in which case the client retries idempotent commands (e.g. `ARTICLE`, `OVER`, or `GROUP`)
+//! on its own. Commands that transfer articles (`POST` and `IHAVE`) are never retried.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use brokaw::ClientConfig;
+//! use brokaw::client::retry::{with_backoff, RetryPolicy};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut client = ClientConfig::default().connect(("news.example.com", 119))?;
+//!
+//! let policy = RetryPolicy::new()
+//!     .max_retries(3)
+//!     .backoff(Duration::from_secs(1), Duration::from_secs(10))
+//!     .to_owned();
+//!
+//! let group = with_backoff(&mut client, |c| c.select_group("misc.test"), &policy)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Thread safety
+//!
+//! The client is mutably borrowed for the duration of [`with_backoff`], so the operation has
+//! exclusive use of the connection and the calling thread blocks while backing off.
+//! Clients are not meant to be shared between threads, give each thread its own client.
+//! A `RetryPolicy` is `Send + Sync` and may be shared freely.
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{Read, Write};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::*;
+
+use crate::client::NntpClient;
+use crate::error::{Error, Result};
+use crate::types::prelude::*;
+
+/// When and how often to retry an operation
+///
+/// The default policy does not retry so that retries are opt-in. Once enabled, the following
+/// are retried:
+///
+/// * Failures with one of the [`retry_codes`](Self::retry_codes) (`403` and `502` by default)
+/// * Connection errors, after reconnecting. This includes `400` (service temporarily
+///   unavailable) as the server closes the connection after sending it, see
+///   [`raw::Error::ServerClosed`](crate::raw::error::Error::ServerClosed).
+///
+/// The delay before each retry doubles, starting from the initial backoff and capped at the
+/// maximum backoff.
+///
+/// When the retries are exhausted the last error is returned as an
+/// [`Error::RetriesExhausted`] recording the number of attempts.
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    retry_codes: Vec<ResponseCode>,
+    jitter: bool,
+    predicate: Option<Arc<RetryPredicate>>,
+}
+
+/// Decides whether an error is retried, see [`RetryPolicy::retry_if`]
+type RetryPredicate = dyn Fn(&Error) -> bool + Send + Sync;
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            retry_codes: vec![
+                Kind::InternalError.into(),
+                Kind::PermanentlyUnavailable.into(),
+            ],
+            jitter: false,
+            predicate: None,
+        }
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_retries", &self.max_retries)
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("retry_codes", &self.retry_codes)
+            .field("jitter", &self.jitter)
+            .field("predicate", &self.predicate.as_ref().map(|_| ".."))
+            .finish()
+    }
+}
+
+impl RetryPolicy {
+    /// Create a policy that does not retry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The maximum number of retries after the first attempt
+    pub fn max_retries(&mut self, max_retries: u32) -> &mut Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// The delay before the first retry and the maximum delay between retries
+    pub fn backoff(&mut self, initial: Duration, max: Duration) -> &mut Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// The response codes that are considered transient
+    ///
+    /// `400` is never seen here as it closes the connection, it is retried as a connection error.
+    pub fn retry_codes(&mut self, codes: &[impl Into<ResponseCode> + Copy]) -> &mut Self {
+        self.retry_codes = codes.iter().map(|&c| c.into()).collect();
+        self
+    }
+
+    /// Randomize each delay between half and all of the backoff
+    ///
+    /// This spreads out the retries of clients that failed at the same time (e.g. when a
+    /// server restarts) rather than having them retry in lockstep.
+    pub fn jitter(&mut self, jitter: bool) -> &mut Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Decide which errors are retried, replacing the [`retry_codes`](Self::retry_codes)
+    ///
+    /// By default connection errors and failures with one of the retry codes are retried.
+    /// Connection errors accepted by the predicate are retried after reconnecting.
+    ///
+    /// ```
+    /// use brokaw::client::retry::RetryPolicy;
+    /// use brokaw::error::Error;
+    ///
+    /// // Only retry failures, never reconnect
+    /// let policy = RetryPolicy::new()
+    ///     .max_retries(2)
+    ///     .retry_if(|e| matches!(e.inner(), Error::Failure { .. }))
+    ///     .to_owned();
+    /// ```
+    pub fn retry_if(
+        &mut self,
+        predicate: impl Fn(&Error) -> bool + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.predicate = Some(Arc::new(predicate));
+        self
+    }
+
+    /// The delay before a retry, starting from `0` for the first retry
+    ///
+    /// This does not include [`jitter`](Self::jitter).
+    pub fn delay(&self, retry: u32) -> Duration {
+        2u32.checked_pow(retry)
+            .and_then(|factor| self.initial_backoff.checked_mul(factor))
+            .map_or(self.max_backoff, |delay| delay.min(self.max_backoff))
+    }
+
+    /// Returns true if the policy retries at all
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.max_retries > 0
+    }
+
+    /// The delay before a retry including jitter
+    fn jittered_delay(&self, retry: u32) -> Duration {
+        let delay = self.delay(retry);
+        if !self.jitter {
+            return delay;
+        }
+
+        // The keys of a `RandomState` are randomized, which is enough to spread out retries
+        let mut hasher = RandomState::new().build_hasher();
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+        hasher.write_u32(nanos);
+        let fraction = (hasher.finish() % 1000) as f64 / 1000.0;
+
+        delay / 2 + (delay / 2).mul_f64(fraction)
+    }
+
+    /// Returns true if the error should be retried
+    fn is_retryable(&self, err: &Error) -> bool {
+        // Cancelled and rejected commands fail the same way every time
+        if err.is_cancelled()
+            || matches!(
+                err.inner(),
+                Error::Connection(crate::raw::error::Error::InvalidCommand)
+            )
+        {
+            return false;
+        }
+        if let Some(predicate) = &self.predicate {
+            return predicate(err);
+        }
+        match err.inner() {
+            Error::Connection(_) => true,
+            Error::Failure { code, .. } => self.retry_codes.contains(code),
+            _ => false,
+        }
+    }
+}
+
+/// Reconnects a client before an operation is retried
+pub(crate) type Reconnect<S> = fn(&mut NntpClient<S>) -> Result<()>;
+
+/// Run an operation, retrying it according to the policy
+///
+/// The operation may be run several times so it should be safe to repeat, e.g. retrying a
+/// `POST` that failed after the article was sent may post it twice.
+///
+/// If the connection fails the client is [reconnected](NntpClient::reconnect) before the
+/// operation is retried. The last error is returned once the retries are exhausted, or
+/// immediately if it is not retryable.
+pub fn with_backoff<T>(
+    client: &mut NntpClient,
+    op: impl FnMut(&mut NntpClient) -> Result<T>,
+    policy: &RetryPolicy,
+) -> Result<T> {
+    retry(client, policy, Some(NntpClient::reconnect), op)
+}
+
+/// Run an operation, retrying it according to the policy and reconnecting with `reconnect`
+///
+/// Connection errors are returned without retrying if `reconnect` is `None`.
+/// The client's deadline and cancellation token are checked before backing off and again
+/// before each retry, and the backoff is cut short if the deadline would pass first.
+pub(crate) fn retry<S: Read + Write, T>(
+    client: &mut NntpClient<S>,
+    policy: &RetryPolicy,
+    reconnect: Option<Reconnect<S>>,
+    mut op: impl FnMut(&mut NntpClient<S>) -> Result<T>,
+) -> Result<T> {
+    let mut retries = 0;
+    let mut needs_reconnect = false;
+
+    loop {
+        let res = match reconnect {
+            Some(reconnect) if needs_reconnect => reconnect(client).and_then(|_| op(client)),
+            _ => op(client),
+        };
+
+        let err = match res {
+            Ok(val) => return Ok(val),
+            Err(e) => e,
+        };
+
+        if !policy.is_retryable(&err) {
+            return Err(err);
+        }
+        needs_reconnect = matches!(err.inner(), Error::Connection(_));
+        if needs_reconnect && reconnect.is_none() {
+            return Err(err);
+        }
+
+        if retries >= policy.max_retries {
+            if retries == 0 {
+                return Err(err);
+            }
+            return Err(Error::RetriesExhausted {
+                attempts: retries + 1,
+                source: Box::new(err),
+            });
+        }
+
+        client.check_interrupted()?;
+        let mut delay = policy.jittered_delay(retries);
+        if let Some(remaining) = client.time_remaining() {
+            delay = delay.min(remaining);
+        }
+        retries += 1;
+        warn!(
+            "Retrying in {:?} ({} of {}) -- {}",
+            delay, retries, policy.max_retries, err
+        );
+        thread::sleep(delay);
+        client.check_interrupted()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{self, Read, Write};
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+
+    use crate::raw::stream::NntpStream;
+    use crate::raw::{CancellationToken, ConnectionConfig};
+    use crate::testing::{fixtures, MockStream};
+    use crate::types::command as cmd;
+    use crate::ClientConfig;
+
+    fn policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy::new()
+            .max_retries(max_retries)
+            .backoff(Duration::from_millis(1), Duration::from_millis(1))
+            .to_owned()
+    }
+
+    /// A mock that can still be inspected once it is boxed in an [`NntpStream`]
+    #[derive(Clone)]
+    struct Shared(Arc<Mutex<MockStream>>);
+
+    impl Shared {
+        fn new(stream: MockStream) -> Self {
+            Shared(Arc::new(Mutex::new(stream)))
+        }
+
+        fn is_done(&self) -> bool {
+            self.0.lock().unwrap().is_done()
+        }
+    }
+
+    impl Read for Shared {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().read(buf)
+        }
+    }
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.0.lock().unwrap().flush()
+        }
+    }
+
+    /// A client that responds to each `GROUP misc.test` with the next response
+    fn mock_client(responses: &[&str]) -> (NntpClient, Shared) {
+        let stream = responses.iter().fold(
+            MockStream::new(fixtures::GREETING).expect("CAPABILITIES", fixtures::CAPABILITIES),
+            |stream, resp| stream.expect("GROUP misc.test", resp),
+        );
+        let stream = Shared::new(stream);
+        let client = ClientConfig::default()
+            .connect_stream(NntpStream::custom(stream.clone()))
+            .unwrap();
+        (client, stream)
+    }
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy::new()
+            .backoff(Duration::from_secs(1), Duration::from_secs(5))
+            .to_owned();
+        assert_eq!(policy.delay(0), Duration::from_secs(1));
+        assert_eq!(policy.delay(1), Duration::from_secs(2));
+        assert_eq!(policy.delay(2), Duration::from_secs(4));
+        assert_eq!(policy.delay(3), Duration::from_secs(5));
+        assert_eq!(policy.delay(100), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_retry_transient_code() {
+        let (mut client, stream) = mock_client(&[
+            "502 try again\r\n",
+            "403 internal fault\r\n",
+            "211 3 1 3 misc.test\r\n",
+        ]);
+
+        let group = with_backoff(&mut client, |c| c.select_group("misc.test"), &policy(2));
+        assert_eq!(group.unwrap().name, "misc.test");
+        assert!(stream.is_done());
+    }
+
+    #[test]
+    fn test_no_retry() {
+        // The default policy does not retry
+        let (mut client, stream) = mock_client(&["502 try again\r\n"]);
+        let res = with_backoff(
+            &mut client,
+            |c| c.select_group("misc.test"),
+            &RetryPolicy::new(),
+        );
+        assert!(matches!(res, Err(Error::Failure { .. })));
+        assert!(stream.is_done());
+
+        // Non-transient codes are not retried
+        let (mut client, stream) = mock_client(&["411 no such group\r\n"]);
+        let res = with_backoff(&mut client, |c| c.select_group("misc.test"), &policy(2));
+        assert!(matches!(res, Err(Error::Failure { .. })));
+        assert!(stream.is_done());
+    }
+
+    #[test]
+    fn test_retries_exhausted() {
+        let (mut client, stream) = mock_client(&["502 try again\r\n", "502 try again\r\n"]);
+        let res = with_backoff(&mut client, |c| c.select_group("misc.test"), &policy(1));
+        match res {
+            Err(Error::RetriesExhausted { attempts, source }) => {
+                assert_eq!(attempts, 2);
+                assert!(matches!(
+                    *source,
+                    Error::Failure {
+                        code: ResponseCode::Known(Kind::PermanentlyUnavailable),
+                        ..
+                    }
+                ));
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+        assert!(stream.is_done());
+    }
+
+    #[test]
+    fn test_retry_if() {
+        let (mut client, stream) = mock_client(&["502 try again\r\n"]);
+        let policy = policy(2).retry_if(|_| false).to_owned();
+        let res = with_backoff(&mut client, |c| c.select_group("misc.test"), &policy);
+        assert!(matches!(res, Err(Error::Failure { .. })));
+        assert!(stream.is_done());
+
+        // Cancellation is never retried
+        let policy = RetryPolicy::new().retry_if(|_| true).to_owned();
+        assert!(!policy.is_retryable(&crate::raw::error::Error::Cancelled.into()));
+    }
+
+    #[test]
+    fn test_invalid_command_not_retried() {
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("GROUP misc.test", "211 3 1 3 misc.test\r\n");
+        let stream = Shared::new(stream);
+        let mut client = ClientConfig::default()
+            .retry_policy(policy(2))
+            .connect_stream(NntpStream::custom(stream.clone()))
+            .unwrap();
+        client.reconnect = |_| panic!("Rejected commands must not reconnect");
+
+        let stat = cmd::Stat::MessageId("<1@example.com>\r\nQUIT".to_string());
+        assert!(matches!(
+            client.stat(stat),
+            Err(Error::Connection(crate::raw::error::Error::InvalidCommand))
+        ));
+        // Nothing was sent so the connection is still usable
+        assert_eq!(client.select_group("misc.test").unwrap().number, 3);
+        assert!(stream.is_done());
+    }
+
+    #[test]
+    fn test_current_article_not_retried_after_reconnect() {
+        // The connection closes before the server responds
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("STAT", "")
+            .expect("STAT 1", "");
+        let stream = Shared::new(stream);
+        let mut client = ClientConfig::default()
+            .retry_policy(policy(2))
+            .connect_stream(NntpStream::custom(stream.clone()))
+            .unwrap();
+        client.reconnect = |client| {
+            let stream = MockStream::new(fixtures::GREETING)
+                .expect("CAPABILITIES", fixtures::CAPABILITIES)
+                .expect("STAT 1", "223 1 <1@example.com>\r\n");
+            *client = ClientConfig::default().connect_stream(NntpStream::custom(stream))?;
+            Ok(())
+        };
+
+        // A new connection has no current article
+        assert!(matches!(
+            client.stat(cmd::Stat::Current),
+            Err(Error::Connection(_))
+        ));
+
+        let stat = client.stat(cmd::Stat::Number(1)).unwrap().unwrap();
+        assert_eq!(stat.number, 1);
+        assert!(stream.is_done());
+    }
+
+    #[test]
+    fn test_backoff_interrupted() {
+        // The backoff is cut short by the deadline
+        let (mut client, _) = mock_client(&["502 try again\r\n", "502 try again\r\n"]);
+        let policy = policy(2)
+            .backoff(Duration::from_secs(10), Duration::from_secs(10))
+            .to_owned();
+        let start = Instant::now();
+        let res = client.with_deadline(start + Duration::from_millis(50), |client| {
+            with_backoff(client, |c| c.select_group("misc.test"), &policy)
+        });
+        assert!(matches!(res, Err(Error::DeadlineExceeded)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // Cancelled operations are not retried
+        let token = CancellationToken::new();
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("GROUP misc.test", "502 try again\r\n");
+        let stream = Shared::new(stream);
+        let mut client = ClientConfig::default()
+            .connection_config(
+                ConnectionConfig::new()
+                    .cancellation_token(Some(token.clone()))
+                    .to_owned(),
+            )
+            .connect_stream(NntpStream::custom(stream.clone()))
+            .unwrap();
+        token.cancel();
+        let res = with_backoff(&mut client, |c| c.select_group("misc.test"), &policy);
+        assert!(matches!(res, Err(ref e) if e.is_cancelled()));
+        assert!(stream.is_done());
+    }
+
+    #[test]
+    fn test_jitter() {
+        let policy = policy(1)
+            .backoff(Duration::from_millis(100), Duration::from_secs(1))
+            .jitter(true)
+            .to_owned();
+        for retry in 0..3 {
+            let delay = policy.jittered_delay(retry);
+            assert!(delay >= policy.delay(retry) / 2);
+            assert!(delay <= policy.delay(retry));
+        }
+    }
+
+    #[test]
+    fn test_configured_policy() {
+        // Idempotent commands are retried by the client without `with_backoff`
+        let stream = MockStream::new(fixtures::GREETING)
+            .expect("CAPABILITIES", fixtures::CAPABILITIES)
+            .expect("GROUP misc.test", "502 try again\r\n")
+            .expect("GROUP misc.test", "403 internal fault\r\n")
+            .expect("GROUP misc.test", "211 3 1 3 misc.test\r\n")
+            .expect("STAT 1", "403 internal fault\r\n")
+            .expect("STAT 1", "403 internal fault\r\n")
+            .expect("STAT 1", "403 internal fault\r\n")
+            .expect("POST", "502 try again\r\n");
+        let mut client = ClientConfig::default()
+            .retry_policy(policy(2))
+            .connect_stream(stream)
+            .unwrap();
+
+        assert_eq!(client.select_group("misc.test").unwrap().number, 3);
+        assert!(matches!(
+            client.stat_by_number(1),
+            Err(Error::RetriesExhausted { attempts: 3, .. })
+        ));
+        // POST is not retried
+        assert!(matches!(
+            client.post(&b"Subject: hi\r\n\r\nbody"[..]),
+            Err(Error::Failure { .. })
+        ));
+        assert!(client.conn().stream().get_ref().is_done());
+    }
+}
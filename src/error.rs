@@ -4,13 +4,14 @@ use crate::types::prelude::*;
 
 /// All of the ways that a failure can occur within Brokaw
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum Error {
     /// This error indicates an application layer failure.
     ///
     /// For example, asking for a non-existent group will return
     /// [`NoSuchNewsGroup`](`crate::types::prelude::Kind::NoSuchNewsgroup`) (code 411),
     /// which is not a protocol error.
-    #[error("Server returned {code:?} -- {msg:?}")]
+    #[error("Server returned {code}{}", failure_detail(.msg))]
     Failure {
         /// The response code
         code: ResponseCode,
@@ -23,21 +24,222 @@ pub enum Error {
     /// An error raised by the underlying connection
     ///
     /// This is usually of an I/O error or a TLS error
-    Connection(#[from] crate::raw::error::Error),
+    Connection(crate::raw::error::Error),
+    /// A required field was missing from the response
+    #[error("Missing field `{field}`")]
+    MissingField {
+        /// The name of the field
+        field: &'static str,
+    },
+    /// A field within the response could not be parsed
+    #[error("Could not parse field `{field}` from `{value}`")]
+    InvalidField {
+        /// The name of the field
+        field: &'static str,
+        /// The value that failed to parse
+        value: String,
+    },
+    /// The server does not advertise the capability required by the operation
+    #[error("Server does not advertise {capability}")]
+    Unsupported {
+        /// The required capability (and argument, if any)
+        capability: &'static str,
+    },
+    /// An article range where `low` is greater than `high`
+    #[error("Invalid article range {low}-{high}")]
+    InvalidRange {
+        /// The low number of the range
+        low: ArticleNumber,
+        /// The high number of the range
+        high: ArticleNumber,
+    },
+    /// The group name is not valid per RFC 3977
+    #[error("Invalid group name {name:?}")]
+    InvalidGroupName {
+        /// The rejected name
+        name: String,
+    },
+    /// The operation requires a selected group but none is selected
+    #[error("No group is selected")]
+    NoGroupSelected,
+    /// The deadline set by [`NntpClient::with_deadline`](crate::client::NntpClient::with_deadline)
+    /// passed before the operation completed
+    #[error("Deadline exceeded")]
+    DeadlineExceeded,
+    /// The group's article numbers are inconsistent with a [`GroupCursor`](crate::client::GroupCursor)
+    ///
+    /// Either the server's high watermark is below the last article seen, or articles after
+    /// the last one seen have already expired. The group may have been renumbered, so the
+    /// caller should decide how to resynchronize rather than refetching everything.
+    #[error("Group {group} was renumbered (last seen {last_seen}, server has {low}-{high})")]
+    Renumbered {
+        /// The name of the group
+        group: String,
+        /// The last article number recorded by the cursor
+        last_seen: ArticleNumber,
+        /// The lowest article number reported by the server
+        low: ArticleNumber,
+        /// The highest article number reported by the server
+        high: ArticleNumber,
+    },
+    /// An operation failed after being retried according to a
+    /// [`RetryPolicy`](crate::client::retry::RetryPolicy)
+    ///
+    /// [`inner`](Error::inner) returns the error from the final attempt.
+    #[error("{source} (after {attempts} attempts)")]
+    RetriesExhausted {
+        /// The number of times the operation was attempted, including the first attempt
+        attempts: u32,
+        /// The error from the final attempt
+        source: Box<Error>,
+    },
+    /// A multi-line response was expected but the response did not contain data blocks
+    #[error("Response is missing multi-line data blocks")]
+    MissingDataBlocks,
+    /// The article headers within the data blocks could not be parsed
+    #[error("Invalid article headers at line {line} (byte offset {offset})")]
+    InvalidHeaders {
+        /// The line within the data blocks where parsing stopped (starting from 1)
+        line: usize,
+        /// The byte offset within the data blocks where parsing stopped
+        offset: usize,
+    },
+    /// The response code did not match the type being deserialized
+    #[error("Unexpected response code {got}, expected one of {expected:?}")]
+    UnexpectedResponseCode {
+        /// The acceptable response codes
+        expected: Vec<ResponseCode>,
+        /// The code returned by the server
+        got: ResponseCode,
+    },
     /// An error deserializing a [RawResponse] into a concrete type
+    ///
+    /// This is a catch-all for deserialization failures not covered by a more specific variant
     #[error("{0}")]
     Deserialization(String),
     /// An error deserializing bytes as UTF-8
     #[error("{0}")]
     Utf8(#[from] Utf8Error),
+    /// A deserialization error along with the bytes of the response that failed to parse
+    ///
+    /// This is only returned if
+    /// [`ConnectionConfig::keep_offending_bytes`](crate::raw::connection::ConnectionConfig::keep_offending_bytes)
+    /// is enabled or the error was created with [`Error::with_offending_bytes`].
+    #[error("{source}")]
+    Offending {
+        /// The underlying deserialization error
+        source: Box<Error>,
+        /// The first line of the response
+        first_line: Vec<u8>,
+        /// Up to [`MAX_OFFENDING_BYTES`] of the data blocks in the response
+        data_blocks: Vec<u8>,
+    },
+}
+
+/// The maximum number of data block bytes retained by [`Error::Offending`]
+pub const MAX_OFFENDING_BYTES: usize = 4 * 1024;
+
+impl From<crate::raw::error::Error> for Error {
+    fn from(e: crate::raw::error::Error) -> Self {
+        match e {
+            // Reads are stopped by the client's deadline, so report it the same way
+            crate::raw::error::Error::DeadlineExceeded => Error::DeadlineExceeded,
+            e => Error::Connection(e),
+        }
+    }
 }
 
 impl Error {
-    pub(crate) fn failure(resp: RawResponse) -> Self {
+    /// Attach the bytes of the response that could not be deserialized to the error
+    ///
+    /// Only the first [`MAX_OFFENDING_BYTES`] of the data blocks are retained.
+    pub fn with_offending_bytes(self, resp: &RawResponse) -> Self {
+        let first_line = resp.first_line().to_vec();
+        let data_blocks = resp
+            .data_blocks()
+            .map(|db| {
+                let payload = db.payload();
+                payload[..payload.len().min(MAX_OFFENDING_BYTES)].to_vec()
+            })
+            .unwrap_or_default();
+
+        Error::Offending {
+            source: Box::new(self.into_inner()),
+            first_line,
+            data_blocks,
+        }
+    }
+
+    /// The first line and a prefix of the data blocks of the response that failed to parse
+    ///
+    /// Returns `None` unless this is an [`Error::Offending`]
+    pub fn offending_bytes(&self) -> Option<(&[u8], &[u8])> {
+        match self {
+            Error::Offending {
+                first_line,
+                data_blocks,
+                ..
+            } => Some((first_line, data_blocks)),
+            _ => None,
+        }
+    }
+
+    /// The underlying error with any offending bytes and retry information stripped
+    pub fn inner(&self) -> &Error {
+        match self {
+            Error::Offending { source, .. } | Error::RetriesExhausted { source, .. } => {
+                source.inner()
+            }
+            e => e,
+        }
+    }
+
+    fn into_inner(self) -> Error {
+        match self {
+            Error::Offending { source, .. } => *source,
+            e => e,
+        }
+    }
+
+    /// Returns true if the operation was cancelled via a
+    /// [`CancellationToken`](crate::raw::CancellationToken)
+    pub fn is_cancelled(&self) -> bool {
+        matches!(
+            self.inner(),
+            Error::Connection(crate::raw::error::Error::Cancelled)
+        )
+    }
+
+    /// Returns true if the error occurred while deserializing a response into a concrete type
+    pub(crate) fn is_deserialization(&self) -> bool {
+        matches!(
+            self.inner(),
+            Error::MissingField { .. }
+                | Error::InvalidField { .. }
+                | Error::MissingDataBlocks
+                | Error::InvalidHeaders { .. }
+                | Error::UnexpectedResponseCode { .. }
+                | Error::Deserialization(_)
+                | Error::Utf8(_)
+        )
+    }
+
+    pub(crate) fn unexpected_code(resp: RawResponse, expected: &[ResponseCode]) -> Self {
+        let code = resp.code();
+        let expected = expected
+            .iter()
+            .map(|code| u16::from(code).to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+
         Error::Failure {
-            code: resp.code(),
+            code,
+            msg: Some(format!(
+                "Expected one of [{}], got {}",
+                expected,
+                code.describe()
+            )),
             resp,
-            msg: None,
         }
     }
 
@@ -45,22 +247,35 @@ impl Error {
         Error::Deserialization(msg.as_ref().to_string())
     }
 
-    pub(crate) fn missing_field(name: impl AsRef<str>) -> Self {
-        Error::Deserialization(format!("Missing field `{}`", name.as_ref()))
+    pub(crate) fn missing_field(field: &'static str) -> Self {
+        Error::MissingField { field }
     }
 
-    pub(crate) fn parse_error(name: impl AsRef<str>) -> Self {
-        Error::Deserialization(format!("Could not parse field `{}`", name.as_ref()))
+    pub(crate) fn invalid_field(field: &'static str, value: impl AsRef<str>) -> Self {
+        Error::InvalidField {
+            field,
+            value: value.as_ref().to_string(),
+        }
     }
 
     pub(crate) fn missing_data_blocks() -> Self {
-        Error::Deserialization("Response is missing multi-line data blocks".to_string())
+        Error::MissingDataBlocks
     }
 
-    pub(crate) fn invalid_data_blocks(msg: impl AsRef<str>) -> Self {
-        Error::Deserialization(format!("Invalid data-block section -- {}", msg.as_ref()))
+    /// Create an [`Error::InvalidHeaders`] from the payload and the unparsed remainder
+    pub(crate) fn invalid_headers(payload: &[u8], remaining: &[u8]) -> Self {
+        let offset = payload.len() - remaining.len();
+        let line = payload[..offset].iter().filter(|&&b| b == b'\n').count() + 1;
+        Error::InvalidHeaders { line, offset }
     }
 }
 
+/// Append the message of an [`Error::Failure`], if any, to its code
+fn failure_detail(msg: &Option<String>) -> String {
+    msg.as_ref()
+        .map(|msg| format!(" -- {}", msg))
+        .unwrap_or_default()
+}
+
 /// A result type returned by the library
 pub type Result<T> = std::result::Result<T, Error>;
@@ -13,9 +13,9 @@
 //! Brokaw provides two primary APIs for interacting with NNTP servers:
 //!
 //! 1. The [`NntpClient`] provides a higher-level that provides a a config
-//! based builder and automatic deserialization of responses into different types.
+//!    based builder and automatic deserialization of responses into different types.
 //! 2. The [`NntpConnection`] provides a lower-level abstraction that
-//! only provides validation that messages adhere to NNTP's wire format.
+//!    only provides validation that messages adhere to NNTP's wire format.
 //!
 //! Brokaw additionally provides strongly typed [commands](types::command),
 //! [responses](types::response), and the [`NntpCommand`](types::NntpCommand)
@@ -23,6 +23,28 @@
 //!
 //! ---
 //!
+//! # Testing
+//!
+//! The `test-util` feature provides a `testing` module containing a scriptable in-memory
+//! stream and canned server responses for testing code that uses Brokaw without a live server.
+//!
+//! ---
+//!
+//! # MIME
+//!
+//! The `mime` feature provides a [`mime`](crate::mime) module for splitting multipart articles
+//! (e.g. `multipart/mixed` posts with attachments) into their parts.
+//!
+//! ---
+//!
+//! # Serde
+//!
+//! The `serde` feature implements `Serialize` and `Deserialize` for plain-data responses
+//! (e.g. [`Group`](types::response::Group) and [`TextArticle`](types::response::TextArticle))
+//! and [response codes](types::response_code::ResponseCode), which are serialized as numbers.
+//!
+//! ---
+//!
 //! Please check out the [git repository](https://github.com/sgg/brokaw) examples.
 
 #[cfg(doctest)]
@@ -34,15 +56,27 @@ pub mod client;
 /// Error and Result types returned by the Brokaw
 pub mod error;
 
+/// MIME multipart parsing of articles
+#[cfg(feature = "mime")]
+pub mod mime;
+
 /// Low level connection/stream APIs
 ///
 /// These deal with raw NNTP connections and byte responses.
 /// Consider using the higher level [`client`] APIs unless you have special requirements
 pub mod raw;
 
+/// Threading articles into discussions using their overview entries
+pub mod threading;
+
 /// Typed commands, responses, and response codes
 pub mod types;
 
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing;
+
+mod base64;
+
 #[doc(inline)]
 pub use client::{ClientConfig, NntpClient};
 #[doc(inline)]
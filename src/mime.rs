@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+
+use crate::error::{Error, Result};
+use crate::raw::parse::strip_line_ending;
+use crate::types::response::article::parse::take_headers;
+use crate::types::response::{BinaryArticle, Headers};
+
+/// The media type of parts without a `Content-Type` header
+///
+/// [RFC 2045 5.2](https://tools.ietf.org/html/rfc2045#section-5.2)
+const DEFAULT_CONTENT_TYPE: &str = "text/plain";
+
+/// A part of a MIME message
+///
+/// Bodies are returned as sent, i.e. they are not decoded according to their
+/// `Content-Transfer-Encoding`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MimePart {
+    headers: Headers,
+    body: Vec<u8>,
+    parts: Vec<MimePart>,
+}
+
+impl MimePart {
+    /// The headers of the part
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    /// The raw contents of the part
+    ///
+    /// For multipart parts this includes the boundaries of the nested parts.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// The media type of the part in lowercase (e.g. `text/plain`), without any parameters
+    ///
+    /// Parts without a `Content-Type` header are `text/plain`.
+    pub fn content_type(&self) -> String {
+        content_type(&self.headers)
+            .map(|(media_type, _)| media_type)
+            .unwrap_or_else(|| DEFAULT_CONTENT_TYPE.to_string())
+    }
+
+    /// Returns true if the part is `multipart/*`
+    pub fn is_multipart(&self) -> bool {
+        self.content_type().starts_with("multipart/")
+    }
+
+    /// The nested parts of a multipart part (e.g. `multipart/alternative`)
+    ///
+    /// Parts are only nested one level deep, parts nested within these are not split.
+    pub fn parts(&self) -> &[MimePart] {
+        &self.parts
+    }
+}
+
+impl BinaryArticle {
+    /// Split a MIME article into its parts
+    ///
+    /// If the article is `multipart/*` the body is split on the `boundary` parameter of the
+    /// `Content-Type`. The preamble and epilogue are discarded, and multipart parts
+    /// (e.g. `multipart/alternative` within `multipart/mixed`) are split one level deep,
+    /// see [`MimePart::parts`]. A missing closing boundary is tolerated.
+    ///
+    /// Articles that are not multipart return a single part containing the article's headers
+    /// and body.
+    ///
+    /// Returns an error if a multipart article does not have a boundary or does not contain
+    /// any parts.
+    pub fn parts(&self) -> Result<Vec<MimePart>> {
+        match multipart_boundary(&self.headers)? {
+            Some(boundary) => split_parts(&self.body, &boundary, true),
+            None => Ok(vec![MimePart {
+                headers: self.headers.clone(),
+                body: self.body.clone(),
+                parts: vec![],
+            }]),
+        }
+    }
+}
+
+/// The boundary of a multipart entity, `None` if the entity is not multipart
+fn multipart_boundary(headers: &Headers) -> Result<Option<String>> {
+    match content_type(headers) {
+        Some((media_type, mut params)) if media_type.starts_with("multipart/") => params
+            .remove("boundary")
+            .filter(|b| !b.is_empty())
+            .map(Some)
+            .ok_or_else(|| Error::missing_field("boundary")),
+        _ => Ok(None),
+    }
+}
+
+/// Split the body of a multipart entity into its parts
+fn split_parts(body: &[u8], boundary: &str, nested: bool) -> Result<Vec<MimePart>> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+
+    // The start of the current part, `None` while in the preamble
+    let mut start = None;
+    let mut parts = Vec::new();
+    let mut pos = 0;
+
+    while pos < body.len() {
+        let end = body[pos..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(body.len(), |i| pos + i + 1);
+        let line = trim_line(&body[pos..end]);
+
+        if line.starts_with(&delimiter) {
+            // Transport padding may follow the delimiter
+            let rest = &line[delimiter.len()..];
+            let is_close = rest.starts_with(b"--");
+            if is_close || rest.iter().all(|b| *b == b' ' || *b == b'\t') {
+                if let Some(part_start) = start {
+                    // The line break preceding the delimiter belongs to the delimiter
+                    let part_end = strip_line_ending(&body[part_start..pos]).len() + part_start;
+                    parts.push(parse_part(&body[part_start..part_end], nested)?);
+                }
+                if is_close {
+                    return Ok(parts);
+                }
+                start = Some(end);
+            }
+        }
+
+        pos = end;
+    }
+
+    match start {
+        Some(part_start) => parts.push(parse_part(&body[part_start..], nested)?),
+        None => {
+            return Err(Error::de(format!(
+                "No MIME parts with boundary {:?}",
+                boundary
+            )))
+        }
+    }
+
+    Ok(parts)
+}
+
+/// Parse the headers and body of a single part
+fn parse_part(part: &[u8], nested: bool) -> Result<MimePart> {
+    let (headers, body) = if part.starts_with(b"\r\n") || part.starts_with(b"\n") {
+        // A part without headers
+        (Headers::default(), strip_leading_line_break(part))
+    } else {
+        match take_headers(part, false) {
+            Ok((body, headers)) => (headers, body),
+            Err(_) => return Err(Error::de("Failed to parse the headers of a MIME part")),
+        }
+    };
+
+    let parts = match multipart_boundary(&headers)? {
+        Some(boundary) if nested => split_parts(body, &boundary, false)?,
+        _ => vec![],
+    };
+
+    Ok(MimePart {
+        headers,
+        body: body.to_vec(),
+        parts,
+    })
+}
+
+/// Parse the `Content-Type` header into a lowercase media type and its parameters
+///
+/// Parameter names are lowercased and quoted values are unquoted.
+fn content_type(headers: &Headers) -> Option<(String, HashMap<String, String>)> {
+    let header = headers
+        .iter()
+        .find(|h| h.name.eq_ignore_ascii_case("Content-Type"))?;
+    // Unfold the header
+    let value = header.content.first()?.replace("\r\n", "");
+    let mut fields = value.split(';');
+
+    let media_type = fields.next()?.trim().to_ascii_lowercase();
+    let params = fields
+        .filter_map(|param| {
+            let mut kv = param.splitn(2, '=');
+            let name = kv.next()?.trim().to_ascii_lowercase();
+            let value = kv.next()?.trim();
+            let value = if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+                &value[1..value.len() - 1]
+            } else {
+                value
+            };
+            Some((name, value.to_string()))
+        })
+        .collect();
+
+    Some((media_type, params))
+}
+
+/// Remove the line break and any trailing whitespace from a line
+fn trim_line(line: &[u8]) -> &[u8] {
+    let end = line
+        .iter()
+        .rposition(|b| !b" \t\r\n".contains(b))
+        .map_or(0, |i| i + 1);
+    &line[..end]
+}
+
+/// Remove a single leading CRLF or LF
+fn strip_leading_line_break(b: &[u8]) -> &[u8] {
+    if b.starts_with(b"\r\n") {
+        &b[2..]
+    } else if b.starts_with(b"\n") {
+        &b[1..]
+    } else {
+        b
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::*;
+    use crate::types::prelude::*;
+
+    fn parse_article(payload: &[u8]) -> BinaryArticle {
+        let start = payload.iter().position(|&b| b == b'\n').unwrap() + 1;
+        let resp = RawResponse {
+            code: ResponseCode::Known(Kind::Article),
+            first_line: payload[..start].to_vec(),
+            data_blocks: Some(DataBlocks::from_payload(&payload[start..])),
+        };
+        BinaryArticle::try_from(&resp).unwrap()
+    }
+
+    #[test]
+    fn test_multipart() {
+        let article = parse_article(include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/multipart_article"
+        )));
+        let parts = article.parts().unwrap();
+        assert_eq!(parts.len(), 2);
+
+        let alternative = &parts[0];
+        assert_eq!(alternative.content_type(), "multipart/alternative");
+        assert!(alternative.is_multipart());
+        assert_eq!(alternative.parts().len(), 2);
+
+        let text = &alternative.parts()[0];
+        assert_eq!(text.content_type(), "text/plain");
+        assert_eq!(text.body(), b"Hello, world!\r\n");
+        assert_eq!(
+            text.headers()
+                .get("Content-Transfer-Encoding")
+                .unwrap()
+                .content,
+            vec!["8bit"]
+        );
+
+        let html = &alternative.parts()[1];
+        assert_eq!(html.content_type(), "text/html");
+        assert_eq!(html.body(), b"<p>Hello, world!</p>");
+
+        let attachment = &parts[1];
+        assert_eq!(attachment.content_type(), "application/octet-stream");
+        assert_eq!(attachment.body(), b"AAECAwQFBgc=");
+        assert!(attachment.parts().is_empty());
+    }
+
+    #[test]
+    fn test_not_multipart() {
+        let article = parse_article(b"220 1 <1@example.com>\r\nSubject: hi\r\n\r\nbody\r\n.\r\n");
+        let parts = article.parts().unwrap();
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].headers(), article.headers());
+        assert_eq!(parts[0].body(), b"body\r\n");
+        assert_eq!(parts[0].content_type(), "text/plain");
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        // No headers in the part, no closing delimiter, and transport padding
+        let article = parse_article(
+            b"220 1 <1@example.com>\r\n\
+            Content-Type: Multipart/Mixed; Boundary=b\r\n\r\n\
+            --b \r\n\r\nno headers\r\n--b\r\nContent-Type: text/x-test\r\n\r\ntruncated\r\n.\r\n",
+        );
+        let parts = article.parts().unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].body(), b"no headers");
+        assert!(parts[0].headers().is_empty());
+        assert_eq!(parts[1].content_type(), "text/x-test");
+        assert_eq!(parts[1].body(), b"truncated\r\n");
+
+        // A delimiter must be followed by nothing but whitespace
+        let article = parse_article(
+            b"220 1 <1@example.com>\r\n\
+            Content-Type: multipart/mixed; boundary=b\r\n\r\n\
+            --b\r\n\r\n--bc\r\n--b--\r\n.\r\n",
+        );
+        let parts = article.parts().unwrap();
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].body(), b"--bc");
+
+        let missing_boundary = parse_article(
+            b"220 1 <1@example.com>\r\nContent-Type: multipart/mixed\r\n\r\nbody\r\n.\r\n",
+        );
+        assert!(matches!(
+            missing_boundary.parts(),
+            Err(Error::MissingField { field: "boundary" })
+        ));
+
+        let no_parts = parse_article(
+            b"220 1 <1@example.com>\r\nContent-Type: multipart/mixed; boundary=b\r\n\r\nbody\r\n.\r\n",
+        );
+        assert!(no_parts.parts().is_err());
+    }
+}
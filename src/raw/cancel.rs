@@ -0,0 +1,52 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A flag for cancelling reads on an [`NntpConnection`](super::NntpConnection) from another thread
+///
+/// The token is checked before each line of a response's data blocks is read, as well as
+/// between the commands of chunked [`NntpClient`](crate::client::NntpClient) operations.
+/// Once tripped, reads fail with [`Error::Cancelled`](super::error::Error::Cancelled) until
+/// the token is [reset](Self::reset).
+///
+/// Clones share the same flag, so keep one clone and hand another to
+/// [`ConnectionConfig::cancellation_token`](super::ConnectionConfig::cancellation_token).
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Create a token that has not been cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel any in-flight and future reads
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst)
+    }
+
+    /// Returns true if the token has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Clear the flag so the token can be used for a new connection
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_flag() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!clone.is_cancelled());
+        token.cancel();
+        assert!(clone.is_cancelled());
+        clone.reset();
+        assert!(!token.is_cancelled());
+    }
+}
@@ -0,0 +1,288 @@
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, Read, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use log::*;
+
+use crate::raw::stream::read_via_buf;
+
+/// A destination for traffic captured from an [`NntpConnection`](super::NntpConnection)
+///
+/// See [`ConnectionConfig::capture`](super::connection::ConnectionConfig::capture)
+///
+/// # Capture Format
+///
+/// Each chunk of bytes read from or written to the connection is recorded as a header line
+/// containing a direction tag (`>>` for sent, `<<` for received) and the length of the chunk,
+/// followed by the raw bytes and a trailing newline:
+///
+/// ```text
+/// << 18
+/// 200 server ready\r\n
+/// >> 14
+/// CAPABILITIES\r\n
+/// ```
+///
+/// Received bytes are recorded *before* decompression while sent commands are recorded
+/// *before* `COMPRESS DEFLATE` compression.
+/// Arguments to `AUTHINFO USER`, `AUTHINFO PASS`, and `AUTHINFO SASL` are masked.
+#[derive(Clone)]
+pub struct CaptureSink(Sink);
+
+#[derive(Clone)]
+enum Sink {
+    Path(PathBuf),
+    Writer(SharedWriter),
+}
+
+type SharedWriter = Arc<Mutex<Box<dyn Write + Send>>>;
+
+impl CaptureSink {
+    /// Capture traffic to a file
+    ///
+    /// The file will be created (or truncated) when the connection is opened
+    pub fn path(path: impl Into<PathBuf>) -> Self {
+        Self(Sink::Path(path.into()))
+    }
+
+    /// Capture traffic to an arbitrary writer
+    ///
+    /// If the sink is used by several connections their traffic will be interleaved
+    pub fn writer(writer: impl Write + Send + 'static) -> Self {
+        Self(Sink::Writer(Arc::new(Mutex::new(Box::new(writer)))))
+    }
+
+    pub(crate) fn open(&self) -> io::Result<SharedWriter> {
+        match &self.0 {
+            Sink::Path(path) => {
+                let file: Box<dyn Write + Send> = Box::new(File::create(path)?);
+                Ok(Arc::new(Mutex::new(file)))
+            }
+            Sink::Writer(writer) => Ok(writer.clone()),
+        }
+    }
+}
+
+impl From<PathBuf> for CaptureSink {
+    fn from(path: PathBuf) -> Self {
+        Self::path(path)
+    }
+}
+
+impl From<Box<dyn Write + Send>> for CaptureSink {
+    fn from(writer: Box<dyn Write + Send>) -> Self {
+        Self(Sink::Writer(Arc::new(Mutex::new(writer))))
+    }
+}
+
+impl fmt::Debug for CaptureSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Sink::Path(path) => f.debug_tuple("CaptureSink").field(path).finish(),
+            Sink::Writer(_) => f.debug_tuple("CaptureSink").field(&"Writer").finish(),
+        }
+    }
+}
+
+/// The direction of a captured chunk of bytes
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Direction {
+    /// Bytes written by the client
+    Sent,
+    /// Bytes read from the server
+    Received,
+}
+
+impl Direction {
+    fn tag(self) -> &'static str {
+        match self {
+            Direction::Sent => ">>",
+            Direction::Received => "<<",
+        }
+    }
+}
+
+/// Records traffic into a [`CaptureSink`]
+pub(crate) struct Capture {
+    sink: SharedWriter,
+}
+
+impl Capture {
+    pub(crate) fn new(sink: &CaptureSink) -> io::Result<Self> {
+        Ok(Self { sink: sink.open()? })
+    }
+
+    /// Record a chunk of bytes
+    ///
+    /// Callers are responsible for redacting any credentials
+    pub(crate) fn record(&self, direction: Direction, bytes: &[u8]) -> io::Result<()> {
+        // A poisoned sink only means another writer panicked mid-record, keep capturing
+        let mut sink = self.sink.lock().unwrap_or_else(|e| e.into_inner());
+        writeln!(sink, "{} {}", direction.tag(), bytes.len())?;
+        sink.write_all(bytes)?;
+        sink.write_all(b"\n")?;
+        sink.flush()
+    }
+}
+
+impl fmt::Debug for Capture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Capture").finish()
+    }
+}
+
+/// A [`BufRead`] adapter that records every consumed byte into a [`Capture`]
+///
+/// Recording on `consume` (rather than on `read`) means the capture is independent of the
+/// underlying stream and contains exactly the bytes that were parsed.
+pub(crate) struct Tee<'a, R> {
+    inner: R,
+    capture: Option<&'a Capture>,
+}
+
+impl<'a, R: BufRead> Tee<'a, R> {
+    pub(crate) fn new(inner: R, capture: Option<&'a Capture>) -> Self {
+        Self { inner, capture }
+    }
+}
+
+impl<R: BufRead> Read for Tee<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        read_via_buf(self, buf)
+    }
+}
+
+impl<R: BufRead> BufRead for Tee<'_, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if let (Some(capture), true) = (self.capture, amt > 0) {
+            // The buffer was filled by the caller prior to consuming so this won't block
+            let res = self
+                .inner
+                .fill_buf()
+                .and_then(|buf| capture.record(Direction::Received, &buf[..amt]));
+            if let Err(e) = res {
+                warn!("Failed to capture received bytes -- {}", e);
+            }
+        }
+        self.inner.consume(amt)
+    }
+}
+
+/// Read a capture written to a [`CaptureSink`] into a list of chunks
+#[cfg_attr(not(any(test, feature = "test-util")), allow(dead_code))]
+pub(crate) fn read_capture(mut reader: impl BufRead) -> io::Result<Vec<(Direction, Vec<u8>)>> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    let mut chunks = vec![];
+
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            break;
+        }
+
+        let mut iter = header.trim_end().split(' ');
+        let direction = match iter.next() {
+            Some(">>") => Direction::Sent,
+            Some("<<") => Direction::Received,
+            _ => return Err(invalid("Invalid direction tag in capture")),
+        };
+        let len = iter
+            .next()
+            .and_then(|s| usize::from_str(s).ok())
+            .ok_or_else(|| invalid("Invalid chunk length in capture"))?;
+
+        let mut bytes = vec![0; len];
+        reader.read_exact(&mut bytes)?;
+
+        let mut newline = [0; 1];
+        reader.read_exact(&mut newline)?;
+        if newline != [b'\n'] {
+            return Err(invalid("Chunk is not terminated by a newline"));
+        }
+
+        chunks.push((direction, bytes));
+    }
+
+    Ok(chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw::connection::{ConnectionConfig, NntpConnection};
+    use crate::testing::MockStream;
+    use crate::types::command as cmd;
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    const SERVER: &[u8] =
+        b"200 ready\r\n381 password required\r\n101 Capability list:\r\nVERSION 2\r\n.\r\n";
+
+    #[test]
+    fn test_capture_round_trip() {
+        let buf = SharedBuf::default();
+        let config = ConnectionConfig::new()
+            .capture(Some(CaptureSink::writer(buf.clone())))
+            .to_owned();
+
+        let (mut conn, _) =
+            NntpConnection::from_stream(MockStream::permissive(SERVER), config).unwrap();
+        conn.command(&cmd::AuthInfo::Pass("hunter2".to_string()))
+            .unwrap();
+        let resp = conn.command(&cmd::Capabilities).unwrap();
+
+        let capture = buf.0.lock().unwrap().clone();
+        let chunks = read_capture(&capture[..]).unwrap();
+
+        let sent = chunks
+            .iter()
+            .filter(|(d, _)| *d == Direction::Sent)
+            .flat_map(|(_, b)| b.clone())
+            .collect::<Vec<_>>();
+        let sent = String::from_utf8(sent).unwrap();
+        assert!(!sent.contains("hunter2"));
+        assert!(sent.contains("AUTHINFO PASS <redacted>"));
+        assert!(sent.contains("CAPABILITIES\r\n"));
+
+        let (mut replay, _) = NntpConnection::from_stream(
+            MockStream::from_capture(&capture[..]).unwrap(),
+            ConnectionConfig::new(),
+        )
+        .unwrap();
+        replay
+            .command(&cmd::AuthInfo::Pass("hunter2".to_string()))
+            .unwrap();
+        let replayed = replay.command(&cmd::Capabilities).unwrap();
+
+        assert_eq!(resp.first_line(), replayed.first_line());
+        assert_eq!(
+            resp.data_blocks().unwrap().payload(),
+            replayed.data_blocks().unwrap().payload()
+        );
+    }
+
+    #[test]
+    fn test_invalid_capture() {
+        assert!(read_capture(&b"?? 3\nabc\n"[..]).is_err());
+        assert!(read_capture(&b"<< 3\nabcd\n"[..]).is_err());
+    }
+}
@@ -1,13 +1,26 @@
-use std::io::{BufRead, BufReader, Read};
+use std::fmt;
+use std::io::{BufRead, BufReader, ErrorKind, Read};
 
-use flate2::bufread::ZlibDecoder;
+use flate2::bufread::{DeflateDecoder, ZlibDecoder};
+use flate2::{Compress, Crc, Decompress, FlushCompress, FlushDecompress, Status};
 use std::io;
 
+use crate::raw::stream::read_via_buf;
+
 /// A type of compression enabled on the server
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Compression {
     /// Giganews style compression
+    ///
+    /// Only the data blocks of responses whose first line ends with `[COMPRESS=GZIP]`
+    /// are compressed.
     XFeature,
+    /// [RFC 8054](https://tools.ietf.org/html/rfc8054) `COMPRESS DEFLATE`
+    ///
+    /// The entire stream (in both directions) is compressed once the server accepts the
+    /// `COMPRESS DEFLATE` command. The [`NntpClient`](crate::NntpClient) will negotiate this
+    /// automatically if the server advertises it.
+    Deflate,
 }
 
 /// An codec that can unpack compressed data streams
@@ -21,12 +34,221 @@ impl Compression {
     pub(crate) fn use_decoder(&self, first_line: impl AsRef<[u8]>) -> bool {
         match self {
             Self::XFeature => first_line.as_ref().ends_with(b"[COMPRESS=GZIP]\r\n"),
+            // The stream itself is compressed, see `Deflate`
+            Self::Deflate => false,
+        }
+    }
+
+    /// Wrap the stream in a decoder that buffers up to `capacity` decompressed bytes
+    pub(crate) fn decoder<S: BufRead + Read>(&self, stream: S, capacity: usize) -> Decoder<S> {
+        match self {
+            Self::XFeature => {
+                Decoder::XFeature(BufReader::with_capacity(capacity, ZlibDecoder::new(stream)))
+            }
+            Self::Deflate => Decoder::Passthrough(stream),
+        }
+    }
+}
+
+impl<S: BufRead> Decoder<S> {
+    /// The number of compressed bytes consumed by the decoder
+    pub(crate) fn total_in(&self) -> u64 {
+        match self {
+            Decoder::XFeature(d) => d.get_ref().total_in(),
+            Decoder::Passthrough(_) => 0,
         }
     }
 
-    pub(crate) fn decoder<S: BufRead + Read>(&self, stream: S) -> Decoder<S> {
+    /// The number of bytes produced by decompression
+    pub(crate) fn total_out(&self) -> u64 {
         match self {
-            Self::XFeature => Decoder::XFeature(BufReader::new(ZlibDecoder::new(stream))),
+            Decoder::XFeature(d) => d.get_ref().total_out(),
+            Decoder::Passthrough(_) => 0,
+        }
+    }
+}
+
+/// A [`BufRead`] adapter that keeps a copy of every byte consumed from the inner reader
+///
+/// This allows a response to be replayed as plain text if decompression fails.
+pub(crate) struct Recorder<'a, R> {
+    inner: R,
+    recorded: Option<&'a mut Vec<u8>>,
+}
+
+impl<'a, R: BufRead> Recorder<'a, R> {
+    pub(crate) fn new(inner: R, recorded: Option<&'a mut Vec<u8>>) -> Self {
+        Self { inner, recorded }
+    }
+}
+
+impl<R: BufRead> Read for Recorder<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        read_via_buf(self, buf)
+    }
+}
+
+impl<R: BufRead> BufRead for Recorder<'_, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if let (Some(recorded), true) = (self.recorded.as_mut(), amt > 0) {
+            // The buffer was filled by the caller prior to consuming so this won't block
+            if let Ok(buf) = self.inner.fill_buf() {
+                recorded.extend_from_slice(&buf[..amt]);
+            }
+        }
+        self.inner.consume(amt)
+    }
+}
+
+/// The state of a stream compressed with `COMPRESS DEFLATE`
+///
+/// Unlike XFEATURE compression, the compression contexts span the entire session so they
+/// are kept by the connection and applied to every read/write once compression is activated.
+pub(crate) struct Deflate {
+    compress: Compress,
+    decompress: Decompress,
+    /// Decompressed bytes that have not been consumed yet
+    buf: Vec<u8>,
+    pos: usize,
+    end: usize,
+}
+
+impl Deflate {
+    /// Create the compression contexts, buffering up to `capacity` decompressed bytes
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            // n.b. RFC 8054 uses raw DEFLATE without a zlib header
+            compress: Compress::new(flate2::Compression::default(), false),
+            decompress: Decompress::new(false),
+            buf: vec![0; capacity],
+            pos: 0,
+            end: 0,
+        }
+    }
+
+    /// The number of bytes read from and produced by the decompression context
+    pub(crate) fn decompressed_totals(&self) -> (u64, u64) {
+        (self.decompress.total_in(), self.decompress.total_out())
+    }
+
+    /// Compress the bytes into `out`, flushing so that the peer can decompress them immediately
+    pub(crate) fn compress(&mut self, input: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+        let mut consumed = 0;
+        loop {
+            out.reserve(input.len() - consumed + 64);
+            let before = self.compress.total_in();
+            self.compress
+                .compress_vec(&input[consumed..], out, FlushCompress::Sync)
+                .map_err(compression_error)?;
+            consumed += (self.compress.total_in() - before) as usize;
+
+            // The flush is complete once there is spare room in the output
+            if consumed == input.len() && out.len() < out.capacity() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// A failure of a `COMPRESS DEFLATE` compression context
+///
+/// This is carried within an [`io::Error`] so that it can pass through the I/O traits, and is
+/// converted into [`Error::Compression`](super::error::Error::Compression) by the connection.
+#[derive(Debug)]
+pub(crate) struct CompressionError(String);
+
+impl fmt::Display for CompressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for CompressionError {}
+
+/// Wrap a compression failure in an [`io::Error`]
+pub(crate) fn compression_error(e: impl fmt::Display) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, CompressionError(e.to_string()))
+}
+
+impl fmt::Debug for Deflate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Deflate")
+            .field("total_in", &self.decompress.total_in())
+            .field("total_out", &self.decompress.total_out())
+            .finish()
+    }
+}
+
+/// A [`BufRead`] adapter that inflates a `COMPRESS DEFLATE` stream
+///
+/// If no [`Deflate`] state is provided the bytes are passed through untouched.
+pub(crate) struct Inflater<'a, R> {
+    inner: R,
+    deflate: Option<&'a mut Deflate>,
+}
+
+impl<'a, R: BufRead> Inflater<'a, R> {
+    pub(crate) fn new(inner: R, deflate: Option<&'a mut Deflate>) -> Self {
+        Self { inner, deflate }
+    }
+}
+
+#[cfg(test)]
+impl<R> Inflater<'_, R> {
+    pub(crate) fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+}
+
+impl<R: BufRead> Read for Inflater<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        read_via_buf(self, buf)
+    }
+}
+
+impl<R: BufRead> BufRead for Inflater<'_, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let state = match self.deflate.as_mut() {
+            Some(state) => state,
+            None => return self.inner.fill_buf(),
+        };
+
+        while state.pos == state.end {
+            let input = self.inner.fill_buf()?;
+            let eof = input.is_empty();
+
+            let (before_in, before_out) =
+                (state.decompress.total_in(), state.decompress.total_out());
+            let status = state
+                .decompress
+                .decompress(input, &mut state.buf, FlushDecompress::None)
+                .map_err(compression_error)?;
+            let consumed = (state.decompress.total_in() - before_in) as usize;
+            let produced = (state.decompress.total_out() - before_out) as usize;
+
+            self.inner.consume(consumed);
+            state.pos = 0;
+            state.end = produced;
+
+            if produced == 0 && (eof || status == Status::StreamEnd) {
+                break;
+            }
+            if produced == 0 && consumed == 0 {
+                return Err(compression_error("Deflate stream made no progress"));
+            }
+        }
+
+        Ok(&state.buf[state.pos..state.end])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match self.deflate.as_mut() {
+            Some(state) => state.pos = (state.pos + amt).min(state.end),
+            None => self.inner.consume(amt),
         }
     }
 }
@@ -56,6 +278,115 @@ impl<S: BufRead> BufRead for Decoder<S> {
     }
 }
 
+/// Returns true if the data blocks contain a yEnc envelope (i.e. `XZVER` and `XZHDR` responses)
+pub(crate) fn is_yenc_envelope(data_blocks: &[u8]) -> bool {
+    data_blocks.starts_with(b"=ybegin ")
+}
+
+/// Decode the yEnc envelope used by `XZVER`/`XZHDR` and inflate its contents
+///
+/// The data blocks should contain a single yEnc encoded part, (`=ybegin` ... `=yend`) followed
+/// by the terminating `.` line. The size and CRC in the trailer are validated if present.
+pub(crate) fn decode_yenc_envelope(data_blocks: &[u8]) -> io::Result<Vec<u8>> {
+    let invalid = |msg: &str| io::Error::new(ErrorKind::InvalidData, msg.to_string());
+
+    let mut lines = data_blocks
+        .split(|&b| b == b'\n')
+        .map(|line| line.strip_suffix_cr());
+    lines.next(); // =ybegin
+
+    let mut encoded = Vec::with_capacity(data_blocks.len());
+    let mut trailer = None;
+    for line in lines {
+        if line.starts_with(b"=ypart ") {
+            continue;
+        }
+        if line.starts_with(b"=yend") {
+            trailer = Some(line);
+            break;
+        }
+        // Undo NNTP dot-stuffing before decoding
+        let line = if line.starts_with(b"..") {
+            &line[1..]
+        } else {
+            line
+        };
+        yenc_decode_line(line, &mut encoded);
+    }
+
+    let trailer = trailer.ok_or_else(|| invalid("yEnc envelope is missing =yend"))?;
+    if let Some(size) = yenc_param(trailer, "size") {
+        if size.parse::<usize>().ok() != Some(encoded.len()) {
+            return Err(invalid("yEnc size does not match decoded data"));
+        }
+    }
+    if let Some(crc32) = yenc_param(trailer, "crc32") {
+        let mut crc = Crc::new();
+        crc.update(&encoded);
+        if u32::from_str_radix(crc32, 16).ok() != Some(crc.sum()) {
+            return Err(invalid("yEnc CRC does not match decoded data"));
+        }
+    }
+
+    // Providers have been observed sending both raw DEFLATE and zlib wrapped streams
+    let mut inflated = Vec::new();
+    if is_zlib_header(&encoded) {
+        ZlibDecoder::new(&encoded[..]).read_to_end(&mut inflated)?;
+    } else {
+        DeflateDecoder::new(&encoded[..]).read_to_end(&mut inflated)?;
+    }
+
+    Ok(inflated)
+}
+
+fn yenc_decode_line(line: &[u8], out: &mut Vec<u8>) {
+    let mut iter = line.iter();
+    while let Some(&b) = iter.next() {
+        let b = if b == b'=' {
+            match iter.next() {
+                Some(&escaped) => escaped.wrapping_sub(64),
+                None => break,
+            }
+        } else {
+            b
+        };
+        out.push(b.wrapping_sub(42));
+    }
+}
+
+/// Get the value of a `key=value` parameter from a yEnc header/trailer line
+fn yenc_param<'a>(line: &'a [u8], key: &str) -> Option<&'a str> {
+    std::str::from_utf8(line)
+        .ok()?
+        .split_whitespace()
+        .filter_map(|param| {
+            let mut iter = param.splitn(2, '=');
+            Some((iter.next()?, iter.next()?))
+        })
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| v)
+}
+
+fn is_zlib_header(data: &[u8]) -> bool {
+    match data {
+        [cmf, flg, ..] => cmf & 0x0F == 8 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0,
+        _ => false,
+    }
+}
+
+trait StripCr {
+    fn strip_suffix_cr(&self) -> &Self;
+}
+
+impl StripCr for [u8] {
+    fn strip_suffix_cr(&self) -> &[u8] {
+        match self.split_last() {
+            Some((b'\r', rest)) => rest,
+            _ => self,
+        }
+    }
+}
+
 /*
     In theory if we wanted to implement extensible compression we could replace Decoder and
     Compression objects w/ traits. That said it didn't seem necessary given the slow moving
@@ -66,6 +397,18 @@ impl<S: BufRead> BufRead for Decoder<S> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_inflate_garbage() {
+        let mut deflate = Deflate::new(8 * 1024);
+        let mut inflater = Inflater::new(&b"\xff\xff\xff\xff garbage"[..], Some(&mut deflate));
+        let err = inflater.read(&mut [0; 16]).unwrap_err();
+
+        match crate::raw::error::Error::from(err) {
+            crate::raw::error::Error::Compression(_) => {}
+            e => panic!("Unexpected error {:?}", e),
+        }
+    }
+
     #[test]
     fn test_use_decoder() {
         assert!(
@@ -99,9 +442,73 @@ mod tests {
 
         assert!(Compression::XFeature.use_decoder(first_line));
 
-        let mut decoder = Compression::XFeature.decoder(&data_blocks[..]);
+        let mut decoder = Compression::XFeature.decoder(data_blocks, 8 * 1024);
         let mut buf = String::new();
         decoder.read_to_string(&mut buf).unwrap();
         assert_eq!(buf, String::from_utf8(plain_resp.to_vec()).unwrap())
     }
+
+    #[test]
+    fn test_xzver() {
+        let xzver_resp = include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/xover_resp_xzver"
+        ));
+        let plain_resp = include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/xover_resp_plain_text"
+        ));
+
+        let line_boundary = xzver_resp.iter().position(|&b| b == b'\n').unwrap();
+        let data_blocks = &xzver_resp[line_boundary + 1..];
+        assert!(is_yenc_envelope(data_blocks));
+
+        let decoded = decode_yenc_envelope(data_blocks).unwrap();
+        assert_eq!(&decoded[..], &plain_resp[..plain_resp.len() - 3]);
+    }
+
+    #[test]
+    fn test_yenc_bad_crc() {
+        let data_blocks =
+            b"=ybegin line=128 size=1 name=xzver\r\nk\r\n=yend size=1 crc32=00000000\r\n.\r\n";
+        assert!(decode_yenc_envelope(data_blocks).is_err());
+    }
+
+    #[test]
+    fn test_deflate_round_trip() {
+        let mut client = Deflate::new(8 * 1024);
+        let mut server = Deflate::new(8 * 1024);
+
+        let mut wire = Vec::new();
+        client.compress(b"CAPABILITIES\r\n", &mut wire).unwrap();
+        client.compress(b"GROUP misc.test\r\n", &mut wire).unwrap();
+
+        let mut inflater = Inflater::new(&wire[..], Some(&mut server));
+        let mut line = String::new();
+        inflater.read_line(&mut line).unwrap();
+        assert_eq!(line, "CAPABILITIES\r\n");
+        line.clear();
+        inflater.read_line(&mut line).unwrap();
+        assert_eq!(line, "GROUP misc.test\r\n");
+    }
+
+    #[test]
+    fn test_inflate_fixture() {
+        let compressed = include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/compress_deflate_resp"
+        ));
+        let mut state = Deflate::new(8 * 1024);
+
+        // Feed the input one byte at a time to exercise partial reads
+        let mut inner = BufReader::with_capacity(1, &compressed[..]);
+        let mut inflater = Inflater::new(&mut inner, Some(&mut state));
+        let mut buf = String::new();
+        inflater.read_to_string(&mut buf).unwrap();
+        assert_eq!(
+            buf,
+            "101 Capability list:\r\nVERSION 2\r\nREADER\r\nCOMPRESS DEFLATE\r\n.\r\n\
+            211 3 1 3 misc.test\r\n"
+        );
+    }
 }
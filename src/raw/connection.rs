@@ -1,19 +1,32 @@
 use std::fmt;
 use std::io;
-use std::io::{ErrorKind, Write};
-use std::net::{TcpStream, ToSocketAddrs};
+use std::io::{BufRead, ErrorKind, Read, Write};
+use std::mem;
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::panic::{self, AssertUnwindSafe};
 use std::str::FromStr;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use log::*;
 use native_tls::TlsConnector;
+use socket2::{Domain, Protocol, Socket, Type};
 
-use crate::raw::compression::{Compression, Decoder};
-use crate::raw::error::Result;
-use crate::raw::parse::{is_end_of_datablock, parse_data_block_line, parse_first_line};
+use crate::raw::cancel::CancellationToken;
+use crate::raw::capture::{Capture, CaptureSink, Direction, Tee};
+use crate::raw::compression::{
+    decode_yenc_envelope, is_yenc_envelope, Compression, Decoder, Deflate, Inflater, Recorder,
+};
+use crate::raw::error::{Error, Result};
+use crate::raw::observer::{redact, ConnectionObserver};
+use crate::raw::parse::{
+    is_terminator_line, parse_data_block_line, parse_data_block_line_lenient, parse_first_line,
+    parse_first_line_lenient,
+};
+use crate::raw::proxy::{ProxyConfig, Target};
 use crate::raw::response::{DataBlocks, RawResponse};
+use crate::raw::stats::{ConnectionStats, Counted, ResponseStats};
 use crate::raw::stream::NntpStream;
-use crate::types::command::NntpCommand;
+use crate::types::command::{self as cmd, MultiStageCommand, NntpCommand};
 use crate::types::prelude::*;
 
 /// TLS configuration for an [`NntpConnection`]
@@ -35,11 +48,19 @@ impl TlsConfig {
     /// Create a `TlsConfig` with the system default TLS settings
     ///
     /// The `domain` will be used to validate server certs during any TLS handshakes.
+    /// A `:port` suffix and the brackets around an IPv6 literal are removed, so
+    /// `news.example.com:563` and `[2001:db8::1]:563` are accepted.
+    ///
+    /// Connections to an IP literal should only use the IP as the domain if the server's
+    /// certificate has a matching IP Subject Alternative Name, otherwise validation will fail
+    /// and the certificate's host name should be used instead. A warning is logged during the
+    /// handshake if the domain is an IP literal, see
+    /// [`ConnectionConfig::tls_domain`] to override it.
     pub fn default_connector(domain: impl AsRef<str>) -> Result<Self> {
         let connector = TlsConnector::new()?;
         Ok(Self {
             connector,
-            domain: domain.as_ref().to_string(),
+            domain: sni_domain(domain.as_ref()).to_string(),
         })
     }
 
@@ -47,6 +68,35 @@ impl TlsConfig {
     pub fn connector(&self) -> &TlsConnector {
         &self.connector
     }
+
+    /// The domain used to validate server certs
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+}
+
+/// Returns true if the domain is an IPv4 or IPv6 literal rather than a host name
+///
+/// Most providers' certificates only cover host names so validating against an IP will fail.
+fn is_ip_literal(domain: &str) -> bool {
+    domain.parse::<IpAddr>().is_ok()
+}
+
+/// Strip any `:port` suffix and IPv6 brackets from a host
+///
+/// Unbracketed IPv6 literals (e.g. `::1`) are returned as is since any port is ambiguous.
+fn sni_domain(host: &str) -> &str {
+    if host.starts_with('[') {
+        match host.find(']') {
+            Some(end) => &host[1..end],
+            None => host,
+        }
+    } else {
+        match (host.find(':'), host.rfind(':')) {
+            (Some(first), Some(last)) if first == last => &host[..first],
+            _ => host,
+        }
+    }
 }
 
 impl fmt::Debug for TlsConfig {
@@ -71,7 +121,7 @@ impl fmt::Debug for TlsConfig {
 /// `NntpConnection` DOES NOT...
 ///
 /// * Manage any of the stateful details of the connection such as server capabilities,
-/// selected group, or selected articles.
+///   selected group, or selected articles.
 /// * Perform detailed parsing of responses.
 ///
 /// For a more ergonomic client please see the [`NntpClient`](crate::client::NntpClient).
@@ -88,6 +138,13 @@ impl fmt::Debug for TlsConfig {
 /// * [`send`](Self::send) & [`send_bytes`](Self::send_bytes) for writing commands
 /// * [`read_response`](Self::read_response) & [`read_response_auto`](Self::read_response_auto)
 ///   for reading responses
+///
+/// ## Streams
+///
+/// By default the connection runs over an [`NntpStream`] (TCP w/ optional TLS), but any
+/// `Read + Write` type can be used via [`from_stream`](Self::from_stream).
+/// This is handy for tunneling through proxies or for testing against an in-memory stream.
+///
 /// ## Buffer Management
 ///
 /// The connection maintains several internal buffers for reading responses.
@@ -126,53 +183,104 @@ impl fmt::Debug for TlsConfig {
 ///     Ok(())
 /// }
 #[derive(Debug)]
-pub struct NntpConnection {
-    stream: BufNntpStream,
+pub struct NntpConnection<S = NntpStream> {
+    stream: io::BufReader<S>,
+    /// Commands that have been queued but not yet written to the stream
+    write_buf: Vec<u8>,
     first_line_buf: Vec<u8>,
     data_blocks_buf: Vec<u8>,
+    line_boundaries_buf: Vec<(usize, usize)>,
     config: ConnectionConfig,
+    observer: Option<Box<dyn ConnectionObserver>>,
+    capture: Option<Capture>,
+    deflate: Option<Deflate>,
+    last_send: Option<Instant>,
+    quit_sent: bool,
+    poisoned: bool,
+    /// Traffic counters, except for `COMPRESS DEFLATE` which is tracked by `deflate`
+    stats: ConnectionStats,
+    /// The `COMPRESS DEFLATE` totals when the stats were last reset
+    deflate_baseline: (u64, u64),
+    last_response: ResponseStats,
+    /// Reads fail with [`Error::DeadlineExceeded`] once this passes, see `set_deadline`
+    deadline: Option<Instant>,
+    /// The socket beneath the stream, used to shorten the read timeout before a deadline
+    tcp_ref: TcpRef<S>,
 }
 
+/// Returns the socket beneath a stream, if it has one
+type TcpRef<S> = fn(&S) -> Option<&TcpStream>;
+
 impl NntpConnection {
     /// Connect to an NNTP server
+    ///
+    /// If a proxy is configured the address is resolved locally, please see
+    /// [`connect_host`](Self::connect_host) if DNS resolution should happen on the proxy.
     pub fn connect(
         addr: impl ToSocketAddrs,
         config: ConnectionConfig,
     ) -> Result<(Self, RawResponse)> {
-        let ConnectionConfig {
-            compression: _,
-            tls_config,
-            read_timeout,
-            write_timeout: _,
-            first_line_buf_size,
-            data_blocks_buf_size,
-        } = config.clone();
-
-        trace!("Opening TcpStream...");
-        let tcp_stream = TcpStream::connect(&addr)?;
-
-        tcp_stream.set_read_timeout(read_timeout)?;
-
-        let nntp_stream = if let Some(TlsConfig { connector, domain }) = tls_config.as_ref() {
-            trace!("Wrapping TcpStream w/ TlsConnector");
+        let tcp_stream = match config.proxy.as_ref() {
+            Some(proxy) => {
+                let addr = addr.to_socket_addrs()?.next().ok_or_else(|| {
+                    io::Error::new(ErrorKind::InvalidInput, "Could not resolve address")
+                })?;
+                open_proxied(proxy, &Target::Addr(addr), &config)?
+            }
+            None => {
+                let tcp_stream = open_tcp_stream(addr, &config)?;
+                configure_tcp_stream(&tcp_stream, &config)?;
+                tcp_stream
+            }
+        };
+
+        Self::from_tcp_stream(tcp_stream, config)
+    }
+
+    /// Connect to an NNTP server by hostname
+    ///
+    /// This behaves like [`connect`](Self::connect) except that the hostname will be passed
+    /// to the proxy if it is configured with [`remote_dns`](ProxyConfig::remote_dns).
+    pub fn connect_host(
+        host: impl AsRef<str>,
+        port: u16,
+        config: ConnectionConfig,
+    ) -> Result<(Self, RawResponse)> {
+        let host = host.as_ref();
+        match config.proxy.as_ref() {
+            Some(proxy) if proxy.remote_dns => {
+                let tcp_stream = open_proxied(proxy, &Target::Host(host, port), &config)?;
+                Self::from_tcp_stream(tcp_stream, config)
+            }
+            _ => Self::connect((host, port), config),
+        }
+    }
+
+    /// Perform the (optional) TLS handshake and read the greeting
+    fn from_tcp_stream(
+        tcp_stream: TcpStream,
+        config: ConnectionConfig,
+    ) -> Result<(Self, RawResponse)> {
+        let nntp_stream = if let Some(TlsConfig { connector, domain }) = config.tls_config.as_ref()
+        {
+            let domain = config.tls_domain.as_deref().unwrap_or(domain);
+            if is_ip_literal(domain) {
+                warn!(
+                    "Validating TLS certificate against IP literal {}, \
+                    set ConnectionConfig::tls_domain if the certificate is for a host name",
+                    domain
+                );
+            }
+            trace!("Wrapping TcpStream w/ TlsConnector for {}", domain);
             connector.connect(domain, tcp_stream)?.into()
         } else {
             trace!("No TLS config providing, continuing with plain text");
             tcp_stream.into()
         };
 
-        let first_line_buf = Vec::with_capacity(first_line_buf_size);
-        let data_blocks_buf = Vec::with_capacity(data_blocks_buf_size);
-
-        let mut conn = Self {
-            stream: io::BufReader::new(nntp_stream),
-            first_line_buf,
-            data_blocks_buf,
-            config,
-        };
-
-        let initial_resp = conn.read_response_auto()?;
-
+        let mut conn = Self::from_stream_without_greeting(nntp_stream, config)?;
+        conn.tcp_ref = NntpStream::get_tcp_ref;
+        let initial_resp = conn.read_greeting()?;
         Ok((conn, initial_resp))
     }
 
@@ -181,6 +289,340 @@ impl NntpConnection {
         Self::connect(addr, Default::default())
     }
 
+    /// Best-effort `QUIT` used when a client is dropped without being closed
+    ///
+    /// The socket's timeouts are set so that sending `QUIT` and waiting for the `205` take
+    /// roughly `grace` at most, then the socket is shut down. Errors are logged and ignored.
+    /// Custom transports do not support timeouts, so `QUIT` is sent without waiting for a reply.
+    pub(crate) fn quit_with_grace(&mut self, grace: Duration) {
+        if self.quit_sent || self.poisoned {
+            return;
+        }
+
+        if self
+            .stream
+            .get_ref()
+            .set_write_timeout(Some(grace))
+            .is_err()
+        {
+            self.quit_without_reply();
+            return;
+        }
+
+        let start = Instant::now();
+        let res = self.send(&cmd::Quit).and_then(|_| {
+            // Sockets reject a zero timeout
+            let remaining = grace
+                .checked_sub(start.elapsed())
+                .filter(|d| *d > Duration::from_millis(0))
+                .ok_or_else(|| io::Error::new(ErrorKind::TimedOut, "Grace period elapsed"))?;
+            self.stream.get_ref().set_read_timeout(Some(remaining))?;
+            self.read_response(Some(false))
+        });
+        if let Err(e) = res {
+            debug!("No response to QUIT while dropping the connection -- {}", e);
+        }
+
+        if let Err(e) = self.stream.get_mut().shutdown() {
+            debug!("Failed to shut down the connection -- {}", e);
+        }
+    }
+
+    /// Send a command with a read timeout that only applies to this command
+    ///
+    /// This is useful for commands with large responses (e.g. `LIST ACTIVE`) while keeping a
+    /// short [`read_timeout`](ConnectionConfig::read_timeout) for interactive commands.
+    /// The previous timeout is restored afterwards, even if the command fails.
+    ///
+    /// Returns an error without sending the command if the stream is a
+    /// [custom](NntpStream::custom) transport.
+    pub fn command_with_timeout<C: NntpCommand>(
+        &mut self,
+        command: &C,
+        timeout: Duration,
+    ) -> Result<RawResponse> {
+        let guard = ReadTimeoutGuard::new(self, Some(timeout))?;
+        guard.conn.command(command)
+    }
+}
+
+/// Stops a read part way through a response
+#[derive(Clone, Copy)]
+struct Interrupt<'a> {
+    cancel: Option<&'a CancellationToken>,
+    deadline: Option<Instant>,
+}
+
+impl Interrupt<'_> {
+    /// Returns an error if the token was cancelled or the deadline has passed
+    fn check(&self) -> Result<()> {
+        if matches!(self.cancel, Some(token) if token.is_cancelled()) {
+            return Err(Error::Cancelled);
+        }
+        if matches!(self.deadline, Some(deadline) if Instant::now() >= deadline) {
+            return Err(Error::DeadlineExceeded);
+        }
+        Ok(())
+    }
+}
+
+/// Returns true if a read failed because the socket's read timeout elapsed
+fn is_timeout(e: &io::Error) -> bool {
+    matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+/// Reports the number of data block bytes read, throttled to every `interval` bytes
+struct Progress<'a> {
+    callback: &'a mut dyn FnMut(u64),
+    interval: u64,
+    next: u64,
+    panicked: bool,
+}
+
+impl<'a> Progress<'a> {
+    fn new(callback: &'a mut dyn FnMut(u64), interval: u64) -> Self {
+        let interval = interval.max(1);
+        Self {
+            callback,
+            interval,
+            next: interval,
+            panicked: false,
+        }
+    }
+
+    fn update(&mut self, bytes: u64) {
+        if bytes >= self.next {
+            self.next = bytes.saturating_add(self.interval);
+            self.report(bytes);
+        }
+    }
+
+    fn finish(&mut self, bytes: u64) {
+        self.report(bytes);
+    }
+
+    /// Call the callback, catching panics so that the response is still read in full
+    fn report(&mut self, bytes: u64) {
+        if self.panicked {
+            return;
+        }
+        let callback = &mut self.callback;
+        if panic::catch_unwind(AssertUnwindSafe(|| callback(bytes))).is_err() {
+            warn!("Progress callback panicked, it will not be called again for this response");
+            self.panicked = true;
+        }
+    }
+}
+
+/// Restores the read timeout of a connection when dropped
+struct ReadTimeoutGuard<'a> {
+    conn: &'a mut NntpConnection,
+    previous: Option<Duration>,
+}
+
+impl<'a> ReadTimeoutGuard<'a> {
+    fn new(conn: &'a mut NntpConnection, timeout: Option<Duration>) -> Result<Self> {
+        let stream = conn.stream.get_ref();
+        let previous = stream.read_timeout()?;
+        stream.set_read_timeout(timeout)?;
+        Ok(Self { conn, previous })
+    }
+}
+
+impl Drop for ReadTimeoutGuard<'_> {
+    fn drop(&mut self) {
+        if let Err(e) = self.conn.stream.get_ref().set_read_timeout(self.previous) {
+            warn!(
+                "Failed to restore read timeout {:?} -- {}",
+                self.previous, e
+            );
+        }
+    }
+}
+
+/// Open a TCP connection, honoring the local address and connect timeout from the config
+///
+/// Like [`TcpStream::connect`], each resolved address is tried until one succeeds.
+fn open_tcp_stream(addr: impl ToSocketAddrs, config: &ConnectionConfig) -> Result<TcpStream> {
+    trace!("Opening TcpStream...");
+    if config.local_addr.is_none() && config.connect_timeout.is_none() {
+        return Ok(TcpStream::connect(addr)?);
+    }
+
+    let mut last_err = None;
+    for addr in addr.to_socket_addrs()? {
+        // A socket can only connect to addresses of the same family it is bound to
+        if let Some(local_addr) = config.local_addr {
+            if local_addr.is_ipv4() != addr.is_ipv4() {
+                trace!(
+                    "Skipping {}, address family does not match {}",
+                    addr,
+                    local_addr
+                );
+                continue;
+            }
+        }
+
+        match connect_socket(addr, config) {
+            Ok(tcp_stream) => return Ok(tcp_stream),
+            Err(e) => last_err = Some(e),
+        }
+    }
+
+    Err(last_err.unwrap_or_else(|| {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            "Could not resolve to any address usable from the local address",
+        )
+        .into()
+    }))
+}
+
+fn connect_socket(addr: SocketAddr, config: &ConnectionConfig) -> Result<TcpStream> {
+    let domain = if addr.is_ipv4() {
+        Domain::ipv4()
+    } else {
+        Domain::ipv6()
+    };
+    let socket = Socket::new(domain, Type::stream(), Some(Protocol::tcp()))?;
+
+    if let Some(local_addr) = config.local_addr {
+        trace!("Binding to local address {}", local_addr);
+        socket
+            .bind(&local_addr.into())
+            .map_err(|source| Error::Bind {
+                addr: local_addr,
+                source,
+            })?;
+    }
+
+    match config.connect_timeout {
+        Some(timeout) => socket.connect_timeout(&addr.into(), timeout)?,
+        None => socket.connect(&addr.into())?,
+    }
+
+    Ok(socket.into_tcp_stream())
+}
+
+/// Apply socket level settings from the config
+fn configure_tcp_stream(tcp_stream: &TcpStream, config: &ConnectionConfig) -> Result<()> {
+    config.tcp_options.validate()?;
+    let TcpOptions {
+        nodelay,
+        keepalive,
+        recv_buffer,
+    } = config.tcp_options;
+
+    tcp_stream.set_read_timeout(config.effective_read_timeout())?;
+    tcp_stream.set_write_timeout(config.write_timeout)?;
+
+    tcp_stream.set_nodelay(nodelay)?;
+
+    if keepalive.is_some() || recv_buffer.is_some() {
+        // n.b. the cloned handle refers to the same underlying socket
+        let socket = Socket::from(tcp_stream.try_clone()?);
+        if let Some(keepalive) = keepalive {
+            trace!("Enabling TCP keepalive every {:?}", keepalive);
+            socket.set_keepalive(Some(keepalive))?;
+        }
+        if let Some(size) = recv_buffer {
+            trace!("Setting socket receive buffer to {} bytes", size);
+            socket.set_recv_buffer_size(size)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Open a connection to the target through a proxy
+fn open_proxied(
+    proxy: &ProxyConfig,
+    target: &Target<'_>,
+    config: &ConnectionConfig,
+) -> Result<TcpStream> {
+    trace!("Connecting to proxy {:?}", proxy.addr());
+    let mut tcp_stream = open_tcp_stream(proxy.addr(), config)?;
+    configure_tcp_stream(&tcp_stream, config)?;
+    proxy.negotiate(&mut tcp_stream, target)?;
+    Ok(tcp_stream)
+}
+
+impl<S: Read + Write> NntpConnection<S> {
+    /// Create a connection from an existing stream
+    ///
+    /// The initial greeting will be read from the stream.
+    ///
+    /// Socket level settings in the config (TLS and timeouts) are **not** applied, the caller
+    /// is responsible for configuring the stream.
+    /// This is useful for testing with a `MockStream` (see the `testing` module provided
+    /// by the `test-util` feature) or for running NNTP over a custom transport.
+    ///
+    /// A greeting of `400` (service temporarily unavailable) or `502` (service permanently
+    /// unavailable) is returned as an [`Error::ServerClosed`].
+    pub fn from_stream(stream: S, config: ConnectionConfig) -> Result<(Self, RawResponse)> {
+        let mut conn = Self::from_stream_without_greeting(stream, config)?;
+        let initial_resp = conn.read_greeting()?;
+        Ok((conn, initial_resp))
+    }
+
+    /// Read the greeting, failing if the server refused the connection
+    fn read_greeting(&mut self) -> Result<RawResponse> {
+        let initial_resp = self.read_response_auto()?;
+
+        if initial_resp.code() == ResponseCode::Known(Kind::PermanentlyUnavailable) {
+            debug!(
+                "Server refused the connection -- {}",
+                initial_resp.first_line_to_utf8_lossy().trim_end()
+            );
+            return Err(Error::ServerClosed {
+                code: initial_resp.code(),
+            });
+        }
+
+        Ok(initial_resp)
+    }
+
+    /// Create a connection from an existing stream without reading the initial greeting
+    ///
+    /// This is useful for streams where the greeting has already been consumed (or was never
+    /// sent), e.g. a session handed off from another component.
+    /// Otherwise behaves like [`from_stream`](Self::from_stream).
+    pub fn from_stream_without_greeting(stream: S, config: ConnectionConfig) -> Result<Self> {
+        let capture = match config.capture.as_ref() {
+            Some(sink) => {
+                trace!("Capturing connection traffic to {:?}", sink);
+                Some(Capture::new(sink)?)
+            }
+            None => None,
+        };
+
+        let first_line_buf = Vec::with_capacity(config.first_line_buf_size);
+        let data_blocks_buf = Vec::with_capacity(config.data_blocks_buf_size);
+        let line_boundaries_buf = Vec::with_capacity(config.line_boundaries_buf_size);
+
+        let conn = Self {
+            stream: io::BufReader::with_capacity(config.read_buffer_capacity, stream),
+            write_buf: Vec::new(),
+            first_line_buf,
+            data_blocks_buf,
+            line_boundaries_buf,
+            config,
+            observer: None,
+            capture,
+            deflate: None,
+            last_send: None,
+            quit_sent: false,
+            poisoned: false,
+            stats: ConnectionStats::default(),
+            deflate_baseline: (0, 0),
+            last_response: ResponseStats::default(),
+            deadline: None,
+            tcp_ref: |_| None,
+        };
+
+        Ok(conn)
+    }
+
     /// Send a command to the server and read the response
     ///
     /// This function will:
@@ -193,6 +635,64 @@ impl NntpConnection {
         Ok(resp)
     }
 
+    /// Send a command and return the response only if it has the expected code
+    ///
+    /// This is shorthand for [`command`](Self::command) followed by
+    /// [`RawResponse::fail_unless`], any other code is returned as an
+    /// [`Error::Failure`](crate::error::Error::Failure).
+    pub fn command_expect<C: NntpCommand>(
+        &mut self,
+        command: &C,
+        expected: impl Into<ResponseCode>,
+    ) -> crate::error::Result<RawResponse> {
+        let resp = self.command(command)?.fail_unless(expected)?;
+        Ok(resp)
+    }
+
+    /// Send a command and return the response only if it has one of the expected codes
+    ///
+    /// See [`command_expect`](Self::command_expect)
+    pub fn command_expect_any<C: NntpCommand>(
+        &mut self,
+        command: &C,
+        expected: &[impl Into<ResponseCode> + Copy],
+    ) -> crate::error::Result<RawResponse> {
+        let resp = self.command(command)?.fail_unless_any(expected)?;
+        Ok(resp)
+    }
+
+    /// Run a [`MultiStageCommand`] (e.g. `POST`) and return the final response
+    ///
+    /// Each stage is sent and its response read before moving on to the next. The exchange
+    /// ends early if a response code is not accepted by the stage (e.g. `440 Posting not
+    /// permitted`), in which case that response is returned. Otherwise the response to the
+    /// final stage is returned.
+    ///
+    /// The caller is responsible for checking the code of the returned response.
+    pub fn command_stages<C: MultiStageCommand>(&mut self, command: &C) -> Result<RawResponse> {
+        let stages = command.stages();
+        let (last, rest) = stages.split_last().ok_or_else(|| {
+            Error::Io(io::Error::new(
+                ErrorKind::InvalidInput,
+                "Multi-stage commands must have at least one stage",
+            ))
+        })?;
+        if stages.iter().any(|stage| stage.is_injection()) {
+            return Err(line_break_error());
+        }
+
+        for stage in rest {
+            self.send_bytes(stage.payload())?;
+            let resp = self.read_response_auto()?;
+            if !stage.continue_on().contains(&resp.code()) {
+                return Ok(resp);
+            }
+        }
+
+        self.send_bytes(last.payload())?;
+        self.read_response_auto()
+    }
+
     /// Send a command and specify whether the response is multiline
     pub fn command_multiline<C: NntpCommand>(
         &mut self,
@@ -207,11 +707,27 @@ impl NntpConnection {
     /// Send a command to the server, returning the number of bytes written
     ///
     /// The caller is responsible for reading the response
+    ///
+    /// Commands containing a CR or LF (e.g. a message-id taken from untrusted input) are
+    /// rejected with an [`Error::InvalidCommand`] before anything is sent, as they
+    /// would otherwise inject additional commands. See [`NntpCommand::contains_line_break`].
     pub fn send<C: NntpCommand>(&mut self, command: &C) -> Result<usize> {
-        let bytes = self.send_bytes(command.encode())?;
+        let bytes = self.queue(command)?;
+        self.flush()?;
         Ok(bytes)
     }
 
+    /// Queue a command without writing it to the server, returning the number of bytes queued
+    ///
+    /// See [`queue_bytes`](Self::queue_bytes) for pipelining, commands are validated as in
+    /// [`send`](Self::send).
+    pub fn queue<C: NntpCommand>(&mut self, command: &C) -> Result<usize> {
+        if command.contains_line_break() {
+            return Err(line_break_error());
+        }
+        self.queue_bytes(command.encode())
+    }
+
     /// Send a command to the server, returning the number of bytes written
     ///
     /// This function can be used for commands not implemented/supported by the library
@@ -219,15 +735,105 @@ impl NntpConnection {
     ///
     /// * The caller is responsible for reading the response
     /// * The command SHOULD NOT include the CRLF terminator
+    ///
+    /// This is equivalent to calling [`queue_bytes`](Self::queue_bytes) followed by
+    /// [`flush`](Self::flush).
     pub fn send_bytes(&mut self, command: impl AsRef<[u8]>) -> Result<usize> {
-        let writer = self.stream.get_mut();
-        // Write the command and terminal char
-        let bytes = writer.write(command.as_ref())? + writer.write(b"\r\n")?;
-        // Flush the buffer
-        writer.flush()?;
+        let bytes = self.queue_bytes(command)?;
+        self.flush()?;
         Ok(bytes)
     }
 
+    /// Queue a command without writing it to the server, returning the number of bytes queued
+    ///
+    /// This allows several commands to be pipelined and written at once, e.g.
+    ///
+    /// ```no_run
+    /// # use brokaw::raw::connection::NntpConnection;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let (mut conn, _) = NntpConnection::with_defaults(("news.example.com", 119))?;
+    /// conn.queue_bytes("STAT <a@example.com>")?;
+    /// conn.queue_bytes("STAT <b@example.com>")?;
+    /// conn.flush()?;
+    ///
+    /// let a = conn.read_response_auto()?;
+    /// let b = conn.read_response_auto()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Ordering
+    ///
+    /// Queued commands are written in the order they were queued. They are written when
+    /// [`flush`](Self::flush) or [`send_bytes`](Self::send_bytes) is called, and before any
+    /// response is read, so reading a response never waits on a command that is still queued.
+    /// The responses must be read in the same order as the commands were queued.
+    ///
+    /// * The command SHOULD NOT include the CRLF terminator
+    pub fn queue_bytes(&mut self, command: impl AsRef<[u8]>) -> Result<usize> {
+        self.check_poisoned()?;
+        let command = command.as_ref();
+        let res = match self.deflate.as_mut() {
+            Some(deflate) => queue_compressed_command(&mut self.write_buf, deflate, command),
+            None => Ok(queue_command(&mut self.write_buf, command)),
+        };
+        self.last_send = Some(Instant::now());
+        self.quit_sent = command.eq_ignore_ascii_case(b"QUIT");
+        if res.is_ok() {
+            self.stats.commands_sent += 1;
+        }
+
+        if let (Some(capture), Ok(_)) = (self.capture.as_ref(), &res) {
+            let line = [&*redact(command), b"\r\n"].concat();
+            if let Err(e) = capture.record(Direction::Sent, &line) {
+                warn!("Failed to capture sent bytes -- {}", e);
+            }
+        }
+
+        if let Some(observer) = self.observer.as_mut() {
+            match &res {
+                Ok(_) => observer.on_command(&redact(command)),
+                Err(e) => observer.on_error(e),
+            }
+        }
+
+        res
+    }
+
+    /// Write any queued commands to the server
+    ///
+    /// See [`queue_bytes`](Self::queue_bytes). This is a no-op if there are no queued commands.
+    ///
+    /// If the write fails part of a command may have been sent, so the queued commands are
+    /// discarded and the connection is [poisoned](Self::is_poisoned).
+    pub fn flush(&mut self) -> Result<()> {
+        let res = self.write_queued();
+
+        if let (Some(observer), Err(e)) = (self.observer.as_mut(), &res) {
+            observer.on_error(e);
+        }
+
+        res
+    }
+
+    fn write_queued(&mut self) -> Result<()> {
+        if self.write_buf.is_empty() {
+            return Ok(());
+        }
+
+        let stream = self.stream.get_mut();
+        let res = stream
+            .write_all(&self.write_buf)
+            .and_then(|_| stream.flush());
+        match res {
+            Ok(()) => self.stats.bytes_sent += self.write_buf.len() as u64,
+            Err(_) => self.poisoned = true,
+        }
+        self.write_buf.clear();
+
+        Ok(res?)
+    }
+
     /// Read any data from the stream into a RawResponse
     ///
     /// This function attempts to automatically determine if the response is muliti-line based
@@ -247,52 +853,442 @@ impl NntpConnection {
     /// If `is_multiline` is set to None then the connection use [`ResponseCode::is_multiline`]
     /// to determine if it should expect a multiline response.
     /// This behavior can be overridden by manually specifying `Some(true)` or `Some(false)`
+    ///
+    /// # Server initiated closes
+    ///
+    /// If the server responds with `400` (e.g. when kicking an idle connection), or with `205`
+    /// to anything other than `QUIT`, an [`Error::ServerClosed`] is returned so that callers
+    /// can decide whether to reconnect.
     pub fn read_response(&mut self, is_multiline: Option<bool>) -> Result<RawResponse> {
-        self.first_line_buf.truncate(0);
-        self.data_blocks_buf.truncate(0);
-        let resp_code = read_initial_response(&mut self.stream, &mut self.first_line_buf)?;
-
-        let data_blocks = match (is_multiline, resp_code.is_multiline()) {
-            // Check for data blocks if the caller tells us to OR the kind is multiline
-            (Some(true), _) | (_, true) => {
-                trace!("Parsing data blocks for response {}", u16::from(resp_code));
+        self.read_response_with_progress(is_multiline, None)
+    }
 
-                // FIXME(ops): Consider pre-allocating this buffer
-                let mut line_boundaries = Vec::with_capacity(10);
+    /// Send a command, reporting the progress of reading the data blocks of the response
+    ///
+    /// `progress` is called with the number of data block bytes read so far (after any
+    /// decompression) each time at least `interval` more bytes have been read, and once more
+    /// when the response is complete. Combined with the size of an article (e.g. from
+    /// [`NntpClient::article_size`](crate::client::NntpClient::article_size)) this can be
+    /// used to display a percentage.
+    ///
+    /// If `progress` panics the panic is caught and logged, and the callback is not called
+    /// again for this response, so the connection is still left in a consistent state.
+    ///
+    /// ```no_run
+    /// # use brokaw::raw::connection::NntpConnection;
+    /// use brokaw::types::command::Body;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let (mut conn, _) = NntpConnection::with_defaults(("news.example.com", 119))?;
+    /// let resp = conn.command_with_progress(&Body::Number(42), 1024 * 1024, |bytes| {
+    ///     println!("{} bytes", bytes);
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn command_with_progress<C: NntpCommand>(
+        &mut self,
+        command: &C,
+        interval: u64,
+        mut progress: impl FnMut(u64),
+    ) -> Result<RawResponse> {
+        self.send(command)?;
+        self.read_response_with_progress(None, Some(Progress::new(&mut progress, interval)))
+    }
 
-                let mut stream = match self.config.compression {
-                    Some(c) if c.use_decoder(&self.first_line_buf) => {
-                        trace!("Compression enabled, wrapping stream with decoder");
-                        c.decoder(&mut self.stream)
-                    }
-                    _ => {
-                        trace!("Using passthrough decoder");
-                        Decoder::Passthrough(&mut self.stream)
-                    }
-                };
+    fn read_response_with_progress(
+        &mut self,
+        is_multiline: Option<bool>,
+        progress: Option<Progress<'_>>,
+    ) -> Result<RawResponse> {
+        self.read_response_observed(is_multiline, false, progress)
+    }
 
-                read_data_blocks(&mut stream, &mut self.data_blocks_buf, &mut line_boundaries)?;
+    fn read_response_observed(
+        &mut self,
+        is_multiline: Option<bool>,
+        skip_data_blocks: bool,
+        progress: Option<Progress<'_>>,
+    ) -> Result<RawResponse> {
+        let start = self.last_send.take().unwrap_or_else(Instant::now);
+        let res = self.read_before_deadline(|conn| {
+            conn.read_response_inner(is_multiline, skip_data_blocks, progress)
+        });
+        if let Err(Error::Cancelled) | Err(Error::DeadlineExceeded) = res {
+            // The rest of the response is still waiting to be read
+            self.poisoned = true;
+        }
 
-                Some(DataBlocks {
-                    payload: self.data_blocks_buf.clone(),
-                    line_boundaries,
-                })
+        if let Some(observer) = self.observer.as_mut() {
+            match &res {
+                Ok(resp) => observer.on_response(resp, start.elapsed()),
+                Err(e) => observer.on_error(e),
             }
-            (Some(false), _) => None, // The caller says not to look for data blocks
-            _ => None,
+        }
+
+        res
+    }
+
+    /// Stop reads once the deadline has passed
+    ///
+    /// The read timeout of the socket (if there is one) is shortened to the time remaining
+    /// and restored afterwards, and a read that times out after the deadline returns
+    /// [`Error::DeadlineExceeded`].
+    fn read_before_deadline<T>(&mut self, read: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let deadline = match self.deadline {
+            Some(deadline) => deadline,
+            None => return read(self),
         };
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining == Duration::from_secs(0) {
+            return Err(Error::DeadlineExceeded);
+        }
 
-        let resp = RawResponse {
-            code: resp_code,
-            first_line: self.first_line_buf.clone(),
-            data_blocks,
+        let previous = match (self.tcp_ref)(self.stream.get_ref()) {
+            Some(tcp_stream) => {
+                let previous = tcp_stream.read_timeout()?;
+                let clamped = previous.map_or(remaining, |timeout| timeout.min(remaining));
+                tcp_stream.set_read_timeout(Some(clamped))?;
+                Some(previous)
+            }
+            None => None,
         };
 
-        self.reset_buffers();
+        let res = read(self);
+
+        if let (Some(previous), Some(tcp_stream)) =
+            (previous, (self.tcp_ref)(self.stream.get_ref()))
+        {
+            if let Err(e) = tcp_stream.set_read_timeout(previous) {
+                warn!("Failed to restore read timeout {:?} -- {}", previous, e);
+            }
+        }
+
+        match res {
+            Err(Error::Io(e)) if is_timeout(&e) && Instant::now() >= deadline => {
+                Err(Error::DeadlineExceeded)
+            }
+            res => res,
+        }
+    }
+
+    fn read_response_inner(
+        &mut self,
+        is_multiline: Option<bool>,
+        skip_data_blocks: bool,
+        mut progress: Option<Progress<'_>>,
+    ) -> Result<RawResponse> {
+        self.check_poisoned()?;
+        // The server cannot respond to commands that are still queued
+        self.write_queued()?;
+        self.first_line_buf.truncate(0);
+        self.data_blocks_buf.truncate(0);
+        self.line_boundaries_buf.clear();
+        self.last_response = ResponseStats::default();
+        let mut tee = Tee::new(&mut self.stream, self.capture.as_ref());
+        let mut counted = Counted::new(&mut tee, &mut self.stats.bytes_received);
+        let mut reader = Inflater::new(&mut counted, self.deflate.as_mut());
+        let resp_code = match read_initial_response(
+            &mut reader,
+            &mut self.first_line_buf,
+            self.config.lenient_line_endings,
+            self.config.max_first_line_len,
+        ) {
+            Ok(code) => code,
+            Err(e) => {
+                if !self.first_line_buf.is_empty() && !self.first_line_buf.ends_with(b"\n") {
+                    // The rest of the line is still waiting to be read
+                    self.poisoned = true;
+                }
+                return Err(e);
+            }
+        };
+        self.stats.responses_read += 1;
+
+        if is_server_closed(resp_code, self.quit_sent) {
+            debug!(
+                "Server closed the connection -- {}",
+                String::from_utf8_lossy(&self.first_line_buf).trim_end()
+            );
+            return Err(Error::ServerClosed { code: resp_code });
+        }
+
+        let interrupt = Interrupt {
+            cancel: self.config.cancellation.as_ref(),
+            deadline: self.deadline,
+        };
+        let data_blocks = match (is_multiline, resp_code.is_multiline()) {
+            // The caller will read the data blocks itself (e.g. `article_lines`)
+            _ if skip_data_blocks => None,
+            // Check for data blocks if the caller tells us to OR the kind is multiline
+            (Some(true), _) | (_, true) => {
+                trace!("Parsing data blocks for response {}", u16::from(resp_code));
+
+                match self.config.compression {
+                    Some(c) if c.use_decoder(&self.first_line_buf) => {
+                        trace!("Compression enabled, wrapping stream with decoder");
+                        let strict = self.config.compression_strict;
+                        let mut recorded = Vec::new();
+
+                        let res = {
+                            let recorder =
+                                Recorder::new(&mut reader, Some(&mut recorded).filter(|_| !strict));
+                            let mut stream = c.decoder(recorder, self.config.read_buffer_capacity);
+                            let res = read_data_blocks(
+                                &mut stream,
+                                &mut self.data_blocks_buf,
+                                &mut self.line_boundaries_buf,
+                                self.config.lenient_line_endings,
+                                &mut progress,
+                                interrupt,
+                            );
+                            self.stats.bytes_compressed += stream.total_in();
+                            self.stats.bytes_decompressed += stream.total_out();
+                            res.map_err(|e| (e, stream.total_in() as usize))
+                        };
+
+                        if let Err((e, bytes_consumed)) = res {
+                            let source = match e {
+                                Error::Io(source) => source,
+                                e => {
+                                    // The rest of the response is still waiting to be read
+                                    self.poisoned = true;
+                                    return Err(e);
+                                }
+                            };
+                            let err = Error::Decompression {
+                                source,
+                                bytes_consumed,
+                            };
+
+                            if strict {
+                                self.poisoned = true;
+                                return Err(err);
+                            }
+
+                            warn!("{}, retrying as plain text", err);
+                            self.data_blocks_buf.truncate(0);
+                            self.line_boundaries_buf.clear();
+                            let mut replay = io::Cursor::new(recorded).chain(&mut reader);
+                            if read_data_blocks(
+                                &mut replay,
+                                &mut self.data_blocks_buf,
+                                &mut self.line_boundaries_buf,
+                                self.config.lenient_line_endings,
+                                &mut progress,
+                                interrupt,
+                            )
+                            .is_err()
+                            {
+                                self.poisoned = true;
+                                return Err(err);
+                            }
+                        }
+                    }
+                    _ => {
+                        trace!("Using passthrough decoder");
+                        if let Err(e) = read_data_blocks(
+                            &mut Decoder::Passthrough(&mut reader),
+                            &mut self.data_blocks_buf,
+                            &mut self.line_boundaries_buf,
+                            self.config.lenient_line_endings,
+                            &mut progress,
+                            interrupt,
+                        ) {
+                            // The rest of the response is still waiting to be read
+                            self.poisoned = true;
+                            return Err(e);
+                        }
+                    }
+                };
+
+                // XZVER/XZHDR responses wrap a DEFLATE stream in yEnc.
+                // yEnc escapes CR and LF so the terminator was still found by the line reader
+                if is_xz_response(resp_code) && is_yenc_envelope(&self.data_blocks_buf) {
+                    trace!("Decoding yEnc envelope");
+                    let mut decoded =
+                        decode_yenc_envelope(&self.data_blocks_buf).map_err(|source| {
+                            Error::Decompression {
+                                source,
+                                bytes_consumed: self.data_blocks_buf.len(),
+                            }
+                        })?;
+                    decoded.extend_from_slice(b".\r\n");
+                    self.data_blocks_buf = decoded;
+                    split_line_boundaries(&self.data_blocks_buf, &mut self.line_boundaries_buf);
+                }
+
+                // Hand the buffers to the response rather than copying them
+                let payload = mem::replace(
+                    &mut self.data_blocks_buf,
+                    Vec::with_capacity(self.config.data_blocks_buf_size),
+                );
+                let line_boundaries = mem::replace(
+                    &mut self.line_boundaries_buf,
+                    Vec::with_capacity(self.config.line_boundaries_buf_size),
+                );
+                Some(DataBlocks {
+                    payload,
+                    line_boundaries,
+                    keep_raw_headers: self.config.keep_raw_headers,
+                })
+            }
+            (Some(false), _) => None, // The caller says not to look for data blocks
+            _ => None,
+        };
+
+        self.last_response = ResponseStats {
+            first_line_bytes: self.first_line_buf.len() as u64,
+            data_block_bytes: data_blocks.as_ref().map_or(0, DataBlocks::payload_len) as u64,
+            lines: data_blocks.as_ref().map_or(0, DataBlocks::lines_len) as u64,
+        };
+
+        let resp = RawResponse {
+            code: resp_code,
+            first_line: self.first_line_buf.clone(),
+            data_blocks,
+        };
+
+        // https://tools.ietf.org/html/rfc8054#section-2.2.2
+        if resp_code == ResponseCode::Known(Kind::CompressionActive) && self.deflate.is_none() {
+            debug!("Server activated COMPRESS DEFLATE");
+            self.deflate = Some(Deflate::new(self.config.read_buffer_capacity));
+        }
+
+        self.reset_buffers();
 
         Ok(resp)
     }
 
+    /// Send an `ARTICLE` command and stream the lines of the article as they are read
+    ///
+    /// Unlike [`command`](Self::command), the article is not buffered in memory. Rather,
+    /// the returned [`ArticleLines`] reads one line at a time from the stream, so memory use
+    /// is bounded by the longest line rather than the size of the article.
+    ///
+    /// The first line of the response is available via [`ArticleLines::response`]. If the
+    /// server did not return `220` (e.g. `430 No such article`) the iterator will be empty.
+    ///
+    /// # Usage
+    ///
+    /// The connection is borrowed for the lifetime of the iterator and cannot be used for other
+    /// commands until the iterator is exhausted or dropped. Dropping the iterator early will
+    /// read (and discard) the rest of the article so that the connection remains usable.
+    ///
+    /// XFEATURE compression is not applied since it only applies to overview responses.
+    pub fn article_lines(&mut self, article: cmd::Article) -> Result<ArticleLines<'_, S>> {
+        self.send(&article)?;
+        // Only read the first line, the data blocks are streamed by the iterator
+        let response = self.read_response_observed(None, true, None)?;
+        let done = response.code() != ResponseCode::Known(Kind::Article);
+
+        Ok(ArticleLines {
+            conn: self,
+            response,
+            buf: Vec::new(),
+            done,
+        })
+    }
+
+    /// Send `QUIT` without waiting for the reply, unless it was already sent
+    ///
+    /// This is used when dropping a client over a stream that does not support timeouts,
+    /// errors are logged and ignored.
+    pub(crate) fn quit_without_reply(&mut self) {
+        if self.quit_sent || self.poisoned {
+            return;
+        }
+        if let Err(e) = self.send(&cmd::Quit) {
+            debug!("Failed to send QUIT while dropping the connection -- {}", e);
+        }
+    }
+
+    /// A snapshot of the traffic on the connection since it was opened or the stats were reset
+    ///
+    /// ```no_run
+    /// # use brokaw::raw::connection::NntpConnection;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let (mut conn, _) = NntpConnection::with_defaults(("news.example.com", 119))?;
+    /// conn.command(&brokaw::types::command::List::Active { wildmat: None })?;
+    ///
+    /// let stats = conn.stats();
+    /// println!("Received {} bytes", stats.bytes_received);
+    /// if let Some(ratio) = stats.compression_ratio() {
+    ///     println!("Compression ratio {:.1}", ratio);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stats(&self) -> ConnectionStats {
+        let mut stats = self.stats;
+        if let Some(deflate) = &self.deflate {
+            let (total_in, total_out) = deflate.decompressed_totals();
+            stats.bytes_compressed += total_in - self.deflate_baseline.0;
+            stats.bytes_decompressed += total_out - self.deflate_baseline.1;
+        }
+        stats
+    }
+
+    /// Reset the counters returned by [`stats`](Self::stats) to zero
+    pub fn reset_stats(&mut self) {
+        self.stats = ConnectionStats::default();
+        self.deflate_baseline = self
+            .deflate
+            .as_ref()
+            .map_or((0, 0), Deflate::decompressed_totals);
+    }
+
+    /// The size of the most recent response read by [`read_response`](Self::read_response)
+    ///
+    /// Sizes are measured after any decompression. If reading the response failed the stats
+    /// are zeroed. Responses streamed by [`article_lines`](Self::article_lines) are not
+    /// included.
+    ///
+    /// ```no_run
+    /// # use std::time::Instant;
+    /// # use brokaw::raw::connection::NntpConnection;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let (mut conn, _) = NntpConnection::with_defaults(("news.example.com", 119))?;
+    /// let start = Instant::now();
+    /// conn.command(&brokaw::types::command::Body::Number(42))?;
+    ///
+    /// let stats = conn.last_response_stats();
+    /// let secs = start.elapsed().as_secs_f64();
+    /// println!("{} lines at {:.0} B/s", stats.lines, stats.total_bytes() as f64 / secs);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn last_response_stats(&self) -> ResponseStats {
+        self.last_response
+    }
+
+    /// Stop reading responses once `deadline` passes
+    ///
+    /// Set by [`NntpClient::with_deadline`](crate::client::NntpClient::with_deadline).
+    pub(crate) fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    /// Returns true if the connection was left mid-response, e.g. because reading the data
+    /// blocks failed or was [cancelled](CancellationToken), or if writing queued commands failed
+    ///
+    /// A poisoned connection will return an error for any further commands and should be
+    /// discarded.
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    fn check_poisoned(&self) -> Result<()> {
+        if self.poisoned {
+            Err(Error::Io(io::Error::new(
+                ErrorKind::NotConnected,
+                "Connection is unusable after a command or response was abandoned part way through",
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
     /// Reset the connection's buffers to their initial size
     ///
     /// This should be run after reading responses to prevent the buffers from growing unbounded
@@ -303,20 +1299,31 @@ impl NntpConnection {
             .truncate(self.config.first_line_buf_size);
         self.first_line_buf.shrink_to_fit();
 
-        self.data_blocks_buf
-            .truncate(self.config.data_blocks_buf_size);
-        self.data_blocks_buf.shrink_to_fit();
+        // The data block buffers are handed off to responses, so keep the configured
+        // capacity around so that it can be reused across responses
+        if self.data_blocks_buf.capacity() > self.config.data_blocks_buf_size {
+            self.data_blocks_buf = Vec::with_capacity(self.config.data_blocks_buf_size);
+        } else {
+            self.data_blocks_buf.clear();
+        }
+
+        if self.line_boundaries_buf.capacity() > self.config.line_boundaries_buf_size {
+            self.line_boundaries_buf = Vec::with_capacity(self.config.line_boundaries_buf_size);
+        } else {
+            self.line_boundaries_buf.clear();
+        }
     }
 
-    /// Get a ref to the underlying NntpStream
-    pub fn stream(&self) -> &io::BufReader<NntpStream> {
+    /// Get a ref to the underlying stream
+    pub fn stream(&self) -> &io::BufReader<S> {
         &self.stream
     }
 
-    /// Get a mutable ref to the underlying NntpStream
+    /// Get a mutable ref to the underlying stream
     ///
-    /// This can be useful if you want to handle response parsing and/or control buffering
-    pub fn stream_mut(&mut self) -> &mut io::BufReader<NntpStream> {
+    /// This can be useful if you want to handle response parsing and/or control buffering.
+    /// Note that bytes read directly from the stream will not be captured.
+    pub fn stream_mut(&mut self) -> &mut io::BufReader<S> {
         &mut self.stream
     }
 
@@ -324,20 +1331,231 @@ impl NntpConnection {
     pub fn config(&self) -> &ConnectionConfig {
         &self.config
     }
+
+    /// Change how the data blocks of responses are decompressed, see [`ConnectionConfig::compression`]
+    ///
+    /// This does not negotiate compression with the server.
+    pub(crate) fn set_compression(&mut self, compression: Option<Compression>) {
+        self.config.compression = compression;
+    }
+
+    /// Returns true if the stream is compressed via `COMPRESS DEFLATE`
+    ///
+    /// Stream compression is activated whenever the server returns a `206` response.
+    pub fn is_deflate_active(&self) -> bool {
+        self.deflate.is_some()
+    }
+
+    /// Set an observer that will be notified of every command and response
+    ///
+    /// See [`ConnectionObserver`] for more info.
+    pub fn set_observer(&mut self, observer: Box<dyn ConnectionObserver>) {
+        self.observer = Some(observer);
+    }
+
+    /// Remove the observer from the connection, returning it if one was set
+    pub fn take_observer(&mut self) -> Option<Box<dyn ConnectionObserver>> {
+        self.observer.take()
+    }
+}
+
+/// An iterator over the lines of an article that are read directly from the stream
+///
+/// * Lines are yielded without the CRLF terminator and are dot-unstuffed
+/// * The headers are yielded first, followed by an empty line and then the body
+/// * The terminating `.` line is not yielded
+///
+/// Created by [`NntpConnection::article_lines`]
+#[derive(Debug)]
+pub struct ArticleLines<'a, S: Read + Write> {
+    conn: &'a mut NntpConnection<S>,
+    response: RawResponse,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+impl<S: Read + Write> ArticleLines<'_, S> {
+    /// The first line of the response to the `ARTICLE` command
+    pub fn response(&self) -> &RawResponse {
+        &self.response
+    }
+
+    fn read_line(&mut self) -> Result<Option<Vec<u8>>> {
+        let buf = &mut self.buf;
+        self.conn
+            .read_before_deadline(|conn| read_article_line(conn, buf))
+    }
+}
+
+/// Read a line of an article streamed by [`ArticleLines`], returning `None` at the terminator
+fn read_article_line<S: Read + Write>(
+    conn: &mut NntpConnection<S>,
+    buf: &mut Vec<u8>,
+) -> Result<Option<Vec<u8>>> {
+    buf.clear();
+    Interrupt {
+        cancel: conn.config.cancellation.as_ref(),
+        deadline: conn.deadline,
+    }
+    .check()?;
+
+    let mut tee = Tee::new(&mut conn.stream, conn.capture.as_ref());
+    let mut counted = Counted::new(&mut tee, &mut conn.stats.bytes_received);
+    let mut reader = Inflater::new(&mut counted, conn.deflate.as_mut());
+
+    if reader.read_until(b'\n', buf)? == 0 {
+        return Err(io::Error::new(
+            ErrorKind::UnexpectedEof,
+            "Stream closed before the end of the article",
+        )
+        .into());
+    }
+
+    let parse_line = if conn.config.lenient_line_endings {
+        parse_data_block_line_lenient
+    } else {
+        parse_data_block_line
+    };
+    let (_empty, line) = parse_line(buf).map_err(|e| {
+        trace!("parse_data_block_line failed -- {:?}", e);
+        io::Error::new(ErrorKind::InvalidData, "Failed to parse line of article")
+    })?;
+
+    if is_terminator_line(line) {
+        return Ok(None);
+    }
+
+    // https://tools.ietf.org/html/rfc3977#section-3.1.1
+    let line = if line.starts_with(b"..") {
+        &line[1..]
+    } else {
+        line
+    };
+
+    Ok(Some(line.to_vec()))
+}
+
+impl<S: Read + Write> Iterator for ArticleLines<'_, S> {
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.read_line() {
+            Ok(Some(line)) => Some(Ok(line)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                // The rest of the article can't be drained
+                self.done = true;
+                self.conn.poisoned = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl<S: Read + Write> Drop for ArticleLines<'_, S> {
+    fn drop(&mut self) {
+        // Drain the remainder of the article so the connection can be reused
+        while let Some(Ok(_)) = self.next() {}
+    }
+}
+
+/// The error returned for a command that contains a CR or LF
+fn line_break_error() -> Error {
+    Error::InvalidCommand
+}
+
+/// Append a command and the CRLF terminator to a buffer, returning the number of bytes queued
+fn queue_command(buf: &mut Vec<u8>, command: &[u8]) -> usize {
+    buf.extend_from_slice(command);
+    buf.extend_from_slice(b"\r\n");
+    command.len() + 2
+}
+
+/// TCP level options applied to connections opened by an [`NntpConnection`]
+///
+/// See the corresponding setters on [`ConnectionConfig`] for more info.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TcpOptions {
+    /// Disable Nagle's algorithm (i.e. `TCP_NODELAY`)
+    pub nodelay: bool,
+    /// The idle time before TCP keepalive probes are sent, `None` if disabled
+    pub keepalive: Option<Duration>,
+    /// The size of the socket receive buffer, `None` to use the OS default
+    pub recv_buffer: Option<usize>,
+}
+
+impl TcpOptions {
+    fn validate(&self) -> io::Result<()> {
+        let invalid = |msg: &str| Err(io::Error::new(ErrorKind::InvalidInput, msg.to_string()));
+
+        match (self.keepalive, self.recv_buffer) {
+            (Some(keepalive), _) if keepalive < Duration::from_secs(1) => {
+                invalid("TCP keepalive must be at least one second")
+            }
+            (_, Some(0)) => invalid("Socket receive buffer size must be greater than zero"),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Append a compressed command and the CRLF terminator to a buffer for a `COMPRESS DEFLATE` stream
+///
+/// Returns the number of compressed bytes queued
+fn queue_compressed_command(
+    buf: &mut Vec<u8>,
+    deflate: &mut Deflate,
+    command: &[u8],
+) -> Result<usize> {
+    let before = buf.len();
+    deflate.compress(&[command, b"\r\n"].concat(), buf)?;
+    Ok(buf.len() - before)
 }
 
 /// A buffered NntpStream
 pub type BufNntpStream = io::BufReader<NntpStream>;
 
+/// The default capacity of the buffer used to read from the stream
+pub const DEFAULT_READ_BUFFER_CAPACITY: usize = 64 * 1024;
+
+/// The read timeout used when a [`CancellationToken`] is configured without a read timeout
+///
+/// Cancellation is only checked between reads, so without a timeout a stalled server would
+/// block the connection indefinitely.
+pub const DEFAULT_CANCELLABLE_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The default limit on the length of the first line of a response
+pub const DEFAULT_MAX_FIRST_LINE_LEN: usize = 4096;
+
 /// A builder for [`NntpConnection`]
 #[derive(Clone, Debug)]
 pub struct ConnectionConfig {
     pub(crate) compression: Option<Compression>,
     pub(crate) tls_config: Option<TlsConfig>,
+    pub(crate) tls_domain: Option<String>,
     pub(crate) read_timeout: Option<Duration>,
     pub(crate) write_timeout: Option<Duration>,
+    pub(crate) connect_timeout: Option<Duration>,
+    pub(crate) local_addr: Option<SocketAddr>,
+    pub(crate) tcp_options: TcpOptions,
     pub(crate) first_line_buf_size: usize,
+    pub(crate) max_first_line_len: usize,
     pub(crate) data_blocks_buf_size: usize,
+    pub(crate) line_boundaries_buf_size: usize,
+    pub(crate) read_buffer_capacity: usize,
+    pub(crate) keep_offending_bytes: bool,
+    pub(crate) lenient_line_endings: bool,
+    pub(crate) keep_raw_headers: bool,
+    pub(crate) capture: Option<CaptureSink>,
+    pub(crate) proxy: Option<ProxyConfig>,
+    pub(crate) compression_strict: bool,
+    pub(crate) cancellation: Option<CancellationToken>,
 }
 
 impl Default for ConnectionConfig {
@@ -345,10 +1563,24 @@ impl Default for ConnectionConfig {
         ConnectionConfig {
             compression: None,
             tls_config: None,
+            tls_domain: None,
             read_timeout: None,
             write_timeout: None,
+            connect_timeout: None,
+            local_addr: None,
+            tcp_options: TcpOptions::default(),
             first_line_buf_size: 128,
+            max_first_line_len: DEFAULT_MAX_FIRST_LINE_LEN,
             data_blocks_buf_size: 16 * 1024,
+            line_boundaries_buf_size: 256,
+            read_buffer_capacity: DEFAULT_READ_BUFFER_CAPACITY,
+            keep_offending_bytes: false,
+            lenient_line_endings: false,
+            keep_raw_headers: false,
+            capture: None,
+            proxy: None,
+            compression_strict: true,
+            cancellation: None,
         }
     }
 }
@@ -365,6 +1597,20 @@ impl ConnectionConfig {
         self
     }
 
+    /// Fail responses whose compressed data blocks cannot be decompressed (the default)
+    ///
+    /// If `false`, a response that fails to decompress will be parsed again as plain text.
+    /// This is useful for servers that send the `[COMPRESS=GZIP]` banner but deliver
+    /// uncompressed data (e.g. for empty responses). Note that the compressed bytes are
+    /// buffered while reading in order to replay them.
+    ///
+    /// If the response cannot be recovered [`Error::Decompression`] is returned and the
+    /// connection is [poisoned](NntpConnection::is_poisoned).
+    pub fn compression_strict(&mut self, strict: bool) -> &mut Self {
+        self.compression_strict = strict;
+        self
+    }
+
     /// Configure TLS on the connection
     pub fn tls_config(&mut self, config: Option<TlsConfig>) -> &mut Self {
         self.tls_config = config;
@@ -380,24 +1626,245 @@ impl ConnectionConfig {
         Ok(self)
     }
 
+    /// Override the domain used for SNI and certificate validation
+    ///
+    /// The address passed to [`connect`](Self::connect) is only used to open the socket, the
+    /// TLS handshake uses the domain of the [`TlsConfig`]. This sets that domain independently,
+    /// e.g. when connecting to an IP address or through a load balancer while the server's
+    /// certificate is issued for `news.example.com`. As with
+    /// [`TlsConfig::default_connector`] a `:port` suffix is removed.
+    ///
+    /// This has no effect unless TLS is configured. Pass `None` to use the domain of the
+    /// `TlsConfig` again.
+    ///
+    /// ```no_run
+    /// use brokaw::raw::connection::ConnectionConfig;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// // e.g. an address read from a config file
+    /// let addr = "203.0.113.7:563";
+    /// let (conn, _) = ConnectionConfig::new()
+    ///     .default_tls(addr)?
+    ///     .tls_domain(Some("news.example.com"))
+    ///     .connect(addr)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tls_domain(&mut self, domain: Option<impl AsRef<str>>) -> &mut Self {
+        self.tls_domain = domain.map(|d| sni_domain(d.as_ref()).to_string());
+        self
+    }
+
+    /// Connect through a SOCKS5 or HTTP CONNECT proxy
+    ///
+    /// The proxy is negotiated before the TLS handshake, so TLS (if configured) still
+    /// terminates at the news server.
+    pub fn proxy(&mut self, proxy: Option<ProxyConfig>) -> &mut Self {
+        self.proxy = proxy;
+        self
+    }
+
     /// Set the read timeout on the socket
     pub fn read_timeout(&mut self, dur: Option<Duration>) -> &mut Self {
         self.read_timeout = dur;
         self
     }
 
+    /// Allow in-flight reads to be cancelled from another thread
+    ///
+    /// The token is checked before each line of a multi-line response is read. When it is
+    /// tripped the read fails with [`Error::Cancelled`] and, as the rest of the response is
+    /// left unread, the connection is [poisoned](NntpConnection::is_poisoned).
+    ///
+    /// Unless a [`read_timeout`](Self::read_timeout) is set, the socket uses
+    /// [`DEFAULT_CANCELLABLE_READ_TIMEOUT`] so that a stalled server cannot block the
+    /// cancellation check indefinitely.
+    ///
+    /// ```no_run
+    /// use std::thread;
+    /// use brokaw::raw::{CancellationToken, ConnectionConfig};
+    /// use brokaw::raw::error::Error;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let token = CancellationToken::new();
+    /// let (mut conn, _) = ConnectionConfig::new()
+    ///     .cancellation_token(Some(token.clone()))
+    ///     .connect(("news.example.com", 119))?;
+    ///
+    /// let cancel = token.clone();
+    /// thread::spawn(move || {
+    ///     // e.g. when the user presses a "cancel download" key
+    ///     cancel.cancel();
+    /// });
+    ///
+    /// let list = brokaw::types::command::List::Active { wildmat: None };
+    /// if let Err(Error::Cancelled) = conn.command(&list) {
+    ///     // The connection is poisoned and must be replaced
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn cancellation_token(&mut self, token: Option<CancellationToken>) -> &mut Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// The read timeout applied to the socket, accounting for any cancellation token
+    pub(crate) fn effective_read_timeout(&self) -> Option<Duration> {
+        self.read_timeout.or_else(|| {
+            self.cancellation
+                .as_ref()
+                .map(|_| DEFAULT_CANCELLABLE_READ_TIMEOUT)
+        })
+    }
+
+    /// Set the write timeout on the socket
+    pub fn write_timeout(&mut self, dur: Option<Duration>) -> &mut Self {
+        self.write_timeout = dur;
+        self
+    }
+
+    /// Set the timeout for establishing the TCP connection
+    ///
+    /// If the address resolves to multiple IPs the timeout applies to each attempt.
+    /// When using a proxy, the timeout applies to the connection to the proxy.
+    pub fn connect_timeout(&mut self, dur: Option<Duration>) -> &mut Self {
+        self.connect_timeout = dur;
+        self
+    }
+
+    /// Bind the outgoing connection to a local address
+    ///
+    /// This is useful on multi-homed hosts to control which address/interface a connection
+    /// originates from. Use port `0` to let the OS pick the source port.
+    ///
+    /// Only remote addresses of the same family (IPv4/IPv6) as the local address will be tried.
+    /// When using a proxy, the connection to the proxy is bound.
+    pub fn local_addr(&mut self, addr: Option<SocketAddr>) -> &mut Self {
+        self.local_addr = addr;
+        self
+    }
+
+    /// Disable Nagle's algorithm on the socket (i.e. set `TCP_NODELAY`)
+    ///
+    /// NNTP sessions typically send small commands and wait for (large) responses, so
+    /// disabling Nagle can reduce latency on high-latency links.
+    pub fn tcp_nodelay(&mut self, nodelay: bool) -> &mut Self {
+        self.tcp_options.nodelay = nodelay;
+        self
+    }
+
+    /// Enable TCP keepalive, sending probes after the socket has been idle for `dur`
+    ///
+    /// This can keep idle sessions from being dropped by NAT devices.
+    /// The duration must be at least one second.
+    pub fn tcp_keepalive(&mut self, dur: Option<Duration>) -> &mut Self {
+        self.tcp_options.keepalive = dur;
+        self
+    }
+
+    /// Set the size of the socket receive buffer (i.e. `SO_RCVBUF`)
+    ///
+    /// Note that the OS may adjust the size, e.g. Linux doubles the requested value.
+    pub fn socket_recv_buffer(&mut self, size: Option<usize>) -> &mut Self {
+        self.tcp_options.recv_buffer = size;
+        self
+    }
+
+    /// The TCP options that will be applied to new connections
+    pub fn tcp_options(&self) -> &TcpOptions {
+        &self.tcp_options
+    }
+
     /// Set the size of the buffer used to read the first line
     pub fn first_line_buf_size(&mut self, s: usize) -> &mut Self {
         self.first_line_buf_size = s;
         self
     }
 
+    /// Set the maximum length in bytes of the first line of a response, including the CRLF
+    ///
+    /// Defaults to [`DEFAULT_MAX_FIRST_LINE_LEN`] (4 KiB). A server that sends a longer line,
+    /// or that never terminates the line, causes the read to fail with an
+    /// [`InvalidData`](ErrorKind::InvalidData) I/O error rather than buffering indefinitely.
+    /// As the rest of the line is left unread the connection is
+    /// [poisoned](NntpConnection::is_poisoned). A limit of `0` is treated as `1`.
+    pub fn max_first_line_len(&mut self, len: usize) -> &mut Self {
+        self.max_first_line_len = len.max(1);
+        self
+    }
+
     /// Set the size of the buffer used to read data blocks
     pub fn data_blocks_buf_size(&mut self, s: usize) -> &mut Self {
         self.data_blocks_buf_size = s;
         self
     }
 
+    /// Set the initial capacity (in lines) of the buffer used to track data block line boundaries
+    pub fn line_boundaries_buf_size(&mut self, s: usize) -> &mut Self {
+        self.line_boundaries_buf_size = s;
+        self
+    }
+
+    /// Set the capacity of the buffer used to read from the stream
+    ///
+    /// Defaults to [`DEFAULT_READ_BUFFER_CAPACITY`] (64 KiB). Larger buffers reduce the number
+    /// of reads when transferring large multi-line responses. Decompressed data is buffered
+    /// with the same capacity when compression is enabled. A capacity of `0` is treated as `1`.
+    pub fn read_buffer_capacity(&mut self, capacity: usize) -> &mut Self {
+        self.read_buffer_capacity = capacity.max(1);
+        self
+    }
+
+    /// Attach the bytes of responses that fail to deserialize to the returned errors
+    ///
+    /// This is useful for reporting bugs against misbehaving servers but is disabled by default
+    /// to avoid copying response data in the error path.
+    /// See [`Error::offending_bytes`](crate::error::Error::offending_bytes) for more info.
+    ///
+    /// This only applies to typed responses returned by the [`NntpClient`](crate::NntpClient).
+    pub fn keep_offending_bytes(&mut self, keep: bool) -> &mut Self {
+        self.keep_offending_bytes = keep;
+        self
+    }
+
+    /// Accept responses whose lines are terminated by a bare LF
+    ///
+    /// [RFC 3977](https://tools.ietf.org/html/rfc3977#section-3.1.1) requires lines to be
+    /// terminated by CRLF, so by default a bare LF is an error. Some old servers and gateways
+    /// terminate lines with a bare LF and end data blocks with `.\n`. When enabled both the
+    /// first line of a response and the lines of its data blocks may end with either
+    /// terminator. Lines are stored as received; the `unterminated` iterators strip whichever
+    /// terminator each line has.
+    pub fn lenient_line_endings(&mut self, lenient: bool) -> &mut Self {
+        self.lenient_line_endings = lenient;
+        self
+    }
+
+    /// Retain the raw bytes of article headers alongside their lossy UTF-8 content
+    ///
+    /// Header content is converted to UTF-8 lossily, which discards the original bytes of
+    /// 8-bit headers. When enabled the bytes are available from
+    /// [`Header::raw_content`](crate::types::response::Header::raw_content), e.g. for decoding
+    /// legacy charsets. This is disabled by default to avoid copying the headers.
+    pub fn keep_raw_headers(&mut self, keep: bool) -> &mut Self {
+        self.keep_raw_headers = keep;
+        self
+    }
+
+    /// Capture all traffic on the connection into a sink
+    ///
+    /// This is useful for debugging parser issues and generating test fixtures.
+    /// Captures can be replayed with `MockStream::from_capture` provided by the `test-util` feature.
+    /// Please see [`CaptureSink`] for details on the capture format.
+    ///
+    /// Credentials sent via `AUTHINFO` are masked but the capture may still contain
+    /// sensitive data (e.g. articles from private groups).
+    pub fn capture(&mut self, sink: Option<CaptureSink>) -> &mut Self {
+        self.capture = sink;
+        self
+    }
+
     /// Create a connection from the config
     pub fn connect(&self, addr: impl ToSocketAddrs) -> Result<(NntpConnection, RawResponse)> {
         NntpConnection::connect(addr, self.clone())
@@ -411,9 +1878,24 @@ impl ConnectionConfig {
 fn read_initial_response<S: io::BufRead>(
     stream: &mut S,
     buffer: &mut Vec<u8>,
+    lenient: bool,
+    max_len: usize,
 ) -> Result<ResponseCode> {
-    stream.read_until(b'\n', buffer)?;
-    let (_initial_line_buffer, resp) = parse_first_line(&buffer).map_err(|_e| {
+    stream.take(max_len as u64).read_until(b'\n', buffer)?;
+    if buffer.len() >= max_len && !buffer.ends_with(b"\n") {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("First line of response exceeds {} bytes", max_len),
+        )
+        .into());
+    }
+
+    let parse = if lenient {
+        parse_first_line_lenient
+    } else {
+        parse_first_line
+    };
+    let (_initial_line_buffer, resp) = parse(buffer).map_err(|_e| {
         io::Error::new(
             ErrorKind::InvalidData,
             "Failed to parse first line of response",
@@ -428,26 +1910,73 @@ fn read_initial_response<S: io::BufRead>(
     Ok(code_u16.into())
 }
 
+/// Returns true if the response code indicates that the server is closing the connection
+/// of its own accord
+fn is_server_closed(code: ResponseCode, quit_sent: bool) -> bool {
+    match code {
+        ResponseCode::Known(Kind::TemporarilyUnavailable) => true,
+        ResponseCode::Known(Kind::ConnectionClosing) => !quit_sent,
+        _ => false,
+    }
+}
+
+/// Returns true if the response code may be used for an `XZVER` or `XZHDR` response
+fn is_xz_response(code: ResponseCode) -> bool {
+    code == ResponseCode::Known(Kind::Overview)
+        || code == ResponseCode::Known(Kind::Head)
+        || code == ResponseCode::Known(Kind::Hdr)
+}
+
+/// Recompute the line boundaries of a buffer containing LF terminated lines
+fn split_line_boundaries(buffer: &[u8], line_boundaries: &mut Vec<(usize, usize)>) {
+    line_boundaries.clear();
+    let mut start = 0;
+    for (i, b) in buffer.iter().enumerate() {
+        if *b == b'\n' {
+            line_boundaries.push((start, i + 1));
+            start = i + 1;
+        }
+    }
+}
+
 /// Read multi-line data block portion from a stream
 ///
 /// * The data will be read line-by-line into the provided `buffer`
 /// * The `line_boundaries` vector will contain a list two-tuples containing the start and ending
 ///   of every line within the `buffer`
 /// * Note that depending on the command the total data size may be on the order of several megabytes!
+///
+/// If `lenient` is set, lines terminated by a bare LF are accepted and stored as is.
 fn read_data_blocks<S: io::BufRead>(
     stream: &mut S,
     buffer: &mut Vec<u8>,
     line_boundaries: &mut Vec<(usize, usize)>,
+    lenient: bool,
+    progress: &mut Option<Progress<'_>>,
+    interrupt: Interrupt<'_>,
 ) -> Result<()> {
+    let parse_line = if lenient {
+        parse_data_block_line_lenient
+    } else {
+        parse_data_block_line
+    };
     let mut read_head = 0;
     trace!("Reading data blocks...");
 
     // n.b. - icky imperative style so that we have zero allocations outside of the reader
     loop {
+        if let Err(e) = interrupt.check() {
+            debug!(
+                "Read interrupted after {} bytes of data blocks -- {}",
+                read_head, e
+            );
+            return Err(e);
+        }
+
         // n.b. - read_until will _append_ data from the current end of the vector
         let bytes_read = stream.read_until(b'\n', buffer)?;
 
-        let (_empty, line) = parse_data_block_line(&buffer[read_head..]).map_err(|e| {
+        let (_empty, line) = parse_line(&buffer[read_head..]).map_err(|e| {
             trace!("parse_data_block_line failed -- {:?}", e);
             io::Error::new(
                 ErrorKind::InvalidData,
@@ -465,15 +1994,1141 @@ fn read_data_blocks<S: io::BufRead>(
         // terminators
         read_head += bytes_read;
 
-        if is_end_of_datablock(line) {
+        if is_terminator_line(line) {
             trace!(
                 "Read {} bytes of data across {} lines",
                 read_head,
                 line_boundaries.len()
             );
+            if let Some(progress) = progress {
+                progress.finish(read_head as u64);
+            }
             break;
         }
+
+        if let Some(progress) = progress {
+            progress.update(read_head as u64);
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::*;
+    use crate::testing::MockStream;
+    use crate::types::command::Encode;
+    use crate::types::response::Head;
+
+    #[test]
+    fn test_decompression_failure() {
+        let mut input = b"200 ready\r\n".to_vec();
+        input.extend_from_slice(include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/xover_resp_xfeature_truncated"
+        )));
+        let mut config = ConnectionConfig::new();
+        config.compression(Some(Compression::XFeature));
+
+        let (mut conn, _) =
+            NntpConnection::from_stream(MockStream::permissive(&input), config.clone()).unwrap();
+        match conn.command(&cmd::XOver::range((1, 10)).unwrap()) {
+            Err(Error::Decompression { bytes_consumed, .. }) => assert!(bytes_consumed > 0),
+            other => panic!("Unexpected result {:?}", other),
+        }
+        assert!(conn.is_poisoned());
+        assert!(conn.command(&cmd::Date).is_err());
+
+        // The truncated stream is not valid plain text either
+        config.compression_strict(false);
+        let (mut conn, _) =
+            NntpConnection::from_stream(MockStream::permissive(&input), config).unwrap();
+        assert!(matches!(
+            conn.command(&cmd::XOver::range((1, 10)).unwrap()),
+            Err(Error::Decompression { .. })
+        ));
+        assert!(conn.is_poisoned());
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut input = b"200 ready\r\n111 20200101000000\r\n".to_vec();
+        input.extend_from_slice(include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/xover_resp_xfeature_compress"
+        )));
+        let mut config = ConnectionConfig::new();
+        config.compression(Some(Compression::XFeature));
+        let (mut conn, _) =
+            NntpConnection::from_stream(MockStream::permissive(&input), config).unwrap();
+
+        conn.command(&cmd::Date).unwrap();
+        let stats = conn.stats();
+        assert_eq!(stats.bytes_sent, 6);
+        assert_eq!(stats.bytes_received, 31);
+        assert_eq!((stats.commands_sent, stats.responses_read), (1, 2));
+        assert_eq!(stats.compression_ratio(), None);
+
+        conn.reset_stats();
+        let resp = conn.command(&cmd::XOver::range((1, 10)).unwrap()).unwrap();
+        let stats = conn.stats();
+        // The fixture contains two responses, only the first is read
+        assert_eq!(stats.bytes_received, 573);
+        assert_eq!(stats.bytes_compressed, 526);
+        assert_eq!(
+            stats.bytes_decompressed,
+            resp.data_blocks().unwrap().payload().len() as u64
+        );
+        assert!(stats.compression_ratio().unwrap() > 1.0);
+    }
+
+    /// Serves a response a few bytes at a time, cancelling a token part way through
+    struct Trickle {
+        data: Vec<u8>,
+        pos: usize,
+        token: CancellationToken,
+        cancel_at: usize,
+    }
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.pos >= self.cancel_at {
+                self.token.cancel();
+            }
+            let end = (self.pos + 8).min(self.data.len());
+            let n = (end - self.pos).min(buf.len());
+            buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    impl Write for Trickle {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Serves the provided bytes and then fails every read with the provided error
+    struct Failing {
+        data: io::Cursor<Vec<u8>>,
+        error: fn() -> io::Error,
+    }
+
+    impl Read for Failing {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.data.read(buf)? {
+                0 => Err((self.error)()),
+                n => Ok(n),
+            }
+        }
+    }
+
+    impl Write for Failing {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Accepts a few bytes and then fails every write
+    struct BrokenPipe(usize);
+
+    impl Read for BrokenPipe {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let greeting = b"200 ready\r\n";
+            buf[..greeting.len()].copy_from_slice(greeting);
+            Ok(greeting.len())
+        }
+    }
+
+    impl Write for BrokenPipe {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.0 == 0 {
+                return Err(io::Error::new(ErrorKind::BrokenPipe, "broken pipe"));
+            }
+            let n = buf.len().min(self.0);
+            self.0 -= n;
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_write_failure_poisons() {
+        let (mut conn, _) =
+            NntpConnection::from_stream(BrokenPipe(4), ConnectionConfig::new()).unwrap();
+        conn.queue_bytes("STAT <a@example.com>").unwrap();
+        assert!(conn.flush().is_err());
+        assert!(conn.is_poisoned());
+        assert_eq!(conn.stats().bytes_sent, 0);
+
+        // Nothing is resent after a partial write
+        assert!(conn.flush().is_ok());
+        assert!(conn.queue_bytes("DATE").is_err());
+    }
+
+    #[test]
+    fn test_compressed_read_failure_poisons() {
+        // A COMPRESS DEFLATE failure while reading XFEATURE compressed data blocks
+        let stream = Failing {
+            data: io::Cursor::new(b"200 ready\r\n224 xover [COMPRESS=GZIP]\r\n".to_vec()),
+            error: || crate::raw::compression::compression_error("corrupt deflate stream"),
+        };
+        let mut config = ConnectionConfig::new();
+        config.compression(Some(Compression::XFeature));
+        let (mut conn, _) = NntpConnection::from_stream(stream, config).unwrap();
+
+        assert!(matches!(
+            conn.command(&cmd::XOver::range((1, 10)).unwrap()),
+            Err(Error::Compression(_))
+        ));
+        assert!(conn.is_poisoned());
+    }
+
+    #[test]
+    fn test_data_blocks_failure_poisons() {
+        let errors: [fn() -> io::Error; 2] = [
+            || io::Error::new(ErrorKind::TimedOut, "timed out"),
+            || crate::raw::compression::compression_error("corrupt deflate stream"),
+        ];
+        for &error in errors.iter() {
+            let stream = Failing {
+                data: io::Cursor::new(b"200 ready\r\n224 overview\r\n1\ta\r\n".to_vec()),
+                error,
+            };
+            let (mut conn, _) =
+                NntpConnection::from_stream(stream, ConnectionConfig::new()).unwrap();
+
+            assert!(conn.command(&cmd::XOver::range((1, 10)).unwrap()).is_err());
+            // The rest of the response may still arrive so the connection can't be reused
+            assert!(conn.is_poisoned());
+            assert!(conn.command(&cmd::Date).is_err());
+        }
+    }
+
+    #[test]
+    fn test_cancellation() {
+        let token = CancellationToken::new();
+        let mut data = b"200 ready\r\n222 1 <a@b> body\r\n".to_vec();
+        for _ in 0..100 {
+            data.extend_from_slice(b"some body text\r\n");
+        }
+        data.extend_from_slice(b".\r\n");
+        let stream = Trickle {
+            data,
+            pos: 0,
+            token: token.clone(),
+            cancel_at: 200,
+        };
+        let mut config = ConnectionConfig::new();
+        config
+            .read_buffer_capacity(8)
+            .cancellation_token(Some(token.clone()));
+        let (mut conn, _) = NntpConnection::from_stream(stream, config).unwrap();
+
+        let res = conn.command(&cmd::Body::Number(1));
+        assert!(matches!(res, Err(Error::Cancelled)));
+        assert!(token.is_cancelled());
+        // The rest of the body is still unread
+        assert!(conn.stream().get_ref().pos < conn.stream().get_ref().data.len());
+        assert!(conn.is_poisoned());
+        assert!(conn.command(&cmd::Date).is_err());
+    }
+
+    /// Serves a response a few bytes at a time, sleeping before each read
+    struct Slow(io::Cursor<Vec<u8>>);
+
+    impl Read for Slow {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            std::thread::sleep(Duration::from_millis(5));
+            let len = buf.len().min(8);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    impl Write for Slow {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_deadline() {
+        let mut data = b"200 ready\r\n".to_vec();
+        for _ in 0..2 {
+            data.extend_from_slice(b"220 1 <a@b> article\r\nSubject: hi\r\n\r\n");
+            for _ in 0..100 {
+                data.extend_from_slice(b"some body text\r\n");
+            }
+            data.extend_from_slice(b".\r\n");
+        }
+        let config = ConnectionConfig::new().read_buffer_capacity(8).to_owned();
+
+        // Data blocks
+        let stream = Slow(io::Cursor::new(data.clone()));
+        let (mut conn, _) = NntpConnection::from_stream(stream, config.clone()).unwrap();
+        conn.set_deadline(Some(Instant::now() + Duration::from_millis(50)));
+        let res = conn.command(&cmd::Article::Number(1));
+        assert!(matches!(res, Err(Error::DeadlineExceeded)));
+        assert!(conn.is_poisoned());
+
+        // Streamed articles
+        let stream = Slow(io::Cursor::new(data));
+        let (mut conn, _) = NntpConnection::from_stream(stream, config).unwrap();
+        conn.set_deadline(Some(Instant::now() + Duration::from_millis(50)));
+        let res = conn
+            .article_lines(cmd::Article::Number(1))
+            .unwrap()
+            .collect::<Result<Vec<_>>>();
+        assert!(matches!(res, Err(Error::DeadlineExceeded)));
+        assert!(conn.is_poisoned());
+    }
+
+    #[test]
+    fn test_deadline_clamps_read_timeout() {
+        use std::net::TcpListener;
+
+        // A server that greets the client and then never responds
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"200 ready\r\n").unwrap();
+            std::thread::sleep(Duration::from_secs(10));
+        });
+
+        let (mut conn, _) = NntpConnection::connect(addr, ConnectionConfig::new()).unwrap();
+        let start = Instant::now();
+        conn.set_deadline(Some(start + Duration::from_millis(100)));
+        let res = conn.command(&cmd::Date);
+        assert!(matches!(res, Err(Error::DeadlineExceeded)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        // The configured timeout is restored
+        assert_eq!(conn.stream().get_ref().read_timeout().unwrap(), None);
+    }
+
+    #[test]
+    fn test_cancellable_read_timeout() {
+        let mut config = ConnectionConfig::new();
+        assert_eq!(config.effective_read_timeout(), None);
+        config.cancellation_token(Some(CancellationToken::new()));
+        assert_eq!(
+            config.effective_read_timeout(),
+            Some(DEFAULT_CANCELLABLE_READ_TIMEOUT)
+        );
+        config.read_timeout(Some(Duration::from_secs(5)));
+        assert_eq!(
+            config.effective_read_timeout(),
+            Some(Duration::from_secs(5))
+        );
+    }
+
+    #[test]
+    fn test_last_response_stats() {
+        let mut input = b"200 ready\r\n111 20200101000000\r\n".to_vec();
+        input.extend_from_slice(include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/xover_resp_xfeature_compress"
+        )));
+        let mut config = ConnectionConfig::new();
+        config.compression(Some(Compression::XFeature));
+        let (mut conn, _) =
+            NntpConnection::from_stream(MockStream::permissive(&input), config).unwrap();
+        assert_eq!(conn.last_response_stats().first_line_bytes, 11);
+
+        conn.command(&cmd::Date).unwrap();
+        assert_eq!(
+            conn.last_response_stats(),
+            ResponseStats {
+                first_line_bytes: 20,
+                data_block_bytes: 0,
+                lines: 0,
+            }
+        );
+
+        let resp = conn.command(&cmd::XOver::range((1, 10)).unwrap()).unwrap();
+        let data_blocks = resp.data_blocks().unwrap();
+        let stats = conn.last_response_stats();
+        assert_eq!(stats.first_line_bytes, 47);
+        assert_eq!(stats.data_block_bytes, data_blocks.payload_len() as u64);
+        assert_eq!(stats.lines, data_blocks.lines_len() as u64);
+        assert_eq!(stats.total_bytes(), 47 + 788);
+    }
+
+    #[test]
+    fn test_command_with_progress() {
+        let input = "200 ready\r\n\
+            222 1 <a@b> body\r\nline one\r\nline two\r\n.\r\n\
+            222 1 <a@b> body\r\nline one\r\n.\r\n\
+            111 20200101000000\r\n";
+        let (mut conn, _) =
+            NntpConnection::from_stream(MockStream::permissive(input), ConnectionConfig::new())
+                .unwrap();
+
+        let mut calls = Vec::new();
+        let resp = conn
+            .command_with_progress(&cmd::Body::Number(1), 10, |bytes| calls.push(bytes))
+            .unwrap();
+        assert_eq!(resp.data_blocks().unwrap().lines_len(), 3);
+        // Throttled to every 10 bytes, followed by the total including the terminator
+        assert_eq!(calls, vec![10, 20, 23]);
+
+        // A panicking callback does not leave the connection out of sync
+        let resp = conn
+            .command_with_progress(&cmd::Body::Number(1), 1, |_| panic!("boom"))
+            .unwrap();
+        assert_eq!(resp.data_blocks().unwrap().lines_len(), 2);
+        let resp = conn.command(&cmd::Date).unwrap();
+        assert_eq!(resp.code(), ResponseCode::from(111));
+    }
+
+    #[test]
+    fn test_compression_not_strict() {
+        let input = "200 ready\r\n\
+            224 xover information follows [COMPRESS=GZIP]\r\n.\r\n\
+            111 20200101000000\r\n";
+        let mut config = ConnectionConfig::new();
+        config.compression(Some(Compression::XFeature));
+
+        let (mut conn, _) =
+            NntpConnection::from_stream(MockStream::permissive(input), config.clone()).unwrap();
+        assert!(matches!(
+            conn.command(&cmd::XOver::range((1, 10)).unwrap()),
+            Err(Error::Decompression { .. })
+        ));
+
+        config.compression_strict(false);
+        let (mut conn, _) =
+            NntpConnection::from_stream(MockStream::permissive(input), config).unwrap();
+        let resp = conn.command(&cmd::XOver::range((1, 10)).unwrap()).unwrap();
+        assert_eq!(resp.data_blocks().unwrap().payload(), b".\r\n");
+        assert!(!conn.is_poisoned());
+
+        let resp = conn.command(&cmd::Date).unwrap();
+        assert_eq!(resp.code(), ResponseCode::Known(Kind::Date));
+    }
+
+    #[test]
+    fn test_send_binary_command() {
+        struct Binary;
+
+        impl Encode for Binary {
+            fn encode(&self) -> Vec<u8> {
+                b"XBINARY \xff\x00\xfe".to_vec()
+            }
+        }
+
+        impl NntpCommand for Binary {}
+
+        let stream = MockStream::permissive("200 ready\r\n");
+        let (mut conn, _) = NntpConnection::from_stream(stream, ConnectionConfig::new()).unwrap();
+        conn.send(&Binary).unwrap();
+
+        assert_eq!(
+            conn.stream().get_ref().written(),
+            b"XBINARY \xff\x00\xfe\r\n"
+        );
+    }
+
+    #[test]
+    fn test_command_binary_payload() {
+        let stream = MockStream::new("200 ready\r\n")
+            .expect("TAKETHIS <1@example.com>", "")
+            .expect("Subject: hi", "")
+            .expect("", "")
+            .expect(b"\xff\x00\xfe", "")
+            .expect(".", "239 <1@example.com>\r\n");
+        let (mut conn, _) = NntpConnection::from_stream(stream, ConnectionConfig::new()).unwrap();
+
+        let take_this = cmd::TakeThis {
+            message_id: "<1@example.com>".to_string(),
+            article: b"Subject: hi\r\n\r\n\xff\x00\xfe".to_vec(),
+        };
+        let resp = conn.command(&take_this).unwrap();
+        assert_eq!(resp.code(), ResponseCode::from(239));
+        assert!(conn.stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_command_expect() {
+        let stream = MockStream::new("200 ready\r\n")
+            .expect("DATE", "111 20200101000000\r\n")
+            .expect("GROUP misc.test", "411 no such group\r\n")
+            .expect("QUIT", "205 bye\r\n");
+        let (mut conn, _) = NntpConnection::from_stream(stream, ConnectionConfig::new()).unwrap();
+
+        let resp = conn.command_expect(&cmd::Date, Kind::Date).unwrap();
+        assert_eq!(resp.code(), ResponseCode::Known(Kind::Date));
+
+        match conn.command_expect(&cmd::Group("misc.test".to_string()), Kind::GroupSelected) {
+            Err(crate::error::Error::Failure { code, .. }) => {
+                assert_eq!(code, ResponseCode::Known(Kind::NoSuchNewsgroup))
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        conn.command_expect_any(&cmd::Quit, &[Kind::ConnectionClosing])
+            .unwrap();
+    }
+
+    #[test]
+    fn test_command_stages() {
+        let post = cmd::Post(b"Subject: hi\n\n.body\n".to_vec());
+
+        // Accepted
+        let stream = MockStream::new("200 ready\r\n")
+            .expect("POST", "340 send article\r\n")
+            .expect("Subject: hi", "")
+            .expect("", "")
+            .expect("..body", "")
+            .expect(".", "240 article received\r\n");
+        let (mut conn, _) = NntpConnection::from_stream(stream, ConnectionConfig::new()).unwrap();
+        let resp = conn.command_stages(&post).unwrap();
+        assert_eq!(resp.code(), ResponseCode::Known(Kind::ArticleReceivedOk));
+        assert!(conn.stream().get_ref().is_done());
+
+        // Rejected at the first stage, the article is never sent
+        let stream =
+            MockStream::new("200 ready\r\n").expect("POST", "440 posting not permitted\r\n");
+        let (mut conn, _) = NntpConnection::from_stream(stream, ConnectionConfig::new()).unwrap();
+        let resp = conn.command_stages(&post).unwrap();
+        assert_eq!(resp.code(), ResponseCode::Known(Kind::PostingNotPermitted));
+        assert_eq!(conn.stream().get_ref().written(), b"POST\r\n");
+
+        // Rejected at the second stage
+        let stream = MockStream::new("200 ready\r\n")
+            .expect("IHAVE <1@example.com>", "335 send it\r\n")
+            .expect("Subject: hi", "")
+            .expect("", "")
+            .expect("..body", "")
+            .expect(".", "437 rejected\r\n");
+        let (mut conn, _) = NntpConnection::from_stream(stream, ConnectionConfig::new()).unwrap();
+        let ihave = cmd::IHaveArticle {
+            message_id: "<1@example.com>".to_string(),
+            article: post.0.clone(),
+        };
+        let resp = conn.command_stages(&ihave).unwrap();
+        assert_eq!(resp.code(), ResponseCode::Known(Kind::TransferRejected));
+    }
+
+    #[test]
+    fn test_tls_sni_domain() {
+        for (host, domain) in &[
+            ("example.com", "example.com"),
+            ("example.com:563", "example.com"),
+            ("[2001:db8::1]", "2001:db8::1"),
+            ("[2001:db8::1]:563", "2001:db8::1"),
+            ("2001:db8::1", "2001:db8::1"),
+        ] {
+            assert_eq!(sni_domain(host), *domain);
+        }
+
+        let tls_config = TlsConfig::default_connector("[::1]:563").unwrap();
+        assert_eq!(tls_config.domain(), "::1");
+    }
+
+    #[test]
+    fn test_tls_domain_ip_literal() {
+        assert!(is_ip_literal("203.0.113.7"));
+        assert!(is_ip_literal("2001:db8::1"));
+        assert!(!is_ip_literal("news.example.com"));
+        assert!(!is_ip_literal("203.0.113.7.example.com"));
+
+        let mut config = ConnectionConfig::new();
+        config.tls_domain(Some("news.example.com:563"));
+        assert_eq!(config.tls_domain.as_deref(), Some("news.example.com"));
+        config.tls_domain(None::<&str>);
+        assert_eq!(config.tls_domain, None);
+    }
+
+    #[test]
+    fn test_custom_stream() {
+        let stream =
+            NntpStream::custom(MockStream::new("").expect("DATE", "111 20200101000000\r\n"));
+        let mut conn =
+            NntpConnection::from_stream_without_greeting(stream, ConnectionConfig::new()).unwrap();
+
+        let resp = conn.command(&cmd::Date).unwrap();
+        assert_eq!(resp.code(), ResponseCode::Known(Kind::Date));
+    }
+
+    #[test]
+    fn test_server_closed() {
+        let stream = MockStream::permissive("200 ready\r\n400 idle timeout\r\n");
+        let (mut conn, _) = NntpConnection::from_stream(stream, ConnectionConfig::new()).unwrap();
+        match conn.command(&cmd::Group("misc.test".to_string())) {
+            Err(Error::ServerClosed { code }) => {
+                assert_eq!(code, ResponseCode::Known(Kind::TemporarilyUnavailable))
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        let stream = MockStream::permissive("200 ready\r\n205 over quota\r\n205 bye\r\n");
+        let (mut conn, _) = NntpConnection::from_stream(stream, ConnectionConfig::new()).unwrap();
+        assert!(matches!(
+            conn.command(&cmd::Date),
+            Err(Error::ServerClosed { .. })
+        ));
+        // A 205 in response to QUIT is expected
+        let resp = conn.command(&cmd::Quit).unwrap();
+        assert_eq!(resp.code(), ResponseCode::Known(Kind::ConnectionClosing));
+
+        for greeting in &["400 service unavailable\r\n", "502 go away\r\n"] {
+            assert!(matches!(
+                NntpConnection::from_stream(
+                    MockStream::permissive(greeting),
+                    ConnectionConfig::new()
+                ),
+                Err(Error::ServerClosed { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_xzver() {
+        let mut input = b"200 ready\r\n".to_vec();
+        input.extend_from_slice(include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/xover_resp_xzver"
+        )));
+        let plain_resp = include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/xover_resp_plain_text"
+        ));
+
+        let (mut conn, _) =
+            NntpConnection::from_stream(MockStream::permissive(input), ConnectionConfig::new())
+                .unwrap();
+        let resp = conn.command(&cmd::XzVer::range((1, 10)).unwrap()).unwrap();
+        assert_eq!(resp.code(), ResponseCode::Known(Kind::Overview));
+
+        let expected = DataBlocks::from_payload(plain_resp);
+        let data_blocks = resp.data_blocks().unwrap();
+        assert_eq!(data_blocks.payload(), expected.payload());
+        assert_eq!(
+            data_blocks.lines().collect::<Vec<_>>(),
+            expected.lines().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_lenient_line_endings() {
+        let input = "200 ready\r\n215 list follows\r\nSubject\nFrom\r\n.\n";
+
+        let (mut conn, _) =
+            NntpConnection::from_stream(MockStream::new(input), ConnectionConfig::new()).unwrap();
+        assert!(conn.read_response(Some(true)).is_err());
+
+        let config = ConnectionConfig::new()
+            .lenient_line_endings(true)
+            .to_owned();
+        let (mut conn, _) = NntpConnection::from_stream(MockStream::new(input), config).unwrap();
+        let resp = conn.read_response(Some(true)).unwrap();
+        let data_blocks = resp.data_blocks().unwrap();
+        assert_eq!(data_blocks.payload(), b"Subject\nFrom\r\n.\n");
+        assert_eq!(
+            data_blocks.unterminated().collect::<Vec<_>>(),
+            vec![&b"Subject"[..], b"From"]
+        );
+    }
+
+    #[test]
+    fn test_lenient_line_endings_fixtures() {
+        use crate::raw::parse::strip_line_ending;
+
+        let crlf = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/text_article"));
+        let bare_lf = include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/text_article_bare_lf"
+        ));
+        let read = |fixture: &[u8], lenient: bool| {
+            let input = [&b"200 ready\r\n"[..], fixture].concat();
+            let config = ConnectionConfig::new()
+                .lenient_line_endings(lenient)
+                .to_owned();
+            let (mut conn, _) =
+                NntpConnection::from_stream(MockStream::new(&input), config).unwrap();
+            conn.read_response(Some(true))
+        };
+
+        // The strict default only accepts CRLF
+        assert!(read(bare_lf, false).is_err());
+        let strict = read(crlf, false).unwrap();
+
+        for fixture in &[&crlf[..], &bare_lf[..]] {
+            let resp = read(fixture, true).unwrap();
+            assert_eq!(resp.code(), strict.code());
+            assert_eq!(
+                strip_line_ending(resp.first_line()),
+                strip_line_ending(strict.first_line())
+            );
+            let data_blocks = resp.data_blocks().unwrap();
+            assert_eq!(
+                data_blocks.payload_len(),
+                fixture.len() - resp.first_line().len()
+            );
+            assert_eq!(
+                data_blocks.unterminated().collect::<Vec<_>>(),
+                strict
+                    .data_blocks()
+                    .unwrap()
+                    .unterminated()
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_max_first_line_len() {
+        let long = format!("200 {}\r\n", "x".repeat(DEFAULT_MAX_FIRST_LINE_LEN));
+        let err = NntpConnection::from_stream(MockStream::new(&long), ConnectionConfig::new())
+            .unwrap_err();
+        assert!(matches!(err, Error::Io(ref e) if e.kind() == ErrorKind::InvalidData));
+
+        // A server that never sends a newline
+        let input = format!("200 ready\r\n{}", "x".repeat(100));
+        let config = ConnectionConfig::new().max_first_line_len(64).to_owned();
+        let (mut conn, _) = NntpConnection::from_stream(MockStream::new(&input), config).unwrap();
+        assert!(conn.read_response(None).is_err());
+        assert!(conn.is_poisoned());
+
+        // Longer lines are accepted if the limit is raised
+        let config = ConnectionConfig::new()
+            .max_first_line_len(2 * DEFAULT_MAX_FIRST_LINE_LEN)
+            .to_owned();
+        let (_conn, resp) = NntpConnection::from_stream(MockStream::new(&long), config).unwrap();
+        assert_eq!(resp.first_line().len(), long.len());
+    }
+
+    #[test]
+    fn test_bare_response_code() {
+        let input = "200 ready\r\n111\r\n";
+        let (mut conn, _) =
+            NntpConnection::from_stream(MockStream::permissive(input), ConnectionConfig::new())
+                .unwrap();
+        let resp = conn.command(&cmd::Date).unwrap();
+        assert_eq!(resp.code(), ResponseCode::from(111));
+        assert_eq!(resp.first_line_without_code(), b"\r\n");
+    }
+
+    #[test]
+    fn test_keep_raw_headers() {
+        let input = "200 ready\r\n221 1 <1@example.com>\r\nSubject: hi\r\n\r\n.\r\n";
+        for &keep in &[false, true] {
+            let config = ConnectionConfig::new().keep_raw_headers(keep).to_owned();
+            let (mut conn, _) =
+                NntpConnection::from_stream(MockStream::new(input), config).unwrap();
+            let resp = conn.read_response(Some(true)).unwrap();
+            let head = Head::try_from(&resp).unwrap();
+            let raw = head.headers.get("Subject").unwrap().raw_content();
+            assert_eq!(raw.is_some(), keep);
+        }
+    }
+
+    #[test]
+    fn test_command_injection() {
+        let stream = MockStream::new(b"200 ready\r\n");
+        let (mut conn, _) = NntpConnection::from_stream(stream, ConnectionConfig::new()).unwrap();
+
+        let group = cmd::Group("misc.test\r\nPOST".to_string());
+        match conn.command(&group) {
+            Err(Error::InvalidCommand) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+        let ihave = cmd::IHaveArticle {
+            message_id: "<a@example.com>\nQUIT".to_string(),
+            article: b"Subject: hi\r\n\r\nbody".to_vec(),
+        };
+        assert!(conn.command_stages(&ihave).is_err());
+        assert!(conn.stream().get_ref().written().is_empty());
+
+        // Validated constructors reject the name before a command is created
+        assert!(cmd::Group::new("misc.test\r\nPOST").is_err());
+    }
+
+    #[test]
+    fn test_queue_bytes() {
+        let stream = MockStream::new(b"200 ready\r\n")
+            .expect("DATE", "111 20200101000000\r\n")
+            .expect("HELP", "100 help\r\nnone\r\n.\r\n")
+            .expect("DATE", "111 20200101000001\r\n");
+        let (mut conn, _) = NntpConnection::from_stream(stream, ConnectionConfig::new()).unwrap();
+
+        assert_eq!(conn.queue_bytes("DATE").unwrap(), 6);
+        assert_eq!(conn.queue_bytes("HELP").unwrap(), 6);
+        assert!(conn.stream().get_ref().written().is_empty());
+
+        conn.flush().unwrap();
+        assert_eq!(conn.stream().get_ref().written(), b"DATE\r\nHELP\r\n");
+
+        // Queued commands are written before a response is read
+        conn.queue_bytes("DATE").unwrap();
+        assert_eq!(
+            conn.read_response_auto().unwrap().code(),
+            ResponseCode::from(111)
+        );
+        assert_eq!(
+            conn.read_response_auto().unwrap().code(),
+            ResponseCode::from(100)
+        );
+        assert_eq!(
+            conn.read_response_auto().unwrap().code(),
+            ResponseCode::from(111)
+        );
+        assert!(conn.stream().get_ref().is_done());
+    }
+
+    #[test]
+    fn test_small_read_buffer() {
+        let mut input = b"200 ready\r\n".to_vec();
+        input.extend_from_slice(include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/xover_resp_xzver"
+        )));
+        input.extend_from_slice(b"224 overview\r\n1\ta\r\n.\r\n");
+        let plain_resp = include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/xover_resp_plain_text"
+        ));
+
+        // Both the stream and the decoder must refill many times per response
+        let config = ConnectionConfig::new().read_buffer_capacity(3).to_owned();
+        let (mut conn, initial) =
+            NntpConnection::from_stream(MockStream::permissive(input), config).unwrap();
+        assert_eq!(initial.first_line, b"200 ready\r\n");
+
+        let resp = conn.command(&cmd::XzVer::range((1, 10)).unwrap()).unwrap();
+        assert_eq!(
+            resp.data_blocks().unwrap().payload(),
+            DataBlocks::from_payload(plain_resp).payload()
+        );
+
+        let resp = conn.read_response_auto().unwrap();
+        assert_eq!(resp.data_blocks().unwrap().payload(), b"1\ta\r\n.\r\n");
+    }
+
+    #[test]
+    fn test_article_lines() {
+        let stream = MockStream::permissive(
+            "200 ready\r\n\
+            220 1 <1@example.com>\r\nSubject: test\r\n\r\nfirst\r\n..dot\r\n.\r\n\
+            430 no such article\r\n\
+            220 2 <2@example.com>\r\nSubject: dropped\r\n\r\nbody\r\n.\r\n\
+            205 bye\r\n",
+        );
+        let (mut conn, _) = NntpConnection::from_stream(stream, ConnectionConfig::new()).unwrap();
+
+        let lines = conn.article_lines(cmd::Article::Number(1)).unwrap();
+        assert_eq!(lines.response().code(), ResponseCode::Known(Kind::Article));
+        let lines = lines.collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(lines, vec![&b"Subject: test"[..], b"", b"first", b".dot"]);
+
+        let mut lines = conn.article_lines(cmd::Article::Number(42)).unwrap();
+        assert_eq!(
+            lines.response().code(),
+            ResponseCode::Known(Kind::NoArticleWithMessageId)
+        );
+        assert!(lines.next().is_none());
+        drop(lines);
+
+        // Dropping the iterator early drains the rest of the article
+        let mut lines = conn.article_lines(cmd::Article::Number(2)).unwrap();
+        assert_eq!(lines.next().unwrap().unwrap(), b"Subject: dropped");
+        drop(lines);
+
+        let resp = conn.command(&cmd::Quit).unwrap();
+        assert_eq!(resp.code(), ResponseCode::Known(Kind::ConnectionClosing));
+    }
+
+    #[test]
+    fn test_multiline_code_reads_data_blocks() {
+        let stream = MockStream::permissive(
+            "200 ready\r\n\
+            224 overview\r\n1\ta\r\n.\r\n\
+            205 bye\r\n",
+        );
+        let (mut conn, _) = NntpConnection::from_stream(stream, ConnectionConfig::new()).unwrap();
+
+        // A multiline response code always has data blocks, even if the caller says otherwise
+        conn.send(&cmd::Over::Current).unwrap();
+        let resp = conn.read_response(Some(false)).unwrap();
+        assert_eq!(resp.data_blocks().unwrap().payload(), b"1\ta\r\n.\r\n");
+
+        let resp = conn.command(&cmd::Quit).unwrap();
+        assert_eq!(resp.code(), ResponseCode::Known(Kind::ConnectionClosing));
+    }
+
+    #[test]
+    fn test_line_boundaries_buf_is_reused() {
+        let stream = MockStream::new(
+            "200 ready\r\n\
+            224 overview\r\n1\ta\r\n2\tb\r\n3\tc\r\n.\r\n\
+            224 overview\r\n4\td\r\n.\r\n",
+        );
+        let config = ConnectionConfig::new()
+            .line_boundaries_buf_size(2)
+            .data_blocks_buf_size(64)
+            .to_owned();
+        let (mut conn, _) = NntpConnection::from_stream(stream, config).unwrap();
+
+        let first = conn.read_response_auto().unwrap();
+        let lines = first.data_blocks().unwrap().lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![&b"1\ta\r\n"[..], b"2\tb\r\n", b"3\tc\r\n", b".\r\n"]
+        );
+        // The buffers were handed to the response and replaced with the configured sizes
+        assert_eq!(conn.line_boundaries_buf.capacity(), 2);
+        assert_eq!(conn.data_blocks_buf.capacity(), 64);
+
+        let second = conn.read_response_auto().unwrap();
+        let lines = second.data_blocks().unwrap().lines().collect::<Vec<_>>();
+        assert_eq!(lines, vec![&b"4\td\r\n"[..], b".\r\n"]);
+        assert!(conn.line_boundaries_buf.is_empty());
+    }
+
+    #[test]
+    fn test_local_addr() {
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, peer) = listener.accept().unwrap();
+            stream.write_all(b"200 ready\r\n").unwrap();
+            peer
+        });
+
+        let local_addr = SocketAddr::from(([127, 0, 0, 2], 0));
+        let config = ConnectionConfig::new()
+            .local_addr(Some(local_addr))
+            .connect_timeout(Some(Duration::from_secs(5)))
+            .to_owned();
+        let (_conn, resp) = NntpConnection::connect(server_addr, config).unwrap();
+        assert_eq!(resp.code(), ResponseCode::from(200));
+
+        let peer = server.join().unwrap();
+        assert_eq!(peer.ip(), local_addr.ip());
+    }
+
+    #[test]
+    fn test_command_with_timeout() {
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"200 ready\r\n").unwrap();
+            let mut reader = io::BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            stream.write_all(b"111 20200101000000\r\n").unwrap();
+            // Never respond to the second command
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+            line
+        });
+
+        let config = ConnectionConfig::new()
+            .read_timeout(Some(Duration::from_secs(5)))
+            .to_owned();
+        let (mut conn, _) = NntpConnection::connect(addr, config).unwrap();
+        let timeout = |conn: &NntpConnection| conn.stream().get_ref().read_timeout().unwrap();
+
+        let resp = conn
+            .command_with_timeout(&cmd::Date, Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(resp.code(), ResponseCode::from(111));
+        assert_eq!(timeout(&conn), Some(Duration::from_secs(5)));
+
+        // The timeout is restored when the command fails
+        let err = conn
+            .command_with_timeout(&cmd::Date, Duration::from_millis(50))
+            .unwrap_err();
+        assert!(matches!(err, Error::Io(_)));
+        assert_eq!(timeout(&conn), Some(Duration::from_secs(5)));
+
+        assert_eq!(server.join().unwrap(), "DATE\r\n");
+    }
+
+    #[test]
+    fn test_command_with_timeout_custom_stream() {
+        let stream = NntpStream::custom(MockStream::new("200 ready\r\n"));
+        let (mut conn, _) = NntpConnection::from_stream(stream, ConnectionConfig::new()).unwrap();
+        assert!(conn
+            .command_with_timeout(&cmd::Date, Duration::from_secs(1))
+            .is_err());
+    }
+
+    /// Accept a single connection and send a greeting
+    fn greeting_server() -> SocketAddr {
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"200 ready\r\n").unwrap();
+        });
+        addr
+    }
+
+    #[test]
+    fn test_tcp_options() {
+        let config = ConnectionConfig::new()
+            .tcp_nodelay(true)
+            .tcp_keepalive(Some(Duration::from_secs(30)))
+            .socket_recv_buffer(Some(64 * 1024))
+            .to_owned();
+        let (conn, _) = NntpConnection::connect(greeting_server(), config).unwrap();
+
+        assert_eq!(
+            conn.config().tcp_options(),
+            &TcpOptions {
+                nodelay: true,
+                keepalive: Some(Duration::from_secs(30)),
+                recv_buffer: Some(64 * 1024),
+            }
+        );
+
+        let tcp_stream = match conn.stream().get_ref() {
+            NntpStream::Tcp(s) => s,
+            s => panic!("Unexpected stream {:?}", s),
+        };
+        assert!(tcp_stream.nodelay().unwrap());
+
+        let socket = Socket::from(tcp_stream.try_clone().unwrap());
+        assert_eq!(socket.keepalive().unwrap(), Some(Duration::from_secs(30)));
+        // Linux doubles the requested size
+        assert!(socket.recv_buffer_size().unwrap() >= 64 * 1024);
+    }
+
+    #[test]
+    fn test_invalid_tcp_options() {
+        let config = ConnectionConfig::new()
+            .socket_recv_buffer(Some(0))
+            .to_owned();
+        let err = NntpConnection::connect(greeting_server(), config).unwrap_err();
+        assert!(matches!(err, Error::Io(e) if e.kind() == ErrorKind::InvalidInput));
+
+        let config = ConnectionConfig::new()
+            .tcp_keepalive(Some(Duration::from_millis(10)))
+            .to_owned();
+        let err = NntpConnection::connect(greeting_server(), config).unwrap_err();
+        assert!(matches!(err, Error::Io(e) if e.kind() == ErrorKind::InvalidInput));
+    }
+
+    #[test]
+    fn test_compress_deflate_inside_tls() {
+        use crate::types::command as cmd;
+        use native_tls::{Identity, TlsAcceptor};
+        use std::io::{BufRead, BufReader};
+        use std::net::TcpListener;
+
+        let identity = include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/tls/identity.p12"
+        ));
+        let compressed = include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/compress_deflate_resp"
+        ));
+
+        let acceptor =
+            TlsAcceptor::new(Identity::from_pkcs12(identity, "brokaw").unwrap()).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = std::thread::spawn(move || {
+            let (tcp_stream, _) = listener.accept().unwrap();
+            let mut tls_stream = acceptor.accept(tcp_stream).unwrap();
+            tls_stream.write_all(b"200 ready\r\n").unwrap();
+
+            let mut reader = BufReader::new(tls_stream);
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            assert_eq!(line, "COMPRESS DEFLATE\r\n");
+            reader
+                .get_mut()
+                .write_all(b"206 Compression active\r\n")
+                .unwrap();
+
+            // Everything from here on is compressed
+            let mut deflate = Deflate::new(8 * 1024);
+            let mut inflater = Inflater::new(&mut reader, Some(&mut deflate));
+            let mut commands = String::new();
+            inflater.read_line(&mut commands).unwrap();
+            inflater.get_mut().get_mut().write_all(compressed).unwrap();
+            inflater.read_line(&mut commands).unwrap();
+            commands
+        });
+
+        let tls_config = TlsConfig::new(
+            "localhost".to_string(),
+            TlsConnector::builder()
+                .danger_accept_invalid_certs(true)
+                .build()
+                .unwrap(),
+        );
+        let config = ConnectionConfig::new()
+            .tls_config(Some(tls_config))
+            .to_owned();
+        let (mut conn, _) = NntpConnection::connect(addr, config).unwrap();
+
+        let resp = conn.command(&cmd::Compress::Deflate).unwrap();
+        assert_eq!(resp.code(), ResponseCode::Known(Kind::CompressionActive));
+        assert!(conn.is_deflate_active());
+
+        let resp = conn.command(&cmd::Capabilities).unwrap();
+        assert_eq!(resp.code(), ResponseCode::Known(Kind::Capabilities));
+        let lines = resp
+            .data_blocks()
+            .unwrap()
+            .unterminated()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![&b"VERSION 2"[..], b"READER", b"COMPRESS DEFLATE"]
+        );
+
+        let resp = conn.command(&cmd::Group("misc.test".to_string())).unwrap();
+        assert_eq!(resp.first_line(), b"211 3 1 3 misc.test\r\n");
+
+        let stats = conn.stats();
+        assert_eq!(stats.bytes_compressed, compressed.len() as u64);
+        assert!(stats.bytes_decompressed > 0);
+
+        assert_eq!(
+            server.join().unwrap(),
+            "CAPABILITIES\r\nGROUP misc.test\r\n"
+        );
+    }
+
+    #[test]
+    fn test_local_addr_family_mismatch() {
+        let config = ConnectionConfig::new()
+            .local_addr(Some(SocketAddr::from(([127, 0, 0, 1], 0))))
+            .to_owned();
+        let err = NntpConnection::connect("[::1]:119", config).unwrap_err();
+        assert!(matches!(err, Error::Io(e) if e.kind() == ErrorKind::InvalidInput));
+    }
+}
@@ -1,4 +1,7 @@
-use std::net::TcpStream;
+use std::net::{SocketAddr, TcpStream};
+
+use crate::raw::compression::CompressionError;
+use crate::types::response_code::ResponseCode;
 
 /// Low level API Errors
 ///
@@ -9,7 +12,7 @@ use std::net::TcpStream;
 pub enum Error {
     /// The connection encountered some sort of I/O error
     #[error("IO {0}")]
-    Io(#[from] std::io::Error),
+    Io(#[source] std::io::Error),
     /// An error raised by the system's TLS implementation
     #[error("TLS Error -- {0}")]
     Tls(#[from] native_tls::Error),
@@ -21,9 +24,98 @@ pub enum Error {
     /// This likely indicates that either a bug in Brokaw's response parser,
     /// data corruption, or an out of spec server.
     ///
-    /// This could also occur if an unsupported compression mechanism is enabled.
+    /// Compression failures are reported as [`Error::Compression`] or
+    /// [`Error::Decompression`] instead.
     #[error("Failed to parse response")]
     Parse,
+    /// A `COMPRESS DEFLATE` stream could not be compressed or decompressed
+    ///
+    /// This indicates that compression was negotiated incorrectly or that the compressed
+    /// stream is corrupt, rather than the server sending malformed responses. The compression
+    /// contexts span the session so the connection cannot be used for further commands.
+    ///
+    /// Failures to decompress the data blocks of a single response (e.g. with
+    /// [`Compression::XFeature`](crate::raw::Compression::XFeature)) are reported
+    /// as [`Error::Decompression`].
+    #[error("Compression Error -- {0}")]
+    Compression(String),
+    /// The socket could not be bound to the requested local address
+    #[error("Failed to bind to local address {addr} -- {source}")]
+    Bind {
+        /// The local address that was requested
+        addr: SocketAddr,
+        /// The underlying error
+        source: std::io::Error,
+    },
+    /// The data blocks of a compressed response could not be decompressed
+    ///
+    /// This may indicate a corrupt stream or a server that advertised compression
+    /// (e.g. `[COMPRESS=GZIP]`) without actually compressing the response.
+    /// Unless the response could be recovered as plain text (see
+    /// [`ConnectionConfig::compression_strict`](crate::raw::ConnectionConfig::compression_strict))
+    /// the connection is left mid-response and cannot be used for further commands.
+    #[error("Decompression failed after {bytes_consumed} bytes -- {source}")]
+    Decompression {
+        /// The underlying error
+        source: std::io::Error,
+        /// The number of compressed bytes consumed before the failure
+        bytes_consumed: usize,
+    },
+    /// The server closed the connection of its own accord
+    ///
+    /// This is returned when the server responds with `400` (service temporarily unavailable)
+    /// or with `205` to a command other than `QUIT`. Providers send these when kicking idle or
+    /// over-quota connections; the connection should not be reused, but reconnecting may succeed.
+    ///
+    /// It is also returned if the server greets the client with `502` (service permanently
+    /// unavailable), in which case reconnecting is unlikely to help.
+    #[error("Server closed the connection (code {code})")]
+    ServerClosed {
+        /// The response code returned by the server
+        code: ResponseCode,
+    },
+    /// Negotiating a connection through a proxy failed
+    ///
+    /// The `code` is the reply code returned by the proxy, i.e. the SOCKS5 reply/status field
+    /// or the HTTP status code.
+    #[error("Proxy Error -- {msg} (code {code})")]
+    Proxy {
+        /// The reply code returned by the proxy
+        code: u16,
+        /// A description of the failure
+        msg: String,
+    },
+    /// The read was cancelled via a [`CancellationToken`](crate::raw::CancellationToken)
+    ///
+    /// If the response was only partially read the connection is
+    /// [poisoned](crate::raw::NntpConnection::is_poisoned) and should be discarded.
+    #[error("Cancelled")]
+    Cancelled,
+    /// A command was rejected before being sent because it contains a CR or LF
+    ///
+    /// Nothing was written so the connection can still be used.
+    /// See [`NntpCommand::contains_line_break`](crate::types::command::NntpCommand::contains_line_break).
+    #[error("Command arguments may not contain CR or LF")]
+    InvalidCommand,
+    /// The read was stopped by the deadline of
+    /// [`NntpClient::with_deadline`](crate::client::NntpClient::with_deadline)
+    ///
+    /// This is converted into [`crate::error::Error::DeadlineExceeded`] by the client.
+    /// The connection is [poisoned](crate::raw::NntpConnection::is_poisoned) since the rest
+    /// of the response may still arrive.
+    #[error("Deadline exceeded")]
+    DeadlineExceeded,
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        let is_compression = e.get_ref().map(|inner| inner.is::<CompressionError>());
+        if matches!(is_compression, Some(true)) {
+            Error::Compression(e.to_string())
+        } else {
+            Error::Io(e)
+        }
+    }
 }
 
 /// A Result returned by the low level API
@@ -1,9 +1,21 @@
+/// Cancelling long-running reads
+pub mod cancel;
+
+/// Traffic capture for debugging and test fixtures
+pub mod capture;
+
 /// Raw connection implementation
 pub mod connection;
 
 /// Low level API errors
 pub mod error;
 
+/// Hooks for observing connection traffic
+pub mod observer;
+
+/// SOCKS5 and HTTP CONNECT proxy support
+pub mod proxy;
+
 /// Response parsing logic
 ///
 /// * The parsing is line based
@@ -14,16 +26,29 @@ pub(crate) mod parse;
 /// Raw NNTP response types
 pub mod response;
 
+/// Traffic statistics
+pub mod stats;
+
 /// Raw TCP stream implementation
 pub(crate) mod stream;
 
 #[doc(inline)]
-pub use connection::{NntpConnection, TlsConfig};
+pub use cancel::CancellationToken;
+#[doc(inline)]
+pub use capture::CaptureSink;
+#[doc(inline)]
+pub use connection::{ArticleLines, ConnectionConfig, NntpConnection, TcpOptions, TlsConfig};
+#[doc(inline)]
+pub use observer::{ConnectionObserver, LogObserver};
+#[doc(inline)]
+pub use proxy::ProxyConfig;
 #[doc(inline)]
 pub use response::{DataBlocks, RawResponse};
+#[doc(inline)]
+pub use stats::{ConnectionStats, ResponseStats};
 
 #[doc(inline)]
-pub use stream::NntpStream;
+pub use stream::{NntpStream, Transport};
 
 pub(crate) mod compression;
 
@@ -0,0 +1,100 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::time::Duration;
+
+use log::*;
+
+use crate::raw::error::Error;
+use crate::raw::response::RawResponse;
+
+/// A hook for observing the traffic on an [`NntpConnection`](super::NntpConnection)
+///
+/// Observers can be used to log, trace, or collect metrics (e.g. per-command latency)
+/// without wrapping every call site. All methods have no-op default implementations.
+///
+/// # Redaction
+///
+/// Sensitive commands such as `AUTHINFO USER`, `AUTHINFO PASS`, and `AUTHINFO SASL` are redacted
+/// before they are passed to [`on_command`](Self::on_command).
+pub trait ConnectionObserver: fmt::Debug + Send {
+    /// Called after a command has been written to the stream
+    ///
+    /// The command does not include the CRLF terminator
+    fn on_command(&mut self, _command: &[u8]) {}
+
+    /// Called after a response has been read from the stream
+    ///
+    /// `elapsed` is the time since the last command was sent, or since the read began if no
+    /// command was sent (e.g. the initial greeting).
+    fn on_response(&mut self, _response: &RawResponse, _elapsed: Duration) {}
+
+    /// Called when sending a command or reading a response fails
+    fn on_error(&mut self, _error: &Error) {}
+}
+
+/// A [`ConnectionObserver`] that logs commands and responses via the [`log`] crate
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LogObserver;
+
+impl ConnectionObserver for LogObserver {
+    fn on_command(&mut self, command: &[u8]) {
+        debug!("Sent `{}`", String::from_utf8_lossy(command));
+    }
+
+    fn on_response(&mut self, response: &RawResponse, elapsed: Duration) {
+        debug!(
+            "Received `{}` in {:?}",
+            String::from_utf8_lossy(response.first_line()).trim_end(),
+            elapsed
+        );
+    }
+
+    fn on_error(&mut self, error: &Error) {
+        debug!("Connection error -- {}", error);
+    }
+}
+
+/// Commands whose arguments must never be passed to an observer
+const SENSITIVE_PREFIXES: [&[u8]; 3] = [b"AUTHINFO USER", b"AUTHINFO PASS", b"AUTHINFO SASL"];
+
+/// Redact the arguments of sensitive commands
+pub(crate) fn redact(command: &[u8]) -> Cow<'_, [u8]> {
+    let sensitive = SENSITIVE_PREFIXES.iter().find(|prefix| {
+        command.len() >= prefix.len() && command[..prefix.len()].eq_ignore_ascii_case(prefix)
+    });
+
+    match sensitive {
+        Some(prefix) => {
+            let mut redacted = command[..prefix.len()].to_vec();
+            redacted.extend_from_slice(b" <redacted>");
+            Cow::Owned(redacted)
+        }
+        None => Cow::Borrowed(command),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(
+            &*redact(b"AUTHINFO USER bob"),
+            &b"AUTHINFO USER <redacted>"[..]
+        );
+        assert_eq!(
+            &*redact(b"AUTHINFO PASS hunter2"),
+            &b"AUTHINFO PASS <redacted>"[..]
+        );
+        assert_eq!(
+            &*redact(b"authinfo pass hunter2"),
+            &b"authinfo pass <redacted>"[..]
+        );
+        assert_eq!(
+            &*redact(b"AUTHINFO SASL PLAIN AGJvYgBodW50ZXIy"),
+            &b"AUTHINFO SASL <redacted>"[..]
+        );
+        assert_eq!(&*redact(b"GROUP misc.test"), &b"GROUP misc.test"[..]);
+    }
+}
@@ -1,9 +1,9 @@
 use std::convert::TryInto;
 
 use nom::bytes::complete::take_until;
-use nom::character::complete::{crlf, one_of};
+use nom::character::complete::{char, crlf, one_of};
 use nom::combinator::all_consuming;
-use nom::sequence::{terminated, tuple};
+use nom::sequence::{preceded, terminated, tuple};
 use nom::IResult;
 
 /// The first line of an NNTP response
@@ -44,25 +44,37 @@ fn take_response_code(b: &[u8]) -> IResult<&[u8], &[u8]> {
     Ok((rest, &b[0..3]))
 }
 
-/// Returns true if the buffer only contains a `.`
-pub(crate) fn is_end_of_datablock(b: &[u8]) -> bool {
-    b == b"."
+/// Takes the text following a response code, up to but not including the terminator
+///
+/// The text is optional, so a bare response code (e.g. `205\r\n`) yields empty text.
+fn take_text<'a>(terminator: &'static str) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], &'a [u8]> {
+    move |b: &'a [u8]| {
+        if b.starts_with(terminator.as_bytes()) {
+            Ok((b, &b[..0]))
+        } else {
+            preceded(char(' '), take_until(terminator))(b)
+        }
+    }
+}
+
+/// Returns true if the line is the `.` line that terminates a multi-line data block
+///
+/// The `.` may be followed by any combination of CR and LF (e.g. `.\r\n`, or `.\n` from
+/// servers that send bare LFs) or by nothing at all (e.g. a line whose terminator was already
+/// stripped).
+pub(crate) fn is_terminator_line(line: &[u8]) -> bool {
+    line.first() == Some(&b'.') && line[1..].iter().all(|&b| b == b'\r' || b == b'\n')
 }
 
 /// Parse an first line of an NNTP response
 ///
 /// Per [RFC 3977](https://tools.ietf.org/html/rfc3977#section-3.2), the first line of an
 /// NNTP response consists of a three-digit response code, a single space, and then
-/// some text terminated with a CRLF.
+/// some text terminated with a CRLF. A bare response code (e.g. `205\r\n`) is also accepted.
 pub(crate) fn parse_first_line(b: &[u8]) -> IResult<&[u8], InitialResponseLine<'_>> {
-    let res = all_consuming(tuple((
-        take_response_code,
-        nom::character::complete::char(' '),
-        take_until("\r\n"),
-        crlf,
-    )))(b)?;
-
-    let (rest, (code, _, data, _crlf)) = res;
+    let res = all_consuming(tuple((take_response_code, take_text("\r\n"), crlf)))(b)?;
+
+    let (rest, (code, data, _crlf)) = res;
     let code = code
         .try_into()
         .expect("Code should be three bytes, there is likely a bug in the parser.");
@@ -77,11 +89,51 @@ pub(crate) fn parse_first_line(b: &[u8]) -> IResult<&[u8], InitialResponseLine<'
     ))
 }
 
+/// Parse the first line of an NNTP response that may be terminated by a bare LF
+///
+/// Lines terminated by CRLF are parsed exactly as [`parse_first_line`] does.
+pub(crate) fn parse_first_line_lenient(b: &[u8]) -> IResult<&[u8], InitialResponseLine<'_>> {
+    parse_first_line(b).or_else(|_| {
+        let (rest, (code, data, _lf)) =
+            all_consuming(tuple((take_response_code, take_text("\n"), char('\n'))))(b)?;
+        let code = code
+            .try_into()
+            .expect("Code should be three bytes, there is likely a bug in the parser.");
+
+        Ok((
+            rest,
+            InitialResponseLine {
+                code,
+                data,
+                buffer: b,
+            },
+        ))
+    })
+}
+
 /// Parse a data block line from the buffer
 pub(crate) fn parse_data_block_line(b: &[u8]) -> IResult<&[u8], &[u8]> {
     all_consuming(take_line)(b)
 }
 
+/// Parse a data block line that may be terminated by a bare LF rather than a CRLF
+pub(crate) fn parse_data_block_line_lenient(b: &[u8]) -> IResult<&[u8], &[u8]> {
+    parse_data_block_line(b).or_else(|_| all_consuming(terminated(take_until("\n"), char('\n')))(b))
+}
+
+/// Strip the line terminator from a line
+///
+/// A trailing CRLF or bare LF is removed, lines without a terminator are returned as is.
+pub(crate) fn strip_line_ending(line: &[u8]) -> &[u8] {
+    if line.ends_with(b"\r\n") {
+        &line[..line.len() - 2]
+    } else if line.ends_with(b"\n") {
+        &line[..line.len() - 1]
+    } else {
+        line
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -103,7 +155,7 @@ mod tests {
             let expected_resp = InitialResponseLine {
                 code: b"200",
                 data: &b"news.example.com InterNetNews server INN 2.5.5 ready (transit mode)"[..],
-                buffer: &MOTD,
+                buffer: MOTD,
             };
             assert_eq!(raw_response, expected_resp)
         }
@@ -114,6 +166,33 @@ mod tests {
 
             assert!(parse_first_line(&data).is_err());
         }
+
+        #[test]
+        fn test_lenient() {
+            let bare_lf = &MOTD[..MOTD.len() - 2]
+                .iter()
+                .chain(b"\n")
+                .copied()
+                .collect::<Vec<_>>();
+            assert!(parse_first_line(bare_lf).is_err());
+
+            let (_remainder, resp) = parse_first_line_lenient(bare_lf).unwrap();
+            assert_eq!(resp.code, b"200");
+            assert_eq!(resp.data, &MOTD_NO_CRLF[4..]);
+            assert_eq!(parse_first_line_lenient(MOTD), parse_first_line(MOTD));
+        }
+
+        #[test]
+        fn test_bare_code() {
+            let (_remainder, resp) = parse_first_line(b"205\r\n").unwrap();
+            assert_eq!((resp.code, resp.data), (b"205", &b""[..]));
+            let (_remainder, resp) = parse_first_line_lenient(b"205\n").unwrap();
+            assert_eq!((resp.code, resp.data), (b"205", &b""[..]));
+
+            assert!(parse_first_line(b"205\r\nmore").is_err());
+            assert!(parse_first_line(b"205text\r\n").is_err());
+            assert!(parse_first_line(b"20\r\n").is_err());
+        }
     }
 
     mod test_take_line {
@@ -146,6 +225,35 @@ mod tests {
             let (_remainder, block) = parse_data_block_line(msg).unwrap();
             assert_eq!(block, b"101 Capability list:")
         }
+
+        #[test]
+        fn test_lenient() {
+            assert_eq!(parse_data_block_line_lenient(b"abc\r\n").unwrap().1, b"abc");
+            assert_eq!(parse_data_block_line_lenient(b"abc\n").unwrap().1, b"abc");
+            assert_eq!(parse_data_block_line_lenient(b".\n").unwrap().1, b".");
+            assert!(parse_data_block_line(b"abc\n").is_err());
+            assert!(parse_data_block_line_lenient(b"abc").is_err());
+        }
+
+        #[test]
+        fn test_is_terminator_line() {
+            for line in &[&b".\r\n"[..], b".\n", b".", b".\r", b".\n\r\n"] {
+                assert!(is_terminator_line(line), "{:?}", line);
+            }
+            for line in &[&b""[..], b"..\r\n", b". \r\n", b"\r\n", b"a.\r\n", b".a"] {
+                assert!(!is_terminator_line(line), "{:?}", line);
+            }
+        }
+
+        #[test]
+        fn test_strip_line_ending() {
+            assert_eq!(strip_line_ending(b"abc\r\n"), b"abc");
+            assert_eq!(strip_line_ending(b"abc\n"), b"abc");
+            assert_eq!(strip_line_ending(b"abc\r"), b"abc\r");
+            assert_eq!(strip_line_ending(b"\n"), b"");
+            assert_eq!(strip_line_ending(b"a"), b"a");
+            assert_eq!(strip_line_ending(b""), b"");
+        }
     }
 
     mod test_parse_response_code {
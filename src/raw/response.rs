@@ -2,8 +2,9 @@ use std::borrow::Cow;
 use std::str::{from_utf8, from_utf8_unchecked};
 
 use crate::error::Error;
+use crate::raw::parse::{is_terminator_line, strip_line_ending};
 
-use crate::types::response_code::ResponseCode;
+use crate::types::response_code::{Category, Class, ResponseCode};
 
 /// A response returned by the low-level [`NntpConnection`](super::connection::NntpConnection)
 ///
@@ -24,7 +25,7 @@ impl RawResponse {
 
     /// Return true if this response is a multi-line response and contains a data block section
     pub fn has_data_blocks(&self) -> bool {
-        matches!(self.data_blocks, Some(_))
+        self.data_blocks.is_some()
     }
 
     /// Return multi-line data blocks
@@ -38,17 +39,64 @@ impl RawResponse {
     }
 
     /// Return the first line of the response without the response code
+    ///
+    /// The space following the code is also removed. For a bare response code (e.g.
+    /// `205\r\n`) only the line terminator remains.
     pub fn first_line_without_code(&self) -> &[u8] {
-        // n.b. this should be infallible barring bugs in the response parsing layer
-        &self.first_line[4..]
+        let rest = self.first_line.get(3..).unwrap_or_default();
+        if rest.starts_with(b" ") {
+            &rest[1..]
+        } else {
+            rest
+        }
     }
 
     /// Converts a response into an error if it does not match the provided status
     pub fn fail_unless(self, desired: impl Into<ResponseCode>) -> Result<RawResponse, Error> {
-        if self.code() != desired.into() {
-            Err(Error::failure(self))
+        self.fail_unless_any(&[desired.into()])
+    }
+
+    /// Converts a response into an error if it does not match any of the provided statuses
+    ///
+    /// This is useful for commands that may return one of several acceptable codes
+    /// (e.g. `MODE READER` returns either 200 or 201).
+    pub fn fail_unless_any(
+        self,
+        desired: &[impl Into<ResponseCode> + Copy],
+    ) -> Result<RawResponse, Error> {
+        let expected = desired.iter().map(|&c| c.into()).collect::<Vec<_>>();
+
+        if expected.contains(&self.code()) {
+            Ok(self)
         } else {
+            Err(Error::unexpected_code(self, &expected))
+        }
+    }
+
+    /// Converts a response into an error if its code is not of the provided class and category
+    ///
+    /// For example, `expect_kind_category(Class::Success, Category::ArticleSelection)` will accept
+    /// any `22x` response.
+    pub fn expect_kind_category(
+        self,
+        class: Class,
+        category: Category,
+    ) -> Result<RawResponse, Error> {
+        let code = self.code();
+        if code.class() == Some(class) && code.category() == Some(category) {
             Ok(self)
+        } else {
+            let msg = format!(
+                "Expected {} ({}) response, got {}",
+                class,
+                category,
+                code.describe()
+            );
+            Err(Error::Failure {
+                code,
+                resp: self,
+                msg: Some(msg),
+            })
         }
     }
 
@@ -78,11 +126,14 @@ impl RawResponse {
 /// [`DataBlocks::payload`](Self::payload) returns the raw bytes in the payload
 /// * [`DataBlocks::lines`](Self::lines) returns an iterator over the lines within the block
 /// * [`DataBlocks::unterminated`](Self::unterminated) returns an iterator over the lines with the
-/// CRLF terminator and the final `.` line of the response stripped
+///   CRLF terminator and the final `.` line of the response stripped
 #[derive(Clone, Debug)]
 pub struct DataBlocks {
     pub(crate) payload: Vec<u8>,
     pub(crate) line_boundaries: Vec<(usize, usize)>,
+    /// Retain the raw bytes of headers when parsed, see
+    /// [`ConnectionConfig::keep_raw_headers`](crate::raw::connection::ConnectionConfig::keep_raw_headers)
+    pub(crate) keep_raw_headers: bool,
 }
 
 impl DataBlocks {
@@ -106,7 +157,7 @@ impl DataBlocks {
 
     /// An iterator over the unterminated data block
     ///
-    /// 1. Lines yielded by this iterator WILL NOT include the CRLF terminator
+    /// 1. Lines yielded by this iterator WILL NOT include the CRLF (or bare LF) terminator
     /// 2. The final line of the message containing only `.` will not be returend
     pub fn unterminated(&self) -> Unterminated<'_> {
         Unterminated {
@@ -130,6 +181,30 @@ impl DataBlocks {
     }
 }
 
+#[cfg(test)]
+impl DataBlocks {
+    /// Create data blocks from a CRLF delimited payload, a trailing partial line is kept
+    pub(crate) fn from_payload(payload: &[u8]) -> Self {
+        let mut line_boundaries = vec![];
+        let mut start = 0;
+        for (i, b) in payload.iter().enumerate() {
+            if *b == b'\n' {
+                line_boundaries.push((start, i + 1));
+                start = i + 1;
+            }
+        }
+        if start < payload.len() {
+            line_boundaries.push((start, payload.len()));
+        }
+
+        Self {
+            payload: payload.to_vec(),
+            line_boundaries,
+            keep_raw_headers: false,
+        }
+    }
+}
+
 /// An iterator over the data blocks within a response
 #[derive(Clone, Debug)]
 pub struct Lines<'a> {
@@ -160,11 +235,84 @@ impl<'a> Iterator for Unterminated<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         match self.inner.next() {
-            Some(line) if line == b".\r\n" => None,
-            Some(line) => Some(&line[..line.len() - 2]),
+            Some(line) if is_terminator_line(line) => None,
+            Some(line) => Some(strip_line_ending(line)),
             None => None,
         }
-        //let foo: ()= self.data_blocks.lines().take_while(|line| line != b".\r\n");
-        //unimplemented!()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::response_code::Kind;
+
+    fn response(code: u16) -> RawResponse {
+        RawResponse {
+            code: code.into(),
+            first_line: format!("{} some text\r\n", code).into_bytes(),
+            data_blocks: None,
+        }
+    }
+
+    #[test]
+    fn test_first_line_without_code() {
+        let mut resp = response(200);
+        assert_eq!(resp.first_line_without_code(), b"some text\r\n");
+
+        for (first_line, rest) in &[
+            (&b"205\r\n"[..], &b"\r\n"[..]),
+            (b"205", b""),
+            (b"20", b""),
+            (b"", b""),
+        ] {
+            resp.first_line = first_line.to_vec();
+            assert_eq!(resp.first_line_without_code(), *rest);
+        }
+    }
+
+    #[test]
+    fn test_unterminated() {
+        for payload in &[&b"a\r\nb\r\n.\r\n"[..], b"a\nb\n.\n", b"a\r\nb\r\n."] {
+            let data_blocks = DataBlocks::from_payload(payload);
+            assert_eq!(
+                data_blocks.unterminated().collect::<Vec<_>>(),
+                vec![&b"a"[..], b"b"],
+                "{:?}",
+                payload
+            );
+        }
+    }
+
+    #[test]
+    fn test_fail_unless_any() {
+        let desired = [Kind::PostingAllowed, Kind::PostingProhibited];
+
+        assert!(response(200).fail_unless_any(&desired).is_ok());
+        assert!(response(201).fail_unless_any(&desired).is_ok());
+
+        match response(502).fail_unless_any(&desired) {
+            Err(e @ Error::Failure { .. }) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Server returned 502 Service permanently unavailable -- \
+                     Expected one of [200, 201], got permanent failure (connection)"
+                );
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_expect_kind_category() {
+        assert!(response(222)
+            .expect_kind_category(Class::Success, Category::ArticleSelection)
+            .is_ok());
+        assert!(response(423)
+            .expect_kind_category(Class::Success, Category::ArticleSelection)
+            .is_err());
+        assert!(response(211)
+            .expect_kind_category(Class::Success, Category::ArticleSelection)
+            .is_err());
     }
 }
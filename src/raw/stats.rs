@@ -0,0 +1,114 @@
+use std::io;
+use std::io::{BufRead, Read};
+
+/// A snapshot of the traffic on an [`NntpConnection`](super::NntpConnection)
+///
+/// Byte counts are measured on the wire, i.e. after `COMPRESS DEFLATE` compression for sent
+/// bytes and before any decompression for received bytes. See
+/// [`NntpConnection::stats`](super::NntpConnection::stats).
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ConnectionStats {
+    /// Bytes written to the server
+    pub bytes_sent: u64,
+    /// Bytes read from the server
+    pub bytes_received: u64,
+    /// Received bytes that were decompressed, via `COMPRESS DEFLATE` or `XFEATURE`
+    pub bytes_compressed: u64,
+    /// Bytes produced by decompressing [`bytes_compressed`](Self::bytes_compressed)
+    pub bytes_decompressed: u64,
+    /// Commands queued or sent, including each stage of a multi-stage command
+    pub commands_sent: u64,
+    /// Responses read, including the greeting
+    pub responses_read: u64,
+}
+
+impl ConnectionStats {
+    /// The number of decompressed bytes per compressed byte received
+    ///
+    /// Returns `None` if nothing has been decompressed. A ratio close to `1.0` indicates that
+    /// compression is not worth enabling for the provider.
+    pub fn compression_ratio(&self) -> Option<f64> {
+        if self.bytes_compressed == 0 {
+            return None;
+        }
+        Some(self.bytes_decompressed as f64 / self.bytes_compressed as f64)
+    }
+}
+
+/// The size of the most recent response read by an [`NntpConnection`](super::NntpConnection)
+///
+/// See [`NntpConnection::last_response_stats`](super::NntpConnection::last_response_stats).
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ResponseStats {
+    /// Bytes in the initial response line, including the CRLF
+    pub first_line_bytes: u64,
+    /// Bytes in the data blocks after any decompression, including the terminator
+    ///
+    /// This is `0` if the response had no data blocks.
+    pub data_block_bytes: u64,
+    /// Lines in the data blocks, including the terminating `.` line
+    pub lines: u64,
+}
+
+impl ResponseStats {
+    /// The total number of bytes in the response after any decompression
+    pub fn total_bytes(&self) -> u64 {
+        self.first_line_bytes + self.data_block_bytes
+    }
+}
+
+/// A reader that counts the bytes consumed from the inner reader
+pub(crate) struct Counted<'a, R> {
+    inner: R,
+    count: &'a mut u64,
+}
+
+impl<'a, R: BufRead> Counted<'a, R> {
+    pub(crate) fn new(inner: R, count: &'a mut u64) -> Self {
+        Self { inner, count }
+    }
+}
+
+impl<R: BufRead> Read for Counted<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = self.inner.read(buf)?;
+        *self.count += bytes_read as u64;
+        Ok(bytes_read)
+    }
+}
+
+impl<R: BufRead> BufRead for Counted<'_, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        *self.count += amt as u64;
+        self.inner.consume(amt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counted() {
+        let mut count = 0;
+        let mut reader = Counted::new(&b"200 ready\r\nmore"[..], &mut count);
+        let mut line = Vec::new();
+        reader.read_until(b'\n', &mut line).unwrap();
+        let mut rest = [0; 2];
+        reader.read_exact(&mut rest).unwrap();
+        assert_eq!(count, 13);
+    }
+
+    #[test]
+    fn test_compression_ratio() {
+        let mut stats = ConnectionStats::default();
+        assert_eq!(stats.compression_ratio(), None);
+        stats.bytes_compressed = 100;
+        stats.bytes_decompressed = 400;
+        assert_eq!(stats.compression_ratio(), Some(4.0));
+    }
+}
@@ -1,16 +1,106 @@
-use std::net::TcpStream;
+use std::fmt;
+use std::net::{Shutdown, TcpStream};
+use std::time::Duration;
 
 use native_tls::TlsStream;
 use std::io;
-use std::io::{Read, Write};
+use std::io::{BufRead, Read, Write};
 
 /// A raw NNTP session
-#[derive(Debug)]
 pub enum NntpStream {
     /// A stream using TLS
     Tls(TlsStream<TcpStream>),
     /// A plain text stream
     Tcp(TcpStream),
+    /// A user provided transport, e.g. an SSH tunnel
+    ///
+    /// See [`NntpStream::custom`]
+    Custom(Box<dyn Transport>),
+}
+
+/// A byte stream that can carry an NNTP session
+///
+/// This is implemented for every `Read + Write + Send` type.
+pub trait Transport: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Transport for T {}
+
+impl NntpStream {
+    /// Wrap an arbitrary stream
+    ///
+    /// This allows connections over custom transports to share a type with TCP and TLS
+    /// connections (e.g. within a pool). Note that [`NntpConnection`](super::NntpConnection)
+    /// is also generic over its stream, which avoids the boxing.
+    pub fn custom(stream: impl Transport + 'static) -> Self {
+        Self::Custom(Box::new(stream))
+    }
+
+    /// The underlying socket, beneath the TLS session if there is one
+    ///
+    /// Returns `None` for [custom](Self::custom) transports, which do not expose a socket.
+    pub fn get_tcp_ref(&self) -> Option<&TcpStream> {
+        match self {
+            NntpStream::Tls(s) => Some(s.get_ref()),
+            NntpStream::Tcp(s) => Some(s),
+            NntpStream::Custom(_) => None,
+        }
+    }
+
+    /// The read timeout of the underlying socket
+    ///
+    /// Returns an error for [custom](Self::custom) transports, which do not expose a socket.
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        self.tcp()?.read_timeout()
+    }
+
+    /// Set the read timeout of the underlying socket, see [`TcpStream::set_read_timeout`]
+    ///
+    /// Returns an error for [custom](Self::custom) transports, which do not expose a socket.
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.tcp()?.set_read_timeout(dur)
+    }
+
+    fn tcp(&self) -> io::Result<&TcpStream> {
+        self.get_tcp_ref().ok_or_else(unsupported_timeout)
+    }
+
+    /// Set the write timeout of the underlying socket, see [`TcpStream::set_write_timeout`]
+    ///
+    /// Returns an error for [custom](Self::custom) transports, which do not expose a socket.
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.tcp()?.set_write_timeout(dur)
+    }
+
+    /// Shut down the stream, sending a TLS `close_notify` first if applicable
+    ///
+    /// Custom transports are flushed, they are closed when dropped.
+    pub fn shutdown(&mut self) -> io::Result<()> {
+        match self {
+            NntpStream::Tls(s) => {
+                s.shutdown()?;
+                s.get_ref().shutdown(Shutdown::Both)
+            }
+            NntpStream::Tcp(s) => s.shutdown(Shutdown::Both),
+            NntpStream::Custom(s) => s.flush(),
+        }
+    }
+}
+
+fn unsupported_timeout() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "Timeouts are not supported by custom transports",
+    )
+}
+
+impl fmt::Debug for NntpStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NntpStream::Tls(s) => f.debug_tuple("Tls").field(s).finish(),
+            NntpStream::Tcp(s) => f.debug_tuple("Tcp").field(s).finish(),
+            NntpStream::Custom(_) => f.debug_tuple("Custom").field(&"..").finish(),
+        }
+    }
 }
 
 impl From<TlsStream<TcpStream>> for NntpStream {
@@ -30,6 +120,7 @@ impl Read for NntpStream {
         match self {
             NntpStream::Tls(s) => s.read(buf),
             NntpStream::Tcp(s) => s.read(buf),
+            NntpStream::Custom(s) => s.read(buf),
         }
     }
 }
@@ -39,6 +130,7 @@ impl Write for NntpStream {
         match self {
             NntpStream::Tls(s) => s.write(buf),
             NntpStream::Tcp(s) => s.write(buf),
+            NntpStream::Custom(s) => s.write(buf),
         }
     }
 
@@ -46,6 +138,22 @@ impl Write for NntpStream {
         match self {
             NntpStream::Tls(s) => s.flush(),
             NntpStream::Tcp(s) => s.flush(),
+            NntpStream::Custom(s) => s.flush(),
         }
     }
 }
+
+/// Implement [`Read::read`] in terms of [`BufRead`]
+///
+/// For readers whose [`consume`](BufRead::consume) does more than advance the buffer (e.g.
+/// recording or decompressing), so that every byte read passes through it.
+pub(crate) fn read_via_buf<R: BufRead>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let bytes_read = {
+        let available = reader.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        len
+    };
+    reader.consume(bytes_read);
+    Ok(bytes_read)
+}
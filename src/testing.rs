@@ -0,0 +1,241 @@
+//! Utilities for testing code that uses Brokaw without a live server
+//!
+//! The [`MockStream`] can be scripted with expected commands and canned responses,
+//! and [`fixtures`] provides responses for common commands.
+//!
+//! ```
+//! use brokaw::ClientConfig;
+//! use brokaw::testing::{fixtures, MockStream};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let stream = MockStream::new(fixtures::GREETING)
+//!     .expect("CAPABILITIES", fixtures::CAPABILITIES)
+//!     .expect("GROUP misc.test", fixtures::GROUP);
+//!
+//! let client = ClientConfig::default()
+//!     .group(Some("misc.test"))
+//!     .connect_stream(stream)?;
+//!
+//! assert_eq!(client.group().unwrap().name, "misc.test");
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::VecDeque;
+use std::io;
+use std::io::{BufRead, Cursor, Read, Write};
+
+use crate::raw::capture::{read_capture, Direction};
+
+/// An in-memory stream for testing
+///
+/// Reads return the bytes provided to [`new`](Self::new) followed by the responses to
+/// any commands registered with [`expect`](Self::expect). All writes are recorded.
+///
+/// Writing a command that was not scripted is an error, use [`permissive`](Self::permissive)
+/// for a stream that accepts anything once the script is exhausted.
+#[derive(Clone, Debug, Default)]
+pub struct MockStream {
+    input: Cursor<Vec<u8>>,
+    output: Vec<u8>,
+    pending: Vec<u8>,
+    script: VecDeque<(Vec<u8>, Vec<u8>)>,
+    permissive: bool,
+}
+
+impl MockStream {
+    /// Create a stream that will return the provided bytes when read
+    ///
+    /// For a scripted conversation this should contain the server's greeting.
+    pub fn new(input: impl AsRef<[u8]>) -> Self {
+        Self {
+            input: Cursor::new(input.as_ref().to_vec()),
+            ..Default::default()
+        }
+    }
+
+    /// Create a stream that accepts commands that were not scripted
+    ///
+    /// Commands registered with [`expect`](Self::expect) are still checked, any commands
+    /// written after the script is exhausted are recorded and ignored.
+    pub fn permissive(input: impl AsRef<[u8]>) -> Self {
+        Self {
+            permissive: true,
+            ..Self::new(input)
+        }
+    }
+
+    /// Expect a command and reply with the provided response
+    ///
+    /// * Commands must be sent in the order they are registered
+    /// * The command should not include the CRLF terminator
+    /// * Writing an unexpected or unscripted command will return an I/O error
+    pub fn expect(mut self, command: impl AsRef<[u8]>, response: impl AsRef<[u8]>) -> Self {
+        self.script
+            .push_back((command.as_ref().to_vec(), response.as_ref().to_vec()));
+        self
+    }
+
+    /// Create a stream that replays the server's side of a capture
+    ///
+    /// Captures can be created via
+    /// [`ConnectionConfig::capture`](crate::raw::connection::ConnectionConfig::capture)
+    pub fn from_capture(reader: impl BufRead) -> io::Result<Self> {
+        let input = read_capture(reader)?
+            .into_iter()
+            .filter(|(direction, _)| *direction == Direction::Received)
+            .flat_map(|(_, bytes)| bytes)
+            .collect::<Vec<_>>();
+
+        Ok(Self::permissive(input))
+    }
+
+    /// The bytes that have been written to the stream
+    pub fn written(&self) -> &[u8] {
+        &self.output
+    }
+
+    /// Returns true if all of the expected commands have been received
+    pub fn is_done(&self) -> bool {
+        self.script.is_empty()
+    }
+
+    /// Handle a complete command line written by the client
+    fn receive_command(&mut self, line: &[u8]) -> io::Result<()> {
+        let command = if line.ends_with(b"\r\n") {
+            &line[..line.len() - 2]
+        } else {
+            line
+        };
+
+        match self.script.pop_front() {
+            Some((expected, response)) if expected == command => {
+                self.input.get_mut().extend_from_slice(&response);
+                Ok(())
+            }
+            Some((expected, _)) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Expected command `{}` but received `{}`",
+                    String::from_utf8_lossy(&expected),
+                    String::from_utf8_lossy(command)
+                ),
+            )),
+            None if self.permissive => Ok(()),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Received unscripted command `{}`",
+                    String::from_utf8_lossy(command)
+                ),
+            )),
+        }
+    }
+}
+
+impl Read for MockStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+impl Write for MockStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.extend_from_slice(buf);
+        self.pending.extend_from_slice(buf);
+
+        while let Some(pos) = self.pending.iter().position(|b| *b == b'\n') {
+            let line = self.pending.drain(..=pos).collect::<Vec<_>>();
+            self.receive_command(&line)?;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Canned server responses
+pub mod fixtures {
+    use crate::types::prelude::ArticleNumber;
+
+    /// A greeting from a server that allows posting
+    pub const GREETING: &[u8] = b"200 brokaw.example.com mock server ready\r\n";
+
+    /// A response to `CAPABILITIES`
+    pub const CAPABILITIES: &[u8] = b"101 Capability list:\r\n\
+        VERSION 2\r\n\
+        READER\r\n\
+        HDR\r\n\
+        OVER MSGID\r\n\
+        LIST ACTIVE NEWSGROUPS HEADERS OVERVIEW.FMT\r\n\
+        .\r\n";
+
+    /// A response to `GROUP misc.test`
+    pub const GROUP: &[u8] = b"211 3 1 3 misc.test\r\n";
+
+    /// A response to `ARTICLE 1` while `misc.test` is selected
+    pub const ARTICLE: &[u8] = b"220 1 <1@brokaw.example.com>\r\n\
+        Path: brokaw.example.com!not-for-mail\r\n\
+        From: \"Demo User\" <nobody@example.com>\r\n\
+        Newsgroups: misc.test\r\n\
+        Subject: I am just a test article\r\n\
+        Date: 6 Oct 1998 04:38:40 -0500\r\n\
+        Message-ID: <1@brokaw.example.com>\r\n\
+        \r\n\
+        This is just a test article.\r\n\
+        .\r\n";
+
+    /// A response to `QUIT`
+    pub const QUIT: &[u8] = b"205 closing connection\r\n";
+
+    /// A response to `OVER` containing an entry for each of `numbers`
+    ///
+    /// Each entry has the subject `subject` and the message-id `<n@example.com>`.
+    pub fn overview(numbers: &[ArticleNumber]) -> String {
+        numbers
+            .iter()
+            .fold("224 overview\r\n".to_string(), |resp, n| {
+                format!("{}{}\tsubject\t\t\t<{}@example.com>\t\t\t\r\n", resp, n, n)
+            })
+            + ".\r\n"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scripted() {
+        let mut stream = MockStream::new(fixtures::GREETING).expect("QUIT", fixtures::QUIT);
+
+        let mut buf = vec![];
+        stream.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, fixtures::GREETING);
+
+        stream.write_all(b"QUIT\r\n").unwrap();
+        assert!(stream.is_done());
+
+        let mut buf = vec![];
+        stream.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, fixtures::QUIT);
+        assert_eq!(stream.written(), b"QUIT\r\n");
+    }
+
+    #[test]
+    fn test_unexpected_command() {
+        let mut stream = MockStream::new(fixtures::GREETING).expect("QUIT", fixtures::QUIT);
+        assert!(stream.write_all(b"GROUP misc.test\r\n").is_err());
+    }
+    #[test]
+    fn test_unscripted_command() {
+        let mut stream = MockStream::new(fixtures::GREETING);
+        assert!(stream.write_all(b"QUIT\r\n").is_err());
+
+        let mut stream = MockStream::permissive(fixtures::GREETING);
+        stream.write_all(b"QUIT\r\n").unwrap();
+        assert_eq!(stream.written(), b"QUIT\r\n");
+    }
+}
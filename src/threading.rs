@@ -0,0 +1,579 @@
+use std::collections::HashMap;
+use std::mem;
+use std::ops::RangeInclusive;
+
+use crate::types::response::OverviewEntry;
+
+/// Reply prefixes stripped from subjects by default
+///
+/// This includes localized variants of `Re` (e.g. German `AW` and Scandinavian `SV`).
+pub const DEFAULT_REPLY_PREFIXES: &[&str] = &["Re", "Aw", "Sv", "Vs", "Antw", "Odp", "Ynt"];
+
+/// A node in a discussion thread
+///
+/// Nodes without an [`article`](Self::article) are placeholders, either for an article that
+/// is referenced but was not provided, or for grouping articles that share a subject.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Thread<'a> {
+    /// The message-id of the node, `None` for placeholders grouping a subject
+    pub message_id: Option<&'a str>,
+    /// The article, `None` for placeholders
+    pub article: Option<&'a OverviewEntry>,
+    /// Replies to the node, oldest first
+    pub children: Vec<Thread<'a>>,
+}
+
+impl<'a> Thread<'a> {
+    /// Returns true if the node does not have an article
+    pub fn is_placeholder(&self) -> bool {
+        self.article.is_none()
+    }
+
+    /// The number of articles in the thread, including this node
+    pub fn len(&self) -> usize {
+        let own = if self.is_placeholder() { 0 } else { 1 };
+        own + self.children.iter().map(Thread::len).sum::<usize>()
+    }
+
+    /// Returns true if the thread does not contain any articles
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The earliest date in the thread, used for ordering
+    fn sort_key(&self) -> (i64, u64) {
+        match self.article {
+            Some(entry) => (
+                entry
+                    .date
+                    .as_deref()
+                    .and_then(parse_date)
+                    .unwrap_or(i64::MAX),
+                entry.number,
+            ),
+            None => self
+                .children
+                .first()
+                .map_or((i64::MAX, u64::MAX), Thread::sort_key),
+        }
+    }
+}
+
+/// Builds threads from overview entries
+///
+/// Threads are built with the algorithm described by
+/// [Jamie Zawinski](https://www.jwz.org/doc/threading.html):
+///
+/// 1. Articles are linked using their `References`, creating placeholders for missing parents
+/// 2. Placeholders without children are removed and those with a single child are replaced by it
+/// 3. Root articles with the same subject (ignoring reply prefixes) are grouped together
+/// 4. Siblings are ordered by date
+#[derive(Clone, Debug)]
+pub struct Threader {
+    reply_prefixes: Vec<String>,
+}
+
+impl Default for Threader {
+    fn default() -> Self {
+        Self {
+            reply_prefixes: DEFAULT_REPLY_PREFIXES
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+        }
+    }
+}
+
+impl Threader {
+    /// Create a threader using the [`DEFAULT_REPLY_PREFIXES`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the prefixes that mark a reply (e.g. `Re` for `Re: subject`)
+    ///
+    /// Prefixes are matched case-insensitively and may be followed by a count
+    /// (e.g. `Re[2]:`).
+    pub fn reply_prefixes(&mut self, prefixes: &[impl AsRef<str>]) -> &mut Self {
+        self.reply_prefixes = prefixes.iter().map(|p| p.as_ref().to_string()).collect();
+        self
+    }
+
+    /// Strip any reply prefixes from a subject, returning the subject and whether it is a reply
+    pub fn normalize_subject<'s>(&self, subject: &'s str) -> (&'s str, bool) {
+        let mut subject = subject.trim();
+        let mut is_reply = false;
+
+        while let Some(rest) = self
+            .reply_prefixes
+            .iter()
+            .find_map(|prefix| strip_reply_prefix(subject, prefix))
+        {
+            subject = rest.trim_start();
+            is_reply = true;
+        }
+
+        (subject, is_reply)
+    }
+
+    /// Build threads from overview entries, oldest thread first
+    pub fn build<'a>(&self, entries: &'a [OverviewEntry]) -> Vec<Thread<'a>> {
+        let mut arena = Arena::default();
+
+        for entry in entries {
+            let node = match entry.message_id.as_deref() {
+                Some(id) => match arena.get_or_create(id) {
+                    // Duplicate message-ids are kept as separate articles
+                    n if arena.nodes[n].entry.is_some() => arena.create(None),
+                    n => n,
+                },
+                None => arena.create(None),
+            };
+            arena.nodes[node].entry = Some(entry);
+
+            let mut parent = None;
+            for id in entry.references() {
+                let child = arena.get_or_create(id);
+                if let Some(parent) = parent {
+                    if arena.nodes[child].parent.is_none() && !arena.is_ancestor(child, parent) {
+                        arena.set_parent(child, parent);
+                    }
+                }
+                parent = Some(child);
+            }
+
+            // The last reference is authoritative
+            if let Some(parent) = parent {
+                if !arena.is_ancestor(node, parent) {
+                    arena.set_parent(node, parent);
+                }
+            }
+        }
+
+        let roots = (0..arena.nodes.len())
+            .filter(|&n| arena.nodes[n].parent.is_none())
+            .collect();
+        let roots = arena.prune(roots, true);
+        let roots = self.group_by_subject(&mut arena, roots);
+
+        let mut threads = roots
+            .into_iter()
+            .map(|n| arena.to_thread(n))
+            .collect::<Vec<_>>();
+        threads.sort_by_key(Thread::sort_key);
+        threads
+    }
+
+    /// Group roots with the same subject
+    fn group_by_subject(&self, arena: &mut Arena<'_>, roots: Vec<usize>) -> Vec<usize> {
+        let mut grouped: Vec<usize> = Vec::with_capacity(roots.len());
+        let mut subjects: HashMap<String, usize> = HashMap::new();
+
+        for root in roots {
+            let (subject, is_reply) = match arena.subject(root) {
+                Some(subject) => self.normalize_subject(subject),
+                None => ("", false),
+            };
+            if subject.is_empty() {
+                grouped.push(root);
+                continue;
+            }
+
+            let key = subject.to_lowercase();
+            let holder = match subjects.get(&key) {
+                Some(&holder) => holder,
+                None => {
+                    subjects.insert(key, root);
+                    grouped.push(root);
+                    continue;
+                }
+            };
+
+            let holder_is_reply = matches!(
+                arena.subject(holder).map(|s| self.normalize_subject(s).1),
+                Some(true)
+            );
+
+            let replacement = match (arena.nodes[holder].entry, arena.nodes[root].entry) {
+                (None, None) => {
+                    for child in mem::take(&mut arena.nodes[root].children) {
+                        arena.set_parent(child, holder);
+                    }
+                    None
+                }
+                (None, Some(_)) => {
+                    arena.set_parent(root, holder);
+                    None
+                }
+                (Some(_), None) => {
+                    arena.set_parent(holder, root);
+                    Some(root)
+                }
+                (Some(_), Some(_)) if is_reply && !holder_is_reply => {
+                    arena.set_parent(root, holder);
+                    None
+                }
+                (Some(_), Some(_)) if holder_is_reply && !is_reply => {
+                    arena.set_parent(holder, root);
+                    Some(root)
+                }
+                (Some(_), Some(_)) => {
+                    let placeholder = arena.create(None);
+                    arena.set_parent(holder, placeholder);
+                    arena.set_parent(root, placeholder);
+                    Some(placeholder)
+                }
+            };
+
+            if let Some(replacement) = replacement {
+                for n in grouped.iter_mut().filter(|n| **n == holder) {
+                    *n = replacement;
+                }
+                subjects.insert(key, replacement);
+            }
+        }
+
+        grouped
+    }
+}
+
+/// Build threads from overview entries using the default [`Threader`]
+pub fn build_threads(entries: &[OverviewEntry]) -> Vec<Thread<'_>> {
+    Threader::default().build(entries)
+}
+
+/// Strip a reply prefix (e.g. `Re:` or `Re[2]:`) from the start of a subject
+fn strip_reply_prefix<'s>(subject: &'s str, prefix: &str) -> Option<&'s str> {
+    let head = subject.get(..prefix.len())?;
+    if !head.eq_ignore_ascii_case(prefix) {
+        return None;
+    }
+
+    let mut rest = &subject[prefix.len()..];
+    if rest.starts_with('[') || rest.starts_with('(') {
+        let end = rest.find(&[']', ')'][..])?;
+        if !rest[1..end].chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        rest = &rest[end + 1..];
+    }
+
+    let mut chars = rest.chars();
+    match chars.next() {
+        Some(':') => Some(chars.as_str()),
+        _ => None,
+    }
+}
+
+/// Parse an RFC 5322 date (e.g. `Thu, 05 Jun 2020 10:13:41 -0700`) into seconds since the epoch
+///
+/// The date comes from the server, so out of range fields return `None` rather than overflowing.
+fn parse_date(date: &str) -> Option<i64> {
+    let date = match date.find(',') {
+        Some(i) => &date[i + 1..],
+        None => date,
+    };
+    let mut tokens = date.split_whitespace();
+
+    let day = parse_bounded(tokens.next()?, 1..=31)?;
+    let month = tokens.next()?.to_ascii_lowercase();
+    let month = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ]
+    .iter()
+    .position(|m| month.starts_with(m))? as i64
+        + 1;
+    let year = match parse_bounded(tokens.next()?, 0..=9999)? {
+        y if y < 50 => y + 2000,
+        y if y < 1000 => y + 1900,
+        y => y,
+    };
+
+    let mut time = tokens.next()?.split(':');
+    let hour = parse_bounded(time.next()?, 0..=23)?;
+    let minute = parse_bounded(time.next()?, 0..=59)?;
+    // Allow for leap seconds
+    let second = time.next().map_or(Some(0), |s| parse_bounded(s, 0..=60))?;
+
+    let offset = match tokens.next().unwrap_or("+0000") {
+        zone if zone.starts_with('+') || zone.starts_with('-') => {
+            let value = parse_bounded(&zone[1..], 0..=9959)?;
+            if value % 100 >= 60 {
+                return None;
+            }
+            let minutes = (value / 100) * 60 + value % 100;
+            if zone.starts_with('-') {
+                -minutes
+            } else {
+                minutes
+            }
+        }
+        zone => match zone.to_ascii_uppercase().as_str() {
+            "EDT" => -4 * 60,
+            "EST" | "CDT" => -5 * 60,
+            "CST" | "MDT" => -6 * 60,
+            "MST" | "PDT" => -7 * 60,
+            "PST" => -8 * 60,
+            _ => 0,
+        },
+    };
+
+    let days = days_from_civil(year, month, day);
+    Some(days * 86_400 + hour * 3_600 + minute * 60 + second - offset * 60)
+}
+
+/// Parse an unsigned decimal number, returning `None` if it is not within `range`
+fn parse_bounded(s: &str, range: RangeInclusive<i64>) -> Option<i64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok().filter(|n| range.contains(n))
+}
+
+/// The number of days since the epoch for a date in the proleptic Gregorian calendar
+///
+/// See [chrono-Compatible Low-Level Date Algorithms](http://howardhinnant.github.io/date_algorithms.html)
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[derive(Debug)]
+struct Container<'a> {
+    id: Option<&'a str>,
+    entry: Option<&'a OverviewEntry>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+/// Storage for the containers being threaded
+#[derive(Debug, Default)]
+struct Arena<'a> {
+    nodes: Vec<Container<'a>>,
+    ids: HashMap<&'a str, usize>,
+}
+
+impl<'a> Arena<'a> {
+    fn create(&mut self, id: Option<&'a str>) -> usize {
+        self.nodes.push(Container {
+            id,
+            entry: None,
+            parent: None,
+            children: Vec::new(),
+        });
+        self.nodes.len() - 1
+    }
+
+    fn get_or_create(&mut self, id: &'a str) -> usize {
+        match self.ids.get(id) {
+            Some(&n) => n,
+            None => {
+                let n = self.create(Some(id));
+                self.ids.insert(id, n);
+                n
+            }
+        }
+    }
+
+    /// Returns true if `ancestor` is `node` or one of its ancestors
+    fn is_ancestor(&self, ancestor: usize, node: usize) -> bool {
+        let mut current = Some(node);
+        while let Some(n) = current {
+            if n == ancestor {
+                return true;
+            }
+            current = self.nodes[n].parent;
+        }
+        false
+    }
+
+    fn set_parent(&mut self, child: usize, parent: usize) {
+        if let Some(old) = self.nodes[child].parent.take() {
+            self.nodes[old].children.retain(|&c| c != child);
+        }
+        self.nodes[child].parent = Some(parent);
+        self.nodes[parent].children.push(child);
+    }
+
+    /// Remove empty placeholders and replace those with a single child by the child
+    ///
+    /// Placeholders with several children are only kept at the root so that siblings
+    /// with a missing parent stay together.
+    fn prune(&mut self, nodes: Vec<usize>, is_root: bool) -> Vec<usize> {
+        let mut pruned = Vec::with_capacity(nodes.len());
+
+        for node in nodes {
+            let children = mem::take(&mut self.nodes[node].children);
+            let children = self.prune(children, false);
+
+            if self.nodes[node].entry.is_none() && (!is_root || children.len() <= 1) {
+                let parent = self.nodes[node].parent;
+                for &child in &children {
+                    self.nodes[child].parent = parent;
+                }
+                pruned.extend(children);
+            } else {
+                self.nodes[node].children = children;
+                pruned.push(node);
+            }
+        }
+
+        pruned
+    }
+
+    /// The subject of a node, or its first child for placeholders
+    fn subject(&self, node: usize) -> Option<&'a str> {
+        match self.nodes[node].entry {
+            Some(entry) => entry.subject.as_deref(),
+            None => self.nodes[node]
+                .children
+                .first()
+                .and_then(|&c| self.subject(c)),
+        }
+    }
+
+    fn to_thread(&self, node: usize) -> Thread<'a> {
+        let mut children = self.nodes[node]
+            .children
+            .iter()
+            .map(|&c| self.to_thread(c))
+            .collect::<Vec<_>>();
+        children.sort_by_key(Thread::sort_key);
+
+        Thread {
+            message_id: self.nodes[node].id,
+            article: self.nodes[node].entry,
+            children,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(number: u64, id: &str, subject: &str, references: &str) -> OverviewEntry {
+        OverviewEntry {
+            number,
+            subject: Some(subject.to_string()),
+            from: None,
+            date: Some(format!("{} Jun 2020 10:00:00 +0000", number)),
+            message_id: Some(id.to_string()),
+            references: Some(references.to_string()).filter(|r| !r.is_empty()),
+            bytes: None,
+            lines: None,
+            extra: vec![],
+        }
+    }
+
+    /// Render a thread as `id(child child)` for compact assertions
+    fn render(thread: &Thread<'_>) -> String {
+        let id = thread.message_id.unwrap_or("*");
+        if thread.children.is_empty() {
+            id.to_string()
+        } else {
+            let children = thread.children.iter().map(render).collect::<Vec<_>>();
+            format!("{}({})", id, children.join(" "))
+        }
+    }
+
+    fn render_all(threads: &[Thread<'_>]) -> Vec<String> {
+        threads.iter().map(render).collect()
+    }
+
+    #[test]
+    fn test_references() {
+        // Out of order input, siblings are sorted by date
+        let entries = vec![
+            entry(4, "d", "Re: hello", "a"),
+            entry(3, "c", "Re: hello", "a b"),
+            entry(1, "a", "hello", ""),
+            entry(2, "b", "Re: hello", "a"),
+        ];
+        let threads = build_threads(&entries);
+        assert_eq!(render_all(&threads), vec!["a(b(c) d)"]);
+        assert_eq!(threads[0].len(), 4);
+    }
+
+    #[test]
+    fn test_missing_parents() {
+        let entries = vec![
+            entry(1, "b", "one", "x"),
+            entry(2, "c", "two", "x"),
+            entry(3, "d", "three", "y"),
+        ];
+        let threads = build_threads(&entries);
+        // `x` is kept as a placeholder for its two children, `y` is replaced by its child
+        assert_eq!(render_all(&threads), vec!["x(b c)", "d"]);
+        assert!(threads[0].is_placeholder());
+    }
+
+    #[test]
+    fn test_subject_fallback() {
+        let entries = vec![
+            entry(1, "a", "Hello", ""),
+            entry(2, "b", "Re: hello", ""),
+            entry(3, "c", "AW: Re[2]: Hello", ""),
+            entry(4, "d", "Other", ""),
+            entry(5, "e", "Other", ""),
+        ];
+        let threads = build_threads(&entries);
+        assert_eq!(render_all(&threads), vec!["a(b c)", "*(d e)"]);
+
+        // Localized prefixes are configurable
+        let threads = Threader::new().reply_prefixes(&["Re"]).build(&entries);
+        assert_eq!(render_all(&threads), vec!["a(b)", "c", "*(d e)"]);
+    }
+
+    #[test]
+    fn test_reference_loop() {
+        let entries = vec![entry(1, "a", "x", "b"), entry(2, "b", "y", "a")];
+        let threads = build_threads(&entries);
+        assert_eq!(threads.iter().map(Thread::len).sum::<usize>(), 2);
+    }
+
+    #[test]
+    fn test_normalize_subject() {
+        let threader = Threader::new();
+        assert_eq!(threader.normalize_subject("Re: RE: hi"), ("hi", true));
+        assert_eq!(threader.normalize_subject("Sv(3): hi"), ("hi", true));
+        assert_eq!(
+            threader.normalize_subject("Regarding: hi"),
+            ("Regarding: hi", false)
+        );
+        assert_eq!(
+            threader.normalize_subject("Re[x]: hi"),
+            ("Re[x]: hi", false)
+        );
+        assert_eq!(threader.normalize_subject("Ré: hi"), ("Ré: hi", false));
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("Thu, 01 Jan 1970 00:00:00 +0000"), Some(0));
+        assert_eq!(parse_date("6 Oct 1998 04:38:40 -0500"), Some(907_666_720));
+        assert_eq!(
+            parse_date("Fri, 5 Jun 2020 10:13:41 PDT (Pacific)"),
+            Some(1_591_377_221)
+        );
+        assert_eq!(parse_date("6 Oct 98 09:38 GMT"), Some(907_666_680));
+        assert_eq!(parse_date("not a date"), None);
+
+        // Out of range fields from a hostile server are rejected instead of overflowing
+        assert_eq!(parse_date("1 Jan 9223372036854775807 00:00"), None);
+        assert_eq!(parse_date("1 Jan 10000 00:00"), None);
+        assert_eq!(parse_date("1 Jan 2020 00:00 +99999999999999999"), None);
+        assert_eq!(parse_date("1 Jan 2020 00:00 +0060"), None);
+        assert_eq!(parse_date("1 Jan 2020 24:00"), None);
+        assert_eq!(parse_date("1 Jan 2020 00:-1"), None);
+        assert_eq!(parse_date("99 Jan 2020 00:00"), None);
+        assert_eq!(
+            parse_date("1 Jan 2020 00:00 +9959"),
+            parse_date("27 Dec 2019 20:01 +0000")
+        );
+    }
+}
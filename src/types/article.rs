@@ -0,0 +1,432 @@
+use std::fmt;
+
+use crate::base64;
+use crate::error::{Error, Result};
+use crate::types::command::{is_valid_group_name, MessageId};
+use crate::types::response::Headers;
+
+/// The maximum length of a header line before it is folded
+///
+/// [RFC 5322 2.1.1](https://tools.ietf.org/html/rfc5322#section-2.1.1)
+const MAX_LINE_LEN: usize = 78;
+
+/// The maximum number of bytes encoded within a single RFC 2047 encoded-word
+///
+/// An encoded-word may be at most 75 characters, `=?UTF-8?B?` and `?=` take up 12 of them,
+/// leaving 60 characters of base64 (i.e. 45 bytes).
+const MAX_ENCODED_WORD_BYTES: usize = 45;
+
+/// Headers that must be present per [RFC 5536 3.1](https://tools.ietf.org/html/rfc5536#section-3.1)
+///
+/// `Date` and `Message-ID` are also required but are filled in by the server if they are
+/// omitted when posting.
+const REQUIRED_HEADERS: &[&str] = &["From", "Newsgroups", "Subject"];
+
+/// A builder for composing an article to be sent with [`Post`](crate::types::command::Post)
+/// or [`NntpClient::post`](crate::client::NntpClient::post)
+///
+/// * Header lines longer than 78 characters are folded
+/// * Header values containing non-ASCII characters are encoded per
+///   [RFC 2047](https://tools.ietf.org/html/rfc2047)
+/// * Body line endings are normalized to CRLF
+///
+/// The payload returned by [`build`](Self::build) is **not** dot-stuffed or terminated, this is
+/// handled when the article is sent (see [`encode_multiline_body`](crate::types::command::encode_multiline_body)).
+///
+/// The [`Display`](fmt::Display) implementation renders a preview of the article.
+///
+/// ```
+/// use brokaw::types::article::ArticleBuilder;
+///
+/// let article = ArticleBuilder::default()
+///     .from("Jane Doe <jane@example.com>")
+///     .newsgroups(&["misc.test"])
+///     .subject("Hello")
+///     .body_text("Hello, world!\n")
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(
+///     article,
+///     b"From: Jane Doe <jane@example.com>\r\n\
+///       Newsgroups: misc.test\r\n\
+///       Subject: Hello\r\n\
+///       \r\n\
+///       Hello, world!\r\n"
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ArticleBuilder {
+    headers: Vec<(String, String)>,
+    newsgroups: Vec<String>,
+    body: Vec<u8>,
+}
+
+impl ArticleBuilder {
+    /// Create a builder with the given headers, e.g. from
+    /// [`TextArticle::reply_template`](crate::types::response::TextArticle::reply_template)
+    ///
+    /// Folded values are unfolded, they are refolded when the article is built.
+    pub fn from_headers(headers: &Headers) -> Self {
+        let mut builder = Self::default();
+        for header in headers.iter() {
+            for content in &header.content {
+                builder.header(&header.name, content.replace("\r\n", ""));
+            }
+        }
+        if let Some(groups) = headers.get("Newsgroups").and_then(|h| h.content.first()) {
+            builder.newsgroups = groups
+                .replace("\r\n", "")
+                .split(',')
+                .map(|g| g.trim().to_string())
+                .collect();
+        }
+        builder
+    }
+
+    /// Set the `From` header
+    pub fn from(&mut self, from: impl AsRef<str>) -> &mut Self {
+        self.set_header("From", from.as_ref())
+    }
+
+    /// Set the `Newsgroups` header
+    ///
+    /// Group names are validated when the article is built.
+    pub fn newsgroups(&mut self, groups: &[&str]) -> &mut Self {
+        self.newsgroups = groups.iter().map(|g| g.to_string()).collect();
+        let value = self.newsgroups.join(",");
+        self.set_header("Newsgroups", &value)
+    }
+
+    /// Set the `Subject` header
+    pub fn subject(&mut self, subject: impl AsRef<str>) -> &mut Self {
+        self.set_header("Subject", subject.as_ref())
+    }
+
+    /// Set the `References` header, the message-ids should be ordered oldest first
+    pub fn references(&mut self, references: &[MessageId]) -> &mut Self {
+        let value = references
+            .iter()
+            .map(MessageId::as_str)
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.set_header("References", &value)
+    }
+
+    /// Set the `Supersedes` header, replacing the article with the given message-id
+    ///
+    /// See [`control`](crate::types::control) for validating the message-id.
+    pub fn supersedes(&mut self, old_id: &MessageId) -> &mut Self {
+        self.set_header("Supersedes", old_id.as_str())
+    }
+
+    /// Add a header
+    ///
+    /// Headers are written in the order they are added. Adding a header more than once will
+    /// result in multiple headers with the same name.
+    pub fn header(&mut self, name: impl AsRef<str>, value: impl AsRef<str>) -> &mut Self {
+        self.headers
+            .push((name.as_ref().to_string(), value.as_ref().to_string()));
+        self
+    }
+
+    /// Set the body of the article from text
+    pub fn body_text(&mut self, body: &str) -> &mut Self {
+        self.body_bytes(body.as_bytes())
+    }
+
+    /// Set the body of the article from raw bytes
+    pub fn body_bytes(&mut self, body: &[u8]) -> &mut Self {
+        self.body = body.to_vec();
+        self
+    }
+
+    /// Validate the article and return the bytes to post
+    ///
+    /// Returns [`Error::MissingField`] if `From`, `Newsgroups`, or `Subject` are missing,
+    /// [`Error::InvalidGroupName`] if a newsgroup is invalid, and [`Error::InvalidField`] if a
+    /// header name is invalid or a header contains a CR or LF.
+    pub fn build(&self) -> Result<Vec<u8>> {
+        for &required in REQUIRED_HEADERS {
+            match self.get_header(required) {
+                Some(value) if !value.trim().is_empty() => {}
+                _ => return Err(Error::missing_field(required)),
+            }
+        }
+
+        if let Some(name) = self.newsgroups.iter().find(|g| !is_valid_group_name(g)) {
+            return Err(Error::InvalidGroupName { name: name.clone() });
+        }
+
+        for (name, value) in &self.headers {
+            if !is_valid_header_name(name) {
+                return Err(Error::invalid_field("header name", name));
+            }
+            if value.contains(&['\r', '\n'][..]) {
+                return Err(Error::invalid_field(
+                    "header",
+                    format!("{}: {:?}", name, value),
+                ));
+            }
+        }
+
+        Ok(self.payload())
+    }
+
+    /// Render the article without validating it
+    fn payload(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        for (name, value) in &self.headers {
+            out.extend_from_slice(fold_header(name, &encode_header_value(value)).as_bytes());
+            out.extend_from_slice(b"\r\n");
+        }
+        out.extend_from_slice(b"\r\n");
+        out.extend_from_slice(&normalize_line_endings(&self.body));
+        out
+    }
+
+    fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Replace the first header with the given name, or add it if it is missing
+    fn set_header(&mut self, name: &str, value: &str) -> &mut Self {
+        match self
+            .headers
+            .iter_mut()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        {
+            Some(header) => header.1 = value.to_string(),
+            None => self.headers.push((name.to_string(), value.to_string())),
+        }
+        self
+    }
+}
+
+impl fmt::Display for ArticleBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&String::from_utf8_lossy(&self.payload()))
+    }
+}
+
+/// Header names are printable ASCII excluding `:`
+///
+/// [RFC 5322 2.2](https://tools.ietf.org/html/rfc5322#section-2.2)
+fn is_valid_header_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|b| b.is_ascii_graphic() && b != b':')
+}
+
+/// Encode the non-ASCII words of a header value as RFC 2047 encoded-words
+///
+/// Adjacent non-ASCII words are encoded together since whitespace between encoded-words is
+/// ignored when decoding. ASCII words (e.g. an address following a display name) are left as is.
+fn encode_header_value(value: &str) -> String {
+    if value.is_ascii() {
+        return value.to_string();
+    }
+
+    let mut words: Vec<String> = Vec::new();
+    let mut run: Option<String> = None;
+
+    for word in value.split(' ') {
+        if word.is_ascii() {
+            if let Some(run) = run.take() {
+                words.push(encode_words(&run));
+            }
+            words.push(word.to_string());
+        } else {
+            match &mut run {
+                Some(run) => {
+                    run.push(' ');
+                    run.push_str(word);
+                }
+                None => run = Some(word.to_string()),
+            }
+        }
+    }
+    if let Some(run) = run {
+        words.push(encode_words(&run));
+    }
+
+    words.join(" ")
+}
+
+/// Encode text as one or more space separated `B` encoded-words
+fn encode_words(text: &str) -> String {
+    let mut words = Vec::new();
+    let mut start = 0;
+
+    while start < text.len() {
+        // Split on character boundaries so each encoded-word is valid UTF-8 on its own
+        let mut end = (start + MAX_ENCODED_WORD_BYTES).min(text.len());
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        words.push(format!(
+            "=?UTF-8?B?{}?=",
+            base64::encode(&text.as_bytes()[start..end])
+        ));
+        start = end;
+    }
+
+    words.join(" ")
+}
+
+/// Render a header, folding it at whitespace so lines do not exceed [`MAX_LINE_LEN`]
+///
+/// Words that are longer than a line on their own are not broken up.
+pub(crate) fn fold_header(name: &str, value: &str) -> String {
+    let mut out = format!("{}:", name);
+    let mut line_len = out.len();
+
+    for (i, word) in value.split(' ').enumerate() {
+        // The first word always follows the header name
+        if i > 0 && line_len + 1 + word.len() > MAX_LINE_LEN && !word.is_empty() {
+            out.push_str("\r\n");
+            line_len = 0;
+        }
+        out.push(' ');
+        out.push_str(word);
+        line_len += 1 + word.len();
+    }
+
+    out
+}
+
+/// Normalize line endings to CRLF, adding a final CRLF if the body does not end with one
+fn normalize_line_endings(body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len() + body.len() / 32 + 2);
+    let mut lines = body.split(|&b| b == b'\n').peekable();
+
+    while let Some(line) = lines.next() {
+        let line = match line.split_last() {
+            Some((b'\r', rest)) => rest,
+            _ => line,
+        };
+        // The final split is empty if the body ends in a newline
+        if lines.peek().is_none() && line.is_empty() {
+            break;
+        }
+        out.extend_from_slice(line);
+        out.extend_from_slice(b"\r\n");
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder() -> ArticleBuilder {
+        let mut builder = ArticleBuilder::default();
+        builder
+            .from("jane@example.com")
+            .newsgroups(&["misc.test", "alt.test"])
+            .subject("Hello");
+        builder
+    }
+
+    #[test]
+    fn test_build() {
+        let article = builder()
+            .references(&[
+                MessageId::new("1@example.com"),
+                MessageId::new("<2@example.com>"),
+            ])
+            .header("X-Test", "yes")
+            .subject("Re: Hello")
+            .body_text("line\n.dot\r\nno newline")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(article).unwrap(),
+            "From: jane@example.com\r\n\
+             Newsgroups: misc.test,alt.test\r\n\
+             Subject: Re: Hello\r\n\
+             References: <1@example.com> <2@example.com>\r\n\
+             X-Test: yes\r\n\
+             \r\n\
+             line\r\n.dot\r\nno newline\r\n"
+        );
+    }
+
+    #[test]
+    fn test_validation() {
+        let mut missing = ArticleBuilder::default();
+        missing.from("jane@example.com").newsgroups(&["misc.test"]);
+        assert!(matches!(
+            missing.build(),
+            Err(Error::MissingField { field: "Subject" })
+        ));
+
+        assert!(matches!(
+            builder().newsgroups(&["misc.*"]).build(),
+            Err(Error::InvalidGroupName { .. })
+        ));
+
+        for (name, value) in &[
+            ("X-Evil", "a\r\nBcc: victim@example.com"),
+            ("X-Evil", "a\nb"),
+            ("Bad Name", "a"),
+            ("Bad:Name", "a"),
+        ] {
+            assert!(matches!(
+                builder().header(name, value).build(),
+                Err(Error::InvalidField { .. })
+            ));
+        }
+        assert!(builder().subject("a\rb").build().is_err());
+    }
+
+    #[test]
+    fn test_fold_header() {
+        let value = "word ".repeat(30);
+        let folded = fold_header("Subject", value.trim_end());
+        assert!(folded.split("\r\n").all(|line| line.len() <= MAX_LINE_LEN));
+        assert!(folded
+            .split("\r\n")
+            .skip(1)
+            .all(|line| line.starts_with(' ')));
+        assert_eq!(
+            folded.replace("\r\n", ""),
+            format!("Subject: {}", value.trim_end())
+        );
+
+        // A single long word is not broken up
+        let long = "x".repeat(100);
+        assert_eq!(fold_header("Subject", &long), format!("Subject: {}", long));
+    }
+
+    #[test]
+    fn test_encode_header_value() {
+        assert_eq!(encode_header_value("plain"), "plain");
+        assert_eq!(
+            encode_header_value("Jöhn Dœ <j@example.com>"),
+            "=?UTF-8?B?SsO2aG4gRMWT?= <j@example.com>"
+        );
+
+        let encoded = encode_header_value(&"é".repeat(40));
+        let words = encoded.split(' ').collect::<Vec<_>>();
+        assert_eq!(words.len(), 2);
+        assert!(words.iter().all(|w| w.len() <= 75));
+    }
+
+    #[test]
+    fn test_preview() {
+        let mut builder = builder();
+        builder.body_text("body");
+        assert_eq!(
+            builder.to_string(),
+            "From: jane@example.com\r\n\
+             Newsgroups: misc.test,alt.test\r\n\
+             Subject: Hello\r\n\
+             \r\n\
+             body\r\n"
+        );
+    }
+}
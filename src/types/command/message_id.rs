@@ -0,0 +1,47 @@
+use std::fmt;
+
+/// A globally unique message-id (e.g. `<1@example.com>`)
+///
+/// Ids are normalized to be enclosed in angle brackets, so ids taken from headers such as
+/// `References` can be used with or without them.
+///
+/// ```
+/// use brokaw::types::command::MessageId;
+///
+/// assert_eq!(MessageId::new("1@example.com").as_str(), "<1@example.com>");
+/// assert_eq!(MessageId::new("<1@example.com>").as_str(), "<1@example.com>");
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct MessageId(String);
+
+impl MessageId {
+    /// Create a message-id, adding angle brackets if they are missing
+    pub fn new(id: impl AsRef<str>) -> Self {
+        let id = id.as_ref().trim();
+        let id = id.trim_start_matches('<').trim_end_matches('>');
+        Self(format!("<{}>", id))
+    }
+
+    /// The message-id including the angle brackets
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for MessageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for MessageId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<MessageId> for String {
+    fn from(id: MessageId) -> Self {
+        id.0
+    }
+}
@@ -42,11 +42,51 @@
 ///
 /// assert_eq!(cmd.to_string(), "LISTGROUP misc.test 10-20")
 /// ```
-pub trait NntpCommand: Encode {}
+pub trait NntpCommand: Encode {
+    /// Returns true if the command contains a CR or LF that would inject another command
+    ///
+    /// Commands are rejected before being sent if this returns true. By default the encoded
+    /// command is checked, commands that carry a multi-line payload (e.g.
+    /// [`TakeThis`]) should check their arguments instead.
+    fn contains_line_break(&self) -> bool {
+        has_line_break(&self.encode())
+    }
+
+    /// Returns true if the command operates on the currently selected article
+    ///
+    /// A new connection has no current article, so these commands are not retried after
+    /// reconnecting.
+    fn uses_current_article(&self) -> bool {
+        false
+    }
+}
 
 /// A type that can be serialized for transmission
 ///
 /// A blanket implementation is provided for types implementing [`ToString`].
+///
+/// Commands whose payload is not valid UTF-8 should implement `Encode` directly
+/// (and **not** implement [`Display`](std::fmt::Display)), the bytes are sent verbatim by
+/// [`NntpConnection::send`](crate::raw::connection::NntpConnection::send).
+///
+/// # Example: A binary command
+/// ```
+/// use brokaw::types::command::{Encode, NntpCommand};
+///
+/// #[derive(Clone, Debug)]
+/// pub struct XBinary(Vec<u8>);
+///
+/// impl Encode for XBinary {
+///     fn encode(&self) -> Vec<u8> {
+///         [&b"XBINARY "[..], &self.0].concat()
+///     }
+/// }
+///
+/// impl NntpCommand for XBinary {}
+///
+/// let cmd = XBinary(vec![0xff, 0xfe]);
+/// assert_eq!(cmd.encode(), b"XBINARY \xff\xfe");
+/// ```
 pub trait Encode {
     /// Return a vector of bytes that can be sent to an NNTP server
     fn encode(&self) -> Vec<u8>;
@@ -58,6 +98,101 @@ impl<T: ToString> Encode for T {
     }
 }
 
+/// Returns true if an encoded command contains a CR or LF
+///
+/// Commands are a single line, an embedded line break in an argument (e.g. a group name taken
+/// from untrusted input) would allow it to inject another command.
+pub(crate) fn has_line_break(encoded: &[u8]) -> bool {
+    encoded.iter().any(|&b| b == b'\r' || b == b'\n')
+}
+
+/// Encode the body of a multi-line block for uploading (e.g. an article sent after `POST`)
+///
+/// * Line endings are normalized to CRLF, lines already ending in CRLF are left as is
+/// * Lines beginning with `.` are dot-stuffed
+/// * A final CRLF is added if the body does not end with a newline
+/// * The `.\r\n` terminator is appended
+///
+/// For more information see [RFC 3977 3.1.1](https://tools.ietf.org/html/rfc3977#section-3.1.1)
+pub fn encode_multiline_body(body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len() + body.len() / 32 + 5);
+
+    let body = match body.split_last() {
+        Some((b'\n', rest)) => rest,
+        _ => body,
+    };
+
+    if !body.is_empty() {
+        for line in body.split(|&b| b == b'\n') {
+            let line = match line.split_last() {
+                Some((b'\r', rest)) => rest,
+                _ => line,
+            };
+            if line.starts_with(b".") {
+                out.push(b'.');
+            }
+            out.extend_from_slice(line);
+            out.extend_from_slice(b"\r\n");
+        }
+    }
+
+    out.extend_from_slice(b".\r\n");
+    out
+}
+
+/// Define argument-less commands
+///
+/// Each command gets a unit struct, a [`Display`](std::fmt::Display) impl that writes the
+/// wire string, and an [`NntpCommand`] impl. A unit test asserting the wire strings is
+/// generated as well, so the macro should be invoked at most once per module.
+macro_rules! simple_commands {
+    ($($(#[$meta:meta])* $name:ident => $wire:literal,)+) => {
+        $(
+            $(#[$meta])*
+            #[derive(Clone, Copy, Debug)]
+            pub struct $name;
+
+            impl std::fmt::Display for $name {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    f.write_str($wire)
+                }
+            }
+
+            impl $crate::types::command::NntpCommand for $name {}
+        )+
+
+        #[cfg(test)]
+        mod simple_commands_tests {
+            use $crate::types::command::Encode;
+
+            #[test]
+            fn test_wire_format() {
+                $(assert_eq!(super::$name.encode(), $wire.as_bytes());)+
+            }
+        }
+    };
+}
+
+mod message_id;
+
+#[doc(inline)]
+pub use message_id::*;
+
+mod multistage;
+
+#[doc(inline)]
+pub use multistage::*;
+
+mod range;
+
+#[doc(inline)]
+pub use range::*;
+
+mod raw;
+
+#[doc(inline)]
+pub use raw::*;
+
 /// Commands specified in [RFC 3977](https://tools.ietf.org/html/rfc3977#appendix-B)
 mod rfc3977;
 
@@ -76,7 +211,49 @@ mod rfc4643;
 #[doc(inline)]
 pub use rfc4643::*;
 
+/// Streaming commands specified in [RFC 4644](https://tools.ietf.org/html/rfc4644)
+mod rfc4644;
+
+#[doc(inline)]
+pub use rfc4644::*;
+
+/// COMPRESS command specified in [RFC 8054](https://tools.ietf.org/html/rfc8054)
+mod rfc8054;
+
+#[doc(inline)]
+pub use rfc8054::*;
+
 mod xfeature;
 
 #[doc(inline)]
 pub use xfeature::*;
+
+mod xzver;
+
+#[doc(inline)]
+pub use xzver::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_multiline_body() {
+        assert_eq!(encode_multiline_body(b""), b".\r\n");
+        assert_eq!(encode_multiline_body(b"a\r\nb\r\n"), b"a\r\nb\r\n.\r\n");
+        assert_eq!(encode_multiline_body(b"a\nb"), b"a\r\nb\r\n.\r\n");
+        // Mixed line endings and blank lines
+        assert_eq!(
+            encode_multiline_body(b"Subject: hi\r\n\nbody\n\r\nend"),
+            &b"Subject: hi\r\n\r\nbody\r\n\r\nend\r\n.\r\n"[..]
+        );
+    }
+
+    #[test]
+    fn test_encode_multiline_body_dot_stuffing() {
+        assert_eq!(
+            encode_multiline_body(b".\n..two\r\nnot.first\n.last"),
+            &b"..\r\n...two\r\nnot.first\r\n..last\r\n.\r\n"[..]
+        );
+    }
+}
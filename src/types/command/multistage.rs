@@ -0,0 +1,115 @@
+use crate::types::command::encode_multiline_body;
+use crate::types::prelude::{NntpCommand, ResponseCode};
+
+/// A single step of a [`MultiStageCommand`]
+///
+/// Each stage is a payload to send and the response codes that allow the exchange to
+/// continue to the next stage. For the final stage these are the codes indicating success.
+#[derive(Clone, Debug)]
+pub struct Stage {
+    payload: Vec<u8>,
+    continue_on: Vec<ResponseCode>,
+    injection: bool,
+}
+
+impl Stage {
+    /// Create a stage from a raw payload
+    ///
+    /// The payload SHOULD NOT include the final CRLF terminator
+    pub fn new(
+        payload: impl Into<Vec<u8>>,
+        continue_on: &[impl Into<ResponseCode> + Copy],
+    ) -> Self {
+        Self {
+            payload: payload.into(),
+            continue_on: continue_on.iter().map(|&c| c.into()).collect(),
+            injection: false,
+        }
+    }
+
+    /// Create a stage that sends a command
+    ///
+    /// The exchange fails before anything is sent if the encoded command contains a CR or LF.
+    pub fn command<C: NntpCommand>(
+        command: &C,
+        continue_on: &[impl Into<ResponseCode> + Copy],
+    ) -> Self {
+        Self {
+            injection: command.contains_line_break(),
+            ..Self::new(command.encode(), continue_on)
+        }
+    }
+
+    /// Create a stage that uploads a multi-line block (e.g. an article)
+    ///
+    /// The body is encoded with [`encode_multiline_body`]
+    pub fn multiline_body(body: &[u8], continue_on: &[impl Into<ResponseCode> + Copy]) -> Self {
+        let mut payload = encode_multiline_body(body);
+        // The connection appends the CRLF after the terminating `.`
+        payload.truncate(payload.len() - 2);
+        Self::new(payload, continue_on)
+    }
+
+    /// The bytes sent for this stage, not including the final CRLF
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// The response codes that allow the exchange to continue
+    pub fn continue_on(&self) -> &[ResponseCode] {
+        &self.continue_on
+    }
+
+    /// Returns true if the stage is a command that contains an embedded line break
+    pub(crate) fn is_injection(&self) -> bool {
+        self.injection
+    }
+}
+
+/// An exchange that requires several round trips with the server (e.g. `POST` or `IHAVE`)
+///
+/// Multi-stage commands are run with
+/// [`NntpConnection::command_stages`](crate::raw::connection::NntpConnection::command_stages).
+/// Stages are sent in order until the server responds with a code the stage does not
+/// accept (e.g. `440 Posting not permitted`), ending the exchange early.
+///
+/// # Example: TAKETHIS
+/// ```
+/// use brokaw::types::command::{MultiStageCommand, Stage};
+/// use brokaw::types::prelude::*;
+///
+/// #[derive(Clone, Debug)]
+/// pub struct TakeThis {
+///     message_id: String,
+///     article: Vec<u8>,
+/// }
+///
+/// impl MultiStageCommand for TakeThis {
+///     fn stages(&self) -> Vec<Stage> {
+///         // TAKETHIS sends the command and article without waiting for a response
+///         let mut payload = format!("TAKETHIS {}\r\n", self.message_id).into_bytes();
+///         payload.extend_from_slice(Stage::multiline_body(&self.article, &[239]).payload());
+///         vec![Stage::new(payload, &[239])]
+///     }
+/// }
+/// ```
+pub trait MultiStageCommand {
+    /// The stages of the exchange, in the order they are sent
+    fn stages(&self) -> Vec<Stage>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::prelude::Kind;
+
+    #[test]
+    fn test_multiline_body_stage() {
+        let stage = Stage::multiline_body(b"Subject: hi\n\n.dot\n", &[Kind::ArticleReceivedOk]);
+        assert_eq!(stage.payload(), b"Subject: hi\r\n\r\n..dot\r\n.");
+        assert_eq!(
+            stage.continue_on(),
+            &[ResponseCode::Known(Kind::ArticleReceivedOk)]
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use std::fmt;
+use std::ops::{RangeFrom, RangeInclusive};
+
+use crate::error::{Error, Result};
+use crate::types::prelude::ArticleNumber;
+
+/// A range of article numbers as accepted by `OVER`, `HDR` and friends
+///
+/// For more information see [RFC 3977 8.3.2](https://tools.ietf.org/html/rfc3977#section-8.3.2)
+///
+/// Every constructor rejects closed ranges where `low` is greater than `high`, so an
+/// `ArticleRange` is always valid. Ranges can be created from a single number, a `(low, high)`
+/// tuple, or a Rust range, see [`IntoArticleRange`]:
+///
+/// ```
+/// use brokaw::types::command::{ArticleRange, IntoArticleRange};
+///
+/// assert_eq!(10.into_article_range().unwrap().to_string(), "10");
+/// assert_eq!((10, 20).into_article_range().unwrap().to_string(), "10-20");
+/// assert_eq!((10..=20).into_article_range().unwrap().to_string(), "10-20");
+/// assert_eq!((10..).into_article_range().unwrap().to_string(), "10-");
+/// assert!((20, 10).into_article_range().is_err());
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ArticleRange(Bounds);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Bounds {
+    Single(ArticleNumber),
+    Closed(ArticleNumber, ArticleNumber),
+    From(ArticleNumber),
+}
+
+impl ArticleRange {
+    /// A single article, `n`
+    pub fn single(n: ArticleNumber) -> Self {
+        ArticleRange(Bounds::Single(n))
+    }
+
+    /// All articles between `low` and `high` inclusive, `low-high`
+    ///
+    /// Returns [`Error::InvalidRange`] if `low` is greater than `high`
+    pub fn closed(low: ArticleNumber, high: ArticleNumber) -> Result<Self> {
+        if low > high {
+            return Err(Error::InvalidRange { low, high });
+        }
+        Ok(ArticleRange(Bounds::Closed(low, high)))
+    }
+
+    /// All articles numbered `low` or higher, `low-`
+    pub fn from_low(low: ArticleNumber) -> Self {
+        ArticleRange(Bounds::From(low))
+    }
+
+    /// The lowest article number in the range
+    pub fn low(&self) -> ArticleNumber {
+        match self.0 {
+            Bounds::Single(n) => n,
+            Bounds::Closed(low, _) | Bounds::From(low) => low,
+        }
+    }
+
+    /// The highest article number in the range, `None` if the range is open-ended
+    pub fn high(&self) -> Option<ArticleNumber> {
+        match self.0 {
+            Bounds::Single(n) => Some(n),
+            Bounds::Closed(_, high) => Some(high),
+            Bounds::From(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for ArticleRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Bounds::Single(n) => write!(f, "{}", n),
+            Bounds::Closed(low, high) => write!(f, "{}-{}", low, high),
+            Bounds::From(low) => write!(f, "{}-", low),
+        }
+    }
+}
+
+/// Conversion into a validated [`ArticleRange`]
+///
+/// Implemented for `ArticleRange`, a single [`ArticleNumber`], a `(low, high)` tuple, `low..=high`
+/// and `low..`.
+pub trait IntoArticleRange {
+    /// Convert `self` into a range, returning [`Error::InvalidRange`] if `low > high`
+    fn into_article_range(self) -> Result<ArticleRange>;
+}
+
+impl IntoArticleRange for ArticleRange {
+    fn into_article_range(self) -> Result<ArticleRange> {
+        Ok(self)
+    }
+}
+
+impl IntoArticleRange for ArticleNumber {
+    fn into_article_range(self) -> Result<ArticleRange> {
+        Ok(ArticleRange::single(self))
+    }
+}
+
+impl IntoArticleRange for (ArticleNumber, ArticleNumber) {
+    fn into_article_range(self) -> Result<ArticleRange> {
+        ArticleRange::closed(self.0, self.1)
+    }
+}
+
+impl IntoArticleRange for RangeInclusive<ArticleNumber> {
+    fn into_article_range(self) -> Result<ArticleRange> {
+        ArticleRange::closed(*self.start(), *self.end())
+    }
+}
+
+impl IntoArticleRange for RangeFrom<ArticleNumber> {
+    fn into_article_range(self) -> Result<ArticleRange> {
+        Ok(ArticleRange::from_low(self.start))
+    }
+}
+
+/// An iterator that splits a range of articles into chunks
+///
+/// Servers often limit the size of the ranges accepted by `OVER` and `HDR`. Each chunk is a
+/// `(low, high)` pair covering at most `chunk_size` articles.
+///
+/// ```
+/// use brokaw::types::command::RangeChunker;
+///
+/// let chunks = RangeChunker::new(1, 250, 100).collect::<Vec<_>>();
+/// assert_eq!(chunks, vec![(1, 100), (101, 200), (201, 250)]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct RangeChunker {
+    next: Option<ArticleNumber>,
+    high: ArticleNumber,
+    chunk_size: ArticleNumber,
+}
+
+impl RangeChunker {
+    /// Split `low..=high` into chunks of at most `chunk_size` articles
+    ///
+    /// A `chunk_size` of `0` is treated as `1`, and no chunks are yielded if `low > high`.
+    pub fn new(low: ArticleNumber, high: ArticleNumber, chunk_size: ArticleNumber) -> Self {
+        Self {
+            next: Some(low).filter(|&low| low <= high),
+            high,
+            chunk_size: chunk_size.max(1),
+        }
+    }
+}
+
+impl Iterator for RangeChunker {
+    type Item = (ArticleNumber, ArticleNumber);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let low = self.next?;
+        let high = low.saturating_add(self.chunk_size - 1).min(self.high);
+        self.next = high.checked_add(1).filter(|&next| next <= self.high);
+        Some((low, high))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert!(ArticleRange::closed(1, 1).is_ok());
+        assert!(matches!(
+            ArticleRange::closed(2, 1),
+            Err(Error::InvalidRange { low: 2, high: 1 })
+        ));
+        assert!(matches!(
+            (2, 1).into_article_range(),
+            Err(Error::InvalidRange { low: 2, high: 1 })
+        ));
+        assert!(RangeInclusive::new(2, 1).into_article_range().is_err());
+        assert!((2..).into_article_range().is_ok());
+    }
+
+    #[test]
+    fn test_bounds() {
+        let range = ArticleRange::single(5);
+        assert_eq!((range.low(), range.high()), (5, Some(5)));
+        let range = ArticleRange::closed(5, 9).unwrap();
+        assert_eq!((range.low(), range.high()), (5, Some(9)));
+        let range = ArticleRange::from_low(5);
+        assert_eq!((range.low(), range.high()), (5, None));
+    }
+
+    #[test]
+    fn test_chunker() {
+        assert_eq!(
+            RangeChunker::new(5, 9, 2).collect::<Vec<_>>(),
+            vec![(5, 6), (7, 8), (9, 9)]
+        );
+        assert_eq!(RangeChunker::new(5, 5, 0).collect::<Vec<_>>(), vec![(5, 5)]);
+        assert_eq!(RangeChunker::new(6, 5, 10).count(), 0);
+        assert_eq!(
+            RangeChunker::new(ArticleNumber::MAX - 1, ArticleNumber::MAX, 10).collect::<Vec<_>>(),
+            vec![(ArticleNumber::MAX - 1, ArticleNumber::MAX)]
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use std::fmt;
+
+use crate::error::{Error, Result};
+use crate::types::command::NntpCommand;
+
+/// An arbitrary command sent exactly as given
+///
+/// This is useful for commands that are not implemented by the library, the command is sent
+/// through the same path as the typed commands so the response can be read with
+/// [`command_multiline`](crate::raw::connection::NntpConnection::command_multiline) or
+/// converted with [`command_as`](crate::client::NntpClient::command_as).
+///
+/// ```
+/// use brokaw::types::command::{Encode, Raw};
+///
+/// let cmd = Raw::new("LISTGROUP misc.test 10-20").unwrap();
+/// assert_eq!(cmd.encode(), b"LISTGROUP misc.test 10-20");
+///
+/// assert!(Raw::new("GROUP misc.test\r\nPOST").is_err());
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Raw(String);
+
+impl Raw {
+    /// Create a command, returning [`Error::InvalidField`] if it contains a CR or LF
+    ///
+    /// Embedded line breaks would allow a single value to smuggle in additional commands.
+    pub fn new(command: impl Into<String>) -> Result<Self> {
+        let command = command.into();
+        if command.contains(&['\r', '\n'][..]) {
+            return Err(Error::invalid_field("command", command));
+        }
+        Ok(Self(command))
+    }
+
+    /// The command as it will be sent
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Raw {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl NntpCommand for Raw {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::command::Encode;
+
+    #[test]
+    fn test_encode() {
+        let cmd = Raw::new("XZVER 1-100").unwrap();
+        assert_eq!(cmd.encode(), b"XZVER 1-100");
+        assert_eq!(Raw::new("").unwrap().encode(), b"");
+    }
+
+    #[test]
+    fn test_rejects_line_breaks() {
+        for &c in &["QUIT\r\n", "GROUP a\nQUIT", "GROUP a\rQUIT"] {
+            assert!(matches!(
+                Raw::new(c),
+                Err(Error::InvalidField {
+                    field: "command",
+                    ..
+                })
+            ));
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use std::fmt;
 
-use crate::types::prelude::{ArticleNumber, NntpCommand};
+use crate::error::Result;
+use crate::types::command::{ArticleRange, IntoArticleRange};
+use crate::types::prelude::NntpCommand;
 
 /// Retrieve a specific header from one or more articles
 #[derive(Clone, Debug)]
@@ -13,13 +15,19 @@ pub enum XHdr {
         id: String,
     },
     /// A range of messages
+    ///
+    /// Replaces the `Range { low, high }` variant of earlier releases, use
+    /// [`XHdr::range`] e.g. `XHdr::range(header, (low, high))?`.
     Range {
         /// The name of the header to retrieve
         header: String,
-        /// The low number of the article range
-        low: ArticleNumber,
-        /// The high number of the article range
-        high: ArticleNumber,
+        /// The range of articles
+        range: ArticleRange,
+    },
+    /// The current message
+    Current {
+        /// The name of the header to retrieve
+        header: String,
     },
 }
 
@@ -27,7 +35,40 @@ impl fmt::Display for XHdr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             XHdr::MessageId { header, id } => write!(f, "XHDR {} {}", header, id),
-            XHdr::Range { header, low, high } => write!(f, "XHDR {} {}-{}", header, low, high),
+            XHdr::Range { header, range } => write!(f, "XHDR {} {}", header, range),
+            XHdr::Current { header } => write!(f, "XHDR {}", header),
+        }
+    }
+}
+
+impl NntpCommand for XHdr {
+    fn uses_current_article(&self) -> bool {
+        matches!(self, XHdr::Current { .. })
+    }
+}
+
+impl XHdr {
+    /// Retrieve a header for a range of articles
+    ///
+    /// Returns [`Error::InvalidRange`](crate::error::Error::InvalidRange) if `low` is greater
+    /// than `high`
+    pub fn range(header: impl Into<String>, range: impl IntoArticleRange) -> Result<Self> {
+        Ok(XHdr::Range {
+            header: header.into(),
+            range: range.into_article_range()?,
+        })
+    }
+}
+
+impl From<super::Hdr> for XHdr {
+    fn from(hdr: super::Hdr) -> Self {
+        match hdr {
+            super::Hdr::MessageId { field, id } => XHdr::MessageId { header: field, id },
+            super::Hdr::Range { field, range } => XHdr::Range {
+                header: field,
+                range,
+            },
+            super::Hdr::Current { field } => XHdr::Current { header: field },
         }
     }
 }
@@ -36,23 +77,80 @@ impl fmt::Display for XHdr {
 #[derive(Copy, Clone, Debug)]
 pub enum XOver {
     /// A range of messages
-    Range {
-        /// The low number of the article range
-        low: ArticleNumber,
-        /// The high number of the article range
-        high: ArticleNumber,
-    },
+    ///
+    /// Replaces the `Range { low, high }` variant of earlier releases, use
+    /// [`XOver::range`] e.g. `XOver::range((low, high))?`.
+    Range(ArticleRange),
     /// The current message
     Current,
 }
 
+impl XOver {
+    /// Retrieve the headers for a range of articles
+    ///
+    /// Returns [`Error::InvalidRange`](crate::error::Error::InvalidRange) if `low` is greater
+    /// than `high`
+    pub fn range(range: impl IntoArticleRange) -> Result<Self> {
+        Ok(XOver::Range(range.into_article_range()?))
+    }
+}
+
 impl fmt::Display for XOver {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            XOver::Range { low, high } => write!(f, "XOVER {}-{}", low, high),
+            XOver::Range(range) => write!(f, "XOVER {}", range),
             XOver::Current => write!(f, "XOVER"),
         }
     }
 }
 
-impl NntpCommand for XOver {}
+impl NntpCommand for XOver {
+    fn uses_current_article(&self) -> bool {
+        matches!(self, XOver::Current)
+    }
+}
+
+simple_commands! {
+    /// Indicate to the server that this client is a slave server
+    ///
+    /// This is used in some transit/peering setups so that the server can give priority to
+    /// slaves over regular readers.
+    ///
+    /// Servers typically respond with `202 slave status noted`.
+    Slave => "SLAVE",
+
+    /// Switch a transit connection to streaming mode
+    ///
+    /// Streaming mode allows articles to be offered with `CHECK`/`TAKETHIS` without waiting
+    /// for each response. Servers respond with `203` if streaming is permitted.
+    ModeStream => "MODE STREAM",
+}
+
+/// Retrieve the storage path(s) of an article by message-id
+///
+/// `XPATH` is non-standard and server-dependent, servers that support it respond with
+/// `223` followed by one or more paths.
+#[derive(Clone, Debug)]
+pub struct XPath(pub String);
+
+impl fmt::Display for XPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "XPATH {}", self.0)
+    }
+}
+
+impl NntpCommand for XPath {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::command::Encode;
+
+    #[test]
+    fn test_xpath() {
+        assert_eq!(
+            XPath("<1@example.com>".to_string()).encode(),
+            b"XPATH <1@example.com>"
+        );
+    }
+}
@@ -1,6 +1,9 @@
 use std::fmt;
+use std::str::FromStr;
 
-use crate::types::prelude::{ArticleNumber, NntpCommand};
+use crate::error::{Error, Result};
+use crate::types::command::{ArticleRange, IntoArticleRange, MultiStageCommand, Stage};
+use crate::types::prelude::{ArticleNumber, Kind, NntpCommand};
 
 /// Retrieve an article's header and body
 #[derive(Clone, Debug)]
@@ -23,7 +26,11 @@ impl fmt::Display for Article {
     }
 }
 
-impl NntpCommand for Article {}
+impl NntpCommand for Article {
+    fn uses_current_article(&self) -> bool {
+        matches!(self, Article::Current)
+    }
+}
 
 /// Retrieve the body for an Article
 #[derive(Clone, Debug)]
@@ -46,43 +53,293 @@ impl fmt::Display for Body {
     }
 }
 
-impl NntpCommand for Body {}
+impl NntpCommand for Body {
+    fn uses_current_article(&self) -> bool {
+        matches!(self, Body::Current)
+    }
+}
+
+simple_commands! {
+    /// Get the capabilities provided by the server
+    Capabilities => "CAPABILITIES",
+
+    /// Get the server time
+    Date => "DATE",
+
+    /// Retrieve help text about the servers capabilities
+    Help => "HELP",
+
+    /// Attempt to set the current article to the previous article number
+    Last => "LAST",
+
+    /// Enable reader mode on a mode switching server
+    ModeReader => "MODE READER",
+
+    /// Attempt to set the current article to the next article number
+    Next => "NEXT",
+
+    /// Close the connection
+    Quit => "QUIT",
+}
+
+/// Select a group
+///
+/// Prefer [`Group::new`] which validates the name, constructing the command directly will
+/// send the name verbatim.
+#[derive(Clone, Debug)]
+pub struct Group(pub String);
+
+impl Group {
+    /// Create a `GROUP` command, returning [`Error::InvalidGroupName`] if the name is invalid
+    ///
+    /// Per [RFC 3977 4.1](https://tools.ietf.org/html/rfc3977#section-4.1) a group name may
+    /// not be empty, contain whitespace or control characters, or contain any of the wildmat
+    /// characters `!*,?[\]`. Non-ASCII UTF-8 is permitted by the RFC. Names beginning with
+    /// `.` are also rejected.
+    pub fn new(name: impl AsRef<str>) -> Result<Self> {
+        let name = name.as_ref();
+        if is_valid_group_name(name) {
+            Ok(Group(name.to_string()))
+        } else {
+            Err(Error::InvalidGroupName {
+                name: name.to_string(),
+            })
+        }
+    }
+}
 
-/// Get the capabilities provided by the server
-#[derive(Clone, Copy, Debug)]
-pub struct Capabilities;
+pub(crate) fn is_valid_group_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| match c {
+            '!' | '*' | ',' | '?' | '[' | '\\' | ']' => false,
+            c if c.is_ascii() => c.is_ascii_graphic(),
+            c => !c.is_control() && !c.is_whitespace(),
+        })
+}
 
-impl fmt::Display for Capabilities {
+impl fmt::Display for Group {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "CAPABILITIES")
+        write!(f, "GROUP {}", self.0)
+    }
+}
+
+impl NntpCommand for Group {}
+
+/// A date argument for commands such as [`NewGroups`]
+///
+/// Dates are sent as `yyyymmdd` unless [`two_digit_year`](Self::two_digit_year) is used,
+/// in which case the legacy `yymmdd` form is sent.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct NntpDate {
+    year: u16,
+    month: u8,
+    day: u8,
+    two_digit_year: bool,
+}
+
+impl NntpDate {
+    /// Create a date, returning `None` if any component is out of range
+    pub fn new(year: u16, month: u8, day: u8) -> Option<Self> {
+        if year > 9999 || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+
+        Some(Self {
+            year,
+            month,
+            day,
+            two_digit_year: false,
+        })
+    }
+
+    /// Send the date in the `yymmdd` form
+    ///
+    /// Per [RFC 3977 7.3.2](https://tools.ietf.org/html/rfc3977#section-7.3.2) the server
+    /// picks the century closest to the current year.
+    pub fn two_digit_year(mut self) -> Self {
+        self.two_digit_year = true;
+        self
     }
 }
 
-impl NntpCommand for Capabilities {}
+impl FromStr for NntpDate {
+    type Err = Error;
 
-/// Get the server time
-#[derive(Clone, Copy, Debug)]
-struct Date;
+    /// Parse a date in the `yyyymmdd` or legacy `yymmdd` form
+    ///
+    /// Returns [`Error::InvalidField`] unless the date is exactly 6 or 8 digits and valid.
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::invalid_field("date", s);
+        let (year, rest, two_digit_year) = match parse_digits(s).ok_or_else(invalid)? {
+            8 => (&s[..4], &s[4..], false),
+            6 => (&s[..2], &s[2..], true),
+            _ => return Err(invalid()),
+        };
+
+        let date = NntpDate::new(
+            year.parse().map_err(|_| invalid())?,
+            rest[..2].parse().map_err(|_| invalid())?,
+            rest[2..].parse().map_err(|_| invalid())?,
+        )
+        .ok_or_else(invalid)?;
+
+        Ok(if two_digit_year {
+            date.two_digit_year()
+        } else {
+            date
+        })
+    }
+}
 
-impl fmt::Display for Date {
+impl fmt::Display for NntpDate {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "DATE")
+        if self.two_digit_year {
+            write!(f, "{:02}{:02}{:02}", self.year % 100, self.month, self.day)
+        } else {
+            write!(f, "{:04}{:02}{:02}", self.year, self.month, self.day)
+        }
     }
 }
 
-impl NntpCommand for Date {}
+/// A time argument for commands such as [`NewGroups`], sent as `hhmmss`
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct NntpTime {
+    hour: u8,
+    minute: u8,
+    second: u8,
+}
 
-/// Select a group
+impl NntpTime {
+    /// Create a time, returning `None` if any component is out of range
+    pub fn new(hour: u8, minute: u8, second: u8) -> Option<Self> {
+        if hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+
+        Some(Self {
+            hour,
+            minute,
+            second,
+        })
+    }
+}
+
+impl FromStr for NntpTime {
+    type Err = Error;
+
+    /// Parse a time in the `hhmmss` form
+    ///
+    /// Returns [`Error::InvalidField`] unless the time is exactly 6 digits and valid.
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::invalid_field("time", s);
+        if parse_digits(s) != Some(6) {
+            return Err(invalid());
+        }
+
+        NntpTime::new(
+            s[..2].parse().map_err(|_| invalid())?,
+            s[2..4].parse().map_err(|_| invalid())?,
+            s[4..].parse().map_err(|_| invalid())?,
+        )
+        .ok_or_else(invalid)
+    }
+}
+
+/// The number of characters in `s` if it consists solely of ASCII digits
+fn parse_digits(s: &str) -> Option<usize> {
+    if s.bytes().all(|b| b.is_ascii_digit()) {
+        Some(s.len())
+    } else {
+        None
+    }
+}
+
+impl fmt::Display for NntpTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}{:02}{:02}", self.hour, self.minute, self.second)
+    }
+}
+
+/// List newsgroups created since a date and time
+///
+/// For more information see [RFC 3977 7.3](https://tools.ietf.org/html/rfc3977#section-7.3)
+///
+/// ```
+/// use brokaw::types::command::{NewGroups, NntpDate, NntpTime};
+///
+/// let cmd = NewGroups::new(NntpDate::new(2020, 1, 2).unwrap(), NntpTime::new(3, 4, 5).unwrap())
+///     .with_gmt()
+///     .with_distributions(vec!["alt", "comp"]);
+/// assert_eq!(cmd.to_string(), "NEWGROUPS 20200102 030405 GMT <alt,comp>");
+/// ```
+///
+/// Earlier releases allowed struct-literal construction and implemented `Copy`. The private
+/// distributions list rules out both, use [`NewGroups::new`] and the `with_*` builders instead.
 #[derive(Clone, Debug)]
-pub struct Group(pub String);
+pub struct NewGroups {
+    /// The date to list groups from
+    pub date: NntpDate,
+    /// The time to list groups from
+    pub time: NntpTime,
+    /// If true the date and time are in UTC, otherwise they are in the server's local time
+    pub gmt: bool,
+    distributions: Option<Vec<String>>,
+}
 
-impl fmt::Display for Group {
+impl NewGroups {
+    /// List groups created since the date and time in the server's local time
+    pub fn new(date: NntpDate, time: NntpTime) -> Self {
+        Self {
+            date,
+            time,
+            gmt: false,
+            distributions: None,
+        }
+    }
+
+    /// Interpret the date and time as UTC
+    pub fn with_gmt(mut self) -> Self {
+        self.gmt = true;
+        self
+    }
+
+    /// Limit the groups to the provided distributions, an empty list sends no distributions
+    ///
+    /// Distributions were removed in RFC 3977 but are still accepted by some servers
+    /// (see [RFC 977](https://tools.ietf.org/html/rfc977#section-3.7)).
+    pub fn with_distributions(
+        mut self,
+        distributions: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        let distributions = distributions
+            .into_iter()
+            .map(Into::into)
+            .collect::<Vec<_>>();
+        self.distributions = Some(distributions).filter(|d| !d.is_empty());
+        self
+    }
+
+    /// The distributions the groups are limited to, if any
+    pub fn distributions(&self) -> Option<&[String]> {
+        self.distributions.as_deref()
+    }
+}
+
+impl fmt::Display for NewGroups {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "GROUP {}", self.0)
+        write!(f, "NEWGROUPS {} {}", self.date, self.time)?;
+        if self.gmt {
+            write!(f, " GMT")?;
+        }
+        match &self.distributions {
+            Some(distributions) => write!(f, " <{}>", distributions.join(",")),
+            None => Ok(()),
+        }
     }
 }
 
-impl NntpCommand for Group {}
+impl NntpCommand for NewGroups {}
 
 /// Retrieve a specific header from one or more articles
 #[derive(Clone, Debug)]
@@ -95,13 +352,14 @@ pub enum Hdr {
         id: String,
     },
     /// A range of articles
+    ///
+    /// Replaces the `Range { low, high }` variant of earlier releases, use
+    /// [`Hdr::range`] e.g. `Hdr::range(field, (low, high))?`.
     Range {
         /// The name of the header
         field: String,
-        /// The low number of the article range
-        low: ArticleNumber,
-        /// The high number of the article range
-        high: ArticleNumber,
+        /// The range of articles
+        range: ArticleRange,
     },
     /// The current article
     Current {
@@ -114,13 +372,29 @@ impl fmt::Display for Hdr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Hdr::MessageId { field, id } => write!(f, "HDR {} {}", field, id),
-            Hdr::Range { field, low, high } => write!(f, "HDR {} {}-{}", field, low, high),
+            Hdr::Range { field, range } => write!(f, "HDR {} {}", field, range),
             Hdr::Current { field } => write!(f, "HDR {}", field),
         }
     }
 }
 
-impl NntpCommand for Hdr {}
+impl NntpCommand for Hdr {
+    fn uses_current_article(&self) -> bool {
+        matches!(self, Hdr::Current { .. })
+    }
+}
+
+impl Hdr {
+    /// Retrieve a header for a range of articles
+    ///
+    /// Returns [`Error::InvalidRange`] if `low` is greater than `high`
+    pub fn range(field: impl Into<String>, range: impl IntoArticleRange) -> Result<Self> {
+        Ok(Hdr::Range {
+            field: field.into(),
+            range: range.into_article_range()?,
+        })
+    }
+}
 
 /// Retrieve the headers for an article
 #[derive(Clone, Debug)]
@@ -143,21 +417,15 @@ impl fmt::Display for Head {
     }
 }
 
-impl NntpCommand for Head {}
-
-/// Retrieve help text about the servers capabilities
-struct Help;
-
-impl fmt::Display for Help {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "HELP")
+impl NntpCommand for Head {
+    fn uses_current_article(&self) -> bool {
+        matches!(self, Head::Current)
     }
 }
 
-impl NntpCommand for Help {}
-
 /// Inform the server that you have an article for upload
-struct IHave(String);
+#[derive(Clone, Debug)]
+pub struct IHave(pub String);
 
 impl fmt::Display for IHave {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -167,17 +435,30 @@ impl fmt::Display for IHave {
 
 impl NntpCommand for IHave {}
 
-/// Attempt to set the current article to the previous article number
-struct Last;
+/// Offer an article to the server and transfer it if it is wanted
+///
+/// This is the two part exchange built on [`IHave`]. The server responds `335` if it wants the
+/// article, and `235` once it has been transferred. As with [`Post`] the article MUST NOT
+/// be dot-stuffed or terminated.
+///
+/// For more information see [RFC 3977 6.3.2](https://tools.ietf.org/html/rfc3977#section-6.3.2)
+#[derive(Clone, Debug)]
+pub struct IHaveArticle {
+    /// The message ID of the article
+    pub message_id: String,
+    /// The article, including headers
+    pub article: Vec<u8>,
+}
 
-impl fmt::Display for Last {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "LAST")
+impl MultiStageCommand for IHaveArticle {
+    fn stages(&self) -> Vec<Stage> {
+        vec![
+            Stage::command(&IHave(self.message_id.clone()), &[Kind::IHaveSendArticle]),
+            Stage::multiline_body(&self.article, &[Kind::ArticleTransferredOk]),
+        ]
     }
 }
 
-impl NntpCommand for Last {}
-
 /// Retrieve a list of information from the server
 ///
 /// Not all LIST keywords are supported by all servers.
@@ -188,6 +469,10 @@ impl NntpCommand for Last {}
 #[derive(Clone, Debug)]
 #[allow(missing_docs)]
 pub enum List {
+    /// `LIST` without a keyword, equivalent to `LIST ACTIVE`
+    ///
+    /// [RFC 3977 7.6.1](https://tools.ietf.org/html/rfc3977#section-7.6.1)
+    NoArgs,
     /// Return a list of active newsgroups
     ///
     /// [RFC 3977 7.6.3](https://tools.ietf.org/html/rfc3977#section-7.6.3)
@@ -204,10 +489,30 @@ pub enum List {
     ///
     /// [RFC 3977 7.6.5](https://tools.ietf.org/html/rfc3977#section-7.6.5)
     DistribPats,
+    /// Return the estimated article counts of newsgroups
+    ///
+    /// [RFC 6048 2.2](https://tools.ietf.org/html/rfc6048#section-2.2)
+    Counts { wildmat: Option<String> },
     /// Return field descriptors for headers returned by OVER/XOVER
     ///
     /// [RFC 3977 8.4](https://tools.ietf.org/html/rfc3977#section-8.4)
     OverviewFmt,
+    /// Return the fields that may be retrieved using the `HDR` command
+    ///
+    /// [RFC 3977 8.6](https://tools.ietf.org/html/rfc3977#section-8.6)
+    Headers,
+    /// Return the fields that may be retrieved using `HDR` with a message-id argument
+    ///
+    /// [RFC 3977 8.6](https://tools.ietf.org/html/rfc3977#section-8.6)
+    HeadersMsgid,
+    /// Return the fields that may be retrieved using `HDR` with a range argument
+    ///
+    /// [RFC 3977 8.6](https://tools.ietf.org/html/rfc3977#section-8.6)
+    HeadersRange,
+    /// Any other keyword, e.g. server-specific extensions such as `LIST MOTD`
+    ///
+    /// The keyword and arguments are sent verbatim, separated by spaces
+    Other { keyword: String, args: Vec<String> },
 }
 
 impl fmt::Display for List {
@@ -222,124 +527,97 @@ impl fmt::Display for List {
 
         write!(f, "LIST")?;
         match self {
+            List::NoArgs => Ok(()),
             List::Active { wildmat } => {
                 write!(f, " ACTIVE")?;
                 print_wildmat(f, wildmat.as_ref())
             }
             List::OverviewFmt => write!(f, " OVERVIEW.FMT"),
             List::ActiveTimes { wildmat } => {
-                write!(f, " ACTIVE TIMES")?;
+                write!(f, " ACTIVE.TIMES")?;
                 print_wildmat(f, wildmat.as_ref())
             }
             List::Newsgroups { wildmat } => {
-                write!(f, " ACTIVE TIMES")?;
+                write!(f, " NEWSGROUPS")?;
                 print_wildmat(f, wildmat.as_ref())
             }
             List::DistribPats => write!(f, " DISTRIB.PATS"),
+            List::Counts { wildmat } => {
+                write!(f, " COUNTS")?;
+                print_wildmat(f, wildmat.as_ref())
+            }
+            List::Headers => write!(f, " HEADERS"),
+            List::HeadersMsgid => write!(f, " HEADERS MSGID"),
+            List::HeadersRange => write!(f, " HEADERS RANGE"),
+            List::Other { keyword, args } => {
+                write!(f, " {}", keyword)?;
+                args.iter().try_for_each(|arg| write!(f, " {}", arg))
+            }
         }
     }
 }
 
 impl NntpCommand for List {}
 
-/// Enable reader mode on a mode switching server
-#[derive(Clone, Copy, Debug)]
-pub struct ModeReader;
-
-impl fmt::Display for ModeReader {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "MODE READER")
-    }
-}
-
-impl NntpCommand for ModeReader {}
-
-// TODO(commands) implement NEWGROUPS
-
 // TODO(commands) implement NEWNEWS
 
-/// Attempt to set the current article to the next article number
-#[derive(Clone, Copy, Debug)]
-pub struct Next;
-
-impl fmt::Display for Next {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "NEXT")
-    }
-}
-
-impl NntpCommand for Next {}
-
 /// Retrieve all of the fields (e.g. headers/metadata) for one or more articles
 #[derive(Clone, Debug)]
 pub enum Over {
     /// A single article by message ID
     MessageId(String),
     /// A range of articles
-    Range {
-        /// The low number of the article
-        low: ArticleNumber,
-        /// The high number of the article
-        high: ArticleNumber,
-    },
+    ///
+    /// Replaces the `Range { low, high }` variant of earlier releases, use
+    /// [`Over::range`] e.g. `Over::range((low, high))?`.
+    Range(ArticleRange),
     /// The current article
     Current,
 }
 
+impl Over {
+    /// Retrieve the overview for a range of articles
+    ///
+    /// Returns [`Error::InvalidRange`] if `low` is greater than `high`
+    pub fn range(range: impl IntoArticleRange) -> Result<Self> {
+        Ok(Over::Range(range.into_article_range()?))
+    }
+}
+
 impl fmt::Display for Over {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Over::MessageId(id) => write!(f, "OVER {}", id),
-            Over::Range { low, high } => write!(f, "OVER {}-{}", low, high),
+            Over::Range(range) => write!(f, "OVER {}", range),
             Over::Current => write!(f, "OVER"),
         }
     }
 }
 
-impl NntpCommand for Over {}
+impl NntpCommand for Over {
+    fn uses_current_article(&self) -> bool {
+        matches!(self, Over::Current)
+    }
+}
 
-// TODO(commands) complete POST implementation
-/*
 /// Post an article to the news server
 ///
-/// POSTING is a two part exchange. The [`Initial`](Post::Initial) variant is used
-/// to determine if the server will accept the article while the [`Article`](Post::Article) is
-/// used to send the data.
-///
+/// POSTING is a two part exchange. The server is asked whether it will accept an article
+/// (`340`) and then the article is sent (`240` on success). The article should include its
+/// headers and MUST NOT be dot-stuffed or terminated, this is handled when it is sent.
 ///
 /// For more information see [RFC 3977 6.3.1](https://tools.ietf.org/html/rfc3977#section-6.3.1)
 #[derive(Clone, Debug)]
-enum Post<'a> {
-    Initial,
-    /// The article body NOT including the terminating sequence
-    Article(&'a [u8])
-}
-
-impl NntpCommand for Post<'_> {}
-
-impl fmt::Display for Post<'_> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Post::Initial => write!(f, "POST"),
-            Post::Article(body) => {
-                unimplemented!()
-            },
-        }
+pub struct Post(pub Vec<u8>);
+
+impl MultiStageCommand for Post {
+    fn stages(&self) -> Vec<Stage> {
+        vec![
+            Stage::new("POST", &[Kind::PostSendArticle]),
+            Stage::multiline_body(&self.0, &[Kind::ArticleReceivedOk]),
+        ]
     }
 }
-*/
-
-/// Close the connection
-#[derive(Clone, Copy, Debug)]
-pub struct Quit;
-
-impl fmt::Display for Quit {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "QUIT")
-    }
-}
-
-impl NntpCommand for Quit {}
 
 /// Check if an article exists in the newsgroup
 #[derive(Clone, Debug)]
@@ -362,4 +640,147 @@ impl fmt::Display for Stat {
     }
 }
 
-impl NntpCommand for Stat {}
+impl NntpCommand for Stat {
+    fn uses_current_article(&self) -> bool {
+        matches!(self, Stat::Current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::command::Encode;
+
+    #[test]
+    fn test_group_name() {
+        assert_eq!(
+            Group::new("misc.test").unwrap().encode(),
+            b"GROUP misc.test"
+        );
+        assert!(Group::new("alt.binaries.x-y_z+1").is_ok());
+        // UTF-8 is permitted by RFC 3977
+        assert!(Group::new("de.talk.grüße").is_ok());
+
+        for name in &[
+            "",
+            "misc test",
+            "misc.test\r\nQUIT",
+            "misc\ttest",
+            ".misc.test",
+            "misc.*",
+            "misc.test\u{a0}",
+        ] {
+            assert!(
+                matches!(Group::new(name), Err(Error::InvalidGroupName { .. })),
+                "{:?} should be rejected",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_newgroups() {
+        let date = NntpDate::new(2020, 1, 2).unwrap();
+        let time = NntpTime::new(3, 4, 5).unwrap();
+
+        let cmd = NewGroups::new(date, time);
+        assert_eq!(cmd.encode(), b"NEWGROUPS 20200102 030405");
+
+        let cmd = NewGroups::new(date.two_digit_year(), time).with_gmt();
+        assert_eq!(cmd.encode(), b"NEWGROUPS 200102 030405 GMT");
+
+        let cmd = NewGroups::new(date, time).with_distributions(vec!["alt"]);
+        assert_eq!(cmd.distributions(), Some(&["alt".to_string()][..]));
+        assert_eq!(cmd.encode(), b"NEWGROUPS 20200102 030405 <alt>");
+
+        let cmd = NewGroups::new(date, time)
+            .with_gmt()
+            .with_distributions(vec!["alt", "comp"]);
+        assert_eq!(cmd.encode(), b"NEWGROUPS 20200102 030405 GMT <alt,comp>");
+
+        let cmd = NewGroups::new(date, time).with_distributions(Vec::<String>::new());
+        assert!(cmd.distributions().is_none());
+        assert_eq!(cmd.encode(), b"NEWGROUPS 20200102 030405");
+
+        assert!(NntpDate::new(2020, 13, 1).is_none());
+        assert!(NntpDate::new(2020, 1, 0).is_none());
+        assert!(NntpTime::new(24, 0, 0).is_none());
+    }
+
+    #[test]
+    fn test_parse_date_time() {
+        assert_eq!(
+            "20200102".parse::<NntpDate>().unwrap(),
+            NntpDate::new(2020, 1, 2).unwrap()
+        );
+        assert_eq!("200102".parse::<NntpDate>().unwrap().to_string(), "200102");
+        assert_eq!(
+            "030405".parse::<NntpTime>().unwrap(),
+            NntpTime::new(3, 4, 5).unwrap()
+        );
+
+        for date in &[
+            "2020012",
+            "202001022",
+            "20201302",
+            "2020-1-2",
+            "",
+            "+2001002",
+        ] {
+            assert!(date.parse::<NntpDate>().is_err(), "{:?}", date);
+        }
+        for time in &["03040", "0304050", "240000", "03:4:5", ""] {
+            assert!(time.parse::<NntpTime>().is_err(), "{:?}", time);
+        }
+    }
+
+    #[test]
+    fn test_ranges() {
+        assert_eq!(Over::range(5..).unwrap().encode(), b"OVER 5-");
+        assert_eq!(Over::range(5).unwrap().encode(), b"OVER 5");
+        assert_eq!(
+            Hdr::range("Subject", (1, 10)).unwrap().encode(),
+            b"HDR Subject 1-10"
+        );
+    }
+
+    #[test]
+    fn test_list() {
+        assert_eq!(List::NoArgs.encode(), b"LIST");
+        assert_eq!(
+            List::ActiveTimes {
+                wildmat: Some("misc.*".to_string())
+            }
+            .encode(),
+            b"LIST ACTIVE.TIMES misc.*"
+        );
+        assert_eq!(
+            List::Newsgroups { wildmat: None }.encode(),
+            b"LIST NEWSGROUPS"
+        );
+        assert_eq!(
+            List::Counts {
+                wildmat: Some("alt.binaries.*".to_string())
+            }
+            .encode(),
+            b"LIST COUNTS alt.binaries.*"
+        );
+    }
+
+    #[test]
+    fn test_article_number_width() {
+        // Busy groups have long since passed 2^32 articles, this fails to compile if
+        // ArticleNumber is narrowed
+        let high: ArticleNumber = u64::MAX;
+        let low = ArticleNumber::from(u32::MAX) + 1;
+
+        assert_eq!(
+            Over::range((low, high)).unwrap().encode(),
+            b"OVER 4294967296-18446744073709551615"
+        );
+        assert_eq!(
+            Article::Number(high).encode(),
+            b"ARTICLE 18446744073709551615"
+        );
+    }
+}
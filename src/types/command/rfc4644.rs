@@ -0,0 +1,81 @@
+use std::fmt;
+
+use crate::types::command::{encode_multiline_body, has_line_break, Encode};
+use crate::types::NntpCommand;
+
+/// Ask the server whether it wants an article (streaming mode only)
+///
+/// The server responds with `238` if it wants the article, `431` if it should be offered
+/// again later, or `438` if it does not want it.
+#[derive(Clone, Debug)]
+pub struct Check(pub String);
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CHECK {}", self.0)
+    }
+}
+
+impl NntpCommand for Check {}
+
+/// Send an article to the server without waiting for permission (streaming mode only)
+///
+/// The command and article are sent together. The article MUST NOT be dot-stuffed or
+/// terminated, this is handled when encoding. The server responds with `239` if the article
+/// was accepted or `439` if it was rejected.
+#[derive(Clone, Debug)]
+pub struct TakeThis {
+    /// The message ID of the article
+    pub message_id: String,
+    /// The article, including headers
+    pub article: Vec<u8>,
+}
+
+impl Encode for TakeThis {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = format!("TAKETHIS {}\r\n", self.message_id).into_bytes();
+        bytes.extend_from_slice(&encode_multiline_body(&self.article));
+        // The connection appends the CRLF after the terminating `.`
+        bytes.truncate(bytes.len() - 2);
+        bytes
+    }
+}
+
+impl NntpCommand for TakeThis {
+    fn contains_line_break(&self) -> bool {
+        has_line_break(self.message_id.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_breaks() {
+        let mut cmd = TakeThis {
+            message_id: "<1@example.com>".to_string(),
+            article: b"Subject: hi\r\n\r\nbody".to_vec(),
+        };
+        assert!(!cmd.contains_line_break());
+        cmd.message_id = "<1@example.com>\r\nQUIT".to_string();
+        assert!(cmd.contains_line_break());
+        assert!(Check("<1@example.com>\r\nQUIT".to_string()).contains_line_break());
+    }
+
+    #[test]
+    fn test_take_this() {
+        let cmd = TakeThis {
+            message_id: "<1@example.com>".to_string(),
+            article: b"Subject: hi\n\n.body".to_vec(),
+        };
+        assert_eq!(
+            cmd.encode(),
+            b"TAKETHIS <1@example.com>\r\nSubject: hi\r\n\r\n..body\r\n."
+        );
+        assert_eq!(
+            Check("<1@example.com>".to_string()).encode(),
+            b"CHECK <1@example.com>"
+        );
+    }
+}
@@ -0,0 +1,34 @@
+use std::fmt;
+
+use crate::types::NntpCommand;
+
+/// Enable compression of the entire stream
+///
+/// The server responds with `206` if compression was activated, after which *all* data in
+/// both directions is compressed.
+#[derive(Clone, Copy, Debug)]
+pub enum Compress {
+    /// Raw DEFLATE compression per [RFC 1951](https://tools.ietf.org/html/rfc1951)
+    Deflate,
+}
+
+impl fmt::Display for Compress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Compress::Deflate => write!(f, "COMPRESS DEFLATE"),
+        }
+    }
+}
+
+impl NntpCommand for Compress {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::command::Encode;
+
+    #[test]
+    fn test_compress() {
+        assert_eq!(Compress::Deflate.encode(), b"COMPRESS DEFLATE");
+    }
+}
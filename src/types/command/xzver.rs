@@ -0,0 +1,102 @@
+use std::fmt;
+
+use crate::error::Result;
+use crate::types::command::{ArticleRange, IntoArticleRange};
+use crate::types::prelude::NntpCommand;
+
+/// Get compressed headers for one or more articles
+///
+/// `XZVER` is a non-standard extension supported by several commercial providers.
+/// The response has the same content as [`XOver`](super::XOver) but the data blocks contain a
+/// yEnc encoded DEFLATE stream. The [`NntpConnection`](crate::NntpConnection) decodes this
+/// transparently so the response can be handled like a regular `XOVER` response.
+#[derive(Copy, Clone, Debug)]
+pub enum XzVer {
+    /// A range of messages
+    ///
+    /// Replaces the `Range { low, high }` variant of earlier releases, use
+    /// [`XzVer::range`] e.g. `XzVer::range((low, high))?`.
+    Range(ArticleRange),
+    /// The current message
+    Current,
+}
+
+impl XzVer {
+    /// Retrieve the compressed headers for a range of articles
+    ///
+    /// Returns [`Error::InvalidRange`](crate::error::Error::InvalidRange) if `low` is greater
+    /// than `high`
+    pub fn range(range: impl IntoArticleRange) -> Result<Self> {
+        Ok(XzVer::Range(range.into_article_range()?))
+    }
+}
+
+impl fmt::Display for XzVer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            XzVer::Range(range) => write!(f, "XZVER {}", range),
+            XzVer::Current => write!(f, "XZVER"),
+        }
+    }
+}
+
+impl NntpCommand for XzVer {
+    fn uses_current_article(&self) -> bool {
+        matches!(self, XzVer::Current)
+    }
+}
+
+/// Retrieve a specific header from one or more articles, compressed
+///
+/// This is the compressed counterpart of [`XHdr`](super::XHdr), please see [`XzVer`] for
+/// details on the compression.
+#[derive(Clone, Debug)]
+pub enum XzHdr {
+    /// A single message
+    MessageId {
+        /// The name of the header to retrieve
+        header: String,
+        /// The message ID of the article
+        id: String,
+    },
+    /// A range of messages
+    ///
+    /// Replaces the `Range { low, high }` variant of earlier releases, the range can be built
+    /// with e.g. [`ArticleRange::closed`].
+    Range {
+        /// The name of the header to retrieve
+        header: String,
+        /// The range of articles
+        range: ArticleRange,
+    },
+}
+
+impl fmt::Display for XzHdr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            XzHdr::MessageId { header, id } => write!(f, "XZHDR {} {}", header, id),
+            XzHdr::Range { header, range } => write!(f, "XZHDR {} {}", header, range),
+        }
+    }
+}
+
+impl NntpCommand for XzHdr {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::command::Encode;
+
+    #[test]
+    fn test_xzver() {
+        assert_eq!(XzVer::range((1, 10)).unwrap().encode(), b"XZVER 1-10");
+        assert_eq!(
+            XzHdr::Range {
+                header: "Subject".to_string(),
+                range: ArticleRange::from_low(1),
+            }
+            .encode(),
+            b"XZHDR Subject 1-"
+        );
+    }
+}
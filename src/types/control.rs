@@ -0,0 +1,193 @@
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{Error, Result};
+use crate::types::article::ArticleBuilder;
+use crate::types::command::{MessageId, Post};
+
+/// The maximum length of a message-id
+///
+/// [RFC 5536 3.1.3](https://tools.ietf.org/html/rfc5536#section-3.1.3)
+const MAX_MESSAGE_ID_LEN: usize = 250;
+
+/// The domain used for generated message-ids if the poster's address does not have one
+const FALLBACK_DOMAIN: &str = "brokaw.invalid";
+
+/// Build a [`Post`] that cancels an article
+///
+/// The cancel is sent to `newsgroups`, which should be the groups the target article was
+/// posted to, and includes a `Control: cancel` header, a generated `Message-ID`, and the
+/// `cmsg cancel` subject recommended by [RFC 5537 5.3](https://tools.ietf.org/html/rfc5537#section-5.3).
+///
+/// Servers (and downstream peers) will generally only honor cancels from the original poster
+/// or an administrator, so the post may be rejected or silently ignored.
+///
+/// Returns [`Error::InvalidField`] if the target message-id is malformed, in addition to the
+/// errors returned by [`ArticleBuilder::build`].
+///
+/// ```
+/// use brokaw::types::command::MessageId;
+/// use brokaw::types::control;
+///
+/// let post = control::cancel(
+///     &MessageId::new("1@example.com"),
+///     "jane@example.com",
+///     &["misc.test"],
+/// ).unwrap();
+///
+/// let article = String::from_utf8(post.0).unwrap();
+/// assert!(article.contains("Control: cancel <1@example.com>\r\n"));
+/// ```
+pub fn cancel(target: &MessageId, from: impl AsRef<str>, newsgroups: &[&str]) -> Result<Post> {
+    validate_message_id(target)?;
+
+    let from = from.as_ref();
+    let article = ArticleBuilder::default()
+        .from(from)
+        .newsgroups(newsgroups)
+        .subject(format!("cmsg cancel {}", target))
+        .header("Control", format!("cancel {}", target))
+        .header("Message-ID", generate_message_id(from).as_str())
+        .body_text("cancel\n")
+        .build()?;
+
+    Ok(Post(article))
+}
+
+/// Set the `Supersedes` header of an article, replacing the article `old_id`
+///
+/// This validates the message-id before calling [`ArticleBuilder::supersedes`].
+/// As with [`cancel`], servers may refuse to replace articles posted by someone else.
+pub fn supersedes<'a>(
+    builder: &'a mut ArticleBuilder,
+    old_id: &MessageId,
+) -> Result<&'a mut ArticleBuilder> {
+    validate_message_id(old_id)?;
+    Ok(builder.supersedes(old_id))
+}
+
+/// Check that a message-id is of the form `<left@right>`
+///
+/// Message-ids must be printable ASCII without whitespace and no longer than 250 characters
+/// per [RFC 5536 3.1.3](https://tools.ietf.org/html/rfc5536#section-3.1.3).
+pub fn validate_message_id(id: &MessageId) -> Result<()> {
+    let s = id.as_str();
+    let inner = &s[1..s.len() - 1];
+    let mut halves = inner.splitn(2, '@');
+    let left = halves.next().unwrap_or_default();
+    let right = halves.next().unwrap_or_default();
+
+    let valid = s.len() <= MAX_MESSAGE_ID_LEN
+        && !left.is_empty()
+        && !right.is_empty()
+        && !right.contains('@')
+        && inner
+            .bytes()
+            .all(|b| b.is_ascii_graphic() && b != b'<' && b != b'>');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::invalid_field("message-id", s))
+    }
+}
+
+/// Generate a unique message-id using the domain of the poster's address
+fn generate_message_id(from: &str) -> MessageId {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let domain = from
+        .rsplit('@')
+        .next()
+        .filter(|_| from.contains('@'))
+        .map(|d| d.trim_end_matches(|c: char| c == '>' || c.is_whitespace()))
+        .filter(|d| !d.is_empty() && d.bytes().all(|b| b.is_ascii_graphic() && b != b'>'))
+        .unwrap_or(FALLBACK_DOMAIN);
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    MessageId::new(format!("{}.{}.{}@{}", nanos, process::id(), count, domain))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel() {
+        let post = cancel(
+            &MessageId::new("1@example.com"),
+            "Jane <jane@example.com>",
+            &["misc.test", "alt.test"],
+        )
+        .unwrap();
+        let article = String::from_utf8(post.0).unwrap();
+
+        assert!(article.starts_with(
+            "From: Jane <jane@example.com>\r\n\
+             Newsgroups: misc.test,alt.test\r\n\
+             Subject: cmsg cancel <1@example.com>\r\n\
+             Control: cancel <1@example.com>\r\n\
+             Message-ID: <"
+        ));
+        assert!(article.contains("@example.com>\r\n\r\ncancel\r\n"));
+
+        assert!(matches!(
+            cancel(
+                &MessageId::new("no-at-sign"),
+                "jane@example.com",
+                &["misc.test"]
+            ),
+            Err(Error::InvalidField { .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate_message_id() {
+        assert!(validate_message_id(&MessageId::new("abc.123@example.com")).is_ok());
+        for id in &[
+            "example.com",
+            "@example.com",
+            "abc@",
+            "a b@example.com",
+            "a@b@example.com",
+            "a<b@example.com",
+        ] {
+            assert!(
+                validate_message_id(&MessageId::new(id)).is_err(),
+                "{} should be invalid",
+                id
+            );
+        }
+        let long = format!("{}@example.com", "a".repeat(250));
+        assert!(validate_message_id(&MessageId::new(long)).is_err());
+    }
+
+    #[test]
+    fn test_generate_message_id() {
+        let a = generate_message_id("Jane <jane@example.com>");
+        let b = generate_message_id("Jane <jane@example.com>");
+        assert_ne!(a, b);
+        assert!(a.as_str().ends_with("@example.com>"));
+        assert!(validate_message_id(&a).is_ok());
+
+        assert!(generate_message_id("jane")
+            .as_str()
+            .ends_with("@brokaw.invalid>"));
+    }
+
+    #[test]
+    fn test_supersedes() {
+        let mut builder = ArticleBuilder::default();
+        supersedes(&mut builder, &MessageId::new("1@example.com")).unwrap();
+        assert!(builder
+            .to_string()
+            .starts_with("Supersedes: <1@example.com>\r\n"));
+        assert!(supersedes(&mut builder, &MessageId::new("bad")).is_err());
+    }
+}
@@ -1,3 +1,6 @@
+/// Composing articles to be posted
+pub mod article;
+
 /// Traits and types for NNTP commands
 ///
 /// The [`NntpCommand`](command::NntpCommand) trait can be used to implement commands not (yet)
@@ -11,6 +14,9 @@
 /// defined in the RFC.
 pub mod command;
 
+/// Helpers for control messages (e.g. cancelling an article)
+pub mod control;
+
 /// Typed NNTP responses for individual commands
 pub mod response;
 
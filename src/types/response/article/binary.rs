@@ -1,5 +1,6 @@
 use std::convert::TryFrom;
 use std::fmt;
+use std::ops::Range;
 use std::result::Result as StdResult;
 use std::str::from_utf8;
 
@@ -7,7 +8,10 @@ use log::*;
 
 use crate::error::{Error, Result};
 use crate::types::prelude::*;
-use crate::types::response::article::iter::{Lines, Unterminated};
+use crate::types::response::article::body::strip_terminator;
+#[cfg(feature = "serde")]
+use crate::types::response::article::iter::split_lines;
+use crate::types::response::article::iter::{clamp_lines, Lines, Unterminated};
 use crate::types::response::article::parse::take_headers;
 use crate::types::response::util::{err_if_not_kind, process_article_first_line};
 
@@ -26,14 +30,47 @@ use crate::types::response::util::{err_if_not_kind, process_article_first_line};
 /// 2. The header contents will be lossily converted to UTF-8
 /// 3. There are no formatting constraints on the body
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate", from = "SerdeBinaryArticle")
+)]
 pub struct BinaryArticle {
     pub(crate) number: ArticleNumber,
     pub(crate) message_id: String,
     pub(crate) headers: Headers,
+    #[cfg_attr(feature = "serde", serde(with = "serde_bytes"))]
     pub(crate) body: Vec<u8>,
+    // Recomputed from the body when deserializing rather than trusted
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub(crate) line_boundaries: Vec<(usize, usize)>,
 }
 
+/// The serialized fields of a [`BinaryArticle`]
+#[cfg(feature = "serde")]
+#[derive(serde_crate::Deserialize)]
+#[serde(crate = "serde_crate")]
+struct SerdeBinaryArticle {
+    number: ArticleNumber,
+    message_id: String,
+    headers: Headers,
+    #[serde(with = "serde_bytes")]
+    body: Vec<u8>,
+}
+
+#[cfg(feature = "serde")]
+impl From<SerdeBinaryArticle> for BinaryArticle {
+    fn from(article: SerdeBinaryArticle) -> Self {
+        Self {
+            number: article.number,
+            message_id: article.message_id,
+            headers: article.headers,
+            line_boundaries: split_lines(&article.body),
+            body: article.body,
+        }
+    }
+}
+
 impl BinaryArticle {
     /// The number of the article relative to the group it was retrieved from
     pub fn number(&self) -> ArticleNumber {
@@ -51,6 +88,8 @@ impl BinaryArticle {
     }
 
     /// The raw contents of the body
+    ///
+    /// The terminating `.` line of the response is not included.
     pub fn body(&self) -> &[u8] {
         &self.body
     }
@@ -75,6 +114,24 @@ impl BinaryArticle {
         }
     }
 
+    /// The line at `idx` including its CRLF terminator, `None` if there is no such line
+    pub fn line(&self, idx: usize) -> Option<&[u8]> {
+        self.line_boundaries
+            .get(idx)
+            .map(|(start, end)| &self.body[*start..*end])
+    }
+
+    /// An iterator over the lines in `range`
+    ///
+    /// The range is clamped to the lines in the body, so an out of bounds range yields fewer
+    /// (or no) lines rather than panicking.
+    pub fn lines_range(&self, range: Range<usize>) -> Lines<'_> {
+        Lines {
+            payload: &self.body,
+            inner: clamp_lines(&self.line_boundaries, range).iter(),
+        }
+    }
+
     /// Convert the article into a [`TextArticle`]
     ///
     /// This will return an error if the body is not valid UTF-8
@@ -143,37 +200,331 @@ impl TryFrom<&RawResponse> for BinaryArticle {
     /// * [article](https://tools.ietf.org/html/rfc3977#section-9.7)
     fn try_from(resp: &RawResponse) -> Result<Self> {
         err_if_not_kind(resp, Kind::Article)?;
-        let (number, message_id) = process_article_first_line(&resp)?;
+        let (number, message_id) = process_article_first_line(resp)?;
 
         let data_blocks = resp
             .data_blocks
             .as_ref()
             .ok_or_else(Error::missing_data_blocks)?;
 
-        let (body, headers) = take_headers(&data_blocks.payload()).map_err(|e| match e {
-            nom::Err::Incomplete(n) => Error::Deserialization(format!("{:?}", n)),
-            nom::Err::Error((_, kind)) | nom::Err::Failure((_, kind)) => {
-                Error::invalid_data_blocks(format!("{:?}", kind))
-            }
-        })?;
+        let Split {
+            headers,
+            header_len,
+            line_boundaries,
+        } = split_headers(data_blocks)?;
 
-        let bytes_read = data_blocks.payload.len() - body.len();
-        trace!("Read {} bytes as headers", bytes_read);
+        let mut body = data_blocks.payload[header_len..].to_vec();
+        let mut line_boundaries = line_boundaries;
+        strip_terminator(&mut body, &mut line_boundaries);
 
-        let mut line_boundaries = data_blocks
-            .line_boundaries
-            .iter()
-            .skip_while(|(start, _end)| start < &bytes_read)
-            .map(|(start, end)| (start - bytes_read, end - bytes_read))
-            .collect::<Vec<_>>();
-        line_boundaries.pop();
+        Ok(Self {
+            number,
+            message_id,
+            headers,
+            body,
+            line_boundaries,
+        })
+    }
+}
+
+impl TryFrom<RawResponse> for BinaryArticle {
+    type Error = Error;
+
+    /// Convert a raw response into an article, moving the body rather than copying it
+    fn try_from(mut resp: RawResponse) -> Result<Self> {
+        err_if_not_kind(&resp, Kind::Article)?;
+        let (number, message_id) = process_article_first_line(&resp)?;
+
+        let data_blocks = resp
+            .data_blocks
+            .take()
+            .ok_or_else(Error::missing_data_blocks)?;
+
+        let Split {
+            headers,
+            header_len,
+            mut line_boundaries,
+        } = split_headers(&data_blocks)?;
+
+        let mut body = data_blocks.payload;
+        body.drain(..header_len);
+        strip_terminator(&mut body, &mut line_boundaries);
 
         Ok(Self {
             number,
             message_id,
             headers,
-            body: body.to_vec(),
+            body,
             line_boundaries,
         })
     }
 }
+
+/// The headers of an article along with the layout of its body
+struct Split {
+    headers: Headers,
+    /// The number of bytes occupied by the headers
+    header_len: usize,
+    /// The line boundaries of the body relative to the end of the headers
+    line_boundaries: Vec<(usize, usize)>,
+}
+
+/// Parse the headers from the data blocks of an article
+fn split_headers(data_blocks: &DataBlocks) -> Result<Split> {
+    let payload = data_blocks.payload();
+    let (body, headers) =
+        take_headers(payload, data_blocks.keep_raw_headers).map_err(|e| match e {
+            nom::Err::Incomplete(n) => Error::de(format!("{:?}", n)),
+            nom::Err::Error((rest, _)) | nom::Err::Failure((rest, _)) => {
+                Error::invalid_headers(payload, rest)
+            }
+        })?;
+
+    let bytes_read = payload.len() - body.len();
+    trace!("Read {} bytes as headers", bytes_read);
+
+    let line_boundaries = data_blocks
+        .line_boundaries
+        .iter()
+        .skip_while(|(start, _end)| start < &bytes_read)
+        .map(|(start, end)| (start - bytes_read, end - bytes_read))
+        .collect::<Vec<_>>();
+
+    Ok(Split {
+        headers,
+        header_len: bytes_read,
+        line_boundaries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_owned_matches_borrowed() {
+        let resp = RawResponse {
+            code: ResponseCode::Known(Kind::Article),
+            first_line: b"220 1 <1@example.com>\r\n".to_vec(),
+            data_blocks: Some(DataBlocks::from_payload(
+                b"Subject: hi\r\nFrom: a@example.com\r\n\r\nfirst\r\nsecond\xff\r\n.\r\n",
+            )),
+        };
+
+        let borrowed = BinaryArticle::try_from(&resp).unwrap();
+        let owned = BinaryArticle::try_from(resp.clone()).unwrap();
+        assert_eq!(owned, borrowed);
+        assert_eq!(
+            owned.unterminated().collect::<Vec<_>>(),
+            vec![&b"first"[..], b"second\xff"]
+        );
+
+        let body = RawResponse {
+            code: ResponseCode::Known(Kind::Body),
+            first_line: b"222 1 <1@example.com>\r\n".to_vec(),
+            data_blocks: resp.data_blocks,
+        };
+        assert_eq!(
+            Body::try_from(body.clone()).unwrap(),
+            Body::try_from(&body).unwrap()
+        );
+    }
+
+    fn response(kind: Kind, first_line: &[u8], payload: &[u8]) -> RawResponse {
+        RawResponse {
+            code: ResponseCode::Known(kind),
+            first_line: first_line.to_vec(),
+            data_blocks: Some(DataBlocks::from_payload(payload)),
+        }
+    }
+
+    #[test]
+    fn test_latin1_headers() {
+        let resp = response(
+            Kind::Article,
+            b"220 1 <1@example.com>\r\n",
+            b"Subject: f\xfchler\r\n\r\nbody\r\n.\r\n",
+        );
+        let article = BinaryArticle::try_from(&resp).unwrap();
+        assert_eq!(
+            article.headers().get("Subject").unwrap().content,
+            vec!["f\u{fffd}hler"]
+        );
+        assert_eq!(article.body(), b"body\r\n");
+    }
+
+    #[test]
+    fn test_raw_headers() {
+        let mut resp = response(
+            Kind::Article,
+            b"220 1 <1@example.com>\r\n",
+            b"Subject: f\xfchler\r\nFrom: a\r\n\r\nbody\r\n.\r\n",
+        );
+        let article = BinaryArticle::try_from(&resp).unwrap();
+        assert_eq!(
+            article.headers().get("Subject").unwrap().raw_content(),
+            None
+        );
+
+        resp.data_blocks.as_mut().unwrap().keep_raw_headers = true;
+        let mut article = BinaryArticle::try_from(resp).unwrap();
+        let subject = article.headers().get("Subject").unwrap();
+        assert_eq!(subject.content, vec!["f\u{fffd}hler"]);
+        assert_eq!(subject.raw_content(), Some(&[b"f\xfchler".to_vec()][..]));
+
+        article.headers.append("From", "b");
+        assert_eq!(
+            article.headers().get("From").unwrap().raw_content(),
+            Some(&[b"a".to_vec(), b"b".to_vec()][..])
+        );
+    }
+
+    #[test]
+    fn test_relaxed_headers() {
+        let resp = response(
+            Kind::Article,
+            b"220 1 <1@example.com>\r\n",
+            b"Subject:hi\r\nCc:\r\nX-Empty:  \r\n\r\nfirst\r\nsecond\r\n.\r\n",
+        );
+        let article = BinaryArticle::try_from(&resp).unwrap();
+        assert_eq!(article.headers().len(), 3);
+        assert_eq!(article.body(), b"first\r\nsecond\r\n");
+        assert_eq!(
+            article.unterminated().collect::<Vec<_>>(),
+            vec![&b"first"[..], b"second"]
+        );
+        assert_eq!(BinaryArticle::try_from(resp).unwrap(), article);
+    }
+
+    #[test]
+    fn test_random_access() {
+        let resp = response(
+            Kind::Article,
+            b"220 1 <1@example.com>\r\n",
+            b"Subject: hi\r\n\r\n=ybegin\r\ndata\r\n=yend\r\n.\r\n",
+        );
+        let article = BinaryArticle::try_from(&resp).unwrap();
+        assert_eq!(article.line(0), Some(&b"=ybegin\r\n"[..]));
+        assert_eq!(article.line(2), Some(&b"=yend\r\n"[..]));
+        assert_eq!(article.line(3), None);
+        assert_eq!(
+            article.lines_range(1..2).collect::<Vec<_>>(),
+            vec![&b"data\r\n"[..]]
+        );
+        assert_eq!(article.lines_range(2..10).len(), 1);
+        assert_eq!(article.lines_range(5..10).len(), 0);
+        assert_eq!(article.body(), b"=ybegin\r\ndata\r\n=yend\r\n");
+
+        let mut lines = article.unterminated();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines.next_back(), Some(&b"=yend"[..]));
+        assert_eq!(lines.next(), Some(&b"=ybegin"[..]));
+        assert_eq!(lines.len(), 1);
+        assert_eq!(
+            article.lines().rev().collect::<Vec<_>>(),
+            vec![&b"=yend\r\n"[..], b"data\r\n", b"=ybegin\r\n"]
+        );
+    }
+
+    #[test]
+    fn test_unterminated_short_lines() {
+        let body = Body {
+            number: 1,
+            message_id: "<1@example.com>".to_string(),
+            payload: b"a\r\nb\n\nc".to_vec(),
+            line_boundaries: vec![(0, 3), (3, 5), (5, 6), (6, 7), (7, 7)],
+        };
+        assert_eq!(
+            body.unterminated().collect::<Vec<_>>(),
+            vec![&b"a"[..], b"b", b"", b"c", b""]
+        );
+        assert_eq!(body.unterminated().rev().count(), 5);
+    }
+
+    #[test]
+    fn test_unterminated_arbitrary_boundaries() {
+        let payload = b"\r\n\n\rab\r\r\n\n.\r\n\nc";
+        // A small LCG so that the boundaries are arbitrary but reproducible
+        let mut seed = 0x2545_f491_u32;
+        let mut next = |max: usize| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (seed >> 16) as usize % (max + 1)
+        };
+
+        for _ in 0..1000 {
+            let mut line_boundaries = vec![];
+            let mut start = 0;
+            while start < payload.len() {
+                let end = start + next(payload.len() - start);
+                line_boundaries.push((start, end));
+                start = end.max(start + 1);
+            }
+
+            let body = Body {
+                number: 1,
+                message_id: "<1@example.com>".to_string(),
+                payload: payload.to_vec(),
+                line_boundaries: line_boundaries.clone(),
+            };
+            assert_eq!(body.unterminated().count(), line_boundaries.len());
+            assert_eq!(body.unterminated().rev().count(), line_boundaries.len());
+
+            let data_blocks = DataBlocks {
+                payload: payload.to_vec(),
+                line_boundaries,
+                keep_raw_headers: false,
+            };
+            data_blocks.unterminated().for_each(drop);
+        }
+    }
+
+    #[test]
+    fn test_terminator_stripped() {
+        // The final line of the body is empty
+        let resp = response(
+            Kind::Article,
+            b"220 1 <1@example.com>\r\n",
+            b"Subject: hi\r\n\r\nfirst\r\n\r\n.\r\n",
+        );
+        for article in &[
+            BinaryArticle::try_from(&resp).unwrap(),
+            BinaryArticle::try_from(resp.clone()).unwrap(),
+        ] {
+            assert_eq!(article.body(), b"first\r\n\r\n");
+            assert_eq!(article.body().len(), 9);
+            assert_eq!(article.lines_len(), 2);
+            assert_eq!(
+                article.unterminated().collect::<Vec<_>>(),
+                vec![&b"first"[..], b""]
+            );
+        }
+
+        let resp = response(
+            Kind::Body,
+            b"222 1 <1@example.com>\r\n",
+            b"first\r\nsecond\r\n.\r\n",
+        );
+        for body in &[
+            Body::try_from(&resp).unwrap(),
+            Body::try_from(resp.clone()).unwrap(),
+        ] {
+            assert_eq!(body.body(), b"first\r\nsecond\r\n");
+            assert_eq!(body.body().len(), 15);
+            assert_eq!(body.lines_len(), 2);
+            assert_eq!(
+                body.unterminated().collect::<Vec<_>>(),
+                vec![&b"first"[..], b"second"]
+            );
+        }
+
+        // An article without a body
+        let resp = response(
+            Kind::Article,
+            b"220 1 <1@example.com>\r\n",
+            b"Subject: hi\r\n\r\n.\r\n",
+        );
+        let article = BinaryArticle::try_from(&resp).unwrap();
+        assert!(article.body().is_empty());
+        assert_eq!(article.lines_len(), 0);
+    }
+}
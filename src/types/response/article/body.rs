@@ -1,21 +1,54 @@
 use std::convert::TryFrom;
+use std::ops::Range;
 
 use crate::error::{Error, Result};
+use crate::raw::parse::is_terminator_line;
 use crate::types::prelude::*;
 use crate::types::response::article::iter::*;
 use crate::types::response::util::{err_if_not_kind, process_article_first_line};
 
 /// An article body returned by the [`BODY`](https://tools.ietf.org/html/rfc3977#section-6.2.3)
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate", from = "SerdeBody")
+)]
 pub struct Body {
     /// The number of the article unique to a particular newsgroup
     pub number: ArticleNumber,
     /// The unique message id for the article
     pub message_id: String,
+    #[cfg_attr(feature = "serde", serde(with = "serde_bytes"))]
     pub(crate) payload: Vec<u8>,
+    // Recomputed from the payload when deserializing rather than trusted
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub(crate) line_boundaries: Vec<(usize, usize)>,
 }
 
+/// The serialized fields of a [`Body`]
+#[cfg(feature = "serde")]
+#[derive(serde_crate::Deserialize)]
+#[serde(crate = "serde_crate")]
+struct SerdeBody {
+    number: ArticleNumber,
+    message_id: String,
+    #[serde(with = "serde_bytes")]
+    payload: Vec<u8>,
+}
+
+#[cfg(feature = "serde")]
+impl From<SerdeBody> for Body {
+    fn from(body: SerdeBody) -> Self {
+        Self {
+            number: body.number,
+            message_id: body.message_id,
+            line_boundaries: split_lines(&body.payload),
+            payload: body.payload,
+        }
+    }
+}
+
 impl Body {
     /// The number of the article relative to the group it was retrieved from
     pub fn number(&self) -> ArticleNumber {
@@ -28,10 +61,17 @@ impl Body {
     }
 
     /// The raw contents of the body
+    ///
+    /// The terminating `.` line of the response is not included.
     pub fn body(&self) -> &[u8] {
         &self.payload
     }
 
+    /// The number of lines in the body
+    pub fn lines_len(&self) -> usize {
+        self.line_boundaries.len()
+    }
+
     /// An iterator over the lines in the body of the article
     pub fn lines(&self) -> Lines<'_> {
         Lines {
@@ -46,6 +86,24 @@ impl Body {
             inner: self.lines(),
         }
     }
+
+    /// The line at `idx` including its CRLF terminator, `None` if there is no such line
+    pub fn line(&self, idx: usize) -> Option<&[u8]> {
+        self.line_boundaries
+            .get(idx)
+            .map(|(start, end)| &self.payload[*start..*end])
+    }
+
+    /// An iterator over the lines in `range`
+    ///
+    /// The range is clamped to the lines in the body, so an out of bounds range yields fewer
+    /// (or no) lines rather than panicking.
+    pub fn lines_range(&self, range: Range<usize>) -> Lines<'_> {
+        Lines {
+            payload: &self.payload,
+            inner: clamp_lines(&self.line_boundaries, range).iter(),
+        }
+    }
 }
 
 impl TryFrom<&RawResponse> for Body {
@@ -54,16 +112,46 @@ impl TryFrom<&RawResponse> for Body {
     fn try_from(resp: &RawResponse) -> Result<Self> {
         err_if_not_kind(resp, Kind::Body)?;
 
-        let (number, message_id) = process_article_first_line(&resp)?;
+        let (number, message_id) = process_article_first_line(resp)?;
 
         let DataBlocks {
-            payload,
-            line_boundaries,
+            mut payload,
+            mut line_boundaries,
+            ..
         } = resp
             .data_blocks
             .as_ref()
             .ok_or_else(Error::missing_data_blocks)?
             .clone();
+        strip_terminator(&mut payload, &mut line_boundaries);
+
+        Ok(Self {
+            number,
+            message_id,
+            payload,
+            line_boundaries,
+        })
+    }
+}
+
+impl TryFrom<RawResponse> for Body {
+    type Error = Error;
+
+    /// Convert a raw response into a body, moving the payload rather than copying it
+    fn try_from(mut resp: RawResponse) -> Result<Self> {
+        err_if_not_kind(&resp, Kind::Body)?;
+
+        let (number, message_id) = process_article_first_line(&resp)?;
+
+        let DataBlocks {
+            mut payload,
+            mut line_boundaries,
+            ..
+        } = resp
+            .data_blocks
+            .take()
+            .ok_or_else(Error::missing_data_blocks)?;
+        strip_terminator(&mut payload, &mut line_boundaries);
 
         Ok(Self {
             number,
@@ -73,3 +161,45 @@ impl TryFrom<&RawResponse> for Body {
         })
     }
 }
+
+/// Remove the terminating `.` line of a multi-line response from a body
+///
+/// Bodies that do not end with the terminator (e.g. ones that were already stripped) are left as is.
+pub(super) fn strip_terminator(payload: &mut Vec<u8>, line_boundaries: &mut Vec<(usize, usize)>) {
+    if let Some(&(start, end)) = line_boundaries.last() {
+        if is_terminator_line(&payload[start..end]) {
+            payload.truncate(start);
+            line_boundaries.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_access() {
+        let resp = RawResponse {
+            code: ResponseCode::Known(Kind::Body),
+            first_line: b"222 1 <1@example.com>\r\n".to_vec(),
+            data_blocks: Some(DataBlocks::from_payload(
+                b"=ybegin\r\ndata\r\n=yend\r\n.\r\n",
+            )),
+        };
+        let body = Body::try_from(&resp).unwrap();
+        assert_eq!(body.line(0), Some(&b"=ybegin\r\n"[..]));
+        assert_eq!(body.line(2), Some(&b"=yend\r\n"[..]));
+        assert_eq!(body.line(3), None);
+        assert_eq!(
+            body.lines_range(0..2).collect::<Vec<_>>(),
+            vec![&b"=ybegin\r\n"[..], b"data\r\n"]
+        );
+        assert_eq!(body.lines_range(2..10).len(), 1);
+        assert_eq!(body.lines_range(5..10).len(), 0);
+        assert_eq!(
+            body.lines_range(0..3).rev().collect::<Vec<_>>(),
+            vec![&b"=yend\r\n"[..], b"data\r\n", b"=ybegin\r\n"]
+        );
+    }
+}
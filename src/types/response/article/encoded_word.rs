@@ -0,0 +1,135 @@
+use crate::base64;
+
+/// Decode the encoded-words (e.g. `=?UTF-8?B?SGk=?=`) within a header value
+///
+/// Whitespace between adjacent encoded-words is removed. Encoded-words that are malformed or
+/// use an unsupported charset are left as is, as is all other text.
+pub(crate) fn decode_encoded_words(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    let mut after_word = false;
+
+    while let Some(start) = rest.find("=?") {
+        let (before, candidate) = rest.split_at(start);
+        match decode_word(candidate) {
+            Some((decoded, len)) => {
+                // Whitespace separating encoded-words is not part of the text
+                if !(after_word && before.chars().all(char::is_whitespace)) {
+                    out.push_str(before);
+                }
+                out.push_str(&decoded);
+                rest = &candidate[len..];
+                after_word = true;
+            }
+            None => {
+                out.push_str(before);
+                out.push_str("=?");
+                rest = &candidate[2..];
+                after_word = false;
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Decode a single encoded-word at the start of `s`, returning the text and its length
+fn decode_word(s: &str) -> Option<(String, usize)> {
+    let mut fields = s[2..].splitn(3, '?');
+    let charset = fields.next()?;
+    let encoding = fields.next()?;
+    let remainder = fields.next()?;
+    let text_len = remainder.find("?=")?;
+    let text = &remainder[..text_len];
+
+    if charset.is_empty() || text.contains(char::is_whitespace) {
+        return None;
+    }
+
+    let bytes = match encoding {
+        "B" | "b" => base64::decode(text)?,
+        "Q" | "q" => decode_q(text)?,
+        _ => return None,
+    };
+
+    // Strip the language (RFC 2231), e.g. `UTF-8*en`
+    let decoded = match charset.split('*').next()?.to_ascii_lowercase().as_str() {
+        "utf-8" | "utf8" => String::from_utf8_lossy(&bytes).into_owned(),
+        "iso-8859-1" | "iso_8859-1" | "latin1" | "us-ascii" => {
+            bytes.iter().map(|&b| b as char).collect()
+        }
+        _ => return None,
+    };
+
+    // `=?` + charset + `?` + encoding + `?` + text + `?=`
+    let len = 2 + charset.len() + 1 + encoding.len() + 1 + text_len + 2;
+    Some((decoded, len))
+}
+
+/// Decode the `Q` encoding, a variant of quoted-printable where `_` is a space
+fn decode_q(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len());
+    let mut bytes = text.bytes();
+    while let Some(b) = bytes.next() {
+        match b {
+            b'_' => out.push(b' '),
+            b'=' => {
+                let hi = hex_value(bytes.next()?)?;
+                let lo = hex_value(bytes.next()?)?;
+                out.push(hi << 4 | lo);
+            }
+            b => out.push(b),
+        }
+    }
+    Some(out)
+}
+
+fn hex_value(b: u8) -> Option<u8> {
+    (b as char).to_digit(16).map(|d| d as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_b_encoded_utf8() {
+        assert_eq!(
+            decode_encoded_words("=?UTF-8?B?SsO2cmc=?= =?utf-8?b?IETDuHJy?= wrote"),
+            "Jörg Dørr wrote"
+        );
+        assert_eq!(decode_encoded_words("Re: =?UTF-8?B?8J+TsA?="), "Re: 📰");
+    }
+
+    #[test]
+    fn test_q_encoded_latin1() {
+        assert_eq!(
+            decode_encoded_words("=?ISO-8859-1?Q?Andr=E9_Pirard?= <pirard@example.be>"),
+            "André Pirard <pirard@example.be>"
+        );
+        assert_eq!(
+            decode_encoded_words("=?iso-8859-1*fr?q?a?=\r\n =?iso-8859-1?q?b?= c"),
+            "ab c"
+        );
+    }
+
+    #[test]
+    fn test_passthrough() {
+        for s in &[
+            "plain text",
+            "=?unknown-charset?Q?abc?=",
+            "=?UTF-8?X?abc?=",
+            "=?UTF-8?Q?a b?=",
+            "=?UTF-8?B?!!!?=",
+            "=?UTF-8?Q?unterminated",
+            "a =? b",
+        ] {
+            assert_eq!(decode_encoded_words(s), *s);
+        }
+        assert_eq!(
+            decode_encoded_words("=?bogus?Q?a?= =?UTF-8?Q?b?="),
+            "=?bogus?Q?a?= b"
+        );
+    }
+}
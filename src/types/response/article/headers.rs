@@ -1,9 +1,11 @@
-use std::collections::{hash_map, HashMap};
 use std::convert::TryFrom;
+use std::slice;
 
 use crate::error::{Error, Result};
 use crate::raw::response::RawResponse;
+use crate::types::article::fold_header;
 use crate::types::prelude::*;
+use crate::types::response::article::encoded_word::decode_encoded_words;
 use crate::types::response::article::parse::take_headers;
 use crate::types::response::util::{err_if_not_kind, process_article_first_line};
 
@@ -11,21 +13,79 @@ use crate::types::response::util::{err_if_not_kind, process_article_first_line};
 ///
 /// Note that per [RFC 5322](https://tools.ietf.org/html/rfc5322#section-3.6) headers
 /// may be repeated (a common example is X-Received for emails mirrored onto Newsgroups)
-#[derive(Clone, Debug, Eq, PartialEq)]
+///
+/// Headers are kept in the order they first appear and names are matched case-insensitively.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 pub struct Headers {
-    pub(crate) inner: HashMap<String, Header>,
+    pub(crate) inner: Vec<Header>,
     pub(crate) len: u32,
 }
 
 /// An individual header within a [`Headers`] collection
-#[derive(Clone, Debug, Eq, PartialEq)]
+///
+/// Headers are compared by name and content, the retained raw bytes are ignored.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 pub struct Header {
     /// The name of the header
     pub name: String,
     /// One-or-more content values for the header
     pub content: Vec<String>,
+    /// The raw bytes of each content value, if retained
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) raw: Option<Vec<Vec<u8>>>,
 }
 
+impl Header {
+    /// Create a header with one-or-more content values
+    pub fn new(name: impl Into<String>, content: Vec<String>) -> Self {
+        Self {
+            name: name.into(),
+            content,
+            raw: None,
+        }
+    }
+
+    /// The raw bytes of each value in [`content`](Self::content)
+    ///
+    /// Returns `None` unless the headers were parsed with
+    /// [`ConnectionConfig::keep_raw_headers`](crate::raw::connection::ConnectionConfig::keep_raw_headers)
+    /// enabled. Values added with [`Headers::append`] or [`Headers::set`] are stored as their
+    /// UTF-8 bytes.
+    pub fn raw_content(&self) -> Option<&[Vec<u8>]> {
+        self.raw.as_deref()
+    }
+
+    /// Each value in [`content`](Self::content) unfolded, with
+    /// [RFC 2047](https://tools.ietf.org/html/rfc2047) encoded-words decoded
+    ///
+    /// UTF-8 and ISO-8859-1 encoded-words are decoded, encoded-words in other charsets are
+    /// left as is.
+    pub fn decoded(&self) -> Vec<String> {
+        self.content
+            .iter()
+            .map(|content| decode_encoded_words(&unfold(content)))
+            .collect()
+    }
+}
+
+impl PartialEq for Header {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.content == other.content
+    }
+}
+
+impl Eq for Header {}
+
 impl Headers {
     /// The total number of headers
     ///
@@ -41,20 +101,182 @@ impl Headers {
 
     /// Get a header by name
     pub fn get(&self, key: impl AsRef<str>) -> Option<&Header> {
-        self.inner.get(key.as_ref())
+        self.position(key.as_ref()).map(|i| &self.inner[i])
     }
 
     /// An iterator over the headers
     pub fn iter(&self) -> Iter<'_> {
         Iter {
-            inner: self.inner.values(),
+            inner: self.inner.iter(),
+        }
+    }
+
+    /// The hosts in the `Path` header, from the most recent to the originating host
+    ///
+    /// The path is split on `!` and empty entries are skipped. The last entry is usually not
+    /// a host (e.g. `not-for-mail`). Returns an empty `Vec` if the header is absent.
+    ///
+    /// For more information see [RFC 5536 3.1.5](https://tools.ietf.org/html/rfc5536#section-3.1.5)
+    pub fn path(&self) -> Vec<String> {
+        self.get("Path")
+            .and_then(|h| h.content.first())
+            .map(|path| {
+                unfold(path)
+                    .split('!')
+                    .map(str::trim)
+                    .filter(|host| !host.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The unfolded values of the `Received` headers in the order they appear in the article
+    pub fn received_chain(&self) -> Vec<String> {
+        self.get("Received")
+            .map(|h| h.content.iter().map(|c| unfold(c)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Add a header if one with the same name does not exist
+    ///
+    /// Returns false (leaving the headers untouched) if the header already exists.
+    pub fn insert(&mut self, name: impl AsRef<str>, value: impl AsRef<str>) -> bool {
+        if self.position(name.as_ref()).is_some() {
+            return false;
+        }
+        self.append(name, value);
+        true
+    }
+
+    /// Add a value to a header, adding the header to the end if it does not exist
+    pub fn append(&mut self, name: impl AsRef<str>, value: impl AsRef<str>) {
+        self.push(name.as_ref(), value.as_ref().to_string(), None)
+    }
+
+    /// Add a value to a header along with its raw bytes, if they are being retained
+    pub(crate) fn push(&mut self, name: &str, content: String, raw: Option<Vec<u8>>) {
+        match self.position(name) {
+            Some(i) => {
+                let header = &mut self.inner[i];
+                if let Some(raws) = &mut header.raw {
+                    raws.push(raw.unwrap_or_else(|| content.clone().into_bytes()));
+                }
+                header.content.push(content);
+            }
+            None => self.inner.push(Header {
+                name: name.to_string(),
+                content: vec![content],
+                raw: raw.map(|raw| vec![raw]),
+            }),
+        }
+        self.len += 1;
+    }
+
+    /// Replace all values of a header with a single value
+    ///
+    /// The header keeps its position, or is added to the end if it does not exist.
+    pub fn set(&mut self, name: impl AsRef<str>, value: impl AsRef<str>) {
+        match self.position(name.as_ref()) {
+            Some(i) => {
+                let header = &mut self.inner[i];
+                self.len -= header.content.len() as u32;
+                header.content = vec![value.as_ref().to_string()];
+                if let Some(raw) = &mut header.raw {
+                    *raw = vec![value.as_ref().as_bytes().to_vec()];
+                }
+                self.len += 1;
+            }
+            None => self.append(name, value),
+        }
+    }
+
+    /// Remove a header and all of its values
+    pub fn remove(&mut self, name: impl AsRef<str>) -> Option<Header> {
+        let header = self.inner.remove(self.position(name.as_ref())?);
+        self.len -= header.content.len() as u32;
+        Some(header)
+    }
+
+    /// Serialize the headers, including the empty line that ends the header section
+    ///
+    /// Values that were folded when parsed are written as is, other values are folded at
+    /// 78 characters. Values are not validated, see
+    /// [`ArticleBuilder`](crate::types::article::ArticleBuilder) for composing articles.
+    pub fn to_wire_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        for header in self.iter() {
+            for content in &header.content {
+                if content.contains('\n') {
+                    out.extend_from_slice(format!("{}: {}", header.name, content).as_bytes());
+                } else {
+                    out.extend_from_slice(fold_header(&header.name, content).as_bytes());
+                }
+                out.extend_from_slice(b"\r\n");
+            }
         }
+        out.extend_from_slice(b"\r\n");
+        out
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.inner
+            .iter()
+            .position(|h| h.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Parse the `Xref` header
+    ///
+    /// Returns `None` if the header is absent. Malformed `group:number` entries are skipped.
+    pub fn xref(&self) -> Option<Xref> {
+        let header = self.iter().find(|h| h.name.eq_ignore_ascii_case("Xref"))?;
+        header.content.first().and_then(|s| Xref::parse(s))
+    }
+}
+
+/// Remove the line breaks from a folded header value
+fn unfold(content: &str) -> String {
+    content.replace("\r\n", "")
+}
+
+/// The `Xref` header, which lists the article's number in each group that carries it
+///
+/// For more information see [RFC 5536 3.2.14](https://tools.ietf.org/html/rfc5536#section-3.2.14)
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub struct Xref {
+    /// The name of the server that assigned the numbers
+    pub host: String,
+    /// The group names and article numbers
+    pub entries: Vec<(String, ArticleNumber)>,
+}
+
+impl Xref {
+    fn parse(content: &str) -> Option<Self> {
+        let mut tokens = content.split_whitespace();
+        let host = tokens.next()?.to_string();
+        let entries = tokens
+            .filter_map(|token| {
+                let sep = token.rfind(':')?;
+                let (group, number) = (&token[..sep], &token[sep + 1..]);
+                match number.parse() {
+                    Ok(number) if !group.is_empty() => Some((group.to_string(), number)),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        Some(Self { host, entries })
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct Iter<'a> {
-    inner: hash_map::Values<'a, String, Header>,
+    inner: slice::Iter<'a, Header>,
 }
 
 impl<'a> Iterator for Iter<'a> {
@@ -67,6 +289,11 @@ impl<'a> Iterator for Iter<'a> {
 
 /// Article headers returned by [`HEAD`](https://tools.ietf.org/html/rfc3977#section-6.2.2)
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 pub struct Head {
     /// The number of the article unique to a particular newsgroup
     pub number: ArticleNumber,
@@ -82,15 +309,21 @@ impl TryFrom<&RawResponse> for Head {
     fn try_from(resp: &RawResponse) -> Result<Self> {
         err_if_not_kind(resp, Kind::Head)?;
 
-        let (number, message_id) = process_article_first_line(&resp)?;
+        let (number, message_id) = process_article_first_line(resp)?;
 
         let data_blocks = resp
             .data_blocks
             .as_ref()
             .ok_or_else(Error::missing_data_blocks)?;
 
-        let (_, headers) = take_headers(&data_blocks.payload())
-            .map_err(|e| Error::invalid_data_blocks(format!("{}", e)))?;
+        let payload = data_blocks.payload();
+        let (_, headers) =
+            take_headers(payload, data_blocks.keep_raw_headers).map_err(|e| match e {
+                nom::Err::Incomplete(n) => Error::de(format!("{:?}", n)),
+                nom::Err::Error((rest, _)) | nom::Err::Failure((rest, _)) => {
+                    Error::invalid_headers(payload, rest)
+                }
+            })?;
 
         Ok(Self {
             number,
@@ -99,3 +332,173 @@ impl TryFrom<&RawResponse> for Head {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xref() {
+        let article = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/text_article"));
+        // Skip the initial response line
+        let start = article.iter().position(|&b| b == b'\n').unwrap() + 1;
+        let (_, headers) = take_headers(&article[start..], false).unwrap();
+        assert_eq!(
+            headers.xref(),
+            Some(Xref {
+                host: "number.nntp.giganews.com".to_string(),
+                entries: vec![("mozilla.dev.platform".to_string(), 47661)],
+            })
+        );
+
+        let (_, headers) =
+            take_headers(b"Xref: host a.b:1 junk :2 c.d:x e.f:3\r\n\r\n", false).unwrap();
+        let xref = headers.xref().unwrap();
+        assert_eq!(xref.host, "host");
+        assert_eq!(
+            xref.entries,
+            vec![("a.b".to_string(), 1), ("e.f".to_string(), 3)]
+        );
+
+        let (_, headers) = take_headers(b"Subject: no xref\r\n\r\n", false).unwrap();
+        assert_eq!(headers.xref(), None);
+    }
+
+    #[test]
+    fn test_trace() {
+        let article = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/text_article"));
+        let start = article.iter().position(|&b| b == b'\n').unwrap() + 1;
+        let (_, headers) = take_headers(&article[start..], false).unwrap();
+        assert_eq!(
+            headers.path(),
+            vec![
+                "buffer1.nntp.dca1.giganews.com",
+                "border2.nntp.dca1.giganews.com",
+                "nntp.giganews.com",
+                "news-out.google.com",
+                "nntp.google.com",
+                "postnews.google.com",
+                "google-groups.googlegroups.com",
+                "not-for-mail",
+            ]
+        );
+        assert!(headers.received_chain().is_empty());
+
+        let (_, headers) = take_headers(
+            b"Received: from b.example.com\r\n\tby c.example.com\r\n\
+            Subject: hi\r\n\
+            received: from a.example.com by b.example.com\r\n\
+            Path: c!b!!a\r\n\r\n",
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            headers.received_chain(),
+            vec![
+                "from b.example.com\tby c.example.com",
+                "from a.example.com by b.example.com"
+            ]
+        );
+        assert_eq!(headers.path(), vec!["c", "b", "a"]);
+
+        let (_, headers) = take_headers(
+            b"Subject: =?UTF-8?B?SsO2cmc=?=\r\n =?ISO-8859-1?Q?_D=F8rr?=\r\n\r\n",
+            false,
+        )
+        .unwrap();
+        assert_eq!(headers.get("Subject").unwrap().decoded(), vec!["Jörg Dørr"]);
+        assert!(Headers::default().path().is_empty());
+    }
+
+    #[test]
+    fn test_mutation_round_trip() {
+        let article = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/text_article"));
+        let start = article.iter().position(|&b| b == b'\n').unwrap() + 1;
+        let (_, mut headers) = take_headers(&article[start..], false).unwrap();
+        let original_len = headers.len();
+
+        // Serializing without changes is lossless, apart from folding long lines
+        let unfolded = |headers: &Headers| {
+            headers
+                .iter()
+                .map(|h| {
+                    let content = h.content.iter().map(|c| c.replace("\r\n", ""));
+                    (h.name.clone(), content.collect::<Vec<_>>())
+                })
+                .collect::<Vec<_>>()
+        };
+        let (_, reparsed) = take_headers(&headers.to_wire_bytes(), false).unwrap();
+        assert_eq!(unfolded(&reparsed), unfolded(&headers));
+        assert_eq!(
+            reparsed.get("X-Received").unwrap(),
+            headers.get("X-Received").unwrap()
+        );
+
+        assert!(!headers.insert("subject", "ignored"));
+        assert!(headers.insert("X-New", "new"));
+        headers.append("x-new", "second");
+        headers.set("x-received", "replaced");
+        headers.set("Summary", "word ".repeat(30).trim_end());
+        assert_eq!(headers.remove("PATH").unwrap().name, "Path");
+        assert!(headers.remove("Path").is_none());
+        assert_eq!(headers.len(), original_len + 1);
+
+        let names = headers.iter().map(|h| h.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names[..2], ["X-Received", "Newsgroups"]);
+        assert_eq!(names[names.len() - 2..], ["X-New", "Summary"]);
+        assert_eq!(headers.get("X-RECEIVED").unwrap().content, vec!["replaced"]);
+        assert_eq!(headers.get("x-new").unwrap().content, vec!["new", "second"]);
+
+        let wire = headers.to_wire_bytes();
+        let summary = fold_header("Summary", &headers.get("Summary").unwrap().content[0]);
+        assert!(summary.contains("\r\n"));
+        assert!(String::from_utf8_lossy(&wire).contains(&summary));
+        let (rest, reparsed) = take_headers(&wire, false).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(reparsed.len(), headers.len());
+        assert_eq!(unfolded(&reparsed), unfolded(&headers));
+    }
+
+    #[test]
+    fn test_raw_ignored_by_eq() {
+        let payload = b"Subject: hello\r\n\r\n";
+        let (_, headers) = take_headers(payload, false).unwrap();
+        let (_, raw_headers) = take_headers(payload, true).unwrap();
+        assert!(raw_headers.get("Subject").unwrap().raw_content().is_some());
+        assert_eq!(headers, raw_headers);
+        assert_eq!(
+            headers.get("subject").unwrap(),
+            &Header::new("Subject", vec!["hello".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_invalid_headers() {
+        let payload = b"From: someone\r\nSubject: hello\r\nNot a header\r\n\r\nbody\r\n.\r\n";
+        let resp = RawResponse {
+            code: ResponseCode::Known(Kind::Head),
+            first_line: b"221 1 <a@b>\r\n".to_vec(),
+            data_blocks: Some(DataBlocks {
+                payload: payload.to_vec(),
+                line_boundaries: vec![],
+                keep_raw_headers: false,
+            }),
+        };
+
+        let err = Head::try_from(&resp).unwrap_err();
+        match err {
+            Error::InvalidHeaders { line, offset } => {
+                assert_eq!(line, 3);
+                assert_eq!(offset, 31);
+            }
+            ref other => panic!("Unexpected error {:?}", other),
+        }
+
+        let err = err.with_offending_bytes(&resp);
+        assert!(matches!(err.inner(), Error::InvalidHeaders { line: 3, .. }));
+        assert_eq!(
+            err.offending_bytes(),
+            Some((&b"221 1 <a@b>\r\n"[..], &payload[..]))
+        );
+    }
+}
@@ -1,3 +1,7 @@
+use std::ops::Range;
+
+use crate::raw::parse::strip_line_ending;
+
 /// An iterator over the lines of an Article body
 ///
 /// Created by [`BinaryArticle::lines`] and [`Body::lines`]
@@ -15,10 +19,27 @@ impl<'a> Iterator for Lines<'a> {
             .next()
             .map(|(start, end)| &self.payload[*start..*end])
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for Lines<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|(start, end)| &self.payload[*start..*end])
+    }
 }
 
+impl ExactSizeIterator for Lines<'_> {}
+
 /// An iterator over the unterimnated lines of an Article body
 ///
+/// A trailing CRLF or bare LF is stripped from each line, lines without a terminator are
+/// yielded as is.
+///
 /// Created by [`BinaryArticle::unterminated`] and [`Body::Lines`]
 #[derive(Clone, Debug)]
 pub struct Unterminated<'a> {
@@ -29,6 +50,47 @@ impl<'a> Iterator for Unterminated<'a> {
     type Item = &'a [u8];
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(|b| &b[..b.len() - 2])
+        self.inner.next().map(strip_line_ending)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for Unterminated<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(strip_line_ending)
+    }
+}
+
+impl ExactSizeIterator for Unterminated<'_> {}
+
+/// The line boundaries within `range`, clamped to the available lines
+pub(crate) fn clamp_lines(
+    line_boundaries: &[(usize, usize)],
+    range: Range<usize>,
+) -> &[(usize, usize)] {
+    let end = range.end.min(line_boundaries.len());
+    let start = range.start.min(end);
+    &line_boundaries[start..end]
+}
+
+/// Find the boundaries of the lines within a payload
+///
+/// Each line includes its LF, a trailing line without one is included as is.
+#[cfg(feature = "serde")]
+pub(crate) fn split_lines(payload: &[u8]) -> Vec<(usize, usize)> {
+    let mut line_boundaries = Vec::new();
+    let mut start = 0;
+    for (i, b) in payload.iter().enumerate() {
+        if *b == b'\n' {
+            line_boundaries.push((start, i + 1));
+            start = i + 1;
+        }
+    }
+    if start < payload.len() {
+        line_boundaries.push((start, payload.len()));
     }
+    line_boundaries
 }
@@ -4,6 +4,9 @@ mod body;
 /// Binary articles
 mod binary;
 
+/// RFC 2047 encoded-words within headers
+mod encoded_word;
+
 /// Article headers
 mod headers;
 
@@ -11,7 +14,7 @@ mod headers;
 mod iter;
 
 /// Parsing logic for for article headers
-mod parse;
+pub(crate) mod parse;
 
 /// Article status
 mod stat;
@@ -21,6 +24,6 @@ mod text;
 
 pub use binary::BinaryArticle;
 pub use body::Body;
-pub use headers::{Head, Header, Headers};
+pub use headers::{Head, Header, Headers, Xref};
 pub use stat::Stat;
 pub use text::TextArticle;
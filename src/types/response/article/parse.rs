@@ -1,5 +1,3 @@
-use std::collections::HashMap;
-
 use log::*;
 use nom::branch::alt;
 use nom::bytes::complete::{take, take_while1};
@@ -10,13 +8,13 @@ use nom::multi::{fold_many1, many0};
 use nom::sequence::{terminated, tuple};
 use nom::IResult;
 
-use crate::types::prelude::{Header, Headers};
+use crate::types::prelude::Headers;
 
 /// Returns true if the character is any ASCII non-control character other than a colon
 ///
 /// [A-NOTCOLON](https://tools.ietf.org/html/rfc3977#section-9.8)
 fn is_a_notcolon(chr: u8) -> bool {
-    (chr >= 0x21 && chr <= 0x39) || (chr >= 0x3b && chr <= 0x7e)
+    (0x21..=0x39).contains(&chr) || (0x3b..=0x7e).contains(&chr)
 }
 
 /// Returns true if the slice is UTF-8 and contains no ascii characters
@@ -35,7 +33,7 @@ fn is_utf8_non_ascii(b: &[u8]) -> bool {
 ///
 /// [`A-CHAR`](https://tools.ietf.org/html/rfc3977#section-9.8)
 fn is_a_char(chr: u8) -> bool {
-    chr >= 0x21 && chr <= 0x7e
+    (0x21..=0x7e).contains(&chr)
 }
 
 /// Returns true if the byte slice is a *single* non ASCII non-control char
@@ -70,13 +68,25 @@ fn take_utf8_non_ascii(b: &[u8]) -> IResult<&[u8], &[u8]> {
     ))(b)
 }
 
+/// Take a single byte that is not ASCII
+///
+/// This accepts bytes from legacy encodings (e.g. Latin-1) that are not valid UTF-8
+fn take_non_ascii_byte(b: &[u8]) -> IResult<&[u8], &[u8]> {
+    verify(take(1u8), |byte: &[u8]| !byte[0].is_ascii())(b)
+}
+
 /// Take a single `A-CHAR` or `UTF8-non-ascii` from the slice
 /// ```abnf
 /// P-CHAR     = A-CHAR / UTF8-non-ascii
 /// A-CHAR     = %x21-7E
 /// ```
+///
+/// # Non-Compliant Encodings
+///
+/// Old and spammy articles often contain raw bytes in other encodings, so any non-ASCII byte
+/// is accepted as well. The content is converted to UTF-8 lossily when the headers are built.
 fn take_p_char(b: &[u8]) -> IResult<&[u8], &[u8]> {
-    alt((take_a_char, take_utf8_non_ascii))(b)
+    alt((take_a_char, take_utf8_non_ascii, take_non_ascii_byte))(b)
 }
 
 /// Take the header-name from a slice
@@ -121,8 +131,8 @@ fn take_ascii_byte(b: &[u8]) -> IResult<&[u8], &[u8]> {
 /// # Non-Compliant Whitespace
 ///
 /// * All of the header RFCs I've come indicate there is no whitespace allowed between tokens and
-/// CLRF characters. Thankfully mail servers don't follow RFCs and violate this anyways so we
-/// do allow this *non-compliant* behavior to ease user suffering
+///   CLRF characters. Thankfully mail servers don't follow RFCs and violate this anyways so we
+///   do allow this *non-compliant* behavior to ease user suffering
 fn take_header_content(b: &[u8]) -> IResult<&[u8], &[u8]> {
     let (rest, (_ws, _token, _more_tokens)) = tuple((
         space0,
@@ -143,47 +153,54 @@ fn take_header_content(b: &[u8]) -> IResult<&[u8], &[u8]> {
 /// header = header-name ":" SP [header-content] CRLF
 /// header-content = [WS] token *( [CRLF] WS token )
 /// ```
+///
+/// # Non-Compliant Headers
+///
+/// [RFC 5322](https://tools.ietf.org/html/rfc5322#section-2.2) is more permissive than
+/// RFC 3977 and real articles make use of it, so:
+///
+/// * The space after the colon is optional (e.g. `X-Something:value`)
+/// * The content may be empty or whitespace only (e.g. `Cc:`), which yields empty content
+/// * The content may start on a folded line (e.g. `Cc:\r\n someone@example.com`)
+/// * Trailing whitespace before the CRLF is ignored
 fn take_header(b: &[u8]) -> IResult<&[u8], (&[u8], &[u8])> {
-    // he
-    let (rest, (header_name, _, _, header_content)) = terminated(
+    let (rest, (header_name, _, _, _, header_content)) = terminated(
         tuple((
             take_header_name,
             char(':'),
-            char(' '),
+            space0,
+            opt(tuple((crlf, space1))),
             opt(take_header_content),
         )),
-        crlf,
+        tuple((space0, crlf)),
     )(b)?;
     Ok((rest, (header_name, header_content.unwrap_or_default())))
 }
 
-pub(crate) fn take_headers(b: &[u8]) -> IResult<&[u8], Headers> {
+/// Parse headers, retaining the raw bytes of each header's content if `keep_raw` is set
+pub(crate) fn take_headers(b: &[u8], keep_raw: bool) -> IResult<&[u8], Headers> {
     // n.b. assuming there are no parsing bugs (big if there), it should be sound to use
     // from_utf8_unchecked on header names since we already did utf8 checks while parsing.
 
     let fold_headers = fold_many1(
         take_header,
-        (HashMap::new(), 0),
-        |(mut map, mut len), (name, content)| {
-            let name = String::from_utf8_lossy(name).to_string();
-            let content = String::from_utf8_lossy(content).to_string();
+        Headers::default(),
+        |mut headers, (name, content)| {
+            let name = String::from_utf8_lossy(name);
+            let raw = if keep_raw {
+                Some(content.to_vec())
+            } else {
+                None
+            };
+            let content = String::from_utf8_lossy(content);
             trace!("Found header name `{}` -- `{}`", name, content);
 
-            let header = map.entry(name.clone()).or_insert(Header {
-                name,
-                content: vec![],
-            });
-            header.content.push(content);
-
-            len += 1;
-
-            (map, len)
+            headers.push(&name, content.into_owned(), raw);
+            headers
         },
     );
 
-    let (rest, (inner, len)) = terminated(fold_headers, crlf)(b)?;
-
-    let headers = Headers { inner, len };
+    let (rest, headers) = terminated(fold_headers, crlf)(b)?;
 
     Ok((rest, headers))
 }
@@ -207,13 +224,13 @@ mod tests {
                 .iter()
                 .for_each(|b| {
                     println!("Testing `{}` -- {:?}", from_utf8(b).unwrap(), b);
-                    assert_eq!(is_utf8_non_ascii(b), true)
+                    assert!(is_utf8_non_ascii(b))
                 });
         }
 
         #[test]
         fn fail_ascii() {
-            assert_eq!(is_utf8_non_ascii(b"1"), false)
+            assert!(!is_utf8_non_ascii(b"1"))
         }
     }
 
@@ -283,8 +300,8 @@ mod tests {
             Thu, 05 Jun 2020 10:13:41 -0700 (PDT)\r\n";
 
             let (rest, (header_name, parsed_content)) = take_header(FOLDED_HEADER).unwrap();
-            dbg!(from_utf8(&header_name).unwrap());
-            dbg!(from_utf8(&rest).unwrap());
+            dbg!(from_utf8(header_name).unwrap());
+            dbg!(from_utf8(rest).unwrap());
             assert_eq!(rest.len(), 0);
             assert_eq!(header_name, &b"X-Received"[..]);
             assert_eq!(parsed_content, &content[..content.len() - 2])
@@ -312,6 +329,32 @@ mod tests {
             assert_eq!(content, b"");
         }
 
+        #[test]
+        fn test_relaxed() {
+            let cases: &[(&[u8], &[u8], &[u8])] = &[
+                (b"Cc:\r\n", b"Cc", b""),
+                (b"Cc: \t \r\n", b"Cc", b""),
+                (b"X-Something:value\r\n", b"X-Something", b"value"),
+                (b"Subject: trailing  \r\n", b"Subject", b"trailing"),
+                (
+                    b"To:\r\n someone@example.com\r\n",
+                    b"To",
+                    b"someone@example.com",
+                ),
+            ];
+
+            for (header, name, content) in cases {
+                let (rest, parsed) = take_header(header).unwrap();
+                assert!(rest.is_empty(), "{:?}", from_utf8(header));
+                assert_eq!(parsed, (*name, *content));
+            }
+
+            // An empty header is not mistaken for a fold when followed by the end of the headers
+            let (rest, (name, content)) = take_header(b"Cc:\r\n\r\nbody\r\n").unwrap();
+            assert_eq!((name, content), (&b"Cc"[..], &b""[..]));
+            assert_eq!(rest, b"\r\nbody\r\n");
+        }
+
         #[test]
         fn test_non_compliant_whitespace() {
             let header = b"X-Received: by 2002:a65:508c:: with SMTP id r12mr626047pgp.233.1591751885013; \r\n Tue, 09 Jun 2020 18:18:05 -0700 (PDT)\r\n";
@@ -324,16 +367,60 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_take_headers_mixed_encodings() {
+        let article = b"From: J\xc3\xbcrgen <j@example.com>\r\n\
+            Subject: f\xfchler\r\n\
+            X-Folded: caf\xe9\r\n cr\xc3\xa8me \xff\r\n\
+            \r\n\
+            body\r\n";
+        let (rest, headers) = take_headers(article, false).unwrap();
+
+        assert_eq!(rest, b"body\r\n");
+        assert_eq!(
+            headers.get("From").unwrap().content,
+            vec!["J\u{fc}rgen <j@example.com>"]
+        );
+        assert_eq!(
+            headers.get("Subject").unwrap().content,
+            vec!["f\u{fffd}hler"]
+        );
+        assert_eq!(
+            headers.get("X-Folded").unwrap().content,
+            vec!["caf\u{fffd}\r\n cr\u{e8}me \u{fffd}"]
+        );
+    }
+
+    #[test]
+    fn test_take_headers_relaxed() {
+        let article = b"Path: news.gmane.io!.POSTED!not-for-mail\r\n\
+            From: Someone <someone@example.com>\r\n\
+            Newsgroups: gmane.test\r\n\
+            Subject:no space\r\n\
+            Cc:\r\n\
+            X-Face:\r\n \"x&$%\r\n\
+            Message-ID: <1@example.com>\r\n\
+            \r\n\
+            body\r\n";
+        let (rest, headers) = take_headers(article, false).unwrap();
+
+        assert_eq!(rest, b"body\r\n");
+        assert_eq!(headers.len(), 7);
+        assert_eq!(headers.get("Subject").unwrap().content, vec!["no space"]);
+        assert_eq!(headers.get("Cc").unwrap().content, vec![""]);
+        assert_eq!(headers.get("X-Face").unwrap().content, vec!["\"x&$%"]);
+    }
+
     #[test]
     fn test_take_headers() {
         // strip the initial response line
         let article = TEXT_ARTICLE.splitn(2, '\n').nth(1).unwrap();
-        let (rest, headers) = take_headers(article.as_bytes()).unwrap();
+        let (rest, headers) = take_headers(article.as_bytes(), false).unwrap();
 
         println!("{:#?}", headers);
 
         assert!(rest.starts_with(b"In bug 1630935 [1], I intend to deprecate support for drawing"));
-        assert!(headers.inner.contains_key("X-Received"));
+        assert!(headers.get("x-received").is_some());
         assert_eq!(headers.get("X-Received").unwrap().content.len(), 2);
     }
 }
@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::convert::TryFrom;
 
 use crate::error::{Error, Result};
@@ -5,7 +6,14 @@ use crate::types::prelude::*;
 use crate::types::response::util::{err_if_not_kind, process_article_first_line};
 
 /// Article metadata returned by [`STAT`](https://tools.ietf.org/html/rfc3977#section-6.2.4)
-#[derive(Clone, Debug, Eq, PartialEq)]
+///
+/// `Stat`s are ordered by article `number`, ties are broken by `message_id`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 pub struct Stat {
     /// The number of the article unique to a particular newsgroup
     pub number: ArticleNumber,
@@ -13,6 +21,20 @@ pub struct Stat {
     pub message_id: String,
 }
 
+impl Ord for Stat {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.number
+            .cmp(&other.number)
+            .then_with(|| self.message_id.cmp(&other.message_id))
+    }
+}
+
+impl PartialOrd for Stat {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl TryFrom<&RawResponse> for Stat {
     type Error = Error;
 
@@ -24,3 +46,35 @@ impl TryFrom<&RawResponse> for Stat {
         Ok(Self { number, message_id })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stat(number: ArticleNumber, message_id: &str) -> Stat {
+        Stat {
+            number,
+            message_id: message_id.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_ordering() {
+        let mut stats = vec![
+            stat(3, "<c@x>"),
+            stat(1, "<z@x>"),
+            stat(2, "<b@x>"),
+            stat(1, "<a@x>"),
+        ];
+        stats.sort();
+        assert_eq!(
+            stats,
+            vec![
+                stat(1, "<a@x>"),
+                stat(1, "<z@x>"),
+                stat(2, "<b@x>"),
+                stat(3, "<c@x>")
+            ]
+        );
+    }
+}
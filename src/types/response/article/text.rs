@@ -9,8 +9,13 @@ use crate::types::prelude::*;
 ///
 /// * [`from_binary`](`Self::from_binary`) is fallible as it performs UTF-8 checks
 /// * [`from_binary_lossy`](Self::from_binary_lossy) is infallible but will replace
-/// non UTF-8 characters with placeholders. Please see [`String::from_utf8_lossy`] for more info.
+///   non UTF-8 characters with placeholders. Please see [`String::from_utf8_lossy`] for more info.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 pub struct TextArticle {
     pub(crate) number: ArticleNumber,
     pub(crate) message_id: String,
@@ -50,6 +55,32 @@ impl TextArticle {
         b.to_text_lossy()
     }
 
+    /// Headers for a follow-up to this article
+    ///
+    /// The headers are cloned with the article's message id appended to `References`,
+    /// the `Subject` prefixed with a single `Re: `, and the `Message-ID` removed. Other headers
+    /// (e.g. `Path` or `Xref`) are kept as is and may need to be removed before posting, see
+    /// [`ArticleBuilder::from_headers`](crate::types::article::ArticleBuilder::from_headers).
+    pub fn reply_template(&self) -> Headers {
+        let mut headers = self.headers.clone();
+
+        let references = match headers.get("References").and_then(|h| h.content.first()) {
+            Some(refs) => format!("{} {}", refs.replace("\r\n", "").trim(), self.message_id),
+            None => self.message_id.clone(),
+        };
+        headers.set("References", references);
+
+        let subject = headers
+            .get("Subject")
+            .and_then(|h| h.content.first())
+            .map(|s| strip_reply_prefixes(&s.replace("\r\n", "")).to_string())
+            .unwrap_or_default();
+        headers.set("Subject", format!("Re: {}", subject));
+
+        headers.remove("Message-ID");
+        headers
+    }
+
     /// An iterator over the lines in the body of the article
     ///
     /// Each line _will not_ include the CRLF terminator
@@ -58,6 +89,17 @@ impl TextArticle {
     }
 }
 
+/// Remove any number of leading `Re:` prefixes (case-insensitive) from a subject
+fn strip_reply_prefixes(mut subject: &str) -> &str {
+    loop {
+        subject = subject.trim_start();
+        match subject.get(..3) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("re:") => subject = &subject[3..],
+            _ => return subject,
+        }
+    }
+}
+
 /// Created with [`TextArticle::lines`]
 #[derive(Clone, Debug)]
 pub struct Lines<'a>(std::slice::Iter<'a, String>);
@@ -69,3 +111,57 @@ impl<'a> Iterator for Lines<'a> {
         self.0.next()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::article::ArticleBuilder;
+    use crate::types::response::article::parse::take_headers;
+
+    fn article(headers: &[u8]) -> TextArticle {
+        TextArticle {
+            number: 1,
+            message_id: "<2@example.com>".to_string(),
+            headers: take_headers(headers, false).unwrap().1,
+            body: vec![],
+        }
+    }
+
+    #[test]
+    fn test_reply_template() {
+        let headers = article(
+            b"From: jane@example.com\r\nNewsgroups: misc.test\r\nSubject: RE: re:Hello\r\n\
+            Message-ID: <2@example.com>\r\nReferences: <1@example.com>\r\n\r\n",
+        )
+        .reply_template();
+
+        assert_eq!(headers.get("Subject").unwrap().content, vec!["Re: Hello"]);
+        assert_eq!(
+            headers.get("References").unwrap().content,
+            vec!["<1@example.com> <2@example.com>"]
+        );
+        assert!(headers.get("Message-ID").is_none());
+
+        let reply = ArticleBuilder::from_headers(&headers)
+            .from("joe@example.com")
+            .body_text("Hi!")
+            .build()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(reply).unwrap(),
+            "From: joe@example.com\r\n\
+             Newsgroups: misc.test\r\n\
+             Subject: Re: Hello\r\n\
+             References: <1@example.com> <2@example.com>\r\n\
+             \r\n\
+             Hi!\r\n"
+        );
+
+        let headers = article(b"Subject: Hello\r\n\r\n").reply_template();
+        assert_eq!(headers.get("Subject").unwrap().content, vec!["Re: Hello"]);
+        assert_eq!(
+            headers.get("References").unwrap().content,
+            vec!["<2@example.com>"]
+        );
+    }
+}
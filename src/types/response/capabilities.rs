@@ -7,13 +7,28 @@ use crate::types::prelude::*;
 use crate::types::response::util::err_if_not_kind;
 
 /// Server capabilities
-#[derive(Clone, Debug, Eq, PartialEq)]
+///
+/// Capability labels and arguments are matched case-insensitively as servers vary in how
+/// they advertise them.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 pub struct Capabilities(HashMap<String, Capability>);
 
 /// A capability advertised by the server
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 pub struct Capability {
+    /// The capability label as advertised by the server
     pub name: String,
+    /// Any arguments following the label
     pub args: Option<HashSet<String>>,
 }
 
@@ -27,7 +42,97 @@ impl Capabilities {
 
     /// Retrieve a capability if it exists
     pub fn get(&self, key: impl AsRef<str>) -> Option<&Capability> {
-        self.0.get(key.as_ref())
+        self.0.get(&key.as_ref().to_ascii_uppercase())
+    }
+
+    /// Returns true if the server advertises the capability
+    pub fn supports(&self, name: impl AsRef<str>) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Returns true if the server advertises the capability with the provided argument
+    ///
+    /// e.g. `supports_arg("LIST", "HEADERS")`
+    pub fn supports_arg(&self, name: impl AsRef<str>, arg: impl AsRef<str>) -> bool {
+        self.get(name).map(|cap| cap.has_arg(arg)).unwrap_or(false)
+    }
+
+    /// The highest protocol version advertised by the `VERSION` capability
+    pub fn version(&self) -> Option<u32> {
+        self.get("VERSION")?
+            .args
+            .as_ref()?
+            .iter()
+            .filter_map(|v| v.parse().ok())
+            .max()
+    }
+
+    /// Returns true if the server advertises `READER`
+    pub fn reader(&self) -> bool {
+        self.supports("READER")
+    }
+
+    /// Returns true if the server advertises `POST`
+    pub fn post(&self) -> bool {
+        self.supports("POST")
+    }
+
+    /// Returns true if the server advertises `HDR`
+    ///
+    /// The fields that may be retrieved are not part of the capability, they must be
+    /// retrieved with `LIST HEADERS`
+    pub fn hdr(&self) -> bool {
+        self.supports("HDR")
+    }
+
+    /// Returns true if the server supports `OVER` with a message-id argument
+    pub fn over_msgid(&self) -> bool {
+        self.supports_arg("OVER", "MSGID")
+    }
+
+    /// Returns true if the server accepts `AUTHINFO USER`
+    ///
+    /// Per [RFC 4643](https://tools.ietf.org/html/rfc4643#section-2.2) the server may
+    /// advertise `AUTHINFO` without `USER` if e.g. TLS must be negotiated first.
+    pub fn authinfo_user(&self) -> bool {
+        self.supports_arg("AUTHINFO", "USER")
+    }
+
+    /// The SASL mechanisms advertised by the server, uppercased and sorted
+    pub fn sasl_mechanisms(&self) -> Vec<String> {
+        self.args_of("SASL")
+    }
+
+    /// The algorithms advertised via the [RFC 8054](https://tools.ietf.org/html/rfc8054)
+    /// `COMPRESS` capability, uppercased and sorted
+    pub fn compress_algorithms(&self) -> Vec<String> {
+        self.args_of("COMPRESS")
+    }
+
+    fn args_of(&self, name: &str) -> Vec<String> {
+        let mut args = self
+            .get(name)
+            .and_then(|cap| cap.args.as_ref())
+            .map(|args| {
+                args.iter()
+                    .map(|arg| arg.to_ascii_uppercase())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        args.sort();
+        args.dedup();
+        args
+    }
+}
+
+impl Capability {
+    /// Returns true if the capability has the provided argument (ignoring case)
+    pub fn has_arg(&self, arg: impl AsRef<str>) -> bool {
+        let arg = arg.as_ref();
+        match self.args.as_ref() {
+            Some(args) => args.iter().any(|a| a.eq_ignore_ascii_case(arg)),
+            None => false,
+        }
     }
 }
 
@@ -73,7 +178,7 @@ impl TryFrom<&RawResponse> for Capabilities {
         let db_iter = resp
             .data_blocks
             .as_ref()
-            .ok_or_else(|| Error::de("Missing data blocks."))
+            .ok_or_else(Error::missing_data_blocks)
             .map(DataBlocks::unterminated)?;
 
         let capabilities: HashMap<String, Capability> = db_iter
@@ -83,7 +188,7 @@ impl TryFrom<&RawResponse> for Capabilities {
                 let label = entry_iter
                     .next()
                     .map(ToString::to_string)
-                    .ok_or_else(|| Error::de("Entry does not have a label"))?;
+                    .ok_or_else(|| Error::missing_field("label"))?;
 
                 let args = if entry_iter.peek().is_some() {
                     Some(entry_iter.map(ToString::to_string).collect::<HashSet<_>>())
@@ -91,15 +196,81 @@ impl TryFrom<&RawResponse> for Capabilities {
                     None
                 };
 
-                let cap = Capability {
-                    name: label.clone(),
-                    args,
-                };
+                let key = label.to_ascii_uppercase();
+                let cap = Capability { name: label, args };
 
-                Ok((label, cap))
+                Ok((key, cap))
             })
             .collect::<Result<_>>()?;
 
         Ok(Self(capabilities))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capabilities(payload: &[u8]) -> Capabilities {
+        let resp = RawResponse {
+            code: ResponseCode::Known(Kind::Capabilities),
+            first_line: b"101 Capability list:\r\n".to_vec(),
+            data_blocks: Some(DataBlocks::from_payload(payload)),
+        };
+        Capabilities::try_from(&resp).unwrap()
+    }
+
+    #[test]
+    fn test_inn_capabilities() {
+        let caps = capabilities(
+            b"VERSION 2\r\n\
+            IMPLEMENTATION INN 2.6.3\r\n\
+            AUTHINFO USER\r\n\
+            COMPRESS DEFLATE\r\n\
+            HDR\r\n\
+            LIST ACTIVE ACTIVE.TIMES COUNTS DISTRIB.PATS HEADERS MOTD NEWSGROUPS OVERVIEW.FMT\r\n\
+            NEWNEWS\r\n\
+            OVER\r\n\
+            POST\r\n\
+            READER\r\n\
+            SASL PLAIN\r\n\
+            STARTTLS\r\n\
+            .\r\n",
+        );
+
+        assert_eq!(caps.version(), Some(2));
+        assert!(caps.reader());
+        assert!(caps.post());
+        assert!(caps.hdr());
+        assert!(!caps.over_msgid());
+        assert!(caps.authinfo_user());
+        assert!(caps.supports_arg("LIST", "HEADERS"));
+        assert_eq!(caps.sasl_mechanisms(), vec!["PLAIN"]);
+        assert_eq!(caps.compress_algorithms(), vec!["DEFLATE"]);
+    }
+
+    #[test]
+    fn test_capabilities_case_insensitive() {
+        let caps = capabilities(
+            b"Version 2 3\r\n\
+            reader\r\n\
+            Over msgid\r\n\
+            authinfo user sasl\r\n\
+            SASL digest-md5 PLAIN\r\n\
+            XFEATURE-COMPRESS GZIP TERMINATOR\r\n\
+            .\r\n",
+        );
+
+        assert_eq!(caps.version(), Some(3));
+        assert!(caps.reader());
+        assert!(!caps.post());
+        assert!(caps.over_msgid());
+        assert!(caps.authinfo_user());
+        assert!(caps.supports("xfeature-compress"));
+        assert!(caps.supports_arg("XFEATURE-COMPRESS", "gzip"));
+        assert_eq!(caps.sasl_mechanisms(), vec!["DIGEST-MD5", "PLAIN"]);
+        assert!(caps.compress_algorithms().is_empty());
+        // The label is preserved as advertised
+        assert_eq!(caps.get("READER").unwrap().name, "reader");
+    }
+}
@@ -1,11 +1,21 @@
+use std::cmp::Ordering;
 use std::convert::TryFrom;
+use std::ops::RangeInclusive;
 
 use crate::error::{Error, Result};
 use crate::types::prelude::*;
 use crate::types::response::util::{err_if_not_kind, parse_field};
 
 /// Newsgroup metadata returned by [`GROUP`](https://tools.ietf.org/html/rfc3977#section-6.1.1)
-#[derive(Clone, Debug, Eq, PartialEq)]
+///
+/// `Group`s are ordered by `name`, ties (e.g. the same group selected at different times)
+/// are broken by `number`, `low`, and then `high`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 pub struct Group {
     /// The _estimated_ number of articles in the group
     pub number: ArticleNumber,
@@ -17,6 +27,45 @@ pub struct Group {
     pub name: String,
 }
 
+impl Group {
+    /// Returns true if the group contains no articles
+    ///
+    /// Per [RFC 3977 6.1.1](https://tools.ietf.org/html/rfc3977#section-6.1.1) an empty group
+    /// reports a `number` of zero, and its `high` may be lower than its `low`.
+    pub fn is_empty(&self) -> bool {
+        self.number == 0
+    }
+
+    /// The range of article numbers reported for the group
+    ///
+    /// The range is empty if the group is [empty](Self::is_empty), even if the server reported
+    /// a `low` less than or equal to `high`.
+    pub fn range(&self) -> RangeInclusive<ArticleNumber> {
+        let (low, high) = if self.is_empty() {
+            (1, 0)
+        } else {
+            (self.low, self.high)
+        };
+        low..=high
+    }
+}
+
+impl Ord for Group {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.name
+            .cmp(&other.name)
+            .then_with(|| self.number.cmp(&other.number))
+            .then_with(|| self.low.cmp(&other.low))
+            .then_with(|| self.high.cmp(&other.high))
+    }
+}
+
+impl PartialOrd for Group {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl TryFrom<&RawResponse> for Group {
     type Error = Error;
 
@@ -42,3 +91,95 @@ impl TryFrom<&RawResponse> for Group {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(first_line: &str) -> RawResponse {
+        RawResponse {
+            code: ResponseCode::from(first_line[..3].parse::<u16>().unwrap()),
+            first_line: first_line.as_bytes().to_vec(),
+            data_blocks: None,
+        }
+    }
+
+    #[test]
+    fn happy_path() {
+        let group = Group::try_from(&response("211 1234 3000234 3002322 misc.test\r\n")).unwrap();
+        assert_eq!(
+            group,
+            Group {
+                number: 1234,
+                low: 3000234,
+                high: 3002322,
+                name: "misc.test".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_range() {
+        let group = Group::try_from(&response("211 3 10 12 misc.test\r\n")).unwrap();
+        assert!(!group.is_empty());
+        assert_eq!(group.range(), 10..=12);
+        assert_eq!(group.range().count(), 3);
+
+        for line in &["211 0 10 9 misc.test\r\n", "211 0 0 0 misc.test\r\n"] {
+            let group = Group::try_from(&response(line)).unwrap();
+            assert!(group.is_empty());
+            assert_eq!(group.range().count(), 0);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        match Group::try_from(&response("211 1234 3000234\r\n")) {
+            Err(Error::MissingField { field }) => assert_eq!(field, "high"),
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        match Group::try_from(&response("211 1234 abc 3002322 misc.test\r\n")) {
+            Err(Error::InvalidField { field, value }) => {
+                assert_eq!(field, "low");
+                assert_eq!(value, "abc");
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        match Group::try_from(&response("411 No such group\r\n")) {
+            Err(Error::UnexpectedResponseCode { expected, got }) => {
+                assert_eq!(expected, vec![ResponseCode::Known(Kind::GroupSelected)]);
+                assert_eq!(got, ResponseCode::Known(Kind::NoSuchNewsgroup));
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_ordering() {
+        let group = |name: &str, number| Group {
+            number,
+            low: 1,
+            high: number,
+            name: name.to_string(),
+        };
+        let mut groups = vec![
+            group("misc.test", 5),
+            group("alt.test", 9),
+            group("misc.test", 2),
+        ];
+        groups.sort();
+        assert_eq!(
+            groups,
+            vec![
+                group("alt.test", 9),
+                group("misc.test", 2),
+                group("misc.test", 5)
+            ]
+        );
+
+        let set = groups.into_iter().collect::<std::collections::HashSet<_>>();
+        assert!(set.contains(&group("alt.test", 9)));
+    }
+}
@@ -0,0 +1,191 @@
+use std::convert::TryFrom;
+
+use crate::error::{Error, Result};
+use crate::types::prelude::*;
+use crate::types::response::util::err_if_not_kind;
+
+/// Header values returned by [`HDR`](https://tools.ietf.org/html/rfc3977#section-8.5)
+///
+/// Each entry is a pair of the article number and the value of the requested field.
+/// Per the RFC the article number will be `0` when the article was requested by message-id.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HdrResponse {
+    /// Article number and field value pairs
+    pub entries: Vec<(ArticleNumber, String)>,
+}
+
+impl HdrResponse {
+    /// An iterator over the article number and field value pairs
+    pub fn iter(&self) -> std::slice::Iter<'_, (ArticleNumber, String)> {
+        self.entries.iter()
+    }
+
+    /// The number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl TryFrom<&RawResponse> for HdrResponse {
+    type Error = Error;
+
+    fn try_from(resp: &RawResponse) -> Result<Self> {
+        err_if_not_kind(resp, Kind::Hdr)?;
+
+        let entries = parse_number_value_pairs(resp)?;
+
+        Ok(Self { entries })
+    }
+}
+
+/// Header values returned by [`XHDR`](https://tools.ietf.org/html/rfc2980#section-2.6)
+///
+/// This is the [RFC 2980](https://tools.ietf.org/html/rfc2980) predecessor of [`HdrResponse`]
+/// and has the same shape. Unlike `HDR`, servers may return `(none)` as the value for
+/// articles lacking the header.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct XHdrResponse {
+    /// Article number and field value pairs
+    pub entries: Vec<(ArticleNumber, String)>,
+}
+
+impl XHdrResponse {
+    /// An iterator over the article number and field value pairs
+    pub fn iter(&self) -> std::slice::Iter<'_, (ArticleNumber, String)> {
+        self.entries.iter()
+    }
+
+    /// The number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl TryFrom<&RawResponse> for XHdrResponse {
+    type Error = Error;
+
+    fn try_from(resp: &RawResponse) -> Result<Self> {
+        err_if_not_kind(resp, Kind::Head)?;
+
+        let entries = parse_number_value_pairs(resp)?;
+
+        Ok(Self { entries })
+    }
+}
+
+impl From<XHdrResponse> for HdrResponse {
+    fn from(xhdr: XHdrResponse) -> Self {
+        HdrResponse {
+            entries: xhdr.entries,
+        }
+    }
+}
+
+/// Parse data blocks consisting of lines containing an article number and a value
+///
+/// The value is everything following the first space and may be empty.
+pub(crate) fn parse_number_value_pairs(resp: &RawResponse) -> Result<Vec<(ArticleNumber, String)>> {
+    resp.data_blocks
+        .as_ref()
+        .ok_or_else(Error::missing_data_blocks)?
+        .unterminated()
+        .map(String::from_utf8_lossy)
+        .map(|line| {
+            let mut iter = line.splitn(2, ' ');
+            let number = iter
+                .next()
+                .filter(|s| !s.is_empty())
+                .ok_or_else(|| Error::missing_field("article-number"))?;
+            let number = number
+                .parse()
+                .map_err(|_| Error::invalid_field("article-number", number))?;
+            let value = iter.next().unwrap_or_default().to_string();
+
+            Ok((number, value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(payload: &[u8]) -> RawResponse {
+        RawResponse {
+            code: ResponseCode::Known(Kind::Hdr),
+            first_line: b"225 Headers follow\r\n".to_vec(),
+            data_blocks: Some(DataBlocks::from_payload(payload)),
+        }
+    }
+
+    #[test]
+    fn happy_path() {
+        let resp = response(b"3000234 I am just a test article\r\n3000235 Re: test\r\n.\r\n");
+        let hdr = HdrResponse::try_from(&resp).unwrap();
+
+        assert_eq!(
+            hdr.entries,
+            vec![
+                (3000234, "I am just a test article".to_string()),
+                (3000235, "Re: test".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_message_id_and_empty_values() {
+        let resp = response(b"0 <i.am.a.test.article@example.com>\r\n3000236 \r\n3000237\r\n.\r\n");
+        let hdr = HdrResponse::try_from(&resp).unwrap();
+
+        assert_eq!(
+            hdr.entries,
+            vec![
+                (0, "<i.am.a.test.article@example.com>".to_string()),
+                (3000236, "".to_string()),
+                (3000237, "".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_xhdr() {
+        let resp = RawResponse {
+            code: ResponseCode::Known(Kind::Head),
+            first_line: b"221 Subject fields follow\r\n".to_vec(),
+            data_blocks: Some(DataBlocks::from_payload(
+                b"3000234 I am just a test article\r\n3000235 (none)\r\n.\r\n",
+            )),
+        };
+        let xhdr = XHdrResponse::try_from(&resp).unwrap();
+
+        assert_eq!(
+            HdrResponse::from(xhdr).entries,
+            vec![
+                (3000234, "I am just a test article".to_string()),
+                (3000235, "(none)".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_number() {
+        let resp = response(b"abc Subject\r\n.\r\n");
+        assert!(matches!(
+            HdrResponse::try_from(&resp),
+            Err(Error::InvalidField {
+                field: "article-number",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,125 @@
+use std::convert::TryFrom;
+
+use crate::error::{Error, Result};
+use crate::types::prelude::*;
+use crate::types::response::util::err_if_not_kind;
+
+/// The fields available to the `HDR` command as returned by
+/// [`LIST HEADERS`](https://tools.ietf.org/html/rfc3977#section-8.6)
+///
+/// # Metadata vs Headers
+///
+/// The server may return two types of fields:
+///
+/// 1. Header names (e.g. `Subject`) which correspond to headers within an article
+/// 2. Metadata items prefixed with a `:` (e.g. `:bytes`, `:lines`) which are
+///    computed by the server rather than stored in the article
+///
+/// A line containing only `:` indicates that _any_ header may be retrieved.
+/// This is reported via [`all_headers`](Self::all_headers) rather than in the `fields`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ListHeaders {
+    /// The header names and metadata items returned by the server
+    pub fields: Vec<String>,
+    /// True if the server indicated that all headers are available (via a lone `:`)
+    pub all_headers: bool,
+}
+
+impl ListHeaders {
+    /// An iterator over the header names (i.e. fields that are not metadata)
+    pub fn headers(&self) -> impl Iterator<Item = &str> {
+        self.fields
+            .iter()
+            .map(String::as_str)
+            .filter(|f| !f.starts_with(':'))
+    }
+
+    /// An iterator over the metadata items (e.g. `:bytes`)
+    pub fn metadata(&self) -> impl Iterator<Item = &str> {
+        self.fields
+            .iter()
+            .map(String::as_str)
+            .filter(|f| f.starts_with(':'))
+    }
+
+    /// Returns true if the field can be retrieved via `HDR`
+    ///
+    /// Header names are compared case-insensitively
+    pub fn contains(&self, field: impl AsRef<str>) -> bool {
+        let field = field.as_ref();
+        if self.all_headers && !field.starts_with(':') {
+            return true;
+        }
+        self.fields.iter().any(|f| f.eq_ignore_ascii_case(field))
+    }
+}
+
+impl TryFrom<&RawResponse> for ListHeaders {
+    type Error = Error;
+
+    fn try_from(resp: &RawResponse) -> Result<Self> {
+        err_if_not_kind(resp, Kind::List)?;
+
+        let lines = resp
+            .data_blocks
+            .as_ref()
+            .ok_or_else(Error::missing_data_blocks)?
+            .unterminated();
+
+        let mut fields = Vec::new();
+        let mut all_headers = false;
+
+        for line in lines.map(String::from_utf8_lossy) {
+            match line.trim() {
+                "" => {}
+                ":" => all_headers = true,
+                field => fields.push(field.to_string()),
+            }
+        }
+
+        Ok(Self {
+            fields,
+            all_headers,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(payload: &[u8]) -> RawResponse {
+        RawResponse {
+            code: ResponseCode::Known(Kind::List),
+            first_line: b"215 headers supported:\r\n".to_vec(),
+            data_blocks: Some(DataBlocks::from_payload(payload)),
+        }
+    }
+
+    #[test]
+    fn happy_path() {
+        let resp = response(b"Subject\r\nFrom\r\n:bytes\r\n:lines\r\n.\r\n");
+        let list = ListHeaders::try_from(&resp).unwrap();
+
+        assert!(!list.all_headers);
+        assert_eq!(list.headers().collect::<Vec<_>>(), vec!["Subject", "From"]);
+        assert_eq!(
+            list.metadata().collect::<Vec<_>>(),
+            vec![":bytes", ":lines"]
+        );
+        assert!(list.contains("subject"));
+        assert!(!list.contains("Xref"));
+    }
+
+    #[test]
+    fn test_all_headers() {
+        let resp = response(b":\r\n:bytes\r\n.\r\n");
+        let list = ListHeaders::try_from(&resp).unwrap();
+
+        assert!(list.all_headers);
+        assert_eq!(list.fields, vec![":bytes"]);
+        assert!(list.contains("Xref"));
+        assert!(list.contains(":bytes"));
+        assert!(!list.contains(":lines"));
+    }
+}
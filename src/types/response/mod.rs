@@ -1,10 +1,200 @@
-mod article;
+pub(crate) mod article;
 mod capabilities;
 mod group;
+mod hdr;
+mod list;
+mod overview;
 mod util;
+mod xpath;
 
 pub use article::*;
 
 pub use group::*;
 
-pub use capabilities::Capabilities;
+pub use capabilities::{Capabilities, Capability};
+
+pub use hdr::{HdrResponse, XHdrResponse};
+
+pub use list::ListHeaders;
+
+pub use overview::{OverviewEntry, OverviewResponse};
+
+pub use xpath::XPathResponse;
+
+use std::convert::TryFrom;
+
+use crate::error::{Error, Result};
+use crate::raw::response::RawResponse;
+use crate::types::response_code::ResponseCode;
+
+/// Implement `TryFrom<RawResponse>` for types that do not take ownership of any response data
+///
+/// The response is borrowed and then dropped, so these impls are purely for API consistency
+/// with [`Body`] and [`BinaryArticle`], which move their payload out of the response.
+macro_rules! try_from_owned {
+    ($($name:ty),+ $(,)?) => {
+        $(
+            impl TryFrom<RawResponse> for $name {
+                type Error = Error;
+
+                fn try_from(resp: RawResponse) -> Result<Self> {
+                    Self::try_from(&resp)
+                }
+            }
+        )+
+    };
+}
+
+try_from_owned!(
+    Capabilities,
+    Group,
+    HdrResponse,
+    Head,
+    ListHeaders,
+    OverviewResponse,
+    Stat,
+    XHdrResponse,
+    XPathResponse,
+);
+
+/// A typed response body along with the code that produced it
+///
+/// This is useful when several codes may produce the same body
+/// (e.g. `MODE READER` returns `200` or `201`).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Response<B> {
+    code: ResponseCode,
+    body: B,
+}
+
+impl<B> Response<B> {
+    /// Create a response
+    pub fn new(code: ResponseCode, body: B) -> Self {
+        Self { code, body }
+    }
+
+    /// The response code
+    pub fn code(&self) -> ResponseCode {
+        self.code
+    }
+
+    /// The body of the response
+    pub fn body(&self) -> &B {
+        &self.body
+    }
+
+    /// Consume the response, returning the body
+    pub fn into_body(self) -> B {
+        self.body
+    }
+
+    /// Transform the body of the response, keeping the code
+    pub fn map_body<T>(self, f: impl FnOnce(B) -> T) -> Response<T> {
+        Response {
+            code: self.code,
+            body: f(self.body),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests {
+    use std::convert::TryFrom;
+    use std::fmt::Debug;
+
+    use serde_crate::de::DeserializeOwned;
+    use serde_crate::Serialize;
+
+    use super::*;
+    use crate::raw::response::{DataBlocks, RawResponse};
+    use crate::types::prelude::*;
+
+    fn round_trip<T: Serialize + DeserializeOwned + Debug + PartialEq>(value: &T) {
+        let json = serde_json::to_string(value).unwrap();
+        assert_eq!(&serde_json::from_str::<T>(&json).unwrap(), value);
+    }
+
+    fn response(first_line: &str, payload: Option<&[u8]>) -> RawResponse {
+        RawResponse {
+            code: ResponseCode::from(first_line[..3].parse::<u16>().unwrap()),
+            first_line: first_line.as_bytes().to_vec(),
+            data_blocks: payload.map(DataBlocks::from_payload),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let article = response(
+            "220 1 <1@example.com>\r\n",
+            Some(b"Subject: hi\r\nNewsgroups: misc.test\r\n\r\nbody\xff\r\n.\r\n"),
+        );
+        let binary = BinaryArticle::try_from(&article).unwrap();
+        round_trip(&binary);
+        round_trip(&binary.to_text_lossy());
+        round_trip(binary.headers());
+        round_trip(
+            &Head::try_from(&response(
+                "221 1 <1@example.com>\r\n",
+                Some(b"Subject: hi\r\n\r\n.\r\n"),
+            ))
+            .unwrap(),
+        );
+        round_trip(
+            &Body::try_from(&response(
+                "222 1 <1@example.com>\r\n",
+                Some(b"\x00\x01\xff\r\n.\r\n"),
+            ))
+            .unwrap(),
+        );
+        round_trip(&Stat::try_from(&response("223 1 <1@example.com>\r\n", None)).unwrap());
+        round_trip(&Group::try_from(&response("211 3 1 3 misc.test\r\n", None)).unwrap());
+        round_trip(
+            &Capabilities::try_from(&response(
+                "101 Capability list:\r\n",
+                Some(b"VERSION 2\r\nREADER\r\nLIST ACTIVE HEADERS\r\n.\r\n"),
+            ))
+            .unwrap(),
+        );
+
+        round_trip(&OverviewEntry::parse_line("1\tsubject\t\t\t<1@example.com>\t\t10\t1").unwrap());
+
+        round_trip(&Kind::Article);
+        round_trip(&ResponseCode::Known(Kind::Article));
+        round_trip(&ResponseCode::Unknown(999));
+        // Response codes are serialized as numbers
+        assert_eq!(
+            serde_json::to_string(&ResponseCode::Known(Kind::Article)).unwrap(),
+            "220"
+        );
+    }
+
+    #[test]
+    fn test_line_boundaries_recomputed() {
+        let article = response(
+            "220 1 <1@example.com>\r\n",
+            Some(b"Subject: hi\r\n\r\nbody\r\n.\r\n"),
+        );
+        let binary = BinaryArticle::try_from(&article).unwrap();
+        let body = Body::try_from(&response(
+            "222 1 <1@example.com>\r\n",
+            Some(b"body\r\n.\r\n"),
+        ))
+        .unwrap();
+
+        // Boundaries from untrusted input must not be used to index the body
+        let mut json = serde_json::to_value(&binary).unwrap();
+        json["body"] = serde_json::json!(b"a\r\nb");
+        json["line_boundaries"] = serde_json::json!([[0, 999]]);
+        let binary: BinaryArticle = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            binary.lines().collect::<Vec<_>>(),
+            vec![&b"a\r\n"[..], b"b"]
+        );
+
+        let mut json = serde_json::to_value(&body).unwrap();
+        json["payload"] = serde_json::json!(b"a\r\nb");
+        json["line_boundaries"] = serde_json::json!([[5, 2]]);
+        let body: Body = serde_json::from_value(json).unwrap();
+        assert_eq!(body.lines().collect::<Vec<_>>(), vec![&b"a\r\n"[..], b"b"]);
+    }
+}
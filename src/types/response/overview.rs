@@ -0,0 +1,191 @@
+use std::convert::TryFrom;
+
+use crate::error::{Error, Result};
+use crate::types::prelude::*;
+use crate::types::response::util::err_if_not_kind;
+
+/// An entry in the overview database returned by
+/// [`OVER`](https://tools.ietf.org/html/rfc3977#section-8.3) or `XOVER`
+///
+/// Only the fields of the default overview format are parsed, any additional fields
+/// (see `LIST OVERVIEW.FMT`) are kept in [`extra`](Self::extra). Empty fields are `None`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub struct OverviewEntry {
+    /// The article number, `0` if the article was requested by message-id
+    pub number: ArticleNumber,
+    /// The `Subject` header
+    pub subject: Option<String>,
+    /// The `From` header
+    pub from: Option<String>,
+    /// The `Date` header
+    pub date: Option<String>,
+    /// The `Message-ID` header
+    pub message_id: Option<String>,
+    /// The `References` header
+    pub references: Option<String>,
+    /// The size of the article in bytes (`:bytes`)
+    pub bytes: Option<u64>,
+    /// The number of lines in the body (`:lines`)
+    pub lines: Option<u64>,
+    /// Any additional fields, e.g. `Xref: host group:1`
+    pub extra: Vec<String>,
+}
+
+impl OverviewEntry {
+    /// Parse a single tab-separated overview line
+    pub fn parse_line(line: &str) -> Result<Self> {
+        let line = line.trim_end_matches(&['\r', '\n'][..]);
+        let mut fields = line.split('\t');
+
+        let number = fields
+            .next()
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| Error::missing_field("article-number"))?;
+        let number = number
+            .parse()
+            .map_err(|_| Error::invalid_field("article-number", number))?;
+
+        let mut text = || {
+            fields
+                .next()
+                .filter(|s| !s.is_empty())
+                .map(ToString::to_string)
+        };
+        let subject = text();
+        let from = text();
+        let date = text();
+        let message_id = text();
+        let references = text();
+        let bytes = text().and_then(|s| s.parse().ok());
+        let lines = text().and_then(|s| s.parse().ok());
+        let extra = fields.map(ToString::to_string).collect();
+
+        Ok(Self {
+            number,
+            subject,
+            from,
+            date,
+            message_id,
+            references,
+            bytes,
+            lines,
+            extra,
+        })
+    }
+
+    /// The size of the article in bytes
+    ///
+    /// Unlike the [`bytes`](Self::bytes) field this is `None` if the server reported `0`,
+    /// which some servers use for unknown sizes.
+    pub fn bytes(&self) -> Option<u64> {
+        self.bytes.filter(|&b| b > 0)
+    }
+
+    /// The number of lines in the body
+    ///
+    /// Unlike the [`lines`](Self::lines) field this is `None` if the server reported `0`.
+    pub fn lines(&self) -> Option<u64> {
+        self.lines.filter(|&l| l > 0)
+    }
+
+    /// The message-ids in the `References` header, oldest first
+    pub fn references(&self) -> impl Iterator<Item = &str> {
+        self.references
+            .as_deref()
+            .unwrap_or_default()
+            .split_whitespace()
+    }
+}
+
+/// Overview entries returned by [`OVER`](https://tools.ietf.org/html/rfc3977#section-8.3)
+/// or `XOVER`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OverviewResponse {
+    /// The entries in the order returned by the server
+    pub entries: Vec<OverviewEntry>,
+}
+
+impl TryFrom<&RawResponse> for OverviewResponse {
+    type Error = Error;
+
+    fn try_from(resp: &RawResponse) -> Result<Self> {
+        err_if_not_kind(resp, Kind::Overview)?;
+
+        let entries = resp
+            .data_blocks
+            .as_ref()
+            .ok_or_else(Error::missing_data_blocks)?
+            .unterminated()
+            .map(|line| OverviewEntry::parse_line(&String::from_utf8_lossy(line)))
+            .collect::<Result<_>>()?;
+
+        Ok(Self { entries })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overview() {
+        let resp = RawResponse {
+            code: ResponseCode::Known(Kind::Overview),
+            first_line: b"224 Overview information follows\r\n".to_vec(),
+            data_blocks: Some(DataBlocks::from_payload(
+                b"3000234\tI am just a test article\t\"Demo User\" <nobody@example.com>\t\
+                6 Oct 1998 04:38:40 -0500\t<45223423@example.com>\t<45454@example.net>\t1234\t\
+                17\tXref: news.example.com misc.test:3000363\r\n\
+                3000235\tAnother test article\tnobody@nowhere.to (Demo User)\t\
+                6 Oct 1998 04:38:45 -0500\t<45223425@to.to>\t\t4818\t37\t\tDistribution: fi\r\n\
+                .\r\n",
+            )),
+        };
+
+        let overview = OverviewResponse::try_from(&resp).unwrap();
+        assert_eq!(overview.entries.len(), 2);
+
+        let first = &overview.entries[0];
+        assert_eq!(first.number, 3000234);
+        assert_eq!(first.subject.as_deref(), Some("I am just a test article"));
+        assert_eq!(first.message_id.as_deref(), Some("<45223423@example.com>"));
+        assert_eq!(
+            first.references().collect::<Vec<_>>(),
+            vec!["<45454@example.net>"]
+        );
+        assert_eq!(first.bytes, Some(1234));
+        assert_eq!(first.lines, Some(17));
+        assert_eq!(
+            first.extra,
+            vec!["Xref: news.example.com misc.test:3000363"]
+        );
+
+        assert_eq!(first.bytes(), Some(1234));
+
+        let unknown = OverviewEntry::parse_line("1\tsubject\t\t\t<1@example.com>\t\t0\t").unwrap();
+        assert_eq!(unknown.bytes, Some(0));
+        assert_eq!(unknown.bytes(), None);
+        assert_eq!(unknown.lines(), None);
+
+        let second = &overview.entries[1];
+        assert_eq!(second.references, None);
+        assert_eq!(second.references().count(), 0);
+        assert_eq!(second.extra, vec!["", "Distribution: fi"]);
+    }
+
+    #[test]
+    fn test_invalid_number() {
+        assert!(matches!(
+            OverviewEntry::parse_line("abc\tsubject"),
+            Err(Error::InvalidField {
+                field: "article-number",
+                ..
+            })
+        ));
+    }
+}
@@ -11,33 +11,38 @@ use crate::types::prelude::*;
 /// 2. This will advance the provided iterator
 pub(crate) fn parse_field<'a, T: FromStr>(
     iter: &mut impl Iterator<Item = &'a str>,
-    name: impl AsRef<str>,
+    name: &'static str,
 ) -> Result<T> {
-    let name = name.as_ref();
     iter.next()
         .ok_or_else(|| Error::missing_field(name))
-        .and_then(|s| s.parse().map_err(|_| Error::parse_error(name)))
+        .and_then(|s| s.parse().map_err(|_| Error::invalid_field(name, s)))
 }
 
 /// Return a deserialization error if the response does match the desired error code
 pub(crate) fn err_if_not_kind(resp: &RawResponse, desired: Kind) -> Result<()> {
     if resp.code != ResponseCode::Known(desired) {
-        Err(Error::Deserialization(format!(
-            "Invalid response code {}",
-            resp.code()
-        )))
+        Err(Error::UnexpectedResponseCode {
+            expected: vec![desired.into()],
+            got: resp.code(),
+        })
     } else {
         Ok(())
     }
 }
 
+/// Parse the article number and message-id from the first line of an article response
+///
+/// Servers are expected to send `0` as the number when an article is retrieved by message-id
+/// ([RFC 3977 6.2.1.2](https://tools.ietf.org/html/rfc3977#section-6.2.1.2)) but some omit it
+/// entirely, in which case the number defaults to `0`.
 pub(crate) fn process_article_first_line(resp: &RawResponse) -> Result<(ArticleNumber, String)> {
     let lossy = resp.first_line_to_utf8_lossy();
-    let mut iter = lossy.split_whitespace();
-
-    iter.next(); // skip response code since we already parsed it
+    let mut iter = lossy.split_whitespace().skip(1).peekable(); // skip the response code
 
-    let number: ArticleNumber = parse_field(&mut iter, "article-number")?;
+    let number: ArticleNumber = match iter.peek() {
+        Some(field) if field.starts_with('<') => 0,
+        _ => parse_field(&mut iter, "article-number")?,
+    };
     // https://tools.ietf.org/html/rfc3977#section-9.8
     let message_id: String = parse_field(&mut iter, "message-id")?;
 
@@ -49,3 +54,36 @@ pub(crate) fn process_article_first_line(resp: &RawResponse) -> Result<(ArticleN
 
     Ok((number, message_id))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(first_line: &str) -> RawResponse {
+        RawResponse {
+            code: ResponseCode::Known(Kind::Article),
+            first_line: first_line.as_bytes().to_vec(),
+            data_blocks: None,
+        }
+    }
+
+    #[test]
+    fn test_process_article_first_line() {
+        let (number, id) = process_article_first_line(&response("220 47661 <a@b.c>\r\n")).unwrap();
+        assert_eq!((number, id.as_str()), (47661, "<a@b.c>"));
+
+        let (number, id) = process_article_first_line(&response("220 0 <a@b.c>\r\n")).unwrap();
+        assert_eq!((number, id.as_str()), (0, "<a@b.c>"));
+
+        // Number omitted when retrieved by message-id
+        let (number, id) = process_article_first_line(&response("220 <a@b.c>\r\n")).unwrap();
+        assert_eq!((number, id.as_str()), (0, "<a@b.c>"));
+    }
+
+    #[test]
+    fn test_process_article_first_line_invalid() {
+        assert!(process_article_first_line(&response("220 abc <a@b.c>\r\n")).is_err());
+        assert!(process_article_first_line(&response("220 1\r\n")).is_err());
+        assert!(process_article_first_line(&response("220\r\n")).is_err());
+    }
+}
@@ -0,0 +1,70 @@
+use std::convert::TryFrom;
+
+use crate::error::{Error, Result};
+use crate::types::prelude::*;
+use crate::types::response::util::err_if_not_kind;
+
+/// Storage paths returned by [`XPATH`](https://tools.ietf.org/html/rfc2980#section-2.11)
+///
+/// `XPATH` is non-standard and the format of the paths is server-dependent.
+/// INN, for example, returns `group/number` pairs for every group the article was posted to.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct XPathResponse {
+    /// The paths where the article is stored
+    pub paths: Vec<String>,
+}
+
+impl XPathResponse {
+    /// The first path returned by the server
+    pub fn path(&self) -> Option<&str> {
+        self.paths.first().map(String::as_str)
+    }
+}
+
+impl TryFrom<&RawResponse> for XPathResponse {
+    type Error = Error;
+
+    fn try_from(resp: &RawResponse) -> Result<Self> {
+        err_if_not_kind(resp, Kind::ArticleExists)?;
+
+        let lossy = resp.first_line_to_utf8_lossy();
+        let mut iter = lossy.split_whitespace();
+        iter.next(); // skip response code since we already parsed it
+
+        let paths = iter.map(ToString::to_string).collect::<Vec<_>>();
+        if paths.is_empty() {
+            return Err(Error::missing_field("path"));
+        }
+
+        Ok(Self { paths })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xpath() {
+        let resp = RawResponse {
+            code: ResponseCode::Known(Kind::ArticleExists),
+            first_line: b"223 misc.test/1 alt.test/42\r\n".to_vec(),
+            data_blocks: None,
+        };
+
+        let xpath = XPathResponse::try_from(&resp).unwrap();
+        assert_eq!(xpath.paths, vec!["misc.test/1", "alt.test/42"]);
+        assert_eq!(xpath.path(), Some("misc.test/1"));
+    }
+
+    #[test]
+    fn test_xpath_missing_path() {
+        let resp = RawResponse {
+            code: ResponseCode::Known(Kind::ArticleExists),
+            first_line: b"223\r\n".to_vec(),
+            data_blocks: None,
+        };
+
+        assert!(XPathResponse::try_from(&resp).is_err());
+    }
+}
@@ -1,5 +1,8 @@
 use std::convert::TryFrom;
 use std::fmt;
+use std::str::FromStr;
+
+use crate::error::Error;
 
 /// An NNTP [Response Code](https://tools.ietf.org/html/rfc3977#section-3.2)
 ///
@@ -9,6 +12,11 @@ use std::fmt;
 /// Because proprietary NNTP extensions may define their own codes, there is no way for this library
 /// to know about all of the codes that exist. Unknown codes will be stored as `u16`s.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate", from = "u16", into = "u16")
+)]
 pub enum ResponseCode {
     /// A response code implemented by the library
     Known(Kind),
@@ -19,34 +27,103 @@ pub enum ResponseCode {
 }
 
 impl ResponseCode {
+    /// The class of the response as determined by the first digit of the code
+    ///
+    /// Returns `None` if the first digit is not defined by
+    /// [RFC 3977](https://tools.ietf.org/html/rfc3977#section-3.2).
+    pub fn class(&self) -> Option<Class> {
+        match u16::from(*self) / 100 {
+            1 => Some(Class::Informational),
+            2 => Some(Class::Success),
+            3 => Some(Class::Continuation),
+            4 => Some(Class::TransientFailure),
+            5 => Some(Class::PermanentFailure),
+            _ => None,
+        }
+    }
+
+    /// The category of the response as determined by the second digit of the code
+    ///
+    /// Returns `None` if the second digit is not defined by
+    /// [RFC 3977](https://tools.ietf.org/html/rfc3977#section-3.2).
+    pub fn category(&self) -> Option<Category> {
+        match u16::from(*self) / 10 % 10 {
+            0 => Some(Category::Connection),
+            1 => Some(Category::Navigation),
+            2 => Some(Category::ArticleSelection),
+            3 => Some(Category::Distribution),
+            4 => Some(Category::Posting),
+            8 => Some(Category::Authentication),
+            9 => Some(Category::Extension),
+            _ => None,
+        }
+    }
+
+    /// The response is a 1xx
+    pub fn is_informational(&self) -> bool {
+        self.class() == Some(Class::Informational)
+    }
+
     /// The response is a 1xx
+    ///
+    /// This is an alias for [`is_informational`](Self::is_informational)
     pub fn is_info(&self) -> bool {
-        let code = u16::from(*self);
-        code >= 100 && code < 200
+        self.is_informational()
     }
 
     /// The response is a 2xx
     pub fn is_success(&self) -> bool {
-        let code = u16::from(*self);
-        code >= 200 && code < 300
+        self.class() == Some(Class::Success)
+    }
+
+    /// The response is a 3xx
+    pub fn is_continuation(&self) -> bool {
+        self.class() == Some(Class::Continuation)
     }
 
     /// The response is a 3xx
+    ///
+    /// This is an alias for [`is_continuation`](Self::is_continuation)
     pub fn is_success_so_far(&self) -> bool {
-        let code = u16::from(*self);
-        code >= 300 && code < 400
+        self.is_continuation()
     }
 
     /// The response is a 4xx
+    pub fn is_transient_failure(&self) -> bool {
+        self.class() == Some(Class::TransientFailure)
+    }
+
+    /// The response is a 4xx
+    ///
+    /// This is an alias for [`is_transient_failure`](Self::is_transient_failure)
     pub fn is_failure(&self) -> bool {
-        let code = u16::from(*self);
-        code >= 400 && code < 500
+        self.is_transient_failure()
+    }
+
+    /// The response is a 5xx
+    pub fn is_permanent_failure(&self) -> bool {
+        self.class() == Some(Class::PermanentFailure)
     }
 
     /// The response is a 5xx
+    ///
+    /// This is an alias for [`is_permanent_failure`](Self::is_permanent_failure)
     pub fn is_error(&self) -> bool {
-        let code = u16::from(*self);
-        code >= 500 && code < 600
+        self.is_permanent_failure()
+    }
+
+    /// A human readable description of the class and category of the code
+    ///
+    /// e.g. `transient failure (article selection)`
+    pub(crate) fn describe(&self) -> String {
+        let class = self
+            .class()
+            .map_or_else(|| "unknown class".to_string(), |c| c.to_string());
+
+        match self.category() {
+            Some(category) => format!("{} ({})", class, category),
+            None => class,
+        }
     }
 
     /// Returns true if the response is a Known multiline response
@@ -84,10 +161,94 @@ impl From<&ResponseCode> for u16 {
     }
 }
 
+/// Known codes are displayed with their reason phrase (e.g. `411 No such newsgroup`),
+/// unknown codes are displayed as a number
 impl fmt::Display for ResponseCode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let code: u16 = self.into();
-        write!(f, "{}", code)
+        match self {
+            ResponseCode::Known(kind) => kind.fmt(f),
+            ResponseCode::Unknown(code) => write!(f, "{}", code),
+        }
+    }
+}
+
+/// Parse a three digit response code (e.g. `"223"`)
+impl FromStr for ResponseCode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 3 || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(Error::invalid_field("response code", s));
+        }
+        s.parse::<u16>()
+            .map(ResponseCode::from)
+            .map_err(|_| Error::invalid_field("response code", s))
+    }
+}
+
+/// The class of a response code as indicated by its first digit
+///
+/// [RFC 3977 Section 3.2](https://tools.ietf.org/html/rfc3977#section-3.2)
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Class {
+    /// 1xx - Informative message
+    Informational,
+    /// 2xx - Command completed OK
+    Success,
+    /// 3xx - Command OK so far; send the rest of it
+    Continuation,
+    /// 4xx - Command was syntactically correct but failed for some reason
+    TransientFailure,
+    /// 5xx - Command unknown, unsupported, unavailable, or syntax error
+    PermanentFailure,
+}
+
+impl fmt::Display for Class {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Class::Informational => "informational",
+            Class::Success => "success",
+            Class::Continuation => "continuation",
+            Class::TransientFailure => "transient failure",
+            Class::PermanentFailure => "permanent failure",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// The category of a response code as indicated by its second digit
+///
+/// [RFC 3977 Section 3.2](https://tools.ietf.org/html/rfc3977#section-3.2)
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Category {
+    /// x0x - Connection, setup, and miscellaneous messages
+    Connection,
+    /// x1x - Newsgroup selection
+    Navigation,
+    /// x2x - Article selection
+    ArticleSelection,
+    /// x3x - Distribution functions
+    Distribution,
+    /// x4x - Posting
+    Posting,
+    /// x8x - Reserved for authentication and privacy extensions
+    Authentication,
+    /// x9x - Reserved for private use (non-standard extensions)
+    Extension,
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Category::Connection => "connection",
+            Category::Navigation => "navigation",
+            Category::ArticleSelection => "article selection",
+            Category::Distribution => "distribution",
+            Category::Posting => "posting",
+            Category::Authentication => "authentication",
+            Category::Extension => "extension",
+        };
+        write!(f, "{}", s)
     }
 }
 
@@ -99,6 +260,11 @@ impl fmt::Display for ResponseCode {
 /// * [RFC 3977 Appendix C](https://tools.ietf.org/html/rfc3977#appendix-C)
 #[repr(u16)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 #[allow(missing_docs)]
 pub enum Kind {
     Help = 100,
@@ -107,7 +273,11 @@ pub enum Kind {
 
     PostingAllowed = 200,
     PostingProhibited = 201,
+    /// Returned by `MODE STREAM` ([RFC 4644](https://tools.ietf.org/html/rfc4644))
+    StreamingPermitted = 203,
     ConnectionClosing = 205,
+    /// Returned by `COMPRESS`, all subsequent data on the connection is compressed
+    CompressionActive = 206,
     /// This is generated by `GROUP` and `LISTGROUP` and the bodies are different depending
     /// on which command is used. Buyer beware!
     GroupSelected = 211,
@@ -118,8 +288,14 @@ pub enum Kind {
     Body = 222,
     ArticleExists = 223,
     Overview = 224,
+    Hdr = 225,
+    NewNews = 230,
+    NewGroups = 231,
 
     ArticleTransferredOk = 235,
+    CheckSendArticle = 238,
+    TakeThisAccepted = 239,
+    ArticleReceivedOk = 240,
 
     IHaveSendArticle = 335,
     PostSendArticle = 340,
@@ -134,9 +310,12 @@ pub enum Kind {
     NoPreviousArticle = 422,
     NoArticleWithNumber = 423,
     NoArticleWithMessageId = 430,
+    CheckTryLater = 431,
     ArticleNotWanted = 435,
     TransferFailed = 436,
     TransferRejected = 437,
+    CheckNotWanted = 438,
+    TakeThisRejected = 439,
     PostingNotPermitted = 440,
     PostingFailed = 441,
     AuthenticationRequired = 480,
@@ -175,6 +354,86 @@ impl Kind {
     pub fn is_multiline(&self) -> bool {
         matches!(*self as u16, 100..=101 | 215 | 220..=222 | 224..=225 | 230..=231)
     }
+
+    /// The description of the code from the RFC that defines it
+    ///
+    /// e.g. `No such newsgroup` for [`NoSuchNewsgroup`](Kind::NoSuchNewsgroup)
+    pub fn reason_phrase(&self) -> &'static str {
+        match self {
+            Kind::Help => "Help text follows",
+            Kind::Capabilities => "Capability list follows",
+            Kind::Date => "Server date and time",
+            Kind::PostingAllowed => "Service available, posting allowed",
+            Kind::PostingProhibited => "Service available, posting prohibited",
+            Kind::StreamingPermitted => "Streaming permitted",
+            Kind::ConnectionClosing => "Connection closing",
+            Kind::CompressionActive => "Compression active",
+            Kind::GroupSelected => "Group selected",
+            Kind::List => "Information follows",
+            Kind::Article => "Article follows",
+            Kind::Head => "Headers follow",
+            Kind::Body => "Body follows",
+            Kind::ArticleExists => "Article exists and selected",
+            Kind::Overview => "Overview information follows",
+            Kind::Hdr => "Headers follow",
+            Kind::NewNews => "List of new articles follows",
+            Kind::NewGroups => "List of new newsgroups follows",
+            Kind::ArticleTransferredOk => "Article transferred OK",
+            Kind::CheckSendArticle => "Send article",
+            Kind::TakeThisAccepted => "Article transferred OK",
+            Kind::ArticleReceivedOk => "Article received OK",
+            Kind::IHaveSendArticle => "Send article to be transferred",
+            Kind::PostSendArticle => "Send article to be posted",
+            Kind::TemporarilyUnavailable => "Service temporarily unavailable",
+            Kind::WrongMode => "Server is in the wrong mode",
+            Kind::InternalError => "Internal fault",
+            Kind::NoSuchNewsgroup => "No such newsgroup",
+            Kind::NoNewsgroupSelected => "No newsgroup selected",
+            Kind::InvalidCurrentArticleNumber => "Current article number is invalid",
+            Kind::NoNextArticle => "No next article in this group",
+            Kind::NoPreviousArticle => "No previous article in this group",
+            Kind::NoArticleWithNumber => "No article with that number",
+            Kind::NoArticleWithMessageId => "No article with that message-id",
+            Kind::CheckTryLater => "Transfer not possible, try again later",
+            Kind::ArticleNotWanted => "Article not wanted",
+            Kind::TransferFailed => "Transfer not possible, try again later",
+            Kind::TransferRejected => "Transfer rejected, do not retry",
+            Kind::CheckNotWanted => "Article not wanted",
+            Kind::TakeThisRejected => "Transfer rejected, do not retry",
+            Kind::PostingNotPermitted => "Posting not permitted",
+            Kind::PostingFailed => "Posting failed",
+            Kind::AuthenticationRequired => "Authentication required",
+            Kind::SecureConnectionRequired => "Encryption or stronger authentication required",
+            Kind::UnknownCommand => "Unknown command",
+            Kind::SyntaxError => "Syntax error in command",
+            Kind::PermanentlyUnavailable => "Service permanently unavailable",
+            Kind::FeatureNotSupported => "Feature not supported",
+            Kind::Base64Error => "Error in base64-encoding of an argument",
+            Kind::AuthenticationAccepted => "Authentication accepted",
+            Kind::PasswordRequired => "Password required",
+            Kind::AuthenticationFailed => "Authentication failed",
+            Kind::AuthenticationOutOfSequence => "Authentication commands issued out of sequence",
+        }
+    }
+}
+
+/// Displays the code followed by its [reason phrase](Kind::reason_phrase), e.g. `223 Article exists and selected`
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", *self as u16, self.reason_phrase())
+    }
+}
+
+/// Parse a three digit response code, returning an error if the code is not a known [`Kind`]
+impl FromStr for Kind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse()? {
+            ResponseCode::Known(kind) => Ok(kind),
+            ResponseCode::Unknown(_) => Err(Error::invalid_field("response code", s)),
+        }
+    }
 }
 
 impl From<Kind> for u16 {
@@ -188,3 +447,141 @@ impl From<Kind> for ResponseCode {
         ResponseCode::Known(kind)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_class() {
+        assert_eq!(ResponseCode::from(100).class(), Some(Class::Informational));
+        assert_eq!(ResponseCode::from(211).class(), Some(Class::Success));
+        assert_eq!(ResponseCode::from(381).class(), Some(Class::Continuation));
+        assert_eq!(
+            ResponseCode::from(400).class(),
+            Some(Class::TransientFailure)
+        );
+        assert_eq!(
+            ResponseCode::from(480).class(),
+            Some(Class::TransientFailure)
+        );
+        assert_eq!(
+            ResponseCode::from(500).class(),
+            Some(Class::PermanentFailure)
+        );
+        assert_eq!(
+            ResponseCode::from(502).class(),
+            Some(Class::PermanentFailure)
+        );
+        assert_eq!(ResponseCode::from(600).class(), None);
+    }
+
+    #[test]
+    fn test_boundaries() {
+        let code = ResponseCode::from(400);
+        assert!(code.is_transient_failure());
+        assert!(!code.is_success());
+        assert!(!code.is_permanent_failure());
+
+        let code = ResponseCode::from(499);
+        assert!(code.is_transient_failure());
+        assert!(!code.is_permanent_failure());
+
+        let code = ResponseCode::from(500);
+        assert!(code.is_permanent_failure());
+        assert!(!code.is_transient_failure());
+
+        assert!(ResponseCode::from(399).is_continuation());
+        assert!(ResponseCode::from(199).is_informational());
+    }
+
+    #[test]
+    fn test_category() {
+        assert_eq!(
+            ResponseCode::from(400).category(),
+            Some(Category::Connection)
+        );
+        assert_eq!(
+            ResponseCode::from(411).category(),
+            Some(Category::Navigation)
+        );
+        assert_eq!(
+            ResponseCode::from(436).category(),
+            Some(Category::Distribution)
+        );
+        assert_eq!(
+            ResponseCode::from(480).category(),
+            Some(Category::Authentication)
+        );
+        assert_eq!(
+            ResponseCode::from(502).category(),
+            Some(Category::Connection)
+        );
+        assert_eq!(
+            ResponseCode::from(223).category(),
+            Some(Category::ArticleSelection)
+        );
+        assert_eq!(ResponseCode::from(340).category(), Some(Category::Posting));
+        assert_eq!(ResponseCode::from(250).category(), None);
+    }
+
+    #[test]
+    fn test_unknown_codes() {
+        let code = ResponseCode::from(499);
+        assert_eq!(code, ResponseCode::Unknown(499));
+        assert_eq!(code.class(), Some(Class::TransientFailure));
+        assert_eq!(code.category(), Some(Category::Extension));
+
+        let code = ResponseCode::from(290);
+        assert!(code.is_success());
+        assert_eq!(code.category(), Some(Category::Extension));
+    }
+
+    #[test]
+    fn test_reason_phrases() {
+        let kinds = (0..1000)
+            .filter_map(|code| Kind::try_from(code).ok())
+            .collect::<Vec<_>>();
+        assert_eq!(kinds.len(), 53);
+
+        for kind in kinds {
+            assert!(!kind.reason_phrase().is_empty(), "{:?}", kind);
+            let code = (kind as u16).to_string();
+            assert_eq!(code.parse::<Kind>().unwrap(), kind);
+            assert_eq!(code.parse::<ResponseCode>().unwrap(), kind.into());
+            assert_eq!(
+                kind.to_string(),
+                format!("{} {}", code, kind.reason_phrase())
+            );
+        }
+
+        assert_eq!(
+            Kind::ArticleExists.to_string(),
+            "223 Article exists and selected"
+        );
+        assert_eq!(ResponseCode::from(411).to_string(), "411 No such newsgroup");
+        assert_eq!(ResponseCode::from(299).to_string(), "299");
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(
+            "299".parse::<ResponseCode>().unwrap(),
+            ResponseCode::Unknown(299)
+        );
+        for s in &["", "22", "2233", "abc", "+22", " 22"] {
+            assert!(s.parse::<ResponseCode>().is_err(), "{:?}", s);
+        }
+        assert!("299".parse::<Kind>().is_err());
+    }
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(
+            ResponseCode::from(480).describe(),
+            "transient failure (authentication)"
+        );
+        assert_eq!(ResponseCode::from(250).describe(), "success");
+        assert_eq!(ResponseCode::from(650).describe(), "unknown class");
+    }
+}